@@ -0,0 +1,55 @@
+//! Timings of the work done per keystroke and per frame, on the embedded keymaps.
+//!
+//! Run with `cargo bench`; criterion compares each run against the last one.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use lazyvim_helper::commands::{self, Leaders};
+use lazyvim_helper::keyboard::{Keyboard, KeyboardState, KeyboardWidget};
+use lazyvim_helper::search::SearchEngine;
+use ratatui::{buffer::Buffer, layout::Rect, widgets::StatefulWidget};
+
+fn parsing(c: &mut Criterion) {
+    let commands = commands::embedded_commands().unwrap();
+    let leaders = Leaders::default();
+
+    c.bench_function("parse embedded commands", |b| {
+        b.iter(|| commands::parse_frames(black_box(&commands), &leaders))
+    });
+    c.bench_function("parse one sequence", |b| {
+        b.iter(|| commands::parse_key_string(black_box("3\"ayy<C-w><S-Left>ciw")))
+    });
+}
+
+fn searching(c: &mut Criterion) {
+    let commands = commands::embedded_commands().unwrap();
+    let mut engine = SearchEngine::new();
+    engine.index(&commands);
+
+    for query in ["", "find files", "ctrl w v", "#picker grep"] {
+        c.bench_function(&format!("search {:?}", query), |b| {
+            b.iter(|| engine.search(black_box(&commands), query).len())
+        });
+    }
+}
+
+fn rendering(c: &mut Criterion) {
+    let keyboard = Keyboard::new();
+    let frames = commands::parse_key_string("<leader>sg");
+    let lit: Vec<Vec<&'static str>> = frames.iter().map(|kf| keyboard.frame_ids(kf)).collect();
+
+    for (name, large) in [("render keyboard", false), ("render large keyboard", true)] {
+        let area = Rect::new(0, 0, 100, 40);
+        let mut buf = Buffer::empty(area);
+        c.bench_function(name, |b| {
+            b.iter(|| {
+                let mut state = KeyboardState::legend(lit.clone());
+                KeyboardWidget::new()
+                    .large(large)
+                    .render(area, &mut buf, &mut state)
+            })
+        });
+    }
+}
+
+criterion_group!(benches, parsing, searching, rendering);
+criterion_main!(benches);
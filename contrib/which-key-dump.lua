@@ -0,0 +1,49 @@
+-- Dump which-key groups and keymap descriptions as JSON for `lvcheat import --which-key`.
+--
+-- Run inside Neovim once everything has loaded:
+--   :luafile which-key-dump.lua
+-- The dump is written to ~/which-key.json, or to vim.g.lvcheat_dump when set.
+
+local out = vim.g.lvcheat_dump or vim.fn.expand("~/which-key.json")
+local leader = vim.fn.keytrans(vim.g.mapleader or "\\")
+local modes = { n = true, i = true, v = true, x = true, c = true }
+
+-- Normalize raw or notation keys to notation, with the leader spelled `<leader>`
+local function notation(lhs)
+  local keys = vim.fn.keytrans(vim.keycode(lhs))
+  if keys:sub(1, #leader) == leader then
+    keys = "<leader>" .. keys:sub(#leader + 1)
+  end
+  return keys
+end
+
+local entries = {}
+local function add(lhs, mode, desc, group)
+  if lhs and desc and desc ~= "" and modes[mode] then
+    table.insert(entries, { lhs = notation(lhs), mode = mode, desc = desc, group = group })
+  end
+end
+
+-- which-key's own spec first: it carries the group names
+local ok, config = pcall(require, "which-key.config")
+if ok and type(config.mappings) == "table" then
+  for _, m in ipairs(config.mappings) do
+    local desc = m.desc
+    if type(desc) == "function" then
+      desc = desc()
+    end
+    add(m.lhs, m.mode or "n", desc, m.group == true)
+  end
+end
+
+-- Then every keymap with a description, including ones which-key never saw
+for mode in pairs(modes) do
+  for _, map in ipairs(vim.api.nvim_get_keymap(mode)) do
+    add(map.lhs, mode, map.desc, false)
+  end
+end
+
+local file = assert(io.open(out, "w"))
+file:write(vim.json.encode(entries))
+file:close()
+print(("lvcheat: wrote %d entries to %s"):format(#entries, out))
@@ -0,0 +1,176 @@
+use crate::commands::{Category, CategoryLayout, Command};
+use crate::ui::{self, ellipsize};
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph},
+    Frame,
+};
+
+/// Commands listed on each card, the first ones of its category in the command file
+const FEATURED: usize = 3;
+const CARD_WIDTH: u16 = 34;
+/// Borders, then a line per featured command
+const CARD_HEIGHT: u16 = FEATURED as u16 + 2;
+/// Width given to the keys before each featured command's description
+const KEYS_WIDTH: usize = 12;
+
+/// A category on the start screen, with how many commands it has and the first few of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Card {
+    pub category: Category,
+    pub count: usize,
+    /// Indices into the commands, in their order
+    pub featured: Vec<usize>,
+}
+
+/// A card for each category among `results`, in the order of `layout`
+pub fn cards(commands: &[Command], results: &[usize], layout: &CategoryLayout) -> Vec<Card> {
+    let used = results.iter().map(|&idx| &commands[idx].category);
+    layout
+        .in_order(used)
+        .into_iter()
+        .filter_map(|category| {
+            let mut members: Vec<usize> = results
+                .iter()
+                .copied()
+                .filter(|&idx| commands[idx].category == category)
+                .collect();
+            if members.is_empty() {
+                return None;
+            }
+            members.sort_unstable();
+            let count = members.len();
+            members.truncate(FEATURED);
+            Some(Card {
+                category,
+                count,
+                featured: members,
+            })
+        })
+        .collect()
+}
+
+/// How many cards fit side by side in `width`
+pub fn columns(width: u16) -> usize {
+    (width / CARD_WIDTH).max(1) as usize
+}
+
+/// The card `dx` columns and `dy` rows away from `selected` in a grid `columns` wide, staying
+/// put at the edges
+pub fn step(selected: usize, count: usize, columns: usize, dx: isize, dy: isize) -> usize {
+    let moved = selected as isize + dx + dy * columns as isize;
+    let same_row = dx == 0 || moved.div_euclid(columns as isize) == (selected / columns) as isize;
+    if moved < 0 || moved as usize >= count || !same_row {
+        selected
+    } else {
+        moved as usize
+    }
+}
+
+/// The cards in a grid filling `area`, scrolled to keep the selected one in view
+pub fn draw_cards(
+    frame: &mut Frame,
+    area: Rect,
+    cards: &[Card],
+    selected: usize,
+    commands: &[Command],
+    icons: bool,
+    layout: &CategoryLayout,
+) {
+    let columns = columns(area.width);
+    let width = area.width / columns as u16;
+    let visible_rows = (area.height / CARD_HEIGHT).max(1) as usize;
+    let first_row = (selected / columns).saturating_sub(visible_rows - 1);
+    let dim = Style::default().fg(Color::DarkGray);
+
+    for (i, card) in cards.iter().enumerate().skip(first_row * columns) {
+        let (row, column) = (i / columns - first_row, i % columns);
+        if row >= visible_rows {
+            break;
+        }
+        let rect = Rect::new(
+            area.x + column as u16 * width,
+            area.y + row as u16 * CARD_HEIGHT,
+            width,
+            CARD_HEIGHT,
+        );
+        let color = ui::color(layout.color(&card.category));
+        let icon = if icons { format!("{} ", card.category.icon()) } else { String::new() };
+        let title = Span::styled(
+            format!(" {}{} ({}) ", icon, layout.name(&card.category), card.count),
+            Style::default().fg(color).add_modifier(Modifier::BOLD),
+        );
+        let border = if i == selected {
+            Style::default().fg(color).add_modifier(Modifier::BOLD)
+        } else {
+            dim
+        };
+        let text_width = (width as usize).saturating_sub(KEYS_WIDTH + 3);
+        let lines: Vec<Line> = card
+            .featured
+            .iter()
+            .map(|&idx| {
+                let cmd = &commands[idx];
+                let keys = ellipsize(&cmd.keys, KEYS_WIDTH);
+                Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$} ", keys, width = KEYS_WIDTH),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(ellipsize(&cmd.description, text_width)),
+                ])
+            })
+            .collect();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(border)
+            .title(title);
+        frame.render_widget(Paragraph::new(lines).block(block), rect);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(keys: &str, category: Category) -> Command {
+        Command {
+            keys: keys.to_string(),
+            category,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_cards() {
+        let commands = vec![
+            command("<leader>ff", Category::Search),
+            command("<leader>gg", Category::Git),
+            command("<leader>sg", Category::Search),
+            command("<leader>sh", Category::Search),
+            command("<leader>sk", Category::Search),
+            command("<leader>rr", Category::named("Testing")),
+        ];
+        // Results come best first; cards keep the file's order
+        let cards = cards(&commands, &[4, 3, 2, 1, 0], &CategoryLayout::default());
+        let names: Vec<&str> = cards.iter().map(|card| card.category.as_str()).collect();
+        assert_eq!(names, ["Search", "Git"]);
+        assert_eq!(cards[0].count, 4);
+        assert_eq!(cards[0].featured, [0, 2, 3]);
+        assert_eq!(cards[1].featured, [1]);
+    }
+
+    #[test]
+    fn test_step() {
+        // 7 cards, 3 to a row
+        assert_eq!(step(0, 7, 3, 1, 0), 1);
+        assert_eq!(step(2, 7, 3, 1, 0), 2);
+        assert_eq!(step(3, 7, 3, -1, 0), 3);
+        assert_eq!(step(1, 7, 3, 0, 1), 4);
+        assert_eq!(step(5, 7, 3, 0, 1), 5);
+        assert_eq!(step(4, 7, 3, 0, -1), 1);
+        assert_eq!(step(0, 7, 3, 0, -1), 0);
+    }
+}
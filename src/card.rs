@@ -0,0 +1,119 @@
+use crate::commands::{Command, Leaders};
+use crate::keyboard::{Keyboard, KeyboardState, KeyboardWidget};
+use crate::ui::{self, KEYBOARD_HEIGHT, KEYBOARD_WIDTH};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, StatefulWidget, Widget},
+};
+
+/// Size of the keyboard art, without the block the app draws around it
+const ART_WIDTH: u16 = KEYBOARD_WIDTH - 2;
+const ART_HEIGHT: u16 = KEYBOARD_HEIGHT - 2;
+
+/// A framed card for `cmd`: the keys as its title, what the command does, and the keyboard
+/// with every frame lit in its legend color. The frame numbers are also written on each
+/// key's top edge, so the card still reads without color.
+pub fn render_card(cmd: &Command, keyboard: &Keyboard, leaders: &Leaders) -> Buffer {
+    let frames = cmd.parse_keys_with(leaders);
+    let ids: Vec<Vec<&'static str>> = frames.iter().map(|kf| keyboard.frame_ids(kf)).collect();
+
+    let mut header = vec![
+        Line::from(Span::styled(
+            cmd.description.clone(),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(vec![
+            Span::styled(
+                cmd.category.as_str(),
+                Style::default().fg(ui::color(cmd.category.color())),
+            ),
+            Span::styled(
+                format!(" · {} mode", cmd.mode.as_str()),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ]),
+    ];
+    if let Some(mnemonic) = &cmd.mnemonic {
+        header.push(Line::from(Span::styled(
+            mnemonic.clone(),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+
+    let mut legend = Vec::new();
+    for (i, kf) in frames.iter().enumerate() {
+        let text = kf.note.clone().unwrap_or_else(|| kf.label());
+        let color = keyboard.palette.frame_color(i);
+        legend.push(Span::raw(format!("{} ", i + 1)));
+        legend.push(Span::styled(
+            format!(" {} ", text),
+            Style::default().fg(Color::Black).bg(color),
+        ));
+        legend.push(Span::raw("  "));
+    }
+    let legend = Line::from(legend);
+
+    let header_height = header.len() as u16;
+    let text_width = header.iter().chain([&legend]).map(Line::width).max().unwrap_or(0);
+    let area = Rect::new(
+        0,
+        0,
+        ART_WIDTH.max(text_width as u16) + 2,
+        header_height + ART_HEIGHT + 5,
+    );
+    let mut buf = Buffer::empty(area);
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(format!(" {} ", cmd.keys));
+    let inner = block.inner(area);
+    block.render(area, &mut buf);
+
+    Paragraph::new(header).render(Rect { height: header_height, ..inner }, &mut buf);
+    let art = Rect::new(inner.x, inner.y + header_height + 1, ART_WIDTH, ART_HEIGHT);
+    KeyboardWidget::new()
+        .layout(keyboard.layout)
+        .platform(keyboard.platform)
+        .render(
+            art,
+            &mut buf,
+            &mut KeyboardState::legend(ids).palette(keyboard.palette).numbered(),
+        );
+    Paragraph::new(legend).render(Rect::new(inner.x, art.bottom() + 1, inner.width, 1), &mut buf);
+
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cast::text_lines;
+    use crate::commands::{Category, Mode};
+
+    #[test]
+    fn test_render_card() {
+        let cmd = Command {
+            keys: "<leader>ff".to_string(),
+            description: "Find files".to_string(),
+            category: Category::Search,
+            mode: Mode::Normal,
+            mnemonic: Some("ff = Find Files".to_string()),
+            ..Default::default()
+        };
+        let lines = text_lines(&render_card(&cmd, &Keyboard::new(), &Leaders::default()));
+
+        assert!(lines[0].starts_with("┌ <leader>ff ─"));
+        assert_eq!(lines[1].trim_end_matches(['│', ' ']), "│Find files");
+        assert_eq!(lines[2].trim_end_matches(['│', ' ']), "│Search · normal mode");
+        assert_eq!(lines[3].trim_end_matches(['│', ' ']), "│ff = Find Files");
+        // Space is the first frame and f both of the others
+        let numbered: Vec<&String> = lines.iter().filter(|l| l.contains("2,3")).collect();
+        assert_eq!(numbered.len(), 1);
+        assert!(lines.iter().any(|l| l.contains("┬1─")));
+        let legend = &lines[lines.len() - 2];
+        assert_eq!(legend.trim_end_matches(['│', ' ']), "│1  ␣   2  F   3  F");
+        assert!(lines.last().unwrap().starts_with("└"));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::commands::KeyFrame;
+use crate::keyboard::{Keyboard, KeyboardState, KeyboardWidget};
+use crate::ui::{KEYBOARD_HEIGHT, KEYBOARD_WIDTH};
+use ratatui::{
+    buffer::{Buffer, Cell},
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::{Block, Borders, StatefulWidget},
+};
+use serde_json::json;
+use std::fmt::Write;
+use std::io;
+use std::time::Duration;
+
+/// An asciinema v2 recording of `frames` played once on the keyboard, one frame
+/// every `frame_ms`, ending on the keyboard at rest
+pub fn render_cast(
+    title: &str,
+    frames: &[KeyFrame],
+    frame_ms: u64,
+    keyboard: &Keyboard,
+) -> String {
+    let area = Rect::new(0, 0, KEYBOARD_WIDTH, KEYBOARD_HEIGHT);
+    let header = json!({
+        "version": 2,
+        "width": area.width,
+        "height": area.height,
+        "title": title,
+    });
+    let mut cast = format!("{}\n", header);
+
+    for (i, buf) in screens(title, frames, keyboard).iter().enumerate() {
+        let clear = if i == 0 { "\x1b[2J" } else { "" };
+        let seconds = (i as u64 * frame_ms) as f64 / 1000.0;
+        let screen = ansi_lines(buf).join("\r\n");
+        let event = json!([seconds, "o", format!("{}\x1b[H{}", clear, screen)]);
+        let _ = writeln!(cast, "{}", event);
+    }
+    cast
+}
+
+/// The keyboard with each of `frames` lit in turn, then at rest, titled like the animation
+/// view
+pub fn screens(title: &str, frames: &[KeyFrame], keyboard: &Keyboard) -> Vec<Buffer> {
+    let area = Rect::new(0, 0, KEYBOARD_WIDTH, KEYBOARD_HEIGHT);
+    let lit = frames.iter().map(|kf| keyboard.frame_ids(kf));
+    lit.chain(std::iter::once(Vec::new()))
+        .enumerate()
+        .map(|(i, ids)| {
+            let mut buf = Buffer::empty(area);
+            let block = Block::default()
+                .borders(Borders::ALL)
+                .title(frame_title(title, frames, i));
+            KeyboardWidget::new()
+                .layout(keyboard.layout)
+                .platform(keyboard.platform)
+                .block(block)
+                .render(area, &mut buf, &mut KeyboardState::frame(ids, i));
+            buf
+        })
+        .collect()
+}
+
+/// Play `screens` `loops` times on `out` below the prompt, each one drawn over the last
+/// `frame` apart, rather than taking over the terminal like the app does
+pub fn play(
+    out: &mut impl io::Write,
+    screens: &[Vec<String>],
+    loops: u32,
+    frame: Duration,
+) -> io::Result<()> {
+    let mut drawn = false;
+    for screen in std::iter::repeat_n(screens, loops as usize).flatten() {
+        if drawn {
+            // Back to the first line of the previous screen
+            write!(out, "\r\x1b[{}A", screen.len() - 1)?;
+            std::thread::sleep(frame);
+        }
+        write!(out, "{}", screen.join("\r\n"))?;
+        out.flush()?;
+        drawn = true;
+    }
+    if drawn {
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Block title like the animation view's: keys, frame counter and the frame's note
+fn frame_title(title: &str, frames: &[KeyFrame], index: usize) -> String {
+    match frames.get(index) {
+        Some(frame) if frames.len() > 1 => {
+            let note = frame
+                .note
+                .as_deref()
+                .map(|note| format!(" · {}", note))
+                .unwrap_or_default();
+            format!(" {} [frame {}/{}{}] ", title, index + 1, frames.len(), note)
+        }
+        _ => format!(" {} ", title),
+    }
+}
+
+/// Each row of the buffer as text with SGR color codes
+pub fn ansi_lines(buf: &Buffer) -> Vec<String> {
+    (0..buf.area.height)
+        .map(|y| {
+            let mut line = String::new();
+            let mut current = None;
+            for cell in row_cells(buf, y) {
+                let style = cell.style();
+                if current != Some(style) {
+                    line.push_str(&sgr(style));
+                    current = Some(style);
+                }
+                line.push_str(cell.symbol());
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect()
+}
+
+/// Each row of the buffer as plain text, without trailing spaces
+pub fn text_lines(buf: &Buffer) -> Vec<String> {
+    (0..buf.area.height)
+        .map(|y| {
+            let line: String = row_cells(buf, y).map(|cell| cell.symbol()).collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// The cells of row `y` that start a character; the ones a wide character covers are skipped
+fn row_cells(buf: &Buffer, y: u16) -> impl Iterator<Item = &Cell> {
+    let mut covered = 0;
+    (0..buf.area.width).filter_map(move |x| {
+        if covered > 0 {
+            covered -= 1;
+            return None;
+        }
+        let cell = &buf[(x, y)];
+        covered = Span::raw(cell.symbol()).width().saturating_sub(1);
+        Some(cell)
+    })
+}
+
+fn sgr(style: Style) -> String {
+    let mut codes = vec!["0".to_string()];
+    if style.add_modifier.contains(Modifier::BOLD) {
+        codes.push("1".to_string());
+    }
+    if style.add_modifier.contains(Modifier::REVERSED) {
+        codes.push("7".to_string());
+    }
+    codes.extend(style.fg.and_then(|c| color_code(c, false)));
+    codes.extend(style.bg.and_then(|c| color_code(c, true)));
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+fn color_code(color: Color, background: bool) -> Option<String> {
+    let base = match color {
+        Color::Reset => return None,
+        Color::Rgb(r, g, b) => {
+            let layer = if background { 48 } else { 38 };
+            return Some(format!("{};2;{};{};{}", layer, r, g, b));
+        }
+        Color::Indexed(i) => {
+            let layer = if background { 48 } else { 38 };
+            return Some(format!("{};5;{}", layer, i));
+        }
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::Gray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::LightYellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+    };
+    Some((base + if background { 10 } else { 0 }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse_key_string;
+    use serde_json::Value;
+
+    #[test]
+    fn test_render_cast() {
+        let cast = render_cast(
+            "<leader>ff",
+            &parse_key_string("<leader>ff"),
+            250,
+            &Keyboard::new(),
+        );
+        let lines: Vec<Value> = cast
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], KEYBOARD_WIDTH);
+        // Three frames, then the keyboard at rest
+        assert_eq!(lines.len(), 5);
+        let times: Vec<f64> = lines[1..].iter().map(|e| e[0].as_f64().unwrap()).collect();
+        assert_eq!(times, [0.0, 0.25, 0.5, 0.75]);
+
+        let first = lines[1][2].as_str().unwrap();
+        assert!(first.starts_with("\x1b[2J\x1b[H"));
+        assert!(first.contains("[frame 1/3]"));
+        // Space lit in the first frame's color: black text on a yellow background
+        assert!(first.contains("\x1b[0;30;43m"));
+        assert_eq!(first.matches("\r\n").count(), KEYBOARD_HEIGHT as usize - 1);
+        assert!(lines[2][2].as_str().unwrap().contains("\x1b[0;30;42m"));
+        assert!(!lines[4][2].as_str().unwrap().contains(";43m"));
+    }
+
+    #[test]
+    fn test_play() {
+        let frames = parse_key_string("gd");
+        let screens: Vec<Vec<String>> = screens("gd", &frames, &Keyboard::new())
+            .iter()
+            .map(text_lines)
+            .collect();
+        assert_eq!(screens.len(), 3);
+        assert!(screens[0][0].contains("[frame 1/2]"));
+
+        let mut out = Vec::new();
+        play(&mut out, &screens, 2, Duration::ZERO).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // Every screen after the first goes back up over the one before it
+        let up = format!("\r\x1b[{}A", KEYBOARD_HEIGHT - 1);
+        assert_eq!(out.matches(&up).count(), 5);
+        assert_eq!(out.matches("[frame 2/2]").count(), 2);
+        assert!(out.ends_with(&format!("{}\n", screens[2].last().unwrap())));
+    }
+}
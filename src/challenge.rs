@@ -0,0 +1,435 @@
+use crate::commands::{Command, KeyFrame};
+use crate::state::UserState;
+use crate::stats::{HighScore, Stats};
+use crate::tip::Rng;
+use crate::typing::{self, Typing};
+use crossterm::event::KeyEvent;
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+/// How long a round lasts
+pub const ROUND: Duration = Duration::from_secs(60);
+
+/// Descriptions to pick from in a reverse round, the right one among them
+const CHOICES: usize = 4;
+
+/// Keys that control the round, so bindings using them never come up
+const RESERVED_KEYS: &[&str] = &["Esc", "Tab"];
+
+/// How a prompt ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Scored(u32),
+    Skipped,
+}
+
+/// A timed round: descriptions come up one after another and the binding has to be typed,
+/// or in reverse, bindings play on the keyboard and what they do has to be picked
+#[derive(Debug, Clone)]
+pub struct Challenge {
+    /// Commands that can come up, by index into the app's commands, with their frames
+    pool: Vec<(usize, Vec<KeyFrame>)>,
+    /// How likely each of `pool` is to come up, from when it's due for review in this direction
+    weights: Vec<u32>,
+    /// Index into `pool` of the prompt showing
+    current: usize,
+    /// Showing keys and asking what they do, rather than the other way around
+    pub reverse: bool,
+    /// In reverse, the commands whose descriptions can be picked, by index into the commands
+    pub choices: Vec<usize>,
+    /// In reverse, the choices already picked wrong on the prompt showing
+    pub wrong: Vec<usize>,
+    pub typing: Typing,
+    started: Instant,
+    prompted: Instant,
+    /// Wrong keys on the prompt showing
+    misses: u32,
+    pub score: u32,
+    /// Bindings typed right
+    pub hits: usize,
+    right_keys: usize,
+    wrong_keys: usize,
+    /// The previous prompt's command and how it ended
+    pub last: Option<(usize, Outcome)>,
+    /// Commands answered since these were last taken for review, and whether they were got
+    /// right without a miss
+    pub answered: Vec<(usize, bool)>,
+    /// Once time is up: the round, and its place in the high scores if it made it
+    pub result: Option<(HighScore, Option<usize>)>,
+}
+
+impl Challenge {
+    /// A round asking for `commands`, whose key presses are `frames`, starting at `now` on
+    /// `today`, with the ones due for review in this direction coming up most; `None` if no
+    /// command can be asked for, or too few to pick from in reverse
+    pub fn new(
+        commands: &[Command],
+        frames: &[Vec<KeyFrame>],
+        state: &UserState,
+        reverse: bool,
+        rng: &mut Rng,
+        now: Instant,
+        today: u64,
+    ) -> Option<Self> {
+        let pool = pool(commands, frames, state);
+        if reverse && pool.len() < 2 {
+            return None;
+        }
+        let weights: Vec<u32> = pool
+            .iter()
+            .map(|(i, _)| state.stats.review_weight(&commands[*i].id(), reverse, today))
+            .collect();
+        let current = next_prompt(&weights, rng, None)?;
+        let choices = if reverse { choices(&pool, current, rng) } else { Vec::new() };
+        Some(Self {
+            pool,
+            weights,
+            current,
+            reverse,
+            choices,
+            wrong: Vec::new(),
+            typing: Typing::default(),
+            started: now,
+            prompted: now,
+            misses: 0,
+            score: 0,
+            hits: 0,
+            right_keys: 0,
+            wrong_keys: 0,
+            last: None,
+            answered: Vec::new(),
+            result: None,
+        })
+    }
+
+    /// Index of the command to type
+    pub fn command(&self) -> usize {
+        self.pool[self.current].0
+    }
+
+    pub fn frames(&self) -> &[KeyFrame] {
+        &self.pool[self.current].1
+    }
+
+    pub fn time_left(&self, now: Instant) -> Duration {
+        ROUND.saturating_sub(now.saturating_duration_since(self.started))
+    }
+
+    fn is_running(&self, now: Instant) -> bool {
+        self.result.is_none() && !self.time_left(now).is_zero()
+    }
+
+    /// Share of keypresses that were right, in percent
+    pub fn accuracy(&self) -> u32 {
+        let total = self.right_keys + self.wrong_keys;
+        (self.right_keys * 100).checked_div(total).unwrap_or(0) as u32
+    }
+
+    /// Check a keypress against the prompt, moving on once it's typed
+    pub fn press(&mut self, event: KeyEvent, rng: &mut Rng, now: Instant) {
+        if !self.is_running(now) {
+            return;
+        }
+        if self.typing.press(&self.pool[self.current].1, event) {
+            self.right_keys += 1;
+        } else {
+            self.wrong_keys += 1;
+            self.misses += 1;
+        }
+        if self.typing.is_done(self.frames()) {
+            let points = points(now.saturating_duration_since(self.prompted), self.misses);
+            self.score += points;
+            self.hits += 1;
+            self.next(Outcome::Scored(points), rng, now);
+        }
+    }
+
+    /// Pick the `n`th choice of a reverse prompt, moving on once it's the right one
+    pub fn answer(&mut self, n: usize, rng: &mut Rng, now: Instant) {
+        if !self.is_running(now) {
+            return;
+        }
+        let Some(&picked) = self.choices.get(n) else {
+            return;
+        };
+        if picked == self.command() {
+            self.right_keys += 1;
+            let points = points(now.saturating_duration_since(self.prompted), self.misses);
+            self.score += points;
+            self.hits += 1;
+            self.next(Outcome::Scored(points), rng, now);
+        } else if !self.wrong.contains(&picked) {
+            self.wrong_keys += 1;
+            self.misses += 1;
+            self.wrong.push(picked);
+        }
+    }
+
+    /// Give up on the prompt for no points
+    pub fn skip(&mut self, rng: &mut Rng, now: Instant) {
+        if self.is_running(now) {
+            self.next(Outcome::Skipped, rng, now);
+        }
+    }
+
+    fn next(&mut self, outcome: Outcome, rng: &mut Rng, now: Instant) {
+        self.last = Some((self.command(), outcome));
+        let right = matches!(outcome, Outcome::Scored(_)) && self.misses == 0;
+        self.answered.push((self.command(), right));
+        if let Some(next) = next_prompt(&self.weights, rng, Some(self.current)) {
+            self.current = next;
+        }
+        if self.reverse {
+            self.choices = choices(&self.pool, self.current, rng);
+            self.wrong.clear();
+        }
+        self.typing = Typing::default();
+        self.prompted = now;
+        self.misses = 0;
+    }
+
+    /// End the round, adding it to the high scores if it scored
+    pub fn finish(&mut self, stats: &mut Stats, day: u64) {
+        if self.result.is_some() {
+            return;
+        }
+        let round = HighScore {
+            score: self.score,
+            hits: self.hits,
+            accuracy: self.accuracy(),
+            day,
+            reverse: self.reverse,
+        };
+        // A round without a single binding isn't worth a place
+        let rank = if round.score > 0 { stats.record_score(round) } else { None };
+        self.result = Some((round, rank));
+    }
+}
+
+/// Commands worth asking for: typeable without the round's own keys, not hidden, and with a
+/// description no other command shares, so each prompt has one answer
+fn pool(
+    commands: &[Command],
+    frames: &[Vec<KeyFrame>],
+    state: &UserState,
+) -> Vec<(usize, Vec<KeyFrame>)> {
+    let mut descriptions: HashMap<&str, usize> = HashMap::new();
+    for cmd in commands {
+        *descriptions.entry(cmd.description.as_str()).or_default() += 1;
+    }
+    commands
+        .iter()
+        .enumerate()
+        .filter(|(_, cmd)| {
+            descriptions[cmd.description.as_str()] == 1 && !cmd.is_ex() && !state.is_hidden(cmd)
+        })
+        .map(|(i, _)| (i, frames[i].clone()))
+        .filter(|(_, frames)| {
+            typing::is_typeable(frames)
+                && !frames
+                    .iter()
+                    .flat_map(|frame| &frame.keys)
+                    .any(|key| RESERVED_KEYS.contains(&key.key.as_str()))
+        })
+        .collect()
+}
+
+/// A random prompt, each as likely as its weight says, other than `current` when there's a
+/// choice
+fn next_prompt(weights: &[u32], rng: &mut Rng, current: Option<usize>) -> Option<usize> {
+    let mut weights = weights.to_vec();
+    if let Some(current) = current.filter(|_| weights.len() > 1) {
+        weights[current] = 0;
+    }
+    rng.weighted(&weights)
+}
+
+/// The answer to the prompt at `current` and up to three other commands from `pool`, in a
+/// random order
+fn choices(pool: &[(usize, Vec<KeyFrame>)], current: usize, rng: &mut Rng) -> Vec<usize> {
+    let mut weights: Vec<u32> = (0..pool.len()).map(|i| u32::from(i != current)).collect();
+    let mut choices = Vec::new();
+    while choices.len() + 1 < CHOICES {
+        let Some(other) = rng.weighted(&weights) else {
+            break;
+        };
+        weights[other] = 0;
+        choices.push(pool[other].0);
+    }
+    let at = rng.weighted(&vec![1; choices.len() + 1]).unwrap_or(0);
+    choices.insert(at, pool[current].0);
+    choices
+}
+
+/// Points for a binding: 50, up to 50 more for typing it within 10 seconds, and 10 less for
+/// each wrong key, but never below 10
+fn points(took: Duration, misses: u32) -> u32 {
+    let speed = 50u32.saturating_sub((took.as_millis() / 200) as u32);
+    (50 + speed).saturating_sub(misses * 10).max(10)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{self, Category, Leaders, Mode};
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    fn command(keys: &str, description: &str) -> Command {
+        Command {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            category: Category::General,
+            mode: Mode::Normal,
+            ..Default::default()
+        }
+    }
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_pool() {
+        let commands = [
+            command("gd", "Goto definition"),
+            command("<Esc>", "Clear search"),
+            command("gr", "References"),
+            command("<leader>cr", "Rename"),
+            command("<leader>cR", "Rename"),
+            command("K", "Hover"),
+        ];
+        let mut state = UserState::default();
+        state.toggle_hidden(&commands[5]);
+        let frames = commands::parse_frames(&commands, &Leaders::default());
+        let indices: Vec<usize> = pool(&commands, &frames, &state)
+            .iter()
+            .map(|(i, _)| *i)
+            .collect();
+        assert_eq!(indices, [0, 2]);
+    }
+
+    #[test]
+    fn test_points() {
+        assert_eq!(points(Duration::ZERO, 0), 100);
+        assert_eq!(points(Duration::from_secs(5), 1), 65);
+        assert_eq!(points(Duration::from_secs(30), 0), 50);
+        assert_eq!(points(Duration::from_secs(30), 9), 10);
+    }
+
+    #[test]
+    fn test_round() {
+        let commands = [command("gd", "Goto definition"), command("gr", "References")];
+        let mut rng = Rng::new(3);
+        let start = Instant::now();
+        let state = UserState::default();
+        let frames = commands::parse_frames(&commands, &Leaders::default());
+        let mut challenge =
+            Challenge::new(&commands, &frames, &state, false, &mut rng, start, 0).unwrap();
+
+        let first = challenge.command();
+        let second = if commands[first].keys == "gd" { 'd' } else { 'r' };
+        let at = start + Duration::from_secs(2);
+        for c in ['g', 'x', 'g', second] {
+            challenge.press(key(c), &mut rng, at);
+        }
+        // 50 + 40 for two seconds, less 10 for the wrong key
+        assert_eq!(challenge.score, 80);
+        assert_eq!(challenge.last, Some((first, Outcome::Scored(80))));
+        assert_eq!(challenge.accuracy(), 75);
+        // Never the same prompt twice in a row when there's another
+        assert_ne!(challenge.command(), first);
+
+        let prompt = challenge.command();
+        challenge.skip(&mut rng, at);
+        assert_eq!(challenge.last, Some((prompt, Outcome::Skipped)));
+        // Neither was got right without a miss, so both go back for review
+        assert_eq!(challenge.answered, [(first, false), (prompt, false)]);
+
+        // Keys after time is up don't count
+        let late = start + ROUND;
+        assert!(challenge.time_left(late).is_zero());
+        challenge.press(key('g'), &mut rng, late);
+        assert_eq!(challenge.typing, Typing::default());
+
+        let mut stats = Stats::default();
+        challenge.finish(&mut stats, 5);
+        challenge.finish(&mut stats, 5);
+        assert_eq!(challenge.result.unwrap().1, Some(0));
+        assert_eq!(stats.high_scores.len(), 1);
+        assert_eq!(stats.high_scores[0].hits, 1);
+    }
+
+    #[test]
+    fn test_due_reviews_come_up_most() {
+        let commands = [
+            command("gd", "Goto definition"),
+            command("gr", "References"),
+            command("K", "Hover"),
+        ];
+        let frames = commands::parse_frames(&commands, &Leaders::default());
+        let mut state = UserState::default();
+        // gd was known yesterday and isn't due again for days, gr was missed
+        state.stats.record_review(commands[0].id(), false, true, 9);
+        state.stats.record_review(commands[0].id(), false, true, 9);
+        state.stats.record_review(commands[1].id(), false, false, 9);
+        // In reverse, gd was never asked for and the others were known today
+        state.stats.record_review(commands[1].id(), true, true, 10);
+        state.stats.record_review(commands[2].id(), true, true, 10);
+
+        let (mut rng, start) = (Rng::new(1), Instant::now());
+        let mut counts = [0; 3];
+        for _ in 0..300 {
+            let challenge =
+                Challenge::new(&commands, &frames, &state, false, &mut rng, start, 10).unwrap();
+            counts[challenge.command()] += 1;
+        }
+        assert!(counts[1] > counts[2] && counts[2] > counts[0], "{:?}", counts);
+
+        let mut counts = [0; 3];
+        for _ in 0..300 {
+            let challenge =
+                Challenge::new(&commands, &frames, &state, true, &mut rng, start, 10).unwrap();
+            counts[challenge.command()] += 1;
+        }
+        assert!(counts[0] > counts[1] && counts[0] > counts[2], "{:?}", counts);
+    }
+
+    #[test]
+    fn test_reverse_round() {
+        let commands = [
+            command("gd", "Goto definition"),
+            command("gr", "References"),
+            command("gI", "Goto implementation"),
+            command("gy", "Goto type definition"),
+            command("K", "Hover"),
+        ];
+        let mut rng = Rng::new(7);
+        let start = Instant::now();
+        let state = UserState::default();
+        let frames = commands::parse_frames(&commands, &Leaders::default());
+        let single = commands::parse_frames(&commands[..1], &Leaders::default());
+        let lone = Challenge::new(&commands[..1], &single, &state, true, &mut rng, start, 0);
+        assert!(lone.is_none());
+        let mut challenge =
+            Challenge::new(&commands, &frames, &state, true, &mut rng, start, 0).unwrap();
+
+        let first = challenge.command();
+        assert_eq!(challenge.choices.len(), CHOICES);
+        assert!(challenge.choices.contains(&first));
+        let right = challenge.choices.iter().position(|&i| i == first).unwrap();
+        let wrong = (right + 1) % CHOICES;
+
+        let at = start + Duration::from_secs(2);
+        // A wrong pick counts once, however often it's picked
+        challenge.answer(wrong, &mut rng, at);
+        challenge.answer(wrong, &mut rng, at);
+        assert_eq!(challenge.wrong, [challenge.choices[wrong]]);
+        // A choice that isn't there does nothing
+        challenge.answer(CHOICES, &mut rng, at);
+        challenge.answer(right, &mut rng, at);
+        assert_eq!(challenge.last, Some((first, Outcome::Scored(80))));
+        assert_eq!(challenge.accuracy(), 50);
+        assert!(challenge.wrong.is_empty());
+        assert!(challenge.choices.contains(&challenge.command()));
+    }
+}
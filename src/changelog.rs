@@ -0,0 +1,324 @@
+use crate::commands::{data_dir, Category, Command, CommandId, Mode};
+use crate::compare::Change;
+use crate::input::TextInput;
+use crate::popup::centered_rect;
+use crate::state::{read_json, write_json};
+use crate::stats;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Dataset updates kept in the changelog; older ones are dropped
+const RELEASES_KEPT: usize = 20;
+
+/// One binding a dataset update added, changed or removed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Update {
+    pub change: Change,
+    pub category: Category,
+    #[serde(default)]
+    pub mode: Mode,
+    pub keys: String,
+    /// What it did before the update, unless it's new
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub before: Option<String>,
+    /// What it does after the update, unless it's gone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub after: Option<String>,
+}
+
+impl Update {
+    /// Whether the keys, category or either description contain `query`, ignoring case
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        [Some(&self.keys), self.before.as_ref(), self.after.as_ref()]
+            .into_iter()
+            .flatten()
+            .chain(std::iter::once(&self.category.as_str().to_string()))
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+/// The bindings one update of the stock keymaps changed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Release {
+    /// When the update was first seen (days since the Unix epoch, UTC)
+    pub day: u64,
+    /// The lvcheat version that saw it
+    pub version: String,
+    pub updates: Vec<Update>,
+}
+
+/// Changes to the stock keymaps between sessions, from `lvcheat update` or a new release,
+/// kept in the data directory (`changelog.json`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Changelog {
+    /// The stock keymaps as of the last session
+    seen: Vec<Command>,
+    /// Newest first
+    pub releases: Vec<Release>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Changelog {
+    /// Load the changelog from the data directory; a broken file starts a new one
+    pub fn load() -> Self {
+        let Some(path) = data_dir().map(|dir| dir.join("changelog.json")) else {
+            return Self::default();
+        };
+        Self::load_from(&path).unwrap_or_else(|_| Self {
+            path: Some(path),
+            ..Self::default()
+        })
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let mut changelog: Self = read_json(path)?;
+        changelog.path = Some(path.to_path_buf());
+        Ok(changelog)
+    }
+
+    /// Write the changelog back to where it was loaded from; a no-op for an in-memory one
+    pub fn save(&self) -> anyhow::Result<()> {
+        match &self.path {
+            Some(path) => write_json(path, self),
+            None => Ok(()),
+        }
+    }
+
+    /// Note how `stock` differs from the keymaps seen last, as a release on `day`. Returns
+    /// how many bindings changed; the first time, `stock` is only remembered.
+    pub fn record(&mut self, stock: &[Command], day: u64) -> usize {
+        if self.seen.is_empty() {
+            self.seen = stock.to_vec();
+            return 0;
+        }
+        let updates = diff(&self.seen, stock);
+        if updates.is_empty() {
+            return 0;
+        }
+        let count = updates.len();
+        self.seen = stock.to_vec();
+        self.releases.insert(
+            0,
+            Release {
+                day,
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                updates,
+            },
+        );
+        self.releases.truncate(RELEASES_KEPT);
+        count
+    }
+
+    /// [`Changelog::record`], saving the changelog if anything was new to it
+    pub fn check(&mut self, stock: &[Command]) -> anyhow::Result<usize> {
+        let first = self.seen.is_empty();
+        let count = self.record(stock, stats::today());
+        if first || count > 0 {
+            self.save()?;
+        }
+        Ok(count)
+    }
+}
+
+/// The bindings added, changed and removed going from `before` to `after`, keyed on mode and
+/// keys and grouped by category
+pub fn diff(before: &[Command], after: &[Command]) -> Vec<Update> {
+    let (old, new) = (by_id(before), by_id(after));
+
+    // A binding listed twice counts once, as its first entry
+    let first =
+        |map: &HashMap<CommandId, &Command>, cmd: &Command| std::ptr::eq(map[&cmd.id()], cmd);
+    let mut updates = Vec::new();
+    for cmd in after.iter().filter(|cmd| first(&new, cmd)) {
+        let (change, before) = match old.get(&cmd.id()) {
+            None => (Change::Added, None),
+            Some(old_cmd) if old_cmd.description.trim() == cmd.description.trim() => continue,
+            Some(old_cmd) => (Change::Changed, Some(old_cmd.description.clone())),
+        };
+        updates.push(Update {
+            change,
+            category: cmd.category.clone(),
+            mode: cmd.mode,
+            keys: cmd.keys.clone(),
+            before,
+            after: Some(cmd.description.clone()),
+        });
+    }
+    for cmd in before.iter().filter(|cmd| first(&old, cmd) && !new.contains_key(&cmd.id())) {
+        updates.push(Update {
+            change: Change::Removed,
+            category: cmd.category.clone(),
+            mode: cmd.mode,
+            keys: cmd.keys.clone(),
+            before: Some(cmd.description.clone()),
+            after: None,
+        });
+    }
+
+    let order = Category::in_order(updates.iter().map(|u| &u.category));
+    let rank = |category: &Category| order.iter().position(|c| c == category);
+    updates.sort_by_key(|u| (rank(&u.category), u.change as u8));
+    updates
+}
+
+/// Each command by its binding, the first of those listed twice
+fn by_id(commands: &[Command]) -> HashMap<CommandId, &Command> {
+    let mut map = HashMap::new();
+    for cmd in commands {
+        map.entry(cmd.id()).or_insert(cmd);
+    }
+    map
+}
+
+/// The releases newest first, each under a heading with its updates matching `query`
+pub fn lines(releases: &[Release], query: &str) -> Vec<Line<'static>> {
+    let dim = Style::default().fg(Color::DarkGray);
+    let bold = Style::default().add_modifier(Modifier::BOLD);
+
+    let mut lines = Vec::new();
+    for release in releases {
+        let matching: Vec<&Update> =
+            release.updates.iter().filter(|u| u.matches(query)).collect();
+        if matching.is_empty() {
+            continue;
+        }
+        let (month, day) = stats::month_day(release.day);
+        lines.push(Line::from(vec![
+            Span::styled(format!("{:02}-{:02}", month, day), bold),
+            Span::styled(
+                format!(" · lvcheat {} · {} changed", release.version, release.updates.len()),
+                dim,
+            ),
+        ]));
+        for update in matching {
+            let color = match update.change {
+                Change::Added => Color::Green,
+                Change::Changed => Color::Yellow,
+                Change::Removed => Color::Red,
+            };
+            let what = match (&update.before, &update.after) {
+                (Some(before), Some(after)) => format!("{} → {}", before, after),
+                (Some(description), None) | (None, Some(description)) => description.clone(),
+                (None, None) => String::new(),
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("  {} ", update.change.sign()), Style::default().fg(color)),
+                Span::styled(format!("{:<16} ", update.keys), Style::default().fg(Color::Cyan)),
+                Span::styled(format!("{} ", update.mode.short()), dim),
+                Span::raw(what),
+                Span::styled(format!("  {}", update.category.as_str()), dim),
+            ]));
+        }
+        lines.push(Line::from(""));
+    }
+    if lines.is_empty() {
+        let empty = if releases.is_empty() {
+            "No changes to the builtin keymaps yet. New ones are listed here after \
+             `lvcheat update` or an upgrade."
+        } else {
+            "No changes match."
+        };
+        lines.push(Line::from(empty));
+    }
+    lines
+}
+
+/// The changelog's [`lines`] scrolled down `scroll`, under the search box
+pub fn draw_whats_new(frame: &mut Frame, releases: &[Release], query: &TextInput, scroll: usize) {
+    let area = centered_rect(90, 24, frame.area());
+    let dim = Style::default().fg(Color::DarkGray);
+    let lines = lines(releases, query.as_str());
+    let height = area.height.saturating_sub(5) as usize;
+    let mut lines: Vec<Line> = lines.into_iter().skip(scroll).take(height).collect();
+    lines.resize(height, Line::from(""));
+    lines.insert(0, Line::from(""));
+    lines.insert(0, Line::from(vec![Span::styled("Search: ", dim), Span::raw(query.as_str())]));
+    lines.push(Line::from(Span::styled("type to filter · ↑↓: scroll · Esc/F9: close", dim)));
+
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Green))
+            .title(" What's new in the builtin keymaps "),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+
+    let (before, _) = query.split();
+    let cursor_x = area.x + 9 + Span::raw(before).width() as u16;
+    frame.set_cursor_position((cursor_x.min(area.right() - 2), area.y + 1));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(keys: &str, description: &str, category: Category) -> Command {
+        Command {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            category,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diff() {
+        let before = vec![
+            command("<leader>ff", "Find files", Category::Search),
+            command("<leader>sg", "Grep", Category::Search),
+            command("<leader>gG", "Lazygit (cwd)", Category::Git),
+        ];
+        let after = vec![
+            command("<leader>ff", "Find files", Category::Search),
+            command("<leader>sg", "Grep (root dir)", Category::Search),
+            command("<leader>gg", "Lazygit", Category::Git),
+        ];
+        let updates = diff(&before, &after);
+        let summary: Vec<(Change, &str)> =
+            updates.iter().map(|u| (u.change, u.keys.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (Change::Changed, "<leader>sg"),
+                (Change::Added, "<leader>gg"),
+                (Change::Removed, "<leader>gG"),
+            ]
+        );
+        assert_eq!(updates[0].before.as_deref(), Some("Grep"));
+        assert_eq!(updates[0].after.as_deref(), Some("Grep (root dir)"));
+        assert!(updates[1].matches("LAZYGIT"));
+        assert!(updates[1].matches("git"));
+        assert!(!updates[1].matches("grep"));
+    }
+
+    #[test]
+    fn test_record() {
+        let mut changelog = Changelog::default();
+        let first = vec![command("<leader>ff", "Find files", Category::Search)];
+        assert_eq!(changelog.record(&first, 100), 0);
+        assert!(changelog.releases.is_empty());
+        assert_eq!(changelog.record(&first, 101), 0);
+        assert!(changelog.releases.is_empty());
+
+        let second = vec![
+            command("<leader>ff", "Find files", Category::Search),
+            command("<leader>fF", "Find files (cwd)", Category::Search),
+        ];
+        assert_eq!(changelog.record(&second, 102), 1);
+        assert_eq!(changelog.record(&first, 103), 1);
+        let days: Vec<u64> = changelog.releases.iter().map(|r| r.day).collect();
+        assert_eq!(days, [103, 102]);
+        assert_eq!(changelog.releases[0].updates[0].change, Change::Removed);
+    }
+}
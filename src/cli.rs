@@ -0,0 +1,236 @@
+use crate::commands::Category;
+use clap::{ArgGroup, Parser, Subcommand};
+use clap_complete::Shell;
+use std::path::PathBuf;
+
+/// Interactive TUI tool for LazyVim keyboard shortcuts visualization
+#[derive(Debug, Parser)]
+#[command(name = "lvcheat", version, about)]
+pub struct Cli {
+    /// Show every key of a sequence at once instead of animating it
+    #[arg(long)]
+    pub no_animation: bool,
+    /// Spell sequences out as text instead of drawing the keyboard, for screen readers
+    #[arg(long)]
+    pub plain: bool,
+    /// Draw without color, using bold, underline and reverse video (also set by `NO_COLOR`)
+    #[arg(long)]
+    pub no_color: bool,
+    /// Use the settings under `[profiles.NAME]` in `config.toml` over the rest of the file
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+    /// Start with an empty search instead of where the last session left off
+    #[arg(long)]
+    pub fresh: bool,
+    /// Show a random command you haven't learned yet; Space shows another, any other key quits
+    #[arg(long)]
+    pub tip: bool,
+    /// Compact mode for `tmux display-popup`, starting on the search given after it: Enter
+    /// copies the selected keys to tmux's paste buffer and quits
+    #[arg(long, value_name = "QUERY", num_args = 0..)]
+    pub popup: Option<Vec<String>>,
+    /// Start with the command bound to these keys selected, e.g. `--select "<leader>gg"`
+    #[arg(long, value_name = "KEYS")]
+    pub select: Option<String>,
+    /// Start with only this category's commands listed, LazyVim's or a custom one, in any case
+    #[arg(long, value_parser = parse_category)]
+    pub category: Option<Category>,
+    /// Append the legend snapshots taken with Shift+F4 to this file, instead of printing them
+    /// on exit
+    #[arg(long, value_name = "FILE")]
+    pub snapshot_file: Option<PathBuf>,
+    /// Leave the colors out of legend snapshots, for pasting into issues and gists
+    #[arg(long)]
+    pub plain_snapshots: bool,
+    /// List the commands piped in instead of LazyVim's, e.g. `cat mymaps.json | lvcheat --stdin`
+    /// (Unix only, as keys are then read from /dev/tty)
+    #[arg(long)]
+    pub stdin: bool,
+    /// Open a command file in another tab, switched to with Ctrl+Left/Right (repeatable)
+    #[arg(long = "tab", value_name = "FILE", value_parser = parse_absolute)]
+    pub tabs: Vec<PathBuf>,
+    /// List the keymaps of the Neovim listening at this address (`:echo v:servername`, or
+    /// `$NVIM` in its terminal) in a tab that refreshes as they change
+    #[arg(long, value_name = "ADDRESS")]
+    pub nvim: Option<String>,
+    /// Log to `lvcheat.log` in the data directory, and show timings and the selection's parsed
+    /// key frames in an overlay toggled with F12
+    #[arg(long)]
+    pub debug: bool,
+    /// Print how long each step of starting up took, to stderr on exit
+    #[arg(long)]
+    pub profile_startup: bool,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Check a command file for schema and key notation problems
+    Validate {
+        /// Path to the command file
+        file: PathBuf,
+        /// Write keys spelled unusually, like `<c-W>` or `<cr>`, back as `<C-w>` and `<CR>`
+        #[arg(long)]
+        fix: bool,
+    },
+    /// Print the commands matching a query, best first
+    Search {
+        /// Search text, with the same `#tag`, `fav:` and `hidden:` tokens as the search box
+        #[arg(required = true)]
+        query: Vec<String>,
+        /// Print JSON with scores and parsed key frames instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the commands bound to exactly these keys
+    Show {
+        /// Key sequence in Vim notation, e.g. `<leader>ff`
+        keys: String,
+        /// Print a framed card with the keyboard, each step of the sequence numbered on its keys
+        #[arg(long)]
+        card: bool,
+    },
+    /// Play the keyboard animation for a key sequence in place, for screencasts and demos
+    Play {
+        /// Key sequence in Vim notation, e.g. `<C-w>v`
+        keys: String,
+        /// How many times to play it
+        #[arg(short = 'n', long, default_value_t = 3)]
+        loops: u32,
+    },
+    /// Answer search queries over a unix socket, one JSON object per line
+    Serve {
+        /// Socket path (default: `$XDG_RUNTIME_DIR/lvcheat.sock`)
+        #[arg(long)]
+        socket: Option<PathBuf>,
+    },
+    /// Print every command
+    List {
+        /// Only list commands in this category, LazyVim's or a custom one, in any case
+        #[arg(long, value_parser = parse_category)]
+        category: Option<Category>,
+        /// Print JSON with parsed key frames instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Convert keymaps from another tool into a command file, printed to stdout
+    #[command(group(ArgGroup::new("source").required(true)))]
+    Import {
+        /// JSON dump written by `contrib/which-key-dump.lua`
+        #[arg(long, value_name = "FILE", group = "source")]
+        which_key: Option<PathBuf>,
+        /// Saved output of Neovim's `:verbose map`
+        #[arg(long, value_name = "FILE", group = "source")]
+        vim_maps: Option<PathBuf>,
+    },
+    /// List the bindings a keymap file adds, changes or leaves out compared to stock LazyVim,
+    /// by category
+    Compare {
+        /// Command file with a full keymap set, e.g. written by `lvcheat import`
+        file: PathBuf,
+        /// Print JSON instead of text
+        #[arg(long)]
+        json: bool,
+    },
+    /// Download the latest keymap data, used instead of the built-in copy from then on
+    Update {
+        /// Where to download the data from (default: the repository's `data/commands.json`)
+        #[arg(long)]
+        url: Option<String>,
+        /// Delete the downloaded data and go back to the built-in copy
+        #[arg(long, conflicts_with = "url")]
+        reset: bool,
+    },
+    /// Print the cheatsheet in another tool's format
+    Export {
+        #[command(subcommand)]
+        format: ExportFormat,
+    },
+    /// Walk through the essential bindings one at a time, with a mnemonic for each
+    Tutorial {
+        /// Type each binding before moving on to the next
+        #[arg(long)]
+        practice: bool,
+    },
+    /// Type as many bindings as you can in 60 seconds from their descriptions, for a high score
+    Challenge {
+        /// Play bindings on the keyboard and pick what they do instead
+        #[arg(long)]
+        reverse: bool,
+    },
+    /// Replay the steps of a TOML script in the TUI at a steady pace, for screencasts and
+    /// end-to-end checks
+    Demo {
+        /// Script with a `[[step]]` table per action: `type`, `key`, `select`, `view` or
+        /// `wait_ms`
+        script: PathBuf,
+    },
+    /// Print a completion script for a shell, e.g. `lvcheat completions zsh > _lvcheat`
+    Completions {
+        shell: Shell,
+    },
+    /// Print the man page in roff, e.g. `lvcheat manpage > lvcheat.1`
+    Manpage,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ExportFormat {
+    /// Lua spec for `require("which-key").add()`
+    WhichKey,
+    /// asciinema recording of the keyboard animation for a key sequence
+    Cast {
+        /// Key sequence in Vim notation, e.g. `<leader>ff`
+        keys: String,
+        /// Write the recording here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Anki notes for the search results (every command without a query), one card each
+    Anki {
+        /// Only export commands matching this, including filters like `#git` or `fav:`
+        query: Vec<String>,
+        /// Write the deck here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// SVG keyboard diagram for a key sequence, with every frame in its legend color
+    Svg {
+        /// Key sequence in Vim notation, e.g. `<leader>ff`
+        keys: String,
+        /// Write the image here instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Write one image per frame instead, named after the output with `-1`, `-2`, ...
+        #[arg(long, requires = "output")]
+        per_frame: bool,
+    },
+}
+
+fn parse_category(name: &str) -> Result<Category, String> {
+    Ok(Category::named(name))
+}
+
+/// A path given on the command line, taken from the current directory rather than from the
+/// config directory like the config's paths
+fn parse_absolute(path: &str) -> Result<PathBuf, String> {
+    std::path::absolute(path).map_err(|error| error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::error::ErrorKind;
+
+    #[test]
+    fn test_parse() {
+        let cli = Cli::try_parse_from(["lvcheat", "--popup", "find", "files"]).unwrap();
+        assert_eq!(cli.popup.unwrap(), ["find", "files"]);
+        let cli = Cli::try_parse_from(["lvcheat", "--popup"]).unwrap();
+        assert_eq!(cli.popup.unwrap(), Vec::<String>::new());
+        assert!(Cli::try_parse_from(["lvcheat"]).unwrap().popup.is_none());
+
+        let error = Cli::try_parse_from(["lvcheat", "lsit"]).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidSubcommand);
+    }
+}
@@ -1,221 +1,1794 @@
+//! Command data, its on-disk formats, and the Vim key-notation parser.
+
+use crate::keynotation;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+
+/// One cheatsheet entry: a keymap or ex command and what it does
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Command {
+    pub keys: String,
+    pub description: String,
+    pub category: Category,
+    #[serde(default)]
+    pub mode: Mode,
+    /// Free-form labels finer than categories (e.g. `picker`, `motions`, `beginner`)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    /// How to remember the keys, e.g. `sf = Search Files`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mnemonic: Option<String>,
+    /// Keys of related commands, e.g. `<leader>fr` next to `<leader>ff`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub see_also: Vec<String>,
+    /// Other keys doing the same, e.g. `[b` next to `<S-h>`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alternatives: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub diagram: Option<Diagram>,
+    /// What the plugin behind the command says it does, for plugin-backed bindings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doc: Option<Doc>,
+    /// The separate commands of a workflow, in order, with `keys` all of them as typed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub workflow: Vec<WorkflowStep>,
+    /// Where the command was loaded from; set by the loader, never read from a file
+    #[serde(skip)]
+    pub source: Source,
+    /// Set on a user entry kept beside a builtin one with the same keys and mode, so that its
+    /// id names its source and the two are marked and favorited apart
+    #[serde(skip)]
+    pub beside: bool,
+}
+
+/// Where a command was loaded from, shown as a badge and picked with `source:` tokens
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub enum Source {
+    /// The keymap snapshot compiled in, and the ex commands
+    #[default]
+    Builtin,
+    /// Keymap data fetched by `lvcheat update`
+    Downloaded,
+    /// The user's command file
+    User,
+    /// Another command file, e.g. one opened in a tab, by its path from the config directory
+    /// or in full when it's elsewhere
+    File(String),
+}
+
+impl Source {
+    pub fn label(&self) -> &str {
+        match self {
+            Source::Builtin => "builtin",
+            Source::Downloaded => "downloaded",
+            Source::User => "user",
+            Source::File(name) => name,
+        }
+    }
+
+    /// Whether `source:name` picks this source. Another file also answers to its file name,
+    /// with or without the extension, and to `file`.
+    pub fn is_named(&self, name: &str) -> bool {
+        if self.label().eq_ignore_ascii_case(name) {
+            return true;
+        }
+        let Source::File(path) = self else {
+            return false;
+        };
+        let path = Path::new(path);
+        name.eq_ignore_ascii_case("file")
+            || [path.file_name(), path.file_stem()]
+                .into_iter()
+                .flatten()
+                .any(|file| file.eq_ignore_ascii_case(name))
+    }
+}
+
+impl Serialize for Source {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.label())
+    }
+}
+
+/// A short excerpt of a plugin's documentation, kept with the command so it reads offline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Doc {
+    /// The plugin it's quoted from, e.g. `flash.nvim`
+    pub plugin: String,
+    pub text: String,
+}
+
+/// One command of a workflow, e.g. staging a file once lazygit is open
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkflowStep {
+    pub keys: String,
+    pub description: String,
+    /// The program the keys go to when it isn't Neovim, e.g. `lazygit`; such steps don't lead
+    /// to a command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub app: Option<String>,
+}
+
+/// Sample text showing the region a motion or text object covers
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Diagram {
+    pub text: String,
+    /// Char index of the cursor before the keys are pressed
+    pub cursor: usize,
+    /// Char range covered, end exclusive
+    pub start: usize,
+    pub end: usize,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub caption: Option<String>,
+}
+
+impl Diagram {
+    /// Check that the cursor and range fall inside the text
+    pub fn check(&self) -> Result<(), String> {
+        let len = self.text.chars().count();
+        if self.cursor >= len {
+            return Err(format!("cursor {} is past the end of the text", self.cursor));
+        }
+        if self.start > self.end || self.end > len {
+            return Err(format!(
+                "range {}..{} doesn't fit a text of {} chars",
+                self.start, self.end, len
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Identity of a command across sessions and data sources
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CommandId {
+    pub keys: String,
+    #[serde(default)]
+    pub mode: Mode,
+    /// The source's label, for an entry kept beside another with the same keys and mode
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+/// Vim mode a keymap applies in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mode {
+    #[default]
+    Normal,
+    Insert,
+    Visual,
+    Command,
+    /// In a terminal buffer, while typing to the shell
+    Terminal,
+    /// After an operator such as `d` or `y`, where motions and text objects go
+    OperatorPending,
+    /// Select mode, as snippet placeholders use: typing replaces the selection
+    Select,
+}
+
+impl Mode {
+    pub const ALL: &'static [Mode] = &[
+        Mode::Normal,
+        Mode::Insert,
+        Mode::Visual,
+        Mode::Command,
+        Mode::Terminal,
+        Mode::OperatorPending,
+        Mode::Select,
+    ];
+
+    /// The mode called `name` or its letter, in any case
+    pub fn named(name: &str) -> Option<Mode> {
+        Self::ALL.iter().copied().find(|mode| {
+            mode.as_str().eq_ignore_ascii_case(name) || mode.short().eq_ignore_ascii_case(name)
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Mode::Normal => "normal",
+            Mode::Insert => "insert",
+            Mode::Visual => "visual",
+            Mode::Command => "command",
+            Mode::Terminal => "terminal",
+            Mode::OperatorPending => "operator-pending",
+            Mode::Select => "select",
+        }
+    }
+    /// Single-letter label for compact mode chips
+    pub fn short(&self) -> &'static str {
+        match self {
+            Mode::Normal => "N",
+            Mode::Insert => "I",
+            Mode::Visual => "V",
+            Mode::Command => "C",
+            Mode::Terminal => "T",
+            Mode::OperatorPending => "O",
+            Mode::Select => "S",
+        }
+    }
+
+    pub fn color(&self) -> Hue {
+        match self {
+            Mode::Normal => Hue::Blue,
+            Mode::Insert => Hue::Green,
+            Mode::Visual => Hue::Magenta,
+            Mode::Command => Hue::Yellow,
+            Mode::Terminal => Hue::Cyan,
+            Mode::OperatorPending => Hue::Red,
+            Mode::Select => Hue::LightMagenta,
+        }
+    }
+}
+
+/// Section of the cheatsheet a command is listed under: one of LazyVim's, or any other name a
+/// command file gives
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Category {
+    #[default]
+    General,
+    Navigation,
+    Search,
+    Lsp,
+    Git,
+    Buffer,
+    Window,
+    Tab,
+    Code,
+    Debug,
+    Terminal,
+    Ui,
+    Plugin,
+    /// Workflows of several commands for common tasks
+    Recipes,
+    /// A category of the user's own, listed after the builtin ones
+    Custom(String),
+}
+
+/// A terminal color as the data and the config name it, drawn in the UI's own colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hue {
+    Black,
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+    DarkGray,
+    LightRed,
+    LightGreen,
+    LightYellow,
+    LightBlue,
+    LightMagenta,
+    LightCyan,
+    White,
+    /// A color of the 256-color palette
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// A name like `red` or `light blue`, an index like `208`, or `#rrggbb`
+impl FromStr for Hue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex) = s.strip_prefix('#').filter(|hex| hex.len() == 6) {
+            let channel = |i: usize| hex.get(i..i + 2).and_then(|c| u8::from_str_radix(c, 16).ok());
+            if let (Some(r), Some(g), Some(b)) = (channel(0), channel(2), channel(4)) {
+                return Ok(Hue::Rgb(r, g, b));
+            }
+        }
+        if let Ok(index) = s.parse() {
+            return Ok(Hue::Indexed(index));
+        }
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, ' ' | '-' | '_'))
+            .collect::<String>()
+            .to_lowercase()
+            .replace("bright", "light")
+            .replace("grey", "gray");
+        Ok(match name.as_str() {
+            "black" => Hue::Black,
+            "red" => Hue::Red,
+            "green" => Hue::Green,
+            "yellow" => Hue::Yellow,
+            "blue" => Hue::Blue,
+            "magenta" => Hue::Magenta,
+            "cyan" => Hue::Cyan,
+            "gray" => Hue::Gray,
+            "darkgray" => Hue::DarkGray,
+            "lightred" => Hue::LightRed,
+            "lightgreen" => Hue::LightGreen,
+            "lightyellow" => Hue::LightYellow,
+            "lightblue" => Hue::LightBlue,
+            "lightmagenta" => Hue::LightMagenta,
+            "lightcyan" => Hue::LightCyan,
+            "white" => Hue::White,
+            _ => return Err(format!("no such color as {:?}", s)),
+        })
+    }
+}
+
+/// The xterm orange of the Recipes category, a hue no other builtin category has
+pub const RECIPES_ORANGE: u8 = 208;
+
+/// Colors custom categories are spread over, by name
+const CUSTOM_COLORS: &[Hue] = &[
+    Hue::LightBlue,
+    Hue::LightYellow,
+    Hue::LightMagenta,
+    Hue::LightCyan,
+    Hue::LightGreen,
+    Hue::LightRed,
+];
+
+impl Category {
+    /// The builtin categories, in list order
+    pub const ALL: &'static [Category] = &[
+        Category::General,
+        Category::Navigation,
+        Category::Search,
+        Category::Lsp,
+        Category::Git,
+        Category::Buffer,
+        Category::Window,
+        Category::Tab,
+        Category::Code,
+        Category::Debug,
+        Category::Terminal,
+        Category::Ui,
+        Category::Plugin,
+        Category::Recipes,
+    ];
+
+    /// The builtin category called `name` in any case, else a custom one
+    pub fn named(name: &str) -> Self {
+        let name = name.trim();
+        Self::ALL
+            .iter()
+            .find(|category| category.as_str().eq_ignore_ascii_case(name))
+            .cloned()
+            .unwrap_or_else(|| Category::Custom(name.to_string()))
+    }
+
+    /// The builtin categories followed by the custom ones among `used`, sorted and once each
+    pub fn in_order<'a>(used: impl IntoIterator<Item = &'a Category>) -> Vec<Category> {
+        let mut custom: Vec<Category> = used
+            .into_iter()
+            .filter(|category| matches!(category, Category::Custom(_)))
+            .cloned()
+            .collect();
+        custom.sort();
+        custom.dedup();
+        Self::ALL.iter().cloned().chain(custom).collect()
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Category::General => "General",
+            Category::Navigation => "Navigation",
+            Category::Search => "Search",
+            Category::Lsp => "LSP",
+            Category::Git => "Git",
+            Category::Buffer => "Buffer",
+            Category::Window => "Window",
+            Category::Tab => "Tab",
+            Category::Code => "Code",
+            Category::Debug => "Debug",
+            Category::Terminal => "Terminal",
+            Category::Ui => "UI",
+            Category::Plugin => "Plugin",
+            Category::Recipes => "Recipes",
+            Category::Custom(name) => name,
+        }
+    }
+
+    /// Stable color used for this category across the UI
+    pub fn color(&self) -> Hue {
+        match self {
+            Category::General => Hue::White,
+            Category::Navigation => Hue::Blue,
+            Category::Search => Hue::Yellow,
+            Category::Lsp => Hue::Magenta,
+            Category::Git => Hue::LightRed,
+            Category::Buffer => Hue::Cyan,
+            Category::Window => Hue::LightBlue,
+            Category::Tab => Hue::LightCyan,
+            Category::Code => Hue::Green,
+            Category::Debug => Hue::Red,
+            Category::Terminal => Hue::LightGreen,
+            Category::Ui => Hue::LightMagenta,
+            Category::Plugin => Hue::LightYellow,
+            Category::Recipes => Hue::Indexed(RECIPES_ORANGE),
+            Category::Custom(name) => {
+                let sum = name.bytes().map(usize::from).sum::<usize>();
+                CUSTOM_COLORS[sum % CUSTOM_COLORS.len()]
+            }
+        }
+    }
+
+    /// Nerd-font glyph for this category
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Category::General => "\u{f013}",
+            Category::Navigation => "\u{f14e}",
+            Category::Search => "\u{f002}",
+            Category::Lsp => "\u{f0eb}",
+            Category::Git => "\u{e702}",
+            Category::Buffer => "\u{f15b}",
+            Category::Window => "\u{f2d0}",
+            Category::Tab => "\u{f1dd}",
+            Category::Code => "\u{f121}",
+            Category::Debug => "\u{f188}",
+            Category::Terminal => "\u{f120}",
+            Category::Ui => "\u{f1fc}",
+            Category::Plugin => "\u{f1e6}",
+            Category::Recipes => "\u{f0f5}",
+            Category::Custom(_) => "\u{f02b}",
+        }
+    }
+}
+
+/// How categories are arranged, from the `[categories]` table of the config: the order
+/// they're listed in, names and colors shown instead of the usual ones, and those left out
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CategoryLayout {
+    /// Listed first, in this order; the rest follow in the usual one
+    pub order: Vec<Category>,
+    pub names: HashMap<Category, String>,
+    /// A color name like `red`, an index like `208`, or `#rrggbb`, for each category
+    #[serde(deserialize_with = "deserialize_colors")]
+    pub colors: HashMap<Category, Hue>,
+    /// Left out of the listings, with their commands
+    pub hidden: Vec<Category>,
+}
+
+impl CategoryLayout {
+    /// The name `category` is shown by
+    pub fn name<'a>(&'a self, category: &'a Category) -> &'a str {
+        self.names.get(category).map_or(category.as_str(), String::as_str)
+    }
+
+    pub fn color(&self, category: &Category) -> Hue {
+        self.colors.get(category).copied().unwrap_or_else(|| category.color())
+    }
+
+    pub fn is_hidden(&self, category: &Category) -> bool {
+        self.hidden.contains(category)
+    }
+
+    /// [`Category::in_order`], with the categories ordered here moved first and the hidden
+    /// ones left out
+    pub fn in_order<'a>(&self, used: impl IntoIterator<Item = &'a Category>) -> Vec<Category> {
+        let usual = Category::in_order(used);
+        let ordered = self.order.iter().filter(|category| usual.contains(category));
+        let rest = usual.iter().filter(|category| !self.order.contains(category));
+        let mut order: Vec<Category> = Vec::new();
+        for category in ordered.chain(rest) {
+            if !self.is_hidden(category) && !order.contains(category) {
+                order.push(category.clone());
+            }
+        }
+        order
+    }
+}
+
+fn deserialize_colors<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<HashMap<Category, Hue>, D::Error> {
+    let names = HashMap::<Category, String>::deserialize(deserializer)?;
+    names
+        .into_iter()
+        .map(|(category, name)| {
+            let color = name.parse().map_err(serde::de::Error::custom)?;
+            Ok((category, color))
+        })
+        .collect()
+}
+
+/// Builtin categories are written in lowercase, custom ones as given
+impl Serialize for Category {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Category::Custom(name) => serializer.serialize_str(name),
+            builtin => serializer.serialize_str(&builtin.as_str().to_lowercase()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Category {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(|name| Category::named(&name))
+    }
+}
+
+/// A single key in a keypress
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Key {
+    pub key: String,
+    pub is_modifier: bool,
+    pub is_leader: bool,
+    /// A mouse button or wheel pseudo-key like `ScrollWheelUp`, off the keyboard
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub is_mouse: bool,
+}
+
+/// A frame represents keys pressed simultaneously (e.g., Shift+D)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KeyFrame {
+    pub keys: Vec<Key>,
+    /// Label for frames that aren't a plain keypress, like "count: 3"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+impl KeyFrame {
+    pub fn new(keys: Vec<Key>) -> Self {
+        Self { keys, note: None }
+    }
+
+    pub fn single(key: Key) -> Self {
+        Self::new(vec![key])
+    }
+
+    pub fn annotated(keys: Vec<Key>, note: String) -> Self {
+        Self {
+            keys,
+            note: Some(note),
+        }
+    }
+
+    /// Short text for the frame in a sequence legend, e.g. `Ctrl+W` or `␣`
+    pub fn label(&self) -> String {
+        self.keys
+            .iter()
+            .map(|k| {
+                if k.key == "Space" {
+                    "␣".to_string()
+                } else if k.key.len() > 1 {
+                    k.key.clone()
+                } else {
+                    k.key.to_uppercase()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("+")
+    }
+}
+
+/// The sequence spelled out for screen readers, e.g. "Press Space (leader), then f, then f."
+pub fn describe_sequence(frames: &[KeyFrame]) -> String {
+    let spoken: Vec<String> = frames
+        .iter()
+        .map(|kf| {
+            let names: Vec<String> = kf
+                .keys
+                .iter()
+                .map(|k| {
+                    if k.is_leader {
+                        format!("{} (leader)", k.key)
+                    } else {
+                        k.key.clone()
+                    }
+                })
+                .collect();
+            match (&kf.note, names.as_slice()) {
+                (Some(note), _) => format!("{} ({})", names.join(" "), note),
+                (None, [single]) => single.clone(),
+                (None, _) => format!("{} together", names.join(" and ")),
+            }
+        })
+        .collect();
+    if spoken.is_empty() {
+        return String::new();
+    }
+    format!("Press {}.", spoken.join(", then "))
+}
+
+/// One frame in words, for narrating an animation: "Hold Ctrl, press w", "Press v", or
+/// "Press 3 (count: 3)"
+pub fn narrate_frame(frame: &KeyFrame) -> String {
+    let name = |k: &Key| {
+        if k.is_leader {
+            format!("{} (leader)", k.key)
+        } else {
+            k.key.clone()
+        }
+    };
+    let (held, pressed): (Vec<&Key>, Vec<&Key>) = frame.keys.iter().partition(|k| k.is_modifier);
+    let held: Vec<String> = held.into_iter().map(name).collect();
+    let pressed: Vec<String> = pressed.into_iter().map(name).collect();
+    let mut text = if held.is_empty() || pressed.is_empty() {
+        format!("Press {}", [held, pressed].concat().join(" and "))
+    } else {
+        format!("Hold {}, press {}", held.join(" and "), pressed.join(" and "))
+    };
+    if let Some(note) = &frame.note {
+        text.push_str(&format!(" ({})", note));
+    }
+    text
+}
+
+impl Command {
+    /// Keys and mode, which identify the command in saved state
+    pub fn id(&self) -> CommandId {
+        CommandId {
+            keys: self.keys.clone(),
+            mode: self.mode,
+            source: self.beside.then(|| self.source.label().to_string()),
+        }
+    }
+
+    /// Whether any tag starts with `prefix`, ignoring case
+    pub fn has_tag(&self, prefix: &str) -> bool {
+        let prefix = prefix.to_lowercase();
+        self.tags
+            .iter()
+            .any(|tag| tag.to_lowercase().starts_with(&prefix))
+    }
+
+    /// Indices into `commands` of a workflow's step commands that exist, then of the
+    /// `see_also` entries that do
+    pub fn related(&self, commands: &[Command]) -> Vec<usize> {
+        let see_also = self.see_also.iter().filter_map(|keys| self.find(commands, keys));
+        self.step_commands(commands).into_iter().flatten().chain(see_also).collect()
+    }
+
+    /// Index into `commands` of the command each workflow step runs, if it's listed
+    pub fn step_commands(&self, commands: &[Command]) -> Vec<Option<usize>> {
+        self.workflow
+            .iter()
+            .map(|step| match step.app {
+                Some(_) => None,
+                None => self.find(commands, &step.keys),
+            })
+            .collect()
+    }
+
+    /// Index into `commands` of a command bound to `keys`, preferring one in the same mode
+    /// when the keys are mapped in several
+    fn find(&self, commands: &[Command], keys: &str) -> Option<usize> {
+        let mut matching = commands.iter().enumerate().filter(|(_, c)| c.keys == keys);
+        let first = matching.next()?;
+        let same_mode = std::iter::once(first).chain(matching).find(|(_, c)| c.mode == self.mode);
+        Some(same_mode.unwrap_or(first).0)
+    }
+
+    /// Indices into `commands` of the same keys bound in other modes, in mode order
+    pub fn other_modes(&self, commands: &[Command]) -> Vec<usize> {
+        let mut others: Vec<usize> = (0..commands.len())
+            .filter(|&i| commands[i].keys == self.keys && commands[i].mode != self.mode)
+            .collect();
+        others.sort_by_key(|&i| Mode::ALL.iter().position(|&mode| mode == commands[i].mode));
+        others
+    }
+
+    /// Index into `commands` of the other half of a `]x` / `[x` pair: the same keys in the
+    /// same mode, with the leading brackets turned around (`]]` pairs with `[[`)
+    pub fn twin(&self, commands: &[Command]) -> Option<usize> {
+        let brackets = self.keys.chars().take_while(|c| matches!(c, '[' | ']')).count();
+        if brackets == 0 || self.keys.len() < 2 {
+            return None;
+        }
+        let flipped: String = self.keys[..brackets]
+            .chars()
+            .map(|c| if c == ']' { '[' } else { ']' })
+            .collect();
+        let keys = format!("{}{}", flipped, &self.keys[brackets..]);
+        commands.iter().position(|c| c.keys == keys && c.mode == self.mode)
+    }
+
+    /// Whether this is an ex command typed on the command line, like `:Lazy`
+    pub fn is_ex(&self) -> bool {
+        self.keys.starts_with(':')
+    }
+
+    /// Parse keys into animation frames
+    /// Each frame = keys pressed at the same time
+    /// Example: "gD" -> [Frame{g}, Frame{Shift, d}]
+    /// Example: `"<C-w>v"` -> [Frame{Ctrl, w}, Frame{v}]
+    /// Example: ":Lazy" -> [Frame{Shift, ;}, Frame{Shift, l}, Frame{a}, ..., Frame{Enter}]
+    /// [`crate::keynotation::format`] writes the frames back as notation.
+    /// A workflow's frames are its steps' one after another.
+    pub fn parse_keys(&self) -> Vec<KeyFrame> {
+        self.parse_keys_with(&Leaders::default())
+    }
+
+    /// `parse_keys`, with `<leader>` and `<localleader>` standing for `leaders`
+    pub fn parse_keys_with(&self, leaders: &Leaders) -> Vec<KeyFrame> {
+        if self.workflow.is_empty() {
+            return parse_key_string_with(&self.keys, leaders);
+        }
+        self.step_frames(leaders).into_iter().flatten().collect()
+    }
+
+    /// The frames of each step of a workflow, parsed apart so a step after an ex command isn't
+    /// read as more of its command line
+    pub fn step_frames(&self, leaders: &Leaders) -> Vec<Vec<KeyFrame>> {
+        let steps = self.workflow.iter();
+        steps.map(|step| parse_key_string_with(&step.keys, leaders)).collect()
+    }
+
+    /// Whether this is a workflow of several commands rather than one binding
+    pub fn is_workflow(&self) -> bool {
+        !self.workflow.is_empty()
+    }
+}
+
+/// The key presses of every command in `commands`, in order, to look up rather than parse
+/// again
+pub fn parse_frames(commands: &[Command], leaders: &Leaders) -> Vec<Vec<KeyFrame>> {
+    commands.iter().map(|cmd| cmd.parse_keys_with(leaders)).collect()
+}
+
+/// How much there is to press in `frames`, to list the simplest bindings first: the number of
+/// frames, then of keys held down across them
+pub fn complexity(frames: &[KeyFrame]) -> (usize, usize) {
+    (frames.len(), frames.iter().map(|frame| frame.keys.len()).sum())
+}
+
+/// What `<leader>` and `<localleader>` stand for, as the characters Neovim's `mapleader` and
+/// `maplocalleader` are set to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leaders {
+    pub leader: String,
+    pub localleader: String,
+}
+
+impl Default for Leaders {
+    /// LazyVim's: Space, and backslash for the local leader
+    fn default() -> Self {
+        Self {
+            leader: " ".to_string(),
+            localleader: "\\".to_string(),
+        }
+    }
+}
+
+/// Frames typing a leader, its keys marked as the leader
+pub(crate) fn leader_frames(value: &str) -> Vec<KeyFrame> {
+    // Neovim falls back to backslash when the leader is empty
+    let value = if value.is_empty() { "\\" } else { value };
+    value
+        .chars()
+        .map(|c| {
+            let mut frame = typed_frame(c);
+            for key in frame.keys.iter_mut().filter(|key| !key.is_modifier) {
+                key.is_leader = true;
+            }
+            frame
+        })
+        .collect()
+}
+
+/// Parse a key string in Vim notation into frames; see `Command::parse_keys`
+pub fn parse_key_string(keys: &str) -> Vec<KeyFrame> {
+    parse_key_string_with(keys, &Leaders::default())
+}
+
+/// `parse_key_string`, with `<leader>` and `<localleader>` standing for `leaders`
+pub fn parse_key_string_with(keys: &str, leaders: &Leaders) -> Vec<KeyFrame> {
+    if keys.starts_with(':') {
+        return parse_typed(keys);
+    }
+
+    let (mut frames, keys) = parse_prefixes(keys);
+    let mut chars = keys.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        // An unclosed '<' is the literal key, as in Vim
+        if c == '<' && chars.clone().any(|next| next == '>') {
+            // Parse special key like <leader>, <C-w>, <S-Tab>, etc.
+            let mut special = String::new();
+            while let Some(&next) = chars.peek() {
+                chars.next();
+                if next == '>' {
+                    break;
+                }
+                special.push(next);
+            }
+
+            match special.to_lowercase().as_str() {
+                "leader" => frames.extend(leader_frames(&leaders.leader)),
+                "localleader" => frames.extend(leader_frames(&leaders.localleader)),
+                // Nothing inside is no key at all, so Vim reads the brackets as typed
+                "" => frames.extend([typed_frame('<'), typed_frame('>')]),
+                _ => frames.push(parse_special_key(&special)),
+            }
+        } else {
+            // Regular character
+            frames.push(typed_frame(c));
+        }
+    }
+
+    frames
+}
+
+/// Frames for an ex command typed one character at a time, ending on Enter
+fn parse_typed(keys: &str) -> Vec<KeyFrame> {
+    let mut frames = Vec::new();
+    let mut chars = keys.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        // Specials like `<CR>` or `<C-r>` can still appear on the command line
+        if c == '<' && chars.clone().any(|next| next == '>') {
+            let special: String = chars.by_ref().take_while(|&next| next != '>').collect();
+            if special.is_empty() {
+                frames.extend([typed_frame('<'), typed_frame('>')]);
+            } else {
+                frames.push(parse_special_key(&special));
+            }
+        } else {
+            frames.push(typed_frame(c));
+        }
+    }
+
+    let ends_on_enter = frames
+        .last()
+        .is_some_and(|frame| frame.keys.iter().any(|k| k.key == "Enter"));
+    if !ends_on_enter {
+        frames.push(KeyFrame::single(plain_key("Enter".to_string())));
+    }
+    frames
+}
+
+/// The keypress that types `c`, with Shift for uppercase letters and shifted symbols
+fn typed_frame(c: char) -> KeyFrame {
+    if c == ' ' {
+        return KeyFrame::single(plain_key("Space".to_string()));
+    }
+    let base = unshifted(c);
+    if base != c {
+        KeyFrame::new(vec![modifier_key("Shift"), plain_key(base.to_string())])
+    } else {
+        KeyFrame::single(plain_key(c.to_string()))
+    }
+}
+/// Split a leading count (`3<C-w>>`) and register (`"+y`) off a key string
+fn parse_prefixes(mut keys: &str) -> (Vec<KeyFrame>, &str) {
+    let mut frames = Vec::new();
+
+    loop {
+        let digits = keys.chars().take_while(char::is_ascii_digit).count();
+        // A lone digit is a motion (like `0`), and `0` never starts a count
+        if digits > 0 && digits < keys.len() && !keys.starts_with('0') {
+            let (count, rest) = keys.split_at(digits);
+            let pressed = count.chars().map(|c| plain_key(c.to_string())).collect();
+            frames.push(KeyFrame::annotated(pressed, format!("count: {}", count)));
+            keys = rest;
+            continue;
+        }
+
+        let mut chars = keys.chars();
+        if let (Some('"'), Some(register)) = (chars.next(), chars.next()) {
+            if !chars.as_str().is_empty() && is_register(register) {
+                // `"` is Shift+' on a US layout, as are symbol registers like `+`
+                let mut pressed = vec![modifier_key("Shift"), plain_key("'".to_string())];
+                pressed.push(plain_key(unshifted(register).to_string()));
+                frames.push(KeyFrame::annotated(
+                    pressed,
+                    format!("register: {}", register),
+                ));
+                keys = chars.as_str();
+                continue;
+            }
+        }
+
+        return (frames, keys);
+    }
+}
+
+/// Whether `"` followed by `c` picks a register, as it does for letters, digits and the
+/// registers Vim names with a symbol
+pub(crate) fn is_register(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "\"-*+_/:.%#=".contains(c)
+}
+
+/// Symbols typed with Shift on a US layout, paired with the key that types them
+const SHIFTED_SYMBOLS: &[(char, char)] = &[
+    ('~', '`'),
+    ('!', '1'),
+    ('@', '2'),
+    ('#', '3'),
+    ('$', '4'),
+    ('%', '5'),
+    ('^', '6'),
+    ('&', '7'),
+    ('*', '8'),
+    ('(', '9'),
+    (')', '0'),
+    ('_', '-'),
+    ('+', '='),
+    ('{', '['),
+    ('}', ']'),
+    ('|', '\\'),
+    (':', ';'),
+    ('"', '\''),
+    ('<', ','),
+    ('>', '.'),
+    ('?', '/'),
+];
+
+/// The key that types `c` on a US layout, e.g. `;` for `:` and `a` for `A`
+pub fn unshifted(c: char) -> char {
+    if c.is_ascii_uppercase() {
+        return c.to_ascii_lowercase();
+    }
+    SHIFTED_SYMBOLS
+        .iter()
+        .find(|(symbol, _)| *symbol == c)
+        .map_or(c, |&(_, base)| base)
+}
+
+/// The character Shift + `c` types on a US layout; the inverse of `unshifted`
+pub fn shifted(c: char) -> char {
+    if c.is_ascii_lowercase() {
+        return c.to_ascii_uppercase();
+    }
+    SHIFTED_SYMBOLS
+        .iter()
+        .find(|(_, base)| *base == c)
+        .map_or(c, |&(symbol, _)| symbol)
+}
+
+fn plain_key(key: String) -> Key {
+    Key {
+        key,
+        is_modifier: false,
+        is_leader: false,
+        is_mouse: false,
+    }
+}
+
+fn modifier_key(key: &str) -> Key {
+    Key {
+        key: key.to_string(),
+        is_modifier: true,
+        is_leader: false,
+        is_mouse: false,
+    }
+}
+
+fn mouse_key(key: String) -> Key {
+    Key {
+        key,
+        is_modifier: false,
+        is_leader: false,
+        is_mouse: true,
+    }
+}
+
+/// Mouse buttons and wheel directions Vim names as keys, like `<LeftMouse>` or
+/// `<C-ScrollWheelUp>`; a click count goes before a button, as in `<2-LeftMouse>`
+pub const MOUSE_KEYS: &[&str] = &[
+    "LeftMouse",
+    "MiddleMouse",
+    "RightMouse",
+    "X1Mouse",
+    "X2Mouse",
+    "LeftDrag",
+    "MiddleDrag",
+    "RightDrag",
+    "LeftRelease",
+    "MiddleRelease",
+    "RightRelease",
+    "ScrollWheelUp",
+    "ScrollWheelDown",
+    "ScrollWheelLeft",
+    "ScrollWheelRight",
+];
+
+/// The mouse pseudo-key called `name`, spelled as Vim does, ignoring case
+pub fn mouse_name(name: &str) -> Option<&'static str> {
+    MOUSE_KEYS.iter().copied().find(|key| key.eq_ignore_ascii_case(name))
+}
+
+/// Whether `part` of a `<...>` key is the click count of a double, triple or quadruple click
+fn is_click_count(part: &str) -> bool {
+    matches!(part, "2" | "3" | "4")
+}
+
+/// The modifiers of a `<...>` key's inside and the key they go with, which is `-` itself in
+/// `<C-->`
+pub(crate) fn split_special(special: &str) -> (Vec<&str>, &str) {
+    match special.strip_suffix("--").filter(|modifiers| !modifiers.is_empty()) {
+        Some(modifiers) => (modifiers.split('-').collect(), "-"),
+        None => {
+            let mut parts: Vec<&str> = special.split('-').collect();
+            let name = parts.pop().unwrap_or_default();
+            (parts, name)
+        }
+    }
+}
+
+fn parse_special_key(special: &str) -> KeyFrame {
+    // Handle combinations like C-w, S-Tab, A-j
+    let (modifiers, name) = split_special(special);
+    let parts: Vec<&str> = modifiers.into_iter().chain([name]).collect();
+
+    if parts.len() == 1 {
+        // Simple special key like <Space>, <CR>, <Esc>
+        if let Some(name) = mouse_name(special) {
+            return KeyFrame::single(mouse_key(name.to_string()));
+        }
+        let key_lower = special.to_lowercase();
+        let (display_key, is_leader) = match key_lower.as_str() {
+            "leader" => ("Space".to_string(), true),
+            "space" => ("Space".to_string(), false),
+            "cr" | "enter" | "return" => ("Enter".to_string(), false),
+            "esc" | "escape" => ("Esc".to_string(), false),
+            "bs" | "backspace" => ("Backsp".to_string(), false),
+            "tab" => ("Tab".to_string(), false),
+            // `<lt>` is a literal `<`, written so it can't start a `<...>` key
+            "lt" => return typed_frame('<'),
+            _ => (keynotation::spelling(special).unwrap_or_else(|| special.to_string()), false),
+        };
+
+        KeyFrame::single(Key {
+            key: display_key,
+            is_modifier: false,
+            is_leader,
+            is_mouse: false,
+        })
+    } else {
+        // Combination like C-w, S-Tab, A-j, or a mouse chord like C-ScrollWheelUp
+        let mut keys = Vec::new();
+        let mouse = parts.last().and_then(|part| mouse_name(part));
+        let mut clicks = None;
+
+        for (i, part) in parts.iter().enumerate() {
+            let part_lower = part.to_lowercase();
+            let is_last = i == parts.len() - 1;
+
+            if !is_last && mouse.is_some() && is_click_count(part) {
+                clicks = Some(*part);
+            } else if !is_last {
+                // Modifier
+                let Some((_, modifier)) = modifier(part) else {
+                    continue;
+                };
+                keys.push(Key {
+                    key: modifier.to_string(),
+                    is_modifier: true,
+                    is_leader: false,
+                    is_mouse: false,
+                });
+            } else if let Some(name) = mouse {
+                let name = match clicks {
+                    Some(clicks) => format!("{}-{}", clicks, name),
+                    None => name.to_string(),
+                };
+                keys.push(mouse_key(name));
+            } else {
+                // Target key
+                let display_key = match part_lower.as_str() {
+                    "cr" | "enter" | "return" => "Enter".to_string(),
+                    "esc" | "escape" => "Esc".to_string(),
+                    "bs" | "backspace" => "Backsp".to_string(),
+                    "tab" => "Tab".to_string(),
+                    "space" => "Space".to_string(),
+                    "up" => "Up".to_string(),
+                    "down" => "Down".to_string(),
+                    "left" => "Left".to_string(),
+                    "right" => "Right".to_string(),
+                    _ if part.chars().count() > 1 => {
+                        keynotation::spelling(part).unwrap_or_else(|| part.to_lowercase())
+                    }
+                    _ => part.to_lowercase(),
+                };
+                keys.push(Key {
+                    key: display_key,
+                    is_modifier: false,
+                    is_leader: false,
+                    is_mouse: false,
+                });
+            }
+        }
+
+        KeyFrame::new(keys)
+    }
+}
+
+/// A problem found while checking a key string against the parser's grammar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum KeyIssue {
+    /// A `<` without a matching `>`, read as literal characters
+    Unterminated(String),
+    /// `<>` with nothing inside
+    EmptySpecial,
+    /// A modifier prefix the parser doesn't recognize (e.g. `X` in `<X-w>`)
+    UnknownModifier(String),
+    /// A special key name the parser doesn't recognize (e.g. `<Foo>`)
+    UnknownSpecial(String),
+}
+
+impl KeyIssue {
+    /// Whether the issue only loses information instead of producing wrong frames
+    pub fn is_warning(&self) -> bool {
+        matches!(self, KeyIssue::Unterminated(_))
+    }
+}
+
+impl std::fmt::Display for KeyIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyIssue::Unterminated(s) => {
+                write!(f, "unterminated \"<{}\" is read as literal characters", s)
+            }
+            KeyIssue::EmptySpecial => write!(f, "empty special key \"<>\""),
+            KeyIssue::UnknownModifier(m) => write!(f, "unknown modifier \"{}\"", m),
+            KeyIssue::UnknownSpecial(k) => write!(f, "unknown special key \"<{}>\"", k),
+        }
+    }
+}
+
+impl std::error::Error for KeyIssue {}
+
+const SPECIAL_KEYS: &[&str] = &[
+    "leader", "localleader", "space", "cr", "enter", "return", "esc", "escape", "bs", "backspace",
+    "tab", "lt",
+];
+/// Keys outside the main block, valid both alone (`<Home>`) and as combo targets
+const NAV_KEYS: &[&str] = &[
+    "up", "down", "left", "right", "home", "end", "del", "delete", "insert", "pageup", "pagedown",
+];
+/// Each spelling of a modifier inside `<...>`, with its usual letter and the key it names.
+/// Neovim's `<D-…>` is Command on macOS, the Windows or Super key elsewhere.
+const MODIFIERS: &[(&str, &str, &str)] = &[
+    ("c", "C", "Ctrl"),
+    ("ctrl", "C", "Ctrl"),
+    ("control", "C", "Ctrl"),
+    ("s", "S", "Shift"),
+    ("shift", "S", "Shift"),
+    ("a", "A", "Alt"),
+    ("alt", "A", "Alt"),
+    ("m", "M", "Alt"),
+    ("meta", "M", "Alt"),
+    ("d", "D", "Super"),
+    ("super", "D", "Super"),
+    ("cmd", "D", "Super"),
+    ("win", "D", "Super"),
+];
+
+/// The usual letter of the modifier `part` spells, in any case, and the key it names
+pub(crate) fn modifier(part: &str) -> Option<(&'static str, &'static str)> {
+    MODIFIERS
+        .iter()
+        .find(|(spelling, ..)| spelling.eq_ignore_ascii_case(part))
+        .map(|&(_, letter, key)| (letter, key))
+}
+
+/// Check a key string for anything `Command::parse_keys` can't fully parse
+pub fn check_keys(keys: &str) -> Vec<KeyIssue> {
+    let mut issues = Vec::new();
+    let (_, keys) = parse_prefixes(keys);
+    let mut chars = keys.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '<' {
+            if !chars.clone().any(|next| next == '>') {
+                let rest: String = chars.clone().collect();
+                if !rest.is_empty() {
+                    issues.push(KeyIssue::Unterminated(rest));
+                }
+                continue;
+            }
+
+            let special: String = chars.by_ref().take_while(|&next| next != '>').collect();
+            if special.is_empty() {
+                issues.push(KeyIssue::EmptySpecial);
+            } else {
+                issues.extend(check_special_key(&special));
+            }
+        }
+    }
+
+    issues
+}
+
+fn check_special_key(special: &str) -> Vec<KeyIssue> {
+    let (modifiers, name) = split_special(special);
+    let parts: Vec<&str> = modifiers.into_iter().chain([name]).collect();
+
+    if parts.len() == 1 {
+        let lower = special.to_lowercase();
+        if special.chars().count() > 1
+            && !SPECIAL_KEYS.contains(&lower.as_str())
+            && !is_named_key(&lower)
+            && mouse_name(special).is_none()
+        {
+            return vec![KeyIssue::UnknownSpecial(special.to_string())];
+        }
+        return Vec::new();
+    }
+
+    let mut issues = Vec::new();
+    let (target, modifiers) = parts.split_last().unwrap();
+    let mouse = mouse_name(target).is_some();
+    for modifier in modifiers {
+        let known = self::modifier(modifier).is_some() || mouse && is_click_count(modifier);
+        if !known {
+            issues.push(KeyIssue::UnknownModifier(modifier.to_string()));
+        }
+    }
+
+    let lower = target.to_lowercase();
+    if target.is_empty() {
+        issues.push(KeyIssue::UnknownSpecial(special.to_string()));
+    } else if target.chars().count() > 1
+        && !SPECIAL_KEYS.contains(&lower.as_str())
+        && !is_named_key(&lower)
+        && !mouse
+    {
+        issues.push(KeyIssue::UnknownSpecial(target.to_string()));
+    }
+
+    issues
+}
+
+/// Navigation and function keys, like `pagedown` or `f5`
+pub(crate) fn is_named_key(lower: &str) -> bool {
+    let function_key = lower
+        .strip_prefix('f')
+        .and_then(|n| n.parse::<u8>().ok())
+        .is_some_and(|n| (1..=12).contains(&n));
+    function_key || NAV_KEYS.contains(&lower)
+}
+
+/// On-disk formats accepted for command files, detected by extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Json,
+    Toml,
+    Yaml,
+}
+
+impl FileFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(FileFormat::Json),
+            "toml" => Some(FileFormat::Toml),
+            "yaml" | "yml" => Some(FileFormat::Yaml),
+            _ => None,
+        }
+    }
+
+    /// The format `source` is most likely in, for data without a file name: TOML lists
+    /// `[[commands]]`, JSON starts with a bracket, and anything else is read as YAML
+    pub fn sniff(source: &str) -> Self {
+        if source.lines().any(|line| line.trim() == "[[commands]]") {
+            FileFormat::Toml
+        } else if source.trim_start().starts_with(['[', '{']) {
+            FileFormat::Json
+        } else {
+            FileFormat::Yaml
+        }
+    }
+
+    /// Like `from_path`, but with an error naming the supported extensions
+    pub fn detect(path: &Path) -> anyhow::Result<Self> {
+        Self::from_path(path).with_context(|| {
+            format!(
+                "unsupported command file {} (expected .json, .toml or .yaml)",
+                path.display()
+            )
+        })
+    }
+}
+
+/// TOML has no top-level arrays, so entries live under `[[commands]]`
+#[derive(Debug, Serialize, Deserialize)]
+struct TomlCommands<T> {
+    commands: Vec<T>,
+}
+
+/// A command file entry, whose `mode` may list several modes to bind the same keys in
+#[derive(Debug, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    command: Command,
+    mode: Option<Modes>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(
+    untagged,
+    expecting = "expected a mode (normal, insert, visual, command, terminal, operator-pending or \
+                 select) or a list of them"
+)]
+enum Modes {
+    One(Mode),
+    Many(Vec<Mode>),
+}
+
+impl Entry {
+    /// One command per mode the entry lists
+    fn into_commands(self) -> impl Iterator<Item = Command> {
+        let modes = match self.mode {
+            None => vec![Mode::default()],
+            Some(Modes::One(mode)) => vec![mode],
+            Some(Modes::Many(modes)) => modes,
+        };
+        let command = self.command;
+        modes.into_iter().map(move |mode| Command {
+            mode,
+            ..command.clone()
+        })
+    }
+}
+
+/// Parse command entries from a string in the given format
+pub fn parse_commands(source: &str, format: FileFormat) -> anyhow::Result<Vec<Command>> {
+    let entries: Vec<Entry> = match format {
+        FileFormat::Json => serde_json::from_str(source)?,
+        FileFormat::Toml => toml::from_str::<TomlCommands<Entry>>(source)?.commands,
+        FileFormat::Yaml => serde_yaml::from_str(source)?,
+    };
+    Ok(entries.into_iter().flat_map(Entry::into_commands).collect())
+}
+
+/// Load a command file, picking the parser from its extension
+pub fn load_commands_file(path: &Path) -> anyhow::Result<Vec<Command>> {
+    let format = FileFormat::detect(path)?;
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    parse_commands(&source, format).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// A command file entry left out of the cheatsheet, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Problem {
+    pub path: PathBuf,
+    /// 1-based line the entry starts on, or of a syntax error that kept the whole file out
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.path.display(), self.line, self.message)
+    }
+}
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Command {
-    pub keys: String,
-    pub description: String,
-    pub category: Category,
-    #[serde(default)]
-    pub mode: Mode,
+/// Every entry of a command file as a loose value, with the 1-based line it starts on.
+/// A file that doesn't parse gives the line and message of its syntax error instead.
+pub fn raw_entries(
+    source: &str,
+    format: FileFormat,
+) -> Result<Vec<(usize, serde_json::Value)>, (usize, String)> {
+    let (entries, lines) = match format {
+        FileFormat::Json => raw_json(source)?,
+        FileFormat::Toml => raw_toml(source)?,
+        FileFormat::Yaml => raw_yaml(source)?,
+    };
+    Ok(entries
+        .into_iter()
+        .enumerate()
+        .map(|(i, entry)| (lines.get(i).copied().unwrap_or(1), entry))
+        .collect())
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
-#[serde(rename_all = "lowercase")]
-pub enum Mode {
-    #[default]
-    Normal,
-    Insert,
-    Visual,
-    Command,
+type RawEntries = (Vec<serde_json::Value>, Vec<usize>);
+
+fn raw_json(source: &str) -> Result<RawEntries, (usize, String)> {
+    let entries = match serde_json::from_str(source) {
+        Ok(serde_json::Value::Array(entries)) => entries,
+        Ok(_) => return Err((1, "expected a top-level array of commands".to_string())),
+        Err(e) => return Err((e.line(), e.to_string())),
+    };
+
+    let lines = element_offsets(source)
+        .into_iter()
+        .map(|offset| line_of(source, offset))
+        .collect();
+
+    Ok((entries, lines))
 }
 
-impl Mode {
+fn raw_toml(source: &str) -> Result<RawEntries, (usize, String)> {
+    let table: toml::Table = toml::from_str(source).map_err(|e| {
+        let line = e.span().map_or(1, |span| line_of(source, span.start));
+        (line, e.message().to_string())
+    })?;
+
+    let entries = match table.get("commands").cloned().map(serde_json::to_value) {
+        Some(Ok(serde_json::Value::Array(entries))) => entries,
+        _ => return Err((1, "expected a [[commands]] array of tables".to_string())),
+    };
+
+    let lines = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| line.trim() == "[[commands]]")
+        .map(|(i, _)| i + 1)
+        .collect();
+
+    Ok((entries, lines))
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum Category {
-    General,
-    Navigation,
-    Search,
-    Lsp,
-    Git,
-    Buffer,
-    Window,
-    Tab,
-    Code,
-    Debug,
-    Terminal,
-    Ui,
-    Plugin,
+fn raw_yaml(source: &str) -> Result<RawEntries, (usize, String)> {
+    let entries = match serde_yaml::from_str(source) {
+        Ok(serde_json::Value::Array(entries)) => entries,
+        Ok(_) => return Err((1, "expected a top-level list of commands".to_string())),
+        Err(e) => return Err((e.location().map_or(1, |loc| loc.line()), e.to_string())),
+    };
+
+    // Top-level sequence items start with an unindented dash
+    let lines = source
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| *line == "-" || line.starts_with("- "))
+        .map(|(i, _)| i + 1)
+        .collect();
+
+    Ok((entries, lines))
 }
 
-impl Category {
-    pub fn as_str(&self) -> &'static str {
-        match self {
-            Category::General => "General",
-            Category::Navigation => "Navigation",
-            Category::Search => "Search",
-            Category::Lsp => "LSP",
-            Category::Git => "Git",
-            Category::Buffer => "Buffer",
-            Category::Window => "Window",
-            Category::Tab => "Tab",
-            Category::Code => "Code",
-            Category::Debug => "Debug",
-            Category::Terminal => "Terminal",
-            Category::Ui => "UI",
-            Category::Plugin => "Plugin",
+/// Byte offsets where each element of the top-level array starts
+fn element_offsets(source: &str) -> Vec<usize> {
+    let mut offsets = Vec::new();
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut expect_value = false;
+
+    for (offset, c) in source.char_indices() {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == '"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        if expect_value && !c.is_whitespace() && c != ']' {
+            offsets.push(offset);
+            expect_value = false;
+        }
+
+        match c {
+            '"' => in_string = true,
+            '[' | '{' => {
+                depth += 1;
+                if depth == 1 && c == '[' {
+                    expect_value = true;
+                }
+            }
+            ']' | '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 1 => expect_value = true,
+            _ => {}
         }
     }
-}
 
-/// A single key in a keypress
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Key {
-    pub key: String,
-    pub is_modifier: bool,
-    pub is_leader: bool,
+    offsets
 }
 
-/// A frame represents keys pressed simultaneously (e.g., Shift+D)
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct KeyFrame {
-    pub keys: Vec<Key>,
+fn line_of(source: &str, offset: usize) -> usize {
+    source[..offset].matches('\n').count() + 1
 }
 
-impl KeyFrame {
-    pub fn new(keys: Vec<Key>) -> Self {
-        Self { keys }
+/// Parse the entries of a command file that make commands, leaving out the rest. Each entry
+/// left out comes back with its line and reason, as does a syntax error that leaves out all.
+pub fn parse_valid_commands(
+    source: &str,
+    format: FileFormat,
+) -> (Vec<Command>, Vec<(usize, String)>) {
+    let entries = match raw_entries(source, format) {
+        Ok(entries) => entries,
+        Err(error) => return (Vec::new(), vec![error]),
+    };
+    let mut commands = Vec::new();
+    let mut skipped = Vec::new();
+    for (line, entry) in entries {
+        match serde_json::from_value::<Entry>(entry) {
+            Ok(entry) => commands.extend(entry.into_commands()),
+            Err(e) => skipped.push((line, e.to_string())),
+        }
     }
+    (commands, skipped)
+}
 
-    pub fn single(key: Key) -> Self {
-        Self { keys: vec![key] }
+/// Load the valid entries of a command file, with a problem for each one left out
+pub fn load_valid_commands(path: &Path) -> anyhow::Result<(Vec<Command>, Vec<Problem>)> {
+    let format = FileFormat::detect(path)?;
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let (commands, skipped) = parse_valid_commands(&source, format);
+    let problems = skipped
+        .into_iter()
+        .map(|(line, message)| Problem {
+            path: path.to_path_buf(),
+            line,
+            message,
+        })
+        .collect();
+    Ok((commands, problems))
+}
+
+/// Directory holding user configuration and command files
+pub fn config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("lvcheat"))
+}
+
+/// Directory holding persisted user state and downloaded data (`$XDG_DATA_HOME/lvcheat` on Linux)
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("lvcheat"))
+}
+
+/// Where `lvcheat update` saves the latest keymap data
+pub fn downloaded_commands_path() -> Option<PathBuf> {
+    data_dir().map(|dir| dir.join("commands.json"))
+}
+
+/// Names the user's command file can have in the config directory, the first found winning
+pub const USER_COMMAND_FILES: [&str; 4] =
+    ["commands.json", "commands.toml", "commands.yaml", "commands.yml"];
+
+/// The user's command file, if one exists (`commands.json`, `.toml` or `.yaml`)
+pub fn user_commands_path() -> Option<PathBuf> {
+    let dir = config_dir()?;
+    USER_COMMAND_FILES
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.is_file())
+}
+
+/// Append a command to the user's command file, creating `commands.json` if there
+/// is none yet. TOML and YAML files are appended as text so comments survive.
+pub fn append_user_command(cmd: &Command) -> anyhow::Result<PathBuf> {
+    let path = match user_commands_path() {
+        Some(path) => path,
+        None => config_dir()
+            .context("no config directory available")?
+            .join("commands.json"),
+    };
+    let existing = if path.exists() {
+        std::fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+
+    let updated = append_command_source(&existing, FileFormat::detect(&path)?, cmd)?;
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
     }
+    std::fs::write(&path, updated).with_context(|| format!("failed to write {}", path.display()))?;
+    Ok(path)
 }
 
-impl Command {
-    /// Parse keys into animation frames
-    /// Each frame = keys pressed at the same time
-    /// Example: "gD" -> [Frame{g}, Frame{Shift, d}]
-    /// Example: "<C-w>v" -> [Frame{Ctrl, w}, Frame{v}]
-    pub fn parse_keys(&self) -> Vec<KeyFrame> {
-        let mut frames = Vec::new();
-        let keys = &self.keys;
-        let mut chars = keys.chars().peekable();
-
-        while let Some(c) = chars.next() {
-            if c == '<' {
-                // Parse special key like <leader>, <C-w>, <S-Tab>, etc.
-                let mut special = String::new();
-                while let Some(&next) = chars.peek() {
-                    chars.next();
-                    if next == '>' {
-                        break;
-                    }
-                    special.push(next);
-                }
+fn append_command_source(
+    existing: &str,
+    format: FileFormat,
+    cmd: &Command,
+) -> anyhow::Result<String> {
+    let mut updated = existing.to_string();
+    if !updated.is_empty() && !updated.ends_with('\n') {
+        updated.push('\n');
+    }
 
-                let frame = Self::parse_special_key(&special);
-                frames.push(frame);
-            } else if c != '-' && c != '+' {
-                // Regular character
-                let frame = if c.is_ascii_uppercase() {
-                    // Uppercase letter needs Shift
-                    KeyFrame::new(vec![
-                        Key {
-                            key: "Shift".to_string(),
-                            is_modifier: true,
-                            is_leader: false,
-                        },
-                        Key {
-                            key: c.to_lowercase().to_string(),
-                            is_modifier: false,
-                            is_leader: false,
-                        },
-                    ])
-                } else {
-                    KeyFrame::single(Key {
-                        key: c.to_string(),
-                        is_modifier: false,
-                        is_leader: false,
-                    })
-                };
-                frames.push(frame);
+    match format {
+        FileFormat::Json => {
+            let mut entries: Vec<serde_json::Value> = if existing.trim().is_empty() {
+                Vec::new()
+            } else {
+                serde_json::from_str(existing)?
+            };
+            entries.push(serde_json::to_value(cmd)?);
+            updated = serde_json::to_string_pretty(&entries)?;
+            updated.push('\n');
+        }
+        FileFormat::Toml => {
+            if !updated.is_empty() {
+                updated.push('\n');
             }
+            updated.push_str(&toml::to_string(&TomlCommands {
+                commands: vec![cmd.clone()],
+            })?);
         }
+        FileFormat::Yaml => updated.push_str(&serde_yaml::to_string(&[cmd])?),
+    }
+
+    Ok(updated)
+}
+
+/// Load the keymaps (downloaded by `lvcheat update` if available, embedded otherwise) and
+/// ex commands, then layer the user's command file on top.
+/// User entries replace builtin ones with the same keys and mode, or not, as `resolve` says
+/// for those that do something else; the conflicts it has no answer for are returned, and so
+/// are the entries that aren't valid commands, as problems.
+pub fn load_commands(
+    resolve: impl Fn(&CommandId) -> Option<Keep>,
+) -> anyhow::Result<(Vec<Command>, Vec<Problem>, Vec<Conflict>)> {
+    let mut commands = stock_commands()?;
+    commands.extend(ex_commands()?);
+    with_user_commands(commands, resolve)
+}
+
+/// [`load_commands`] on the `stock` keymaps already loaded, without the ex commands, which the
+/// TUI loads in the background. User entries bound like an ex command are then kept over it
+/// rather than asked about.
+pub fn load_keymaps(
+    stock: Vec<Command>,
+    resolve: impl Fn(&CommandId) -> Option<Keep>,
+) -> anyhow::Result<(Vec<Command>, Vec<Problem>, Vec<Conflict>)> {
+    with_user_commands(stock, resolve)
+}
+
+/// `commands` with the user's command file merged in
+fn with_user_commands(
+    mut commands: Vec<Command>,
+    resolve: impl Fn(&CommandId) -> Option<Keep>,
+) -> anyhow::Result<(Vec<Command>, Vec<Problem>, Vec<Conflict>)> {
+    let (mut problems, mut conflicts) = (Vec::new(), Vec::new());
+    if let Some(path) = user_commands_path() {
+        let (user, skipped) = load_valid_commands(&path)?;
+        conflicts = merge_resolved(&mut commands, with_source(user, Source::User), resolve);
+        problems = skipped;
+    }
+
+    Ok((commands, problems, conflicts))
+}
 
-        frames
+/// LazyVim's keymaps, without the user's: downloaded by `lvcheat update` if available,
+/// embedded otherwise
+pub fn stock_commands() -> anyhow::Result<Vec<Command>> {
+    match downloaded_commands_path().map(|path| load_commands_file(&path)) {
+        Some(Ok(downloaded)) if !downloaded.is_empty() => {
+            Ok(with_source(downloaded, Source::Downloaded))
+        }
+        // No download yet, or one that no longer parses: the embedded snapshot always works
+        _ => embedded_commands(),
     }
+}
 
-    fn parse_special_key(special: &str) -> KeyFrame {
-        // Handle combinations like C-w, S-Tab, A-j
-        let parts: Vec<&str> = special.split('-').collect();
+/// The embedded ex commands, listed after the keymaps
+pub fn ex_commands() -> anyhow::Result<Vec<Command>> {
+    parse_commands(include_str!("../data/ex_commands.json"), FileFormat::Json)
+        .context("failed to parse embedded ex commands")
+}
 
-        if parts.len() == 1 {
-            // Simple special key like <leader>, <CR>, <Esc>
-            let key_lower = special.to_lowercase();
-            let (display_key, is_leader) = match key_lower.as_str() {
-                "leader" | "space" => ("Space".to_string(), true),
-                "cr" | "enter" | "return" => ("Enter".to_string(), false),
-                "esc" | "escape" => ("Esc".to_string(), false),
-                "bs" | "backspace" => ("Backsp".to_string(), false),
-                "tab" => ("Tab".to_string(), false),
-                _ => (special.to_string(), false),
-            };
+/// The keymap snapshot compiled into the binary
+pub fn embedded_commands() -> anyhow::Result<Vec<Command>> {
+    parse_commands(include_str!("../data/commands.json"), FileFormat::Json)
+        .context("failed to parse embedded commands")
+}
 
-            KeyFrame::single(Key {
-                key: display_key,
-                is_modifier: false,
-                is_leader,
-            })
-        } else {
-            // Combination like C-w, S-Tab, A-j
-            let mut keys = Vec::new();
-
-            for (i, part) in parts.iter().enumerate() {
-                let part_lower = part.to_lowercase();
-                let is_last = i == parts.len() - 1;
-
-                if !is_last {
-                    // Modifier
-                    let modifier = match part_lower.as_str() {
-                        "c" | "ctrl" | "control" => "Ctrl",
-                        "s" | "shift" => "Shift",
-                        "a" | "alt" | "m" | "meta" => "Alt",
-                        _ => continue,
-                    };
-                    keys.push(Key {
-                        key: modifier.to_string(),
-                        is_modifier: true,
-                        is_leader: false,
-                    });
-                } else {
-                    // Target key
-                    let display_key = match part_lower.as_str() {
-                        "cr" | "enter" | "return" => "Enter".to_string(),
-                        "esc" | "escape" => "Esc".to_string(),
-                        "bs" | "backspace" => "Backsp".to_string(),
-                        "tab" => "Tab".to_string(),
-                        "space" => "Space".to_string(),
-                        "up" => "Up".to_string(),
-                        "down" => "Down".to_string(),
-                        "left" => "Left".to_string(),
-                        "right" => "Right".to_string(),
-                        _ => part.to_lowercase(),
-                    };
-                    keys.push(Key {
-                        key: display_key,
-                        is_modifier: false,
-                        is_leader: false,
-                    });
-                }
-            }
+/// `commands`, each marked as loaded from `source`
+pub fn with_source(mut commands: Vec<Command>, source: Source) -> Vec<Command> {
+    for cmd in &mut commands {
+        cmd.source = source.clone();
+    }
+    commands
+}
+
+/// Which side of a conflict to keep, as chosen on the resolution screen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Keep {
+    Builtin,
+    User,
+    /// Both, each with its source after its description
+    Both,
+}
+
+/// A user entry bound to the same keys and mode as a builtin one, doing something else
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    pub builtin: Command,
+    pub user: Command,
+}
+
+/// Layer user entries over `commands`, replacing those with the same keys and mode
+pub fn merge_commands(commands: &mut Vec<Command>, user: Vec<Command>) {
+    merge_resolved(commands, user, |_| None);
+}
 
-            KeyFrame::new(keys)
+/// Layer user entries over `commands` like `merge_commands`, settling each conflict with a
+/// builtin entry as `resolve` says. The user entry stands in for those it has no answer for,
+/// which are returned.
+pub fn merge_resolved(
+    commands: &mut Vec<Command>,
+    user: Vec<Command>,
+    resolve: impl Fn(&CommandId) -> Option<Keep>,
+) -> Vec<Conflict> {
+    let mut conflicts = Vec::new();
+    for cmd in user {
+        let Some(existing) = commands
+            .iter_mut()
+            .find(|c| c.keys == cmd.keys && c.mode == cmd.mode)
+        else {
+            commands.push(cmd);
+            continue;
+        };
+        let differs = existing.source != cmd.source
+            && !existing.description.trim().eq_ignore_ascii_case(cmd.description.trim());
+        if !differs {
+            *existing = cmd;
+            continue;
+        }
+        let conflict = Conflict {
+            builtin: std::mem::replace(existing, cmd.clone()),
+            user: cmd,
+        };
+        match resolve(&conflict.user.id()) {
+            Some(keep) => resolve_conflict(commands, &conflict, keep),
+            None => conflicts.push(conflict),
         }
     }
+    conflicts
 }
 
-pub fn load_commands() -> anyhow::Result<Vec<Command>> {
-    let json_data = include_str!("../data/commands.json");
-    let commands: Vec<Command> = serde_json::from_str(json_data)?;
-    Ok(commands)
+/// Settle a conflict whose user entry is standing in for the builtin one in `commands`
+pub fn resolve_conflict(commands: &mut Vec<Command>, conflict: &Conflict, keep: Keep) {
+    let id = conflict.user.id();
+    let Some(pos) = commands
+        .iter()
+        .position(|c| c.id() == id && c.source == conflict.user.source)
+    else {
+        return;
+    };
+    let renamed = |cmd: &Command| Command {
+        description: format!("{} ({})", cmd.description, cmd.source.label()),
+        ..cmd.clone()
+    };
+    match keep {
+        Keep::User => {}
+        Keep::Builtin => commands[pos] = conflict.builtin.clone(),
+        Keep::Both => {
+            commands[pos] = renamed(&conflict.builtin);
+            let user = Command {
+                beside: true,
+                ..renamed(&conflict.user)
+            };
+            commands.insert(pos + 1, user);
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_describe_sequence() {
+        assert_eq!(
+            describe_sequence(&parse_key_string("<leader>ff")),
+            "Press Space (leader), then f, then f."
+        );
+        assert_eq!(
+            describe_sequence(&parse_key_string("3<C-w>v")),
+            "Press 3 (count: 3), then Ctrl and w together, then v."
+        );
+        assert_eq!(describe_sequence(&[]), "");
+    }
+
+    #[test]
+    fn test_narrate_frame() {
+        let narrated = |keys| {
+            let frames = parse_key_string(keys);
+            frames.iter().map(narrate_frame).collect::<Vec<_>>()
+        };
+        assert_eq!(narrated("<C-w>v"), ["Hold Ctrl, press w", "Press v"]);
+        assert_eq!(narrated("<leader>F"), ["Press Space (leader)", "Hold Shift, press f"]);
+        assert_eq!(narrated("<C-S-Tab>"), ["Hold Ctrl and Shift, press Tab"]);
+        assert_eq!(narrated("3j")[0], "Press 3 (count: 3)");
+    }
 
     #[test]
     fn test_parse_leader_key() {
@@ -224,6 +1797,7 @@ mod tests {
             description: "Find files".to_string(),
             category: Category::Search,
             mode: Mode::Normal,
+            ..Default::default()
         };
 
         let frames = cmd.parse_keys();
@@ -238,6 +1812,30 @@ mod tests {
         assert_eq!(frames[2].keys[0].key, "f");
     }
 
+    #[test]
+    fn test_parse_custom_leaders() {
+        let leaders = Leaders {
+            leader: ",".to_string(),
+            localleader: String::new(),
+        };
+        let frames = parse_key_string_with("<leader>ff", &leaders);
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].keys[0].key, ",");
+        assert!(frames[0].keys[0].is_leader);
+
+        // An empty local leader is backslash, as in Neovim
+        let frames = parse_key_string_with("<localleader>r", &leaders);
+        assert_eq!(frames[0].keys[0].key, "\\");
+        assert!(frames[0].keys[0].is_leader);
+
+        // The default local leader, and <Space> is a plain key
+        let frames = parse_key_string("<LocalLeader><Space>");
+        assert_eq!(frames[0].keys[0].key, "\\");
+        assert_eq!(frames[1].keys[0].key, "Space");
+        assert!(!frames[1].keys[0].is_leader);
+        assert!(check_keys("<localleader>r").is_empty());
+    }
+
     #[test]
     fn test_parse_ctrl_combo() {
         let cmd = Command {
@@ -245,6 +1843,7 @@ mod tests {
             description: "Split vertical".to_string(),
             category: Category::Window,
             mode: Mode::Normal,
+            ..Default::default()
         };
 
         let frames = cmd.parse_keys();
@@ -266,6 +1865,7 @@ mod tests {
             description: "Go to declaration".to_string(),
             category: Category::Lsp,
             mode: Mode::Normal,
+            ..Default::default()
         };
 
         let frames = cmd.parse_keys();
@@ -280,6 +1880,31 @@ mod tests {
         assert_eq!(frames[1].keys[1].key, "d");
     }
 
+    #[test]
+    fn test_parse_count_and_register() {
+        let frames = parse_key_string("3<C-w>>");
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].note.as_deref(), Some("count: 3"));
+        assert_eq!(frames[0].keys[0].key, "3");
+        assert_eq!(frames[1].keys[0].key, "Ctrl");
+        assert_eq!(frames[2].note, None);
+
+        let frames = parse_key_string("\"+y");
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].note.as_deref(), Some("register: +"));
+        let keys: Vec<&str> = frames[0].keys.iter().map(|k| k.key.as_str()).collect();
+        assert_eq!(keys, ["Shift", "'", "="]);
+        assert_eq!(frames[1].keys[0].key, "y");
+
+        let frames = parse_key_string("\"a2yy");
+        assert_eq!(frames.len(), 4);
+        assert_eq!(frames[1].note.as_deref(), Some("count: 2"));
+
+        // Digits on their own are keys, not counts
+        assert_eq!(parse_key_string("0")[0].note, None);
+        assert_eq!(parse_key_string("05")[0].note, None);
+    }
+
     #[test]
     fn test_parse_shift_combo() {
         let cmd = Command {
@@ -287,6 +1912,7 @@ mod tests {
             description: "Previous buffer".to_string(),
             category: Category::Buffer,
             mode: Mode::Normal,
+            ..Default::default()
         };
 
         let frames = cmd.parse_keys();
@@ -295,4 +1921,407 @@ mod tests {
         assert_eq!(frames[0].keys[0].key, "Shift");
         assert_eq!(frames[0].keys[1].key, "h");
     }
+
+    #[test]
+    fn test_parse_mouse_keys() {
+        let frames = parse_key_string("<C-ScrollWheelUp>");
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].keys[0].key, "Ctrl");
+        assert_eq!(frames[0].keys[1].key, "ScrollWheelUp");
+        assert!(frames[0].keys[1].is_mouse && !frames[0].keys[0].is_mouse);
+
+        let frames = parse_key_string("<leftmouse><2-LeftMouse><S-3-RightMouse>");
+        let keys: Vec<&str> =
+            frames.iter().map(|kf| kf.keys.last().unwrap().key.as_str()).collect();
+        assert_eq!(keys, ["LeftMouse", "2-LeftMouse", "3-RightMouse"]);
+        assert!(frames.iter().all(|kf| kf.keys.last().unwrap().is_mouse));
+        assert_eq!(frames[2].label(), "Shift+3-RightMouse");
+        assert!(!parse_key_string("<C-Up>")[0].keys[1].is_mouse);
+    }
+
+    #[test]
+    fn test_complexity() {
+        let measure = |keys| complexity(&parse_key_string(keys));
+        assert_eq!(measure("K"), (1, 2));
+        assert_eq!(measure("gd"), (2, 2));
+        assert_eq!(measure("<C-w>v"), (2, 3));
+        assert_eq!(measure("<leader>ff"), (3, 3));
+        let mut keys = ["<leader>ff", "<C-w>v", "gd", "K"];
+        keys.sort_by_key(|keys| measure(keys));
+        assert_eq!(keys, ["K", "gd", "<C-w>v", "<leader>ff"]);
+    }
+
+    #[test]
+    fn test_parse_lone_angle_bracket() {
+        let cmd = Command {
+            keys: "<".to_string(),
+            description: "Indent left".to_string(),
+            category: Category::Code,
+            mode: Mode::Visual,
+            ..Default::default()
+        };
+
+        // `<` is typed as Shift+, on a US layout
+        let frames = cmd.parse_keys();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].keys[0].key, "Shift");
+        assert_eq!(frames[0].keys[1].key, ",");
+    }
+
+    #[test]
+    fn test_parse_typed_ex_command() {
+        let frames = parse_key_string(":Lazy sync");
+        let names: Vec<Vec<&str>> = frames
+            .iter()
+            .map(|f| f.keys.iter().map(|k| k.key.as_str()).collect())
+            .collect();
+        assert_eq!(names[0], ["Shift", ";"]);
+        assert_eq!(names[1], ["Shift", "l"]);
+        assert_eq!(names[5], ["Space"]);
+        assert!(!frames[5].keys[0].is_leader);
+        assert_eq!(names.last().unwrap(), &["Enter"]);
+        assert_eq!(frames.len(), 11);
+
+        // An explicit <CR> isn't doubled
+        assert_eq!(parse_key_string(":w<CR>").len(), 3);
+        assert_eq!(unshifted('$'), '4');
+        assert_eq!(shifted('4'), '$');
+    }
+
+    #[test]
+    fn test_workflow_frames() {
+        let step = |keys: &str| WorkflowStep {
+            keys: keys.to_string(),
+            description: String::new(),
+            app: None,
+        };
+        let cmd = Command {
+            keys: ":wgg".to_string(),
+            workflow: vec![step(":w"), step("gg")],
+            ..Default::default()
+        };
+        assert!(cmd.is_workflow());
+        let steps = cmd.step_frames(&Leaders::default());
+        assert_eq!(steps.iter().map(Vec::len).collect::<Vec<_>>(), [3, 2]);
+        // Typed on one command line, the second step would be part of the first
+        assert_eq!(cmd.parse_keys(), steps.concat());
+        assert_eq!(parse_key_string(&cmd.keys).len(), 5);
+    }
+
+    #[test]
+    fn test_parse_toml_and_yaml() {
+        let toml = r#"
+# Comments are the whole point
+[[commands]]
+keys = "<leader>ff"
+description = "Find files"
+category = "search"
+"#;
+        let commands = parse_commands(toml, FileFormat::Toml).unwrap();
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].mode, Mode::Normal);
+
+        let yaml = r#"
+- keys: "<leader>ff"
+  description: Find files
+  category: search
+  mode: visual
+"#;
+        let commands = parse_commands(yaml, FileFormat::Yaml).unwrap();
+        assert_eq!(commands[0].keys, "<leader>ff");
+        assert_eq!(commands[0].mode, Mode::Visual);
+        assert_eq!(FileFormat::sniff(toml), FileFormat::Toml);
+        assert_eq!(FileFormat::sniff(yaml), FileFormat::Yaml);
+        assert_eq!(FileFormat::sniff("\n  [{ \"keys\": \"gd\" }]"), FileFormat::Json);
+    }
+
+    #[test]
+    fn test_skips_invalid_entries() {
+        let json = r#"[
+  { "keys": "<leader>ff", "description": "Find files", "category": "search" },
+  { "keys": "<leader>fg" },
+  { "keys": "gd", "description": "Definition", "mode": "insertt" }
+]"#;
+        let (commands, skipped) = parse_valid_commands(json, FileFormat::Json);
+        assert_eq!(commands.len(), 1);
+        assert_eq!(commands[0].keys, "<leader>ff");
+        let lines: Vec<usize> = skipped.iter().map(|(line, _)| *line).collect();
+        assert_eq!(lines, [3, 4]);
+        assert!(skipped[0].1.contains("description"));
+        assert!(skipped[1].1.contains("visual"));
+
+        let (commands, skipped) = parse_valid_commands("[\n  { \"keys\": ", FileFormat::Json);
+        assert!(commands.is_empty());
+        assert_eq!(skipped.len(), 1);
+        assert_eq!(skipped[0].0, 2);
+    }
+
+    #[test]
+    fn test_custom_categories() {
+        let json = r#"[
+  { "keys": "<leader>rr", "description": "Run", "category": "Testing" },
+  { "keys": "<leader>gg", "description": "Lazygit", "category": "GIT" },
+  { "keys": "<leader>ra", "description": "Run all", "category": "Testing" },
+  { "keys": "<leader>bb", "description": "Build", "category": "Build" }
+]"#;
+        let commands = parse_commands(json, FileFormat::Json).unwrap();
+        let testing = Category::Custom("Testing".to_string());
+        assert_eq!(commands[0].category, testing);
+        assert_eq!(commands[1].category, Category::Git);
+        assert_eq!(serde_json::to_string(&commands[0].category).unwrap(), "\"Testing\"");
+        assert_eq!(serde_json::to_string(&Category::Lsp).unwrap(), "\"lsp\"");
+
+        let order = Category::in_order(commands.iter().map(|cmd| &cmd.category));
+        assert_eq!(order.len(), Category::ALL.len() + 2);
+        assert_eq!(order[Category::ALL.len()..], [Category::Custom("Build".to_string()), testing]);
+        assert_eq!(Category::named("Build").icon(), Category::named("Testing").icon());
+    }
+
+    #[test]
+    fn test_category_layout() {
+        let layout: CategoryLayout = toml::from_str(
+            r##"
+order = ["git", "Testing", "lsp", "git"]
+hidden = ["debug", "plugin"]
+names = { ui = "Toggles", Testing = "Tests" }
+colors = { git = "#f05033", lsp = "208", search = "light blue" }
+"##,
+        )
+        .unwrap();
+        let testing = Category::named("Testing");
+        let order = layout.in_order([&testing, &Category::Git]);
+        assert_eq!(order[..3], [Category::Git, testing.clone(), Category::Lsp]);
+        assert_eq!(order[3], Category::General);
+        assert_eq!(order.len(), Category::ALL.len() + 1 - 2);
+        assert!(!order.contains(&Category::Debug));
+        // A category ordered but not used isn't listed
+        assert!(!layout.in_order([]).contains(&testing));
+
+        assert_eq!(layout.name(&Category::Ui), "Toggles");
+        assert_eq!(layout.name(&testing), "Tests");
+        assert_eq!(layout.name(&Category::Git), "Git");
+        assert_eq!(layout.color(&Category::Git), Hue::Rgb(0xf0, 0x50, 0x33));
+        assert_eq!(layout.color(&Category::Lsp), Hue::Indexed(208));
+        assert_eq!(layout.color(&Category::Search), Hue::LightBlue);
+        assert_eq!(layout.color(&Category::Code), Category::Code.color());
+
+        // Every builtin category has a color of its own
+        let colors: HashSet<Hue> = Category::ALL.iter().map(Category::color).collect();
+        assert_eq!(colors.len(), Category::ALL.len());
+
+        assert!(toml::from_str::<CategoryLayout>("colors = { git = \"mauve\" }").is_err());
+        assert_eq!(CategoryLayout::default().in_order([]), Category::in_order([]));
+    }
+
+    #[test]
+    fn test_several_modes() {
+        let yaml = r#"
+- keys: "<leader>gg"
+  description: Lazygit
+  category: git
+- keys: "<leader>cf"
+  description: Format
+  category: code
+  mode: [normal, visual]
+"#;
+        let commands = parse_commands(yaml, FileFormat::Yaml).unwrap();
+        let modes: Vec<(&str, Mode)> = commands.iter().map(|c| (c.keys.as_str(), c.mode)).collect();
+        assert_eq!(
+            modes,
+            [
+                ("<leader>gg", Mode::Normal),
+                ("<leader>cf", Mode::Normal),
+                ("<leader>cf", Mode::Visual)
+            ]
+        );
+        assert_eq!(commands[1].other_modes(&commands), [2]);
+        assert_eq!(commands[2].other_modes(&commands), [1]);
+        assert!(commands[0].other_modes(&commands).is_empty());
+
+        let toml = "[[commands]]\nkeys = \"gc\"\ndescription = \"Comment\"\ncategory = \"code\"\n\
+                    mode = [\"visual\", \"normal\"]\n";
+        let commands = parse_commands(toml, FileFormat::Toml).unwrap();
+        assert_eq!(commands[0].mode, Mode::Visual);
+        assert_eq!(commands[1].mode, Mode::Normal);
+        assert_eq!(commands[0].other_modes(&commands), [1]);
+
+        assert_eq!(Mode::named("V"), Some(Mode::Visual));
+        assert_eq!(Mode::named("insert"), Some(Mode::Insert));
+        assert_eq!(Mode::named("sideways"), None);
+        assert_eq!(Mode::named("operator-pending"), Some(Mode::OperatorPending));
+        assert_eq!(Mode::named("t"), Some(Mode::Terminal));
+        let mode: Mode = serde_json::from_str("\"operator-pending\"").unwrap();
+        assert_eq!(mode, Mode::OperatorPending);
+    }
+
+    #[test]
+    fn test_merge_user_commands() {
+        let cmd = |keys: &str, desc: &str| Command {
+            keys: keys.to_string(),
+            description: desc.to_string(),
+            category: Category::General,
+            mode: Mode::Normal,
+            ..Default::default()
+        };
+        let mut commands = vec![cmd("gd", "Go to definition"), cmd("K", "Hover")];
+        merge_commands(&mut commands, vec![cmd("K", "Docs"), cmd("gz", "Custom")]);
+
+        assert_eq!(commands.len(), 3);
+        assert_eq!(commands[1].description, "Docs");
+        assert_eq!(commands[2].keys, "gz");
+    }
+
+    #[test]
+    fn test_merge_conflicts() {
+        let cmd = |keys: &str, desc: &str, source: Source| Command {
+            keys: keys.to_string(),
+            description: desc.to_string(),
+            source,
+            ..Default::default()
+        };
+        let builtin = || {
+            vec![
+                cmd("gd", "Go to definition", Source::Builtin),
+                cmd("K", "Hover", Source::Builtin),
+                cmd("gr", "References", Source::Builtin),
+            ]
+        };
+        let user = || {
+            vec![
+                cmd("K", "Docs", Source::User),
+                cmd("gd", "go to definition", Source::User),
+                cmd("gr", "Rename", Source::User),
+            ]
+        };
+
+        // Unresolved, the user entries stand in and the differing ones are returned
+        let mut commands = builtin();
+        let conflicts = merge_resolved(&mut commands, user(), |_| None);
+        let keys: Vec<&str> = conflicts.iter().map(|c| c.user.keys.as_str()).collect();
+        assert_eq!(keys, ["K", "gr"]);
+        assert_eq!(conflicts[0].builtin.description, "Hover");
+        assert_eq!(commands[1].description, "Docs");
+
+        let mut commands = builtin();
+        let conflicts = merge_resolved(&mut commands, user(), |id| match id.keys.as_str() {
+            "K" => Some(Keep::Builtin),
+            _ => Some(Keep::Both),
+        });
+        assert!(conflicts.is_empty());
+        let descriptions: Vec<&str> = commands.iter().map(|c| c.description.as_str()).collect();
+        assert_eq!(
+            descriptions,
+            ["go to definition", "Hover", "References (builtin)", "Rename (user)"]
+        );
+        // Marking or favoriting one of the pair leaves the other alone
+        assert_ne!(commands[2].id(), commands[3].id());
+    }
+
+    #[test]
+    fn test_builtin_ex_commands() {
+        let ex = parse_commands(include_str!("../data/ex_commands.json"), FileFormat::Json).unwrap();
+        assert!(ex.iter().all(Command::is_ex));
+        assert!(ex.iter().any(|cmd| cmd.keys == ":checkhealth"));
+        assert!(ex.iter().all(|cmd| check_keys(&cmd.keys).is_empty()));
+    }
+
+    #[test]
+    fn test_related() {
+        let cmd = |keys: &str, mode| Command {
+            keys: keys.to_string(),
+            mode,
+            ..Default::default()
+        };
+        let commands = [
+            cmd("gc", Mode::Normal),
+            cmd("gc", Mode::Visual),
+            cmd("gcc", Mode::Normal),
+            Command {
+                see_also: vec!["gcc".to_string(), "gx".to_string(), "gc".to_string()],
+                ..cmd("gb", Mode::Visual)
+            },
+        ];
+        // Missing keys are skipped, and `gc` resolves to the visual one
+        assert_eq!(commands[3].related(&commands), [2, 1]);
+
+        // A workflow's steps come first, except those pressed in another program
+        let step = |keys: &str, app: Option<&str>| WorkflowStep {
+            keys: keys.to_string(),
+            description: String::new(),
+            app: app.map(str::to_string),
+        };
+        let workflow = Command {
+            workflow: vec![step("gcc", None), step("gc", Some("lazygit")), step("gq", None)],
+            see_also: vec!["gc".to_string()],
+            ..cmd("gccgcgq", Mode::Normal)
+        };
+        assert_eq!(workflow.step_commands(&commands), [Some(2), None, None]);
+        assert_eq!(workflow.related(&commands), [2, 0]);
+    }
+
+    #[test]
+    fn test_twin() {
+        let cmd = |keys: &str, mode| Command {
+            keys: keys.to_string(),
+            mode,
+            ..Default::default()
+        };
+        let commands = [
+            cmd("]d", Mode::Normal),
+            cmd("[d", Mode::Normal),
+            cmd("]]", Mode::Normal),
+            cmd("[[", Mode::Normal),
+            cmd("]h", Mode::Normal),
+            cmd("[h", Mode::Visual),
+            cmd("]", Mode::Normal),
+        ];
+        let twins: Vec<Option<usize>> = commands.iter().map(|c| c.twin(&commands)).collect();
+        assert_eq!(twins, [Some(1), Some(0), Some(3), Some(2), None, None, None]);
+    }
+
+    #[test]
+    fn test_append_command_source() {
+        let cmd = Command {
+            keys: "<leader>zz".to_string(),
+            description: "Zen mode".to_string(),
+            category: Category::Ui,
+            mode: Mode::Normal,
+            ..Default::default()
+        };
+
+        let toml = "# mine\n[[commands]]\nkeys = \"gz\"\ndescription = \"Custom\"\ncategory = \"code\"\n";
+        let updated = append_command_source(toml, FileFormat::Toml, &cmd).unwrap();
+        assert!(updated.starts_with("# mine\n"));
+        assert_eq!(parse_commands(&updated, FileFormat::Toml).unwrap().len(), 2);
+
+        let yaml = "- keys: gz\n  description: Custom\n  category: code";
+        let updated = append_command_source(yaml, FileFormat::Yaml, &cmd).unwrap();
+        assert_eq!(parse_commands(&updated, FileFormat::Yaml).unwrap()[1].keys, "<leader>zz");
+
+        let updated = append_command_source("", FileFormat::Json, &cmd).unwrap();
+        assert_eq!(parse_commands(&updated, FileFormat::Json).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_check_keys() {
+        assert!(check_keys("<leader>ff").is_empty());
+        assert!(check_keys("<C-w>v").is_empty());
+        assert!(check_keys("<C-Down>").is_empty());
+        assert!(check_keys("<F5>").is_empty());
+        assert!(check_keys("<S-F11><PageDown><Home>").is_empty());
+        assert_eq!(check_keys("<F13>"), vec![KeyIssue::UnknownSpecial("F13".to_string())]);
+        assert_eq!(check_keys("<C-w"), vec![KeyIssue::Unterminated("C-w".to_string())]);
+        assert!(check_keys("<").is_empty());
+        assert_eq!(check_keys("<>"), vec![KeyIssue::EmptySpecial]);
+        assert_eq!(check_keys("<X-w>"), vec![KeyIssue::UnknownModifier("X".to_string())]);
+        assert_eq!(check_keys("<Foo>"), vec![KeyIssue::UnknownSpecial("Foo".to_string())]);
+        assert!(check_keys("<leader>-").is_empty());
+        assert!(check_keys("<D-s><cmd-s><Super-s><win-s>").is_empty());
+        assert!(check_keys("<C-->").is_empty());
+        assert_eq!(check_keys("<C->"), vec![KeyIssue::UnknownSpecial("C-".to_string())]);
+        assert!(check_keys("\"+y").is_empty());
+        assert!(check_keys("<ScrollWheelDown><C-ScrollWheelUp><2-LeftMouse>").is_empty());
+        assert_eq!(check_keys("<2-w>"), vec![KeyIssue::UnknownModifier("2".to_string())]);
+        assert!(KeyIssue::Unterminated("C-w".to_string()).is_warning());
+    }
 }
@@ -0,0 +1,249 @@
+use crate::commands::{Category, Command, Mode};
+use crossterm::style::Stylize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How a binding in my keymaps differs from stock LazyVim
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Change {
+    /// Bound in mine only
+    Added,
+    /// Bound in both, to something else in mine
+    Changed,
+    /// Bound in stock LazyVim only
+    Removed,
+}
+
+impl Change {
+    pub fn sign(self) -> &'static str {
+        match self {
+            Change::Added => "+",
+            Change::Changed => "~",
+            Change::Removed => "-",
+        }
+    }
+}
+
+/// One binding that differs, with what it does on each side that has it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Difference {
+    pub change: Change,
+    pub category: Category,
+    pub mode: Mode,
+    pub keys: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stock: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mine: Option<String>,
+}
+
+/// Key notation as Vim reads it, to match bindings spelled differently: modifiers and key
+/// names in `<...>` ignore case, and so does the letter of a Ctrl chord
+pub fn canonical(keys: &str) -> String {
+    let mut out = String::new();
+    let mut rest = keys;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        let inner = &rest[start + 1..start + len];
+        let parts: Vec<&str> = inner.split('-').collect();
+        let (name, modifiers) = parts.split_last().unwrap_or((&"", &[]));
+        out.push('<');
+        for modifier in modifiers {
+            out.push_str(&modifier.to_uppercase());
+            out.push('-');
+        }
+        let ctrl = modifiers.iter().any(|m| m.eq_ignore_ascii_case("c"));
+        if name.chars().count() == 1 && !ctrl {
+            out.push_str(name);
+        } else {
+            out.push_str(&name.to_lowercase());
+        }
+        out.push('>');
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Whether stock `keys` are likely a mapping LazyVim makes rather than one of Vim's own
+/// commands: a leader sequence, a bracket pair, or anything with a `<...>` key. Imported
+/// keymaps only list mappings, so Vim's `dd` or `ciw` missing from them isn't a removal.
+fn is_mapping(keys: &str) -> bool {
+    keys.contains('<') && keys.contains('>') || keys.starts_with(['[', ']'])
+}
+
+/// The differences between my keymaps and stock LazyVim's, keyed on mode and keys, grouped by
+/// category. A stock binding's alternatives count as the same binding; ex commands and
+/// workflows are left out, and only stock mappings can be removed.
+pub fn compare(stock: &[Command], mine: &[Command]) -> Vec<Difference> {
+    let is_binding = |cmd: &&Command| !cmd.is_ex() && !cmd.is_workflow();
+    let key = |mode: Mode, keys: &str| (mode, canonical(keys));
+    let mut stock_bindings: HashMap<(Mode, String), &Command> = HashMap::new();
+    for cmd in stock.iter().filter(is_binding) {
+        for keys in std::iter::once(&cmd.keys).chain(&cmd.alternatives) {
+            stock_bindings.entry(key(cmd.mode, keys)).or_insert(cmd);
+        }
+    }
+    let mut mine_bindings: HashMap<(Mode, String), &Command> = HashMap::new();
+    for cmd in mine.iter().filter(is_binding) {
+        mine_bindings.entry(key(cmd.mode, &cmd.keys)).or_insert(cmd);
+    }
+
+    let mut differences = Vec::new();
+    for cmd in mine.iter().filter(is_binding) {
+        let (change, stock_cmd) = match stock_bindings.get(&key(cmd.mode, &cmd.keys)) {
+            None => (Change::Added, None),
+            Some(stock_cmd) if same_description(stock_cmd, cmd) => continue,
+            Some(stock_cmd) => (Change::Changed, Some(*stock_cmd)),
+        };
+        differences.push(Difference {
+            change,
+            category: stock_cmd.map_or(&cmd.category, |stock_cmd| &stock_cmd.category).clone(),
+            mode: cmd.mode,
+            keys: cmd.keys.clone(),
+            stock: stock_cmd.map(|stock_cmd| stock_cmd.description.clone()),
+            mine: Some(cmd.description.clone()),
+        });
+    }
+    for cmd in stock.iter().filter(|cmd| is_binding(cmd) && is_mapping(&cmd.keys)) {
+        let bound = std::iter::once(&cmd.keys)
+            .chain(&cmd.alternatives)
+            .any(|keys| mine_bindings.contains_key(&key(cmd.mode, keys)));
+        if !bound {
+            differences.push(Difference {
+                change: Change::Removed,
+                category: cmd.category.clone(),
+                mode: cmd.mode,
+                keys: cmd.keys.clone(),
+                stock: Some(cmd.description.clone()),
+                mine: None,
+            });
+        }
+    }
+
+    let order = Category::in_order(differences.iter().map(|d| &d.category));
+    let rank = |category: &Category| order.iter().position(|c| c == category);
+    // Stable, so each category keeps mine's order, then stock's
+    differences.sort_by_key(|d| (rank(&d.category), d.change as u8));
+    differences
+}
+
+fn same_description(stock: &Command, mine: &Command) -> bool {
+    stock.description.trim().eq_ignore_ascii_case(mine.description.trim())
+}
+
+/// The differences under a heading per category, one line each marked `+`, `~` or `-`, and a
+/// count of each at the end. Colored green, yellow and red when `color` is set.
+pub fn format_differences(differences: &[Difference], color: bool) -> String {
+    let mut lines = Vec::new();
+    let mut category = None;
+    for d in differences {
+        if category != Some(&d.category) {
+            if category.is_some() {
+                lines.push(String::new());
+            }
+            category = Some(&d.category);
+            lines.push(d.category.as_str().to_string());
+        }
+        let what = match (&d.stock, &d.mine) {
+            (Some(stock), Some(mine)) => format!("{} → {}", stock, mine),
+            (Some(description), None) | (None, Some(description)) => description.clone(),
+            (None, None) => String::new(),
+        };
+        let line = format!("  {} {:<16} {} {}", d.change.sign(), d.keys, d.mode.short(), what);
+        lines.push(match (color, d.change) {
+            (false, _) => line,
+            (true, Change::Added) => line.green().to_string(),
+            (true, Change::Changed) => line.yellow().to_string(),
+            (true, Change::Removed) => line.red().to_string(),
+        });
+    }
+
+    let count = |change| differences.iter().filter(|d| d.change == change).count();
+    if !lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines.push(format!(
+        "{} added, {} changed, {} removed",
+        count(Change::Added),
+        count(Change::Changed),
+        count(Change::Removed)
+    ));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(keys: &str, description: &str, category: Category) -> Command {
+        Command {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            category,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        assert_eq!(canonical("<c-W>v"), "<C-w>v");
+        assert_eq!(canonical("<C-w>V"), "<C-w>V");
+        assert_eq!(canonical("<leader>ff"), canonical("<Leader>ff"));
+        assert_eq!(canonical("<cr>"), canonical("<CR>"));
+        assert_eq!(canonical("<S-h>"), "<S-h>");
+        assert_ne!(canonical("<S-h>"), canonical("<S-H>"));
+        assert_eq!(canonical("a<b"), "a<b");
+    }
+
+    #[test]
+    fn test_is_mapping() {
+        assert!(is_mapping("<leader>ff"));
+        assert!(is_mapping("<C-h>"));
+        assert!(is_mapping("]b"));
+        assert!(!is_mapping("ciw"));
+        assert!(!is_mapping("gd"));
+    }
+
+    #[test]
+    fn test_compare() {
+        let mut buffer = command("<S-h>", "Previous buffer", Category::Buffer);
+        buffer.alternatives = vec!["[b".to_string()];
+        let stock = vec![
+            command("<leader>ff", "Find files", Category::Search),
+            command("<leader>sg", "Grep", Category::Search),
+            command("<leader>gg", "Lazygit", Category::Git),
+            command("dd", "Delete line", Category::General),
+            buffer,
+        ];
+        let mine = vec![
+            command("<Leader>ff", "find files", Category::Search),
+            command("<leader>sg", "Live grep with args", Category::Search),
+            command("<leader>xx", "Trouble", Category::General),
+            command("[b", "Previous buffer", Category::Buffer),
+            command(":Lazy", "Plugins", Category::Plugin),
+        ];
+
+        let differences = compare(&stock, &mine);
+        let summary: Vec<(Change, &str)> =
+            differences.iter().map(|d| (d.change, d.keys.as_str())).collect();
+        assert_eq!(
+            summary,
+            [
+                (Change::Added, "<leader>xx"),
+                (Change::Changed, "<leader>sg"),
+                (Change::Removed, "<leader>gg"),
+            ]
+        );
+        assert_eq!(differences[1].stock.as_deref(), Some("Grep"));
+
+        let text = format_differences(&differences, false);
+        assert!(text.starts_with("General\n  + <leader>xx       N Trouble\n\nSearch\n"));
+        assert!(text.contains("  ~ <leader>sg       N Grep → Live grep with args"));
+        assert!(text.ends_with("\n\n1 added, 1 changed, 1 removed"));
+    }
+}
@@ -0,0 +1,177 @@
+use anyhow::Context;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, List, ListItem, ListState},
+    Frame,
+};
+use serde::Deserialize;
+
+/// One half of a composable Vim command
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct GrammarPart {
+    pub keys: String,
+    pub name: String,
+    /// What this part contributes to the combined command
+    pub explain: String,
+}
+
+/// Operators and the motions/text objects they can be combined with
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Grammar {
+    pub operators: Vec<GrammarPart>,
+    pub motions: Vec<GrammarPart>,
+}
+
+impl Grammar {
+    /// Load the embedded operator/motion data
+    pub fn load() -> anyhow::Result<Self> {
+        serde_json::from_str(include_str!("../data/grammar.json"))
+            .context("failed to parse embedded grammar data")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Column {
+    #[default]
+    Operator,
+    Motion,
+}
+
+/// Selection state of the operator + motion picker
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Composer {
+    pub operator: usize,
+    pub motion: usize,
+    pub focus: Column,
+}
+
+impl Composer {
+    pub fn toggle_focus(&mut self) {
+        self.focus = match self.focus {
+            Column::Operator => Column::Motion,
+            Column::Motion => Column::Operator,
+        };
+    }
+
+    /// Move the focused column's selection, wrapping at either end
+    pub fn move_selection(&mut self, grammar: &Grammar, delta: isize) {
+        let (value, len) = match self.focus {
+            Column::Operator => (&mut self.operator, grammar.operators.len()),
+            Column::Motion => (&mut self.motion, grammar.motions.len()),
+        };
+        if len > 0 {
+            *value = (*value as isize + delta).rem_euclid(len as isize) as usize;
+        }
+    }
+
+    /// The selected operator and motion, if the grammar has both
+    pub fn parts<'a>(&self, grammar: &'a Grammar) -> Option<(&'a GrammarPart, &'a GrammarPart)> {
+        Some((
+            grammar.operators.get(self.operator)?,
+            grammar.motions.get(self.motion)?,
+        ))
+    }
+
+    /// Key sequence of the combined command, e.g. `d` + `iw` -> `diw`
+    pub fn keys(&self, grammar: &Grammar) -> Option<String> {
+        self.parts(grammar)
+            .map(|(operator, motion)| format!("{}{}", operator.keys, motion.keys))
+    }
+}
+
+/// Draw the two-column operator/motion picker
+pub fn draw_picker(frame: &mut Frame, area: Rect, grammar: &Grammar, composer: &Composer) {
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(area);
+
+    draw_column(
+        frame,
+        columns[0],
+        " Operator ",
+        &grammar.operators,
+        composer.operator,
+        composer.focus == Column::Operator,
+    );
+    draw_column(
+        frame,
+        columns[1],
+        " Motion / text object (←→ switch · Esc: close) ",
+        &grammar.motions,
+        composer.motion,
+        composer.focus == Column::Motion,
+    );
+}
+
+fn draw_column(
+    frame: &mut Frame,
+    area: Rect,
+    title: &str,
+    parts: &[GrammarPart],
+    selected: usize,
+    focused: bool,
+) {
+    let items: Vec<ListItem> = parts
+        .iter()
+        .map(|part| {
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<4}", part.keys), Style::default().fg(Color::Cyan)),
+                Span::raw(part.name.clone()),
+            ]))
+        })
+        .collect();
+
+    let border = if focused {
+        Style::default().fg(Color::Yellow)
+    } else {
+        Style::default()
+    };
+    let highlight = if focused {
+        Style::default()
+            .bg(Color::DarkGray)
+            .add_modifier(Modifier::BOLD)
+    } else {
+        Style::default().add_modifier(Modifier::BOLD)
+    };
+    let list = List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(border)
+                .title(title.to_string()),
+        )
+        .highlight_style(highlight)
+        .highlight_symbol("> ");
+
+    let mut state = ListState::default();
+    state.select(Some(selected));
+    frame.render_stateful_widget(list, area, &mut state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_grammar() {
+        let grammar = Grammar::load().unwrap();
+        assert!(!grammar.operators.is_empty());
+        assert!(grammar.motions.iter().any(|m| m.keys == "iw"));
+    }
+
+    #[test]
+    fn test_compose_and_wrap() {
+        let grammar = Grammar::load().unwrap();
+        let mut composer = Composer::default();
+        composer.toggle_focus();
+        composer.move_selection(&grammar, -1);
+        assert_eq!(composer.motion, grammar.motions.len() - 1);
+
+        let iw = grammar.motions.iter().position(|m| m.keys == "iw").unwrap();
+        composer.motion = iw;
+        assert_eq!(composer.keys(&grammar).as_deref(), Some("diw"));
+    }
+}
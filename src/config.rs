@@ -0,0 +1,397 @@
+use crate::commands::{self, CategoryLayout, Leaders};
+use crate::keyboard::{Keyboard, KeyboardLayout, ModifierHand, Palette, Platform};
+use crate::search::{MatchMode, SearchEngine, Weights};
+use crate::theme::{ColorSupport, Theme};
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// User settings from `config.toml` in the config directory
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Show nerd-font category icons in the results list
+    pub icons: bool,
+    /// Keep search history between sessions
+    pub save_history: bool,
+    /// Matching used for the search box at startup
+    pub match_mode: MatchMode,
+    /// Score multipliers for each searched field
+    pub weights: Weights,
+    /// Score multipliers used when searching ex commands
+    pub ex_weights: Weights,
+    /// Play motions and edits on sample code next to the details
+    pub simulation: bool,
+    /// With nothing typed, show category cards to open instead of every command
+    pub start_screen: bool,
+    /// How long each frame of the key animation shows, in milliseconds; 0 is rejected
+    pub frame_ms: u64,
+    /// Longest wait for input before the screen is refreshed, in milliseconds. Lower keeps
+    /// clocks and messages prompter, higher uses less CPU; animation frames stay on time either
+    /// way.
+    pub tick_ms: u64,
+    /// How long the selection has to stay put, when it moves in quick succession like a held
+    /// arrow key, before its keys are animated, in milliseconds; 0 animates every step
+    pub settle_ms: u64,
+    /// Percent raising the search scores of favorites and often viewed commands, so the usual
+    /// lookups rank first among close matches; 0 turns it off
+    pub usage_boost: u32,
+    /// Most commands that can be pinned above the results
+    pub max_pinned: usize,
+    /// Animate sequences; when off, the legend view shows every frame at once
+    pub animation: bool,
+    /// Mark each frame change of the animation, to get the rhythm of a sequence: `off`, `pulse`
+    /// (flash the keyboard's border), `bell` (ring the terminal bell), or `both`
+    pub metronome: Metronome,
+    /// Describe sequences in words instead of drawing the keyboard
+    pub plain: bool,
+    /// Say what each frame of the animation does in words under the keyboard, as it plays
+    pub narrate: bool,
+    /// Color scheme: `default`, `high-contrast`, or `monochrome`
+    pub theme: Theme,
+    /// Colors the terminal can show: `auto`, `full`, `16`, or `8`
+    pub colors: ColorSupport,
+    /// Colors of the frames of a sequence: `default`, or the colorblind-friendly
+    /// `deuteranopia` or `protanopia`
+    pub palette: Palette,
+    /// Physical keyboard layout to draw: `us`, `qwertz`, or `azerty`
+    pub layout: KeyboardLayout,
+    /// Which Shift and Ctrl keys chords light: `left`, or `opposite` the key's hand
+    pub modifier_hand: ModifierHand,
+    /// Whose names the bottom-row modifiers get: `linux`, `windows`, or `macos`; the running
+    /// OS when unset
+    pub platform: Option<Platform>,
+    /// What `<leader>` stands for, as Neovim's `mapleader` (e.g. `","`); read from the Neovim
+    /// config when unset
+    pub leader: Option<String>,
+    /// What `<localleader>` stands for, as `maplocalleader`; read from the Neovim config when
+    /// unset
+    pub localleader: Option<String>,
+    /// Draw keycap images under the keyboard labels in terminals with kitty graphics (needs the
+    /// `graphics` build feature)
+    pub keycap_images: bool,
+    /// Command files opened in tabs after the LazyVim commands, relative to the config
+    /// directory unless absolute
+    pub tabs: Vec<PathBuf>,
+    /// Order, names and colors of the categories, and the ones to hide
+    pub categories: CategoryLayout,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            icons: false,
+            save_history: false,
+            match_mode: MatchMode::default(),
+            weights: Weights::default(),
+            ex_weights: Weights::ex(),
+            simulation: true,
+            start_screen: true,
+            frame_ms: 500,
+            tick_ms: 250,
+            settle_ms: 100,
+            usage_boost: 10,
+            max_pinned: 5,
+            animation: true,
+            metronome: Metronome::default(),
+            plain: false,
+            narrate: false,
+            theme: Theme::default(),
+            colors: ColorSupport::default(),
+            palette: Palette::default(),
+            layout: KeyboardLayout::default(),
+            modifier_hand: ModifierHand::default(),
+            platform: None,
+            leader: None,
+            localleader: None,
+            keycap_images: true,
+            tabs: Vec::new(),
+            categories: CategoryLayout::default(),
+        }
+    }
+}
+
+/// A beat on each frame change of the key animation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Metronome {
+    #[default]
+    Off,
+    Pulse,
+    Bell,
+    Both,
+}
+
+impl Metronome {
+    pub fn pulses(self) -> bool {
+        matches!(self, Metronome::Pulse | Metronome::Both)
+    }
+
+    pub fn rings(self) -> bool {
+        matches!(self, Metronome::Bell | Metronome::Both)
+    }
+}
+
+impl Config {
+    pub fn default_path() -> Option<PathBuf> {
+        commands::config_dir().map(|dir| dir.join("config.toml"))
+    }
+
+    /// A search engine using the configured match mode and weights
+    pub fn search_engine(&self) -> SearchEngine {
+        let mut engine = SearchEngine::new();
+        engine.mode = self.match_mode;
+        engine.weights = self.weights;
+        engine.ex_weights = self.ex_weights;
+        engine
+    }
+
+    /// The keyboard to draw, in the configured layout, platform, modifier hand and palette
+    pub fn keyboard(&self) -> Keyboard {
+        let platform = self.platform.unwrap_or_else(Platform::current);
+        let mut keyboard = Keyboard::with_platform(self.layout, platform);
+        keyboard.modifier_hand = self.modifier_hand;
+        keyboard.palette = self.palette;
+        keyboard
+    }
+
+    /// The leaders set here, else in the Neovim config, else LazyVim's
+    pub fn leaders(&self) -> Leaders {
+        let (mut leader, mut localleader) = (self.leader.clone(), self.localleader.clone());
+        if leader.is_none() || localleader.is_none() {
+            if let Some(dir) = dirs::config_dir() {
+                let (detected, detected_local) = detect_leaders(&dir.join("nvim"));
+                leader = leader.or(detected);
+                localleader = localleader.or(detected_local);
+            }
+        }
+        let defaults = Leaders::default();
+        Leaders {
+            leader: leader.unwrap_or(defaults.leader),
+            localleader: localleader.unwrap_or(defaults.localleader),
+        }
+    }
+
+    /// Load the config file with `profile`'s settings on top, using defaults when there is
+    /// none
+    pub fn load(profile: Option<&str>) -> anyhow::Result<Self> {
+        match (Self::default_path(), profile) {
+            (Some(path), _) if path.exists() => Self::load_from(&path, profile),
+            (_, Some(name)) => anyhow::bail!("no config file to take profile {:?} from", name),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path, profile: Option<&str>) -> anyhow::Result<Self> {
+        let data = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        Self::parse(&data, profile).with_context(|| format!("failed to load {}", path.display()))
+    }
+
+    /// The settings in `source`, overridden by those under `[profiles.<profile>]`. Tables
+    /// like `[weights]` are merged key by key; profiles left unused aren't checked.
+    pub fn parse(source: &str, profile: Option<&str>) -> anyhow::Result<Self> {
+        let mut table: toml::Table = toml::from_str(source)?;
+        let profiles = match table.remove("profiles") {
+            Some(toml::Value::Table(profiles)) => profiles,
+            Some(_) => anyhow::bail!("`profiles` should be a table of named profiles"),
+            None => toml::Table::new(),
+        };
+        if let Some(name) = profile {
+            match profiles.get(name) {
+                Some(toml::Value::Table(settings)) => merge(&mut table, settings.clone()),
+                Some(_) => anyhow::bail!("profile {:?} should be a table of settings", name),
+                None => {
+                    let known: Vec<&str> = profiles.keys().map(String::as_str).collect();
+                    anyhow::bail!(
+                        "no profile named {:?} (known: {})",
+                        name,
+                        if known.is_empty() { "none".to_string() } else { known.join(", ") }
+                    );
+                }
+            }
+        }
+        let config: Self = toml::Value::Table(table).try_into()?;
+        anyhow::ensure!(config.frame_ms > 0, "`frame_ms` should be at least 1 millisecond");
+        Ok(config)
+    }
+}
+
+/// Put `overrides` into `table`, merging the tables both have rather than replacing them
+fn merge(table: &mut toml::Table, overrides: toml::Table) {
+    for (key, value) in overrides {
+        match (table.get_mut(&key), value) {
+            (Some(toml::Value::Table(inner)), toml::Value::Table(value)) => merge(inner, value),
+            (_, value) => {
+                table.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Neovim config files that usually set the leaders, in the order they're read
+const NVIM_FILES: &[&str] = &[
+    "init.vim",
+    "init.lua",
+    "lua/config/lazy.lua",
+    "lua/config/options.lua",
+];
+
+/// `mapleader` and `maplocalleader` as set in the Neovim config in `dir`, where it says
+fn detect_leaders(dir: &Path) -> (Option<String>, Option<String>) {
+    let (mut leader, mut localleader) = (None, None);
+    for file in NVIM_FILES {
+        let Ok(source) = std::fs::read_to_string(dir.join(file)) else {
+            continue;
+        };
+        leader = nvim_setting(&source, "mapleader").or(leader);
+        localleader = nvim_setting(&source, "maplocalleader").or(localleader);
+    }
+    (leader, localleader)
+}
+
+/// The last string assigned to global variable `name` in Lua or Vimscript, as in
+/// `vim.g.mapleader = " "` or `let mapleader = ","`
+fn nvim_setting(source: &str, name: &str) -> Option<String> {
+    source.lines().rev().find_map(|line| assigned_string(line.trim(), name))
+}
+
+fn assigned_string(line: &str, name: &str) -> Option<String> {
+    let target = line
+        .strip_prefix("vim.g.")
+        .or_else(|| line.strip_prefix("let g:"))
+        .or_else(|| line.strip_prefix("let "))?;
+    let value = target.strip_prefix(name)?.trim_start().strip_prefix('=')?.trim_start();
+    let mut chars = value.chars();
+    let quote = chars.next().filter(|c| *c == '"' || *c == '\'')?;
+    let mut string = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.push(chars.next()?),
+            c if c == quote => return Some(string),
+            c => string.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_config() {
+        let config: Config = toml::from_str("icons = true").unwrap();
+        assert!(config.icons);
+
+        let config: Config = toml::from_str("").unwrap();
+        assert!(!config.icons);
+
+        assert!(!config.save_history);
+        assert!(config.simulation);
+        assert!(config.start_screen);
+        assert_eq!(config.frame_ms, 500);
+        assert_eq!(config.tick_ms, 250);
+        assert_eq!(config.settle_ms, 100);
+        assert_eq!(config.usage_boost, 10);
+        assert_eq!(config.max_pinned, 5);
+        assert!(config.animation);
+        assert_eq!(config.metronome, Metronome::Off);
+        assert!(!config.plain);
+        assert!(!config.narrate);
+        assert_eq!(config.theme, Theme::Default);
+
+        let config: Config = toml::from_str("theme = \"high-contrast\"").unwrap();
+        assert_eq!(config.theme, Theme::HighContrast);
+
+        let config: Config = toml::from_str("colors = \"16\"").unwrap();
+        assert_eq!(config.colors, ColorSupport::Ansi16);
+
+        let config: Config = toml::from_str("palette = \"deuteranopia\"").unwrap();
+        assert_eq!(config.keyboard().palette, Palette::Deuteranopia);
+
+        let config: Config = toml::from_str("layout = \"azerty\"").unwrap();
+        assert_eq!(config.layout, KeyboardLayout::Azerty);
+        assert_eq!(config.match_mode, MatchMode::Fuzzy);
+
+        let config: Config = toml::from_str("match_mode = \"smartcase\"").unwrap();
+        assert_eq!(config.match_mode, MatchMode::Smartcase);
+
+        let config: Config = toml::from_str("[weights]\nkeys = 5").unwrap();
+        assert_eq!(config.weights.keys, 5);
+        assert_eq!(config.weights.description, 3);
+        assert_eq!(config.ex_weights, Weights::ex());
+
+        assert!(toml::from_str::<Config>("icon = true").is_err());
+
+        let config: Config = toml::from_str("modifier_hand = \"opposite\"").unwrap();
+        assert_eq!(config.keyboard().modifier_hand, ModifierHand::Opposite);
+
+        let config: Config = toml::from_str("platform = \"macos\"").unwrap();
+        assert_eq!(config.keyboard().platform, Platform::Macos);
+
+        let config: Config = toml::from_str("leader = \",\"").unwrap();
+        assert_eq!(config.leaders().leader, ",");
+
+        let config: Config = toml::from_str("metronome = \"both\"").unwrap();
+        assert!(config.metronome.pulses() && config.metronome.rings());
+        assert!(!Metronome::Bell.pulses());
+
+        let config: Config = toml::from_str("tabs = [\"packs/telescope.json\"]").unwrap();
+        assert_eq!(config.tabs, [PathBuf::from("packs/telescope.json")]);
+
+        let config: Config = toml::from_str("[categories]\nhidden = [\"debug\"]").unwrap();
+        assert!(config.categories.is_hidden(&commands::Category::Debug));
+    }
+
+    #[test]
+    fn test_profiles() {
+        let source = r#"
+theme = "high-contrast"
+frame_ms = 500
+
+[weights]
+keys = 5
+
+[profiles.teaching]
+frame_ms = 1200
+weights = { description = 9 }
+
+[profiles.daily]
+tabs = ["mine.json"]
+"#;
+        let config = Config::parse(source, None).unwrap();
+        assert_eq!((config.frame_ms, config.weights.keys), (500, 5));
+        assert!(config.tabs.is_empty());
+
+        let config = Config::parse(source, Some("teaching")).unwrap();
+        assert_eq!(config.frame_ms, 1200);
+        assert_eq!(config.theme, Theme::HighContrast);
+        assert_eq!((config.weights.keys, config.weights.description), (5, 9));
+
+        let config = Config::parse(source, Some("daily")).unwrap();
+        assert_eq!(config.tabs, [PathBuf::from("mine.json")]);
+
+        let error = Config::parse(source, Some("demo")).unwrap_err().to_string();
+        assert_eq!(error, "no profile named \"demo\" (known: daily, teaching)");
+        assert!(Config::parse("[profiles.typo]\nicon = true", Some("typo")).is_err());
+
+        let error = Config::parse("[profiles.fast]\nframe_ms = 0", Some("fast")).unwrap_err();
+        assert_eq!(error.to_string(), "`frame_ms` should be at least 1 millisecond");
+    }
+
+    #[test]
+    fn test_nvim_setting() {
+        let source = r#"
+-- vim.g.mapleader = "x"
+vim.g.mapleader = " "
+vim.g.maplocalleader = "\\"
+vim.g.mapleader_extra = "y"
+"#;
+        assert_eq!(nvim_setting(source, "mapleader").as_deref(), Some(" "));
+        assert_eq!(nvim_setting(source, "maplocalleader").as_deref(), Some("\\"));
+        assert_eq!(nvim_setting("let mapleader = ','", "mapleader").as_deref(), Some(","));
+        assert_eq!(nvim_setting("let g:mapleader=\";\"", "mapleader").as_deref(), Some(";"));
+        assert_eq!(nvim_setting("vim.g.mapleader = leader", "mapleader"), None);
+    }
+}
@@ -0,0 +1,254 @@
+use crate::commands::KeyFrame;
+use crate::keyboard::Keyboard;
+use crate::popup::centered_rect;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Samples an average covers, roughly; older ones fade out
+const AVERAGE_SAMPLES: u32 = 16;
+const OVERLAY_WIDTH: u16 = 52;
+
+/// The latest and running average of something timed over and over
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timing {
+    pub last: Duration,
+    pub average: Duration,
+    samples: u32,
+}
+
+impl Timing {
+    pub fn record(&mut self, took: Duration) {
+        self.last = took;
+        self.samples = (self.samples + 1).min(AVERAGE_SAMPLES);
+        // A plain mean until there are enough samples, then an exponential one
+        self.average = (self.average * (self.samples - 1) + took) / self.samples;
+    }
+}
+
+/// What `--debug` measures, and whether its overlay is showing (F12)
+#[derive(Debug, Clone, Default)]
+pub struct DebugInfo {
+    pub shown: bool,
+    /// Drawing one frame of the screen
+    pub draw: Timing,
+    /// From a key event being read to the frame showing its effect
+    pub event: Timing,
+    /// Running the query and rebuilding the results list
+    pub search: Timing,
+    /// When the last key event was read, until a frame is drawn after it
+    pub event_at: Option<Instant>,
+}
+
+impl DebugInfo {
+    /// Note that a frame was drawn in `took`, which also ends the wait on a key event
+    pub fn drawn(&mut self, took: Duration, now: Instant) {
+        self.draw.record(took);
+        if let Some(at) = self.event_at.take() {
+            self.event.record(now.saturating_duration_since(at));
+        }
+    }
+}
+
+/// How long each step of starting the TUI took, for `--profile-startup`
+#[derive(Debug, Clone)]
+pub struct StartupProfile {
+    started: Instant,
+    last: Instant,
+    /// Each step with how long it took, and whether it ran on the background loader
+    steps: Vec<(String, Duration, bool)>,
+    /// From starting to the first frame drawn
+    first_frame: Option<Duration>,
+}
+
+impl Default for StartupProfile {
+    fn default() -> Self {
+        let now = Instant::now();
+        Self {
+            started: now,
+            last: now,
+            steps: Vec::new(),
+            first_frame: None,
+        }
+    }
+}
+
+impl StartupProfile {
+    /// Note that `step` is done, timed from the end of the one before
+    pub fn mark(&mut self, step: &str) {
+        let now = Instant::now();
+        self.steps.push((step.to_string(), now - self.last, false));
+        self.last = now;
+    }
+
+    /// Note a step the background loader took `took` for
+    pub fn background(&mut self, step: &str, took: Duration) {
+        self.steps.push((step.to_string(), took, true));
+    }
+
+    /// Note that a frame was drawn, the first one ending startup
+    pub fn drawn(&mut self) {
+        if self.first_frame.is_none() {
+            self.mark("first frame");
+            self.first_frame = Some(self.last - self.started);
+        }
+    }
+
+    /// A line per step with its time in milliseconds, then the time to the first frame
+    pub fn report(&self) -> String {
+        let ms = |took: Duration| format!("{:8.2} ms", took.as_secs_f64() * 1000.0);
+        let mut lines = vec!["Startup profile".to_string()];
+        for (step, took, background) in &self.steps {
+            let step = if *background { format!("{} (background)", step) } else { step.clone() };
+            lines.push(format!("  {:<32}{}", step, ms(*took)));
+        }
+        if let Some(took) = self.first_frame {
+            lines.push(format!("  {:<32}{}", "until the first frame", ms(took)));
+        }
+        lines.join("\n")
+    }
+}
+
+/// Where `--debug` writes its log: `lvcheat.log` in the data directory
+pub fn log_path() -> Option<PathBuf> {
+    crate::commands::data_dir().map(|dir| dir.join("lvcheat.log"))
+}
+
+/// Send tracing events at debug level and up to the file at `path`, replacing an older log
+pub fn init_logging(path: &Path) -> anyhow::Result<()> {
+    use anyhow::Context;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    tracing_subscriber::fmt()
+        .with_writer(std::sync::Mutex::new(file))
+        .with_ansi(false)
+        .with_max_level(tracing::Level::DEBUG)
+        .try_init()
+        .map_err(|error| anyhow::anyhow!(error))
+}
+
+fn format_duration(duration: Duration) -> String {
+    format!("{:.2}ms", duration.as_secs_f64() * 1000.0)
+}
+
+/// The timings, then each parsed frame of `keys` as the parser saw it and the keyboard lights
+/// it, the one animating marked. Drawn in the top right corner, over everything else.
+pub fn draw_overlay(
+    frame: &mut Frame,
+    info: &DebugInfo,
+    keys: Option<&str>,
+    frames: &[KeyFrame],
+    current: usize,
+    keyboard: &Keyboard,
+) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let timing = |name: &str, timing: Timing| {
+        Line::from(vec![
+            Span::styled(format!("{:<8}", name), dim),
+            Span::raw(format!("{:>9}", format_duration(timing.last))),
+            Span::styled(format!("  avg {}", format_duration(timing.average)), dim),
+        ])
+    };
+    let mut lines = vec![
+        timing("draw", info.draw),
+        timing("event", info.event),
+        timing("search", info.search),
+        Line::from(""),
+        Line::from(Span::styled(
+            format!("{} frames of {}", frames.len(), keys.unwrap_or("nothing")),
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+    ];
+    for (i, kf) in frames.iter().enumerate() {
+        let parsed: Vec<&str> = kf.keys.iter().map(|key| key.key.as_str()).collect();
+        let marker = if i == current { "▶" } else { " " };
+        let mut spans = vec![
+            Span::raw(format!("{}{:>2} ", marker, i + 1)),
+            Span::styled(parsed.join("+"), Style::default().fg(Color::Cyan)),
+            Span::styled(" → ", dim),
+            Span::raw(keyboard.frame_ids(kf).join(" ")),
+        ];
+        if let Some(note) = &kf.note {
+            spans.push(Span::styled(format!("  ({})", note), dim));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    let height = lines.len() as u16 + 2;
+    let screen = frame.area();
+    let area = centered_rect(OVERLAY_WIDTH, height, screen);
+    let area = Rect {
+        x: screen.right().saturating_sub(area.width),
+        y: screen.y,
+        ..area
+    };
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Magenta))
+            .title(" Debug (F12) "),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing() {
+        let ms = Duration::from_millis;
+        let mut timing = Timing::default();
+        timing.record(ms(4));
+        timing.record(ms(8));
+        assert_eq!(timing.last, ms(8));
+        assert_eq!(timing.average, ms(6));
+
+        // Long after a slow start, the average is near the steady time
+        for _ in 0..100 {
+            timing.record(ms(2));
+        }
+        assert!(timing.average < ms(3));
+    }
+
+    #[test]
+    fn test_event_latency() {
+        let start = Instant::now();
+        let mut info = DebugInfo::default();
+        info.drawn(Duration::from_millis(1), start);
+        assert_eq!(info.event, Timing::default());
+
+        info.event_at = Some(start);
+        info.drawn(Duration::from_millis(1), start + Duration::from_millis(5));
+        assert_eq!(info.event.last, Duration::from_millis(5));
+        assert_eq!(info.event_at, None);
+    }
+
+    #[test]
+    fn test_startup_profile() {
+        let mut profile = StartupProfile::default();
+        profile.mark("commands");
+        profile.background("ex commands", Duration::from_micros(1500));
+        profile.drawn();
+        profile.drawn();
+        let report = profile.report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].starts_with("  commands "));
+        assert!(lines[2].starts_with("  ex commands (background) "));
+        assert!(lines[2].ends_with("    1.50 ms"));
+        assert!(lines[3].starts_with("  first frame "));
+        assert!(lines[4].starts_with("  until the first frame "));
+    }
+}
@@ -0,0 +1,258 @@
+use crate::commands::{self, KeyFrame, Leaders};
+use crate::keynotation;
+use crate::state::ViewMode;
+use crate::ui::App;
+use anyhow::{bail, Context};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// A scripted run of the TUI for `lvcheat demo`, read from a TOML file with a `[[step]]` table
+/// per action
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Script {
+    /// Pause after each key press and step
+    #[serde(default = "default_pace")]
+    pub pace_ms: u64,
+    #[serde(rename = "step", default)]
+    pub steps: Vec<Step>,
+}
+
+fn default_pace() -> u64 {
+    150
+}
+
+/// One action of a demo
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// Type text a character at a time, into the search box or whatever has focus
+    Type(String),
+    /// Press keys written in Vim notation, e.g. `<Down><Down>` or `<C-v>`
+    Key(String),
+    /// Select the nth command listed, counting from 1
+    Select(usize),
+    /// Switch the keyboard view
+    View(ViewMode),
+    /// Wait this many milliseconds
+    WaitMs(u64),
+}
+
+impl Script {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&text).with_context(|| format!("parsing {}", path.display()))
+    }
+}
+
+/// What a demo does next
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Beat {
+    Press(KeyEvent),
+    /// Index of the command among those listed
+    Select(usize),
+    View(ViewMode),
+    Pause(Duration),
+}
+
+/// A script being played into the app, one beat at a time
+pub struct Demo {
+    beats: VecDeque<Beat>,
+    pace: Duration,
+    /// When the next beat plays
+    due: Instant,
+}
+
+impl Demo {
+    /// Start playing `script` a beat from `now`; fails on keys it couldn't press
+    pub fn new(script: &Script, now: Instant) -> anyhow::Result<Self> {
+        let mut beats = VecDeque::new();
+        for (i, step) in script.steps.iter().enumerate() {
+            match step {
+                Step::Type(text) => beats.extend(text.chars().map(|c| Beat::Press(typed(c)))),
+                Step::Key(keys) => {
+                    let frames = keynotation::parse_checked(keys, &Leaders::default())
+                        .with_context(|| format!("step {}: can't press {:?}", i + 1, keys))?;
+                    for frame in &frames {
+                        let presses = presses(frame)
+                            .with_context(|| format!("step {}: can't press {:?}", i + 1, keys))?;
+                        beats.extend(presses.into_iter().map(Beat::Press));
+                    }
+                }
+                Step::Select(0) => bail!("step {}: results are counted from 1", i + 1),
+                Step::Select(n) => beats.push_back(Beat::Select(n - 1)),
+                Step::View(mode) => beats.push_back(Beat::View(*mode)),
+                Step::WaitMs(ms) => beats.push_back(Beat::Pause(Duration::from_millis(*ms))),
+            }
+        }
+        let pace = Duration::from_millis(script.pace_ms);
+        Ok(Self {
+            beats,
+            pace,
+            due: now + pace,
+        })
+    }
+
+    /// Play every beat due by `now` into `app`; false once the last one has played and its
+    /// pause is over
+    pub fn advance(&mut self, app: &mut App, now: Instant) -> anyhow::Result<bool> {
+        while now >= self.due {
+            let Some(beat) = self.beats.pop_front() else {
+                return Ok(false);
+            };
+            self.due += match beat {
+                Beat::Press(key) => {
+                    app.handle_event(Event::Key(key))?;
+                    self.pace
+                }
+                Beat::Select(n) => {
+                    if !app.select_result(n) {
+                        app.messages.push(format!("Demo: no result {} to select", n + 1));
+                    }
+                    self.pace
+                }
+                Beat::View(mode) => {
+                    app.view_mode = mode;
+                    self.pace
+                }
+                Beat::Pause(pause) => pause,
+            };
+        }
+        Ok(true)
+    }
+
+    /// When the next beat plays, for the main loop to wake up in time
+    pub fn due(&self) -> Instant {
+        self.due
+    }
+}
+
+/// The key press typing `c`
+fn typed(c: char) -> KeyEvent {
+    let modifiers = if c.is_ascii_uppercase() {
+        KeyModifiers::SHIFT
+    } else {
+        KeyModifiers::NONE
+    };
+    KeyEvent::new(KeyCode::Char(c), modifiers)
+}
+
+/// The key presses for a frame: its one chord, or each key in turn for a count or register
+fn presses(frame: &KeyFrame) -> anyhow::Result<Vec<KeyEvent>> {
+    let mut presses = Vec::new();
+    let mut modifiers = KeyModifiers::NONE;
+    for key in &frame.keys {
+        if key.is_modifier {
+            modifiers |= match key.key.as_str() {
+                "Ctrl" => KeyModifiers::CONTROL,
+                "Shift" => KeyModifiers::SHIFT,
+                "Alt" => KeyModifiers::ALT,
+                "Super" => KeyModifiers::SUPER,
+                other => bail!("no such modifier as {}", other),
+            };
+            continue;
+        }
+        if key.is_mouse {
+            bail!("{} is a mouse action", key.key);
+        }
+        let code = match (key_code(&key.key), modifiers.contains(KeyModifiers::SHIFT)) {
+            (Some(KeyCode::Tab), true) => KeyCode::BackTab,
+            (Some(KeyCode::Char(c)), true) => KeyCode::Char(commands::shifted(c)),
+            (Some(code), _) => code,
+            (None, _) => bail!("no key named {}", key.key),
+        };
+        let held = std::mem::replace(&mut modifiers, KeyModifiers::NONE);
+        presses.push(KeyEvent::new(code, held));
+    }
+    Ok(presses)
+}
+
+/// The key code for a key as the parser names it, e.g. `Enter`, `PageDown` or `a`
+fn key_code(name: &str) -> Option<KeyCode> {
+    let code = match name {
+        "Enter" => KeyCode::Enter,
+        "Esc" => KeyCode::Esc,
+        "Tab" => KeyCode::Tab,
+        "Backsp" => KeyCode::Backspace,
+        "Space" => KeyCode::Char(' '),
+        "Up" => KeyCode::Up,
+        "Down" => KeyCode::Down,
+        "Left" => KeyCode::Left,
+        "Right" => KeyCode::Right,
+        "Home" => KeyCode::Home,
+        "End" => KeyCode::End,
+        "PageUp" => KeyCode::PageUp,
+        "PageDown" => KeyCode::PageDown,
+        "Insert" => KeyCode::Insert,
+        "Del" => KeyCode::Delete,
+        _ => {
+            let mut chars = name.chars();
+            return match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(KeyCode::Char(c)),
+                _ => name.strip_prefix('F')?.parse().ok().map(KeyCode::F),
+            };
+        }
+    };
+    Some(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_script() {
+        let script: Script = toml::from_str(
+            r#"
+pace_ms = 50
+
+[[step]]
+type = "find"
+
+[[step]]
+key = "<Down><C-v>G"
+
+[[step]]
+select = 2
+
+[[step]]
+view = "both"
+
+[[step]]
+wait_ms = 1000
+"#,
+        )
+        .unwrap();
+        assert_eq!(script.pace_ms, 50);
+        assert_eq!(script.steps[3], Step::View(ViewMode::Both));
+
+        let start = Instant::now();
+        let demo = Demo::new(&script, start).unwrap();
+        assert_eq!(demo.due(), start + Duration::from_millis(50));
+        let beats: Vec<&Beat> = demo.beats.iter().skip(4).collect();
+        assert_eq!(
+            beats,
+            [
+                &Beat::Press(KeyEvent::new(KeyCode::Down, KeyModifiers::NONE)),
+                &Beat::Press(KeyEvent::new(KeyCode::Char('v'), KeyModifiers::CONTROL)),
+                &Beat::Press(KeyEvent::new(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+                &Beat::Select(1),
+                &Beat::View(ViewMode::Both),
+                &Beat::Pause(Duration::from_secs(1)),
+            ]
+        );
+
+        let bad = |source: &str| {
+            let script: Script = toml::from_str(source).unwrap();
+            Demo::new(&script, start).is_err()
+        };
+        assert!(bad("[[step]]\nkey = \"<Nope>\""));
+        assert!(bad("[[step]]\nkey = \"<ScrollWheelUp>\""));
+        assert!(bad("[[step]]\nselect = 0"));
+        assert!(toml::from_str::<Script>("[[step]]\npress = \"j\"").is_err());
+    }
+}
@@ -0,0 +1,178 @@
+use crate::commands::{shifted, Command, KeyFrame};
+use crate::prefixes::Prefixes;
+
+/// What a frame of the sequence contributes, for the explain panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Meaning {
+    Leader,
+    /// A prefix grouping bindings, and what they're about
+    Prefix(String),
+    /// A count or register typed before the keys
+    Note(String),
+    /// The word of the mnemonic or description the key stands for
+    Word(String),
+    /// The last key, when no word stands out: what the whole command does
+    Action(String),
+    Unknown,
+}
+
+/// One frame of the sequence with what it contributes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub keys: String,
+    pub meaning: Meaning,
+}
+
+/// How Vim writes the keys of `kf`, e.g. `g`, `G`, `<C-w>` or `<leader>`, to look up prefixes
+fn notation(kf: &KeyFrame) -> String {
+    let Some(base) = kf.keys.iter().find(|k| !k.is_modifier) else {
+        return String::new();
+    };
+    if base.is_leader {
+        return "<leader>".to_string();
+    }
+    let modifiers: Vec<&str> = kf
+        .keys
+        .iter()
+        .filter(|k| k.is_modifier)
+        .map(|k| k.key.as_str())
+        .collect();
+    let single = (base.key.chars().count() == 1).then(|| base.key.chars().next().unwrap());
+    match (single, modifiers.as_slice()) {
+        (Some(c), []) => c.to_string(),
+        (Some(c), ["Shift"]) => shifted(c).to_string(),
+        _ => {
+            let prefix: String = modifiers
+                .iter()
+                .map(|m| match *m {
+                    "Ctrl" => "C-",
+                    "Alt" => "M-",
+                    "Super" => "D-",
+                    _ => "S-",
+                })
+                .collect();
+            format!("<{}{}>", prefix, base.key)
+        }
+    }
+}
+
+/// The words of `cmd`'s mnemonic after its `=`, or else of its description
+fn words(cmd: &Command) -> Vec<String> {
+    let text = match cmd.mnemonic.as_deref().and_then(|m| m.split_once(" = ")) {
+        Some((_, words)) => words,
+        None => cmd.description.as_str(),
+    };
+    text.split_whitespace()
+        .map(|word| word.trim_matches(|c: char| !c.is_alphanumeric()).to_lowercase())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Each frame of `frames`, the keys of `cmd`, with what it contributes: the leader, known
+/// `prefixes`, counts and registers, and for the rest the word of the mnemonic or description
+/// starting with that key
+pub fn explain(cmd: &Command, frames: &[KeyFrame], prefixes: &Prefixes) -> Vec<Step> {
+    let mut typed = String::new();
+    let mut meanings: Vec<Option<Meaning>> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, kf)| {
+            typed.push_str(&notation(kf));
+            let leader = kf.keys.iter().any(|k| k.is_leader);
+            match (&kf.note, prefixes.name(&typed)) {
+                (Some(note), _) => Some(Meaning::Note(note.clone())),
+                _ if leader => Some(Meaning::Leader),
+                (None, Some(name)) if i < frames.len() - 1 => {
+                    Some(Meaning::Prefix(name.to_string()))
+                }
+                _ => None,
+            }
+        })
+        .collect();
+
+    // The rest take words from the end, where the action usually is: `<leader>ff` "Find
+    // Files" has the prefix take the first f, and the last f stand for "files"
+    let words = words(cmd);
+    let mut end = words.len();
+    for (i, kf) in frames.iter().enumerate().rev() {
+        if meanings[i].is_some() {
+            continue;
+        }
+        let first = kf.keys.iter().find(|k| !k.is_modifier).and_then(|k| k.key.chars().next());
+        let found = first.and_then(|c| {
+            let c = c.to_lowercase().next()?;
+            words[..end].iter().rposition(|word| word.starts_with(c))
+        });
+        meanings[i] = Some(match found {
+            Some(found) => {
+                end = found;
+                Meaning::Word(words[found].clone())
+            }
+            None if i == frames.len() - 1 => Meaning::Action(cmd.description.clone()),
+            None => Meaning::Unknown,
+        });
+    }
+
+    frames
+        .iter()
+        .zip(meanings)
+        .map(|(kf, meaning)| Step {
+            keys: kf.label(),
+            meaning: meaning.unwrap_or(Meaning::Unknown),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse_key_string;
+
+    fn meanings(keys: &str, description: &str, mnemonic: Option<&str>) -> Vec<Meaning> {
+        let cmd = Command {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            mnemonic: mnemonic.map(str::to_string),
+            ..Default::default()
+        };
+        let prefixes = Prefixes::builtin().unwrap();
+        let steps = explain(&cmd, &parse_key_string(keys), &prefixes);
+        steps.into_iter().map(|step| step.meaning).collect()
+    }
+
+    #[test]
+    fn test_notation() {
+        let spelled: Vec<String> = parse_key_string("<leader>gG<C-w>]<S-Tab>")
+            .iter()
+            .map(notation)
+            .collect();
+        assert_eq!(spelled, ["<leader>", "g", "G", "<C-w>", "]", "<S-Tab>"]);
+    }
+
+    #[test]
+    fn test_explain() {
+        let word = |w: &str| Meaning::Word(w.to_string());
+        let prefix = |name: &str| Meaning::Prefix(name.to_string());
+        assert_eq!(
+            meanings("gd", "Goto Definition", Some("gd = Go to Definition")),
+            [prefix("goto"), word("definition")]
+        );
+        assert_eq!(
+            meanings("<leader>ff", "Find Files (Root Dir)", None),
+            [Meaning::Leader, prefix("find / file"), word("files")]
+        );
+        assert_eq!(
+            meanings("<leader>ghs", "Stage Hunk", None),
+            [Meaning::Leader, prefix("git"), prefix("git hunks"), word("stage")]
+        );
+        assert_eq!(
+            meanings("3<C-w>>", "Wider window", None),
+            [
+                Meaning::Note("count: 3".to_string()),
+                word("window"),
+                Meaning::Action("Wider window".to_string())
+            ]
+        );
+        assert_eq!(meanings("K", "Hover", None), [Meaning::Action("Hover".to_string())]);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::keyboard::{Keyboard, KeyboardState};
+use crate::theme;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use ratatui::{layout::Rect, style::Color};
+use std::io::{self, Write};
+
+/// Pixels per terminal cell in a keycap image; the terminal scales it to its own cells
+const CELL_WIDTH: usize = 10;
+const CELL_HEIGHT: usize = 20;
+/// Most base64 data the protocol takes in one escape sequence
+const CHUNK_SIZE: usize = 4096;
+/// Ids of our images start here, one per keyboard on screen
+const FIRST_IMAGE_ID: usize = 7310;
+const KEYCAP: (u8, u8, u8) = (0x3a, 0x3a, 0x3a);
+const CORNER_RADIUS: usize = 4;
+
+/// Whether the terminal is one known to show kitty graphics. Inside tmux they don't get
+/// through, so it's the text keyboard there.
+pub fn supported() -> bool {
+    let var = |name| std::env::var(name).unwrap_or_default();
+    if !var("TMUX").is_empty() {
+        return false;
+    }
+    var("TERM") == "xterm-kitty"
+        || var("TERM") == "xterm-ghostty"
+        || !var("KITTY_WINDOW_ID").is_empty()
+        || ["WezTerm", "ghostty"].contains(&var("TERM_PROGRAM").as_str())
+}
+
+/// A keycap image to put under a keyboard's labels, over its art area
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placement {
+    pub area: Rect,
+    /// Lit keys and their colors, sorted so equal states compare equal
+    colors: Vec<(&'static str, Color)>,
+}
+
+impl Placement {
+    pub fn new(area: Rect, state: &KeyboardState) -> Self {
+        let mut colors: Vec<_> = state.key_colors().into_iter().collect();
+        colors.sort_by_key(|&(id, _)| id);
+        Self { area, colors }
+    }
+}
+
+/// The keycap images on screen, only sent again when one changes
+#[derive(Debug, Default)]
+pub struct Keycaps {
+    shown: Vec<Placement>,
+}
+
+impl Keycaps {
+    /// Replace the images on screen with `placements`
+    pub fn show(
+        &mut self,
+        out: &mut impl Write,
+        keyboard: &Keyboard,
+        placements: Vec<Placement>,
+    ) -> io::Result<()> {
+        if placements == self.shown {
+            return Ok(());
+        }
+        self.clear(out)?;
+        for (i, placement) in placements.iter().enumerate() {
+            let area = placement.area;
+            let (width, height) = (area.width as usize, area.height as usize);
+            let pixels = rasterize(keyboard, &placement.colors, width, height);
+            let data = STANDARD.encode(pixels);
+            // Save the cursor, draw at the area's corner without moving it, and restore it
+            write!(out, "\x1b7\x1b[{};{}H", area.y + 1, area.x + 1)?;
+            let chunks: Vec<&[u8]> = data.as_bytes().chunks(CHUNK_SIZE).collect();
+            for (n, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(n + 1 < chunks.len());
+                if n == 0 {
+                    // Below the text (z=-1), so the labels drawn there stay on top
+                    write!(
+                        out,
+                        "\x1b_Ga=T,f=32,s={},v={},c={},r={},i={},z=-1,C=1,q=2,m={};",
+                        width * CELL_WIDTH,
+                        height * CELL_HEIGHT,
+                        width,
+                        height,
+                        FIRST_IMAGE_ID + i,
+                        more
+                    )?;
+                } else {
+                    write!(out, "\x1b_Gm={};", more)?;
+                }
+                out.write_all(chunk)?;
+                write!(out, "\x1b\\")?;
+            }
+            write!(out, "\x1b8")?;
+        }
+        out.flush()?;
+        self.shown = placements;
+        Ok(())
+    }
+
+    /// Delete every image shown, e.g. before leaving the alternate screen
+    pub fn clear(&mut self, out: &mut impl Write) -> io::Result<()> {
+        for i in 0..self.shown.len() {
+            write!(out, "\x1b_Ga=d,d=I,i={},q=2\x1b\\", FIRST_IMAGE_ID + i)?;
+        }
+        self.shown.clear();
+        out.flush()
+    }
+}
+
+/// RGBA pixels of rounded keycaps over a transparent background, `width` by `height` cells of
+/// the keyboard art. Each cap runs between the middles of its borders, with a darker lip along
+/// the bottom.
+fn rasterize(
+    keyboard: &Keyboard,
+    colors: &[(&'static str, Color)],
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    let (image_width, image_height) = (width * CELL_WIDTH, height * CELL_HEIGHT);
+    let mut pixels = vec![0; image_width * image_height * 4];
+    for (id, pos) in &keyboard.key_positions {
+        let color = colors
+            .iter()
+            .find(|(lit, _)| lit == id)
+            .map_or(KEYCAP, |&(_, color)| theme::rgb(color));
+        let x0 = pos.col * CELL_WIDTH - CELL_WIDTH / 2 + 1;
+        let x1 = ((pos.col + pos.width) * CELL_WIDTH + CELL_WIDTH / 2 - 1).min(image_width);
+        let y0 = (pos.row - 1) * CELL_HEIGHT + CELL_HEIGHT / 2 + 1;
+        let y1 = ((pos.row + 1) * CELL_HEIGHT + CELL_HEIGHT / 2 - 1).min(image_height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                if outside_corner(x - x0, y - y0, x1 - x0, y1 - y0) {
+                    continue;
+                }
+                let (r, g, b) = if y + 3 >= y1 { darken(color) } else { color };
+                let i = (y * image_width + x) * 4;
+                pixels[i..i + 4].copy_from_slice(&[r, g, b, 0xff]);
+            }
+        }
+    }
+    pixels
+}
+
+/// Whether `(x, y)` in a `width` by `height` cap falls outside its rounded corners
+fn outside_corner(x: usize, y: usize, width: usize, height: usize) -> bool {
+    let r = CORNER_RADIUS;
+    let dx = r.saturating_sub(x).max((x + r + 1).saturating_sub(width));
+    let dy = r.saturating_sub(y).max((y + r + 1).saturating_sub(height));
+    dx * dx + dy * dy > r * r
+}
+
+fn darken((r, g, b): (u8, u8, u8)) -> (u8, u8, u8) {
+    let scale = |c: u8| (c as u16 * 7 / 10) as u8;
+    (scale(r), scale(g), scale(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> &[u8] {
+        let i = (y * width * CELL_WIDTH + x) * 4;
+        &pixels[i..i + 4]
+    }
+
+    #[test]
+    fn test_rasterize() {
+        let keyboard = Keyboard::new();
+        let pixels = rasterize(&keyboard, &[("f", Color::Yellow)], 46, 15);
+        assert_eq!(pixels.len(), 46 * CELL_WIDTH * 15 * CELL_HEIGHT * 4);
+
+        let center = |id: &str| {
+            let pos = keyboard.key_positions[id];
+            (pos.col * CELL_WIDTH + CELL_WIDTH / 2, pos.row * CELL_HEIGHT + CELL_HEIGHT / 2)
+        };
+        let (x, y) = center("f");
+        assert_eq!(pixel(&pixels, 46, x, y), [0xe5, 0xe5, 0x10, 0xff]);
+        let (x, y) = center("g");
+        assert_eq!(pixel(&pixels, 46, x, y), [0x3a, 0x3a, 0x3a, 0xff]);
+        // Gaps between keys and the corners stay transparent
+        assert_eq!(pixel(&pixels, 46, 0, 0)[3], 0);
+        assert_eq!(pixel(&pixels, 46, CELL_WIDTH / 2 + 1, CELL_HEIGHT / 2 + 1)[3], 0);
+    }
+
+    #[test]
+    fn test_show_once() {
+        let keyboard = Keyboard::new();
+        let state = KeyboardState::keys(vec!["f"]);
+        let placement = Placement::new(Rect::new(2, 3, 46, 15), &state);
+        let mut keycaps = Keycaps::default();
+        let mut out = Vec::new();
+        keycaps.show(&mut out, &keyboard, vec![placement.clone()]).unwrap();
+        let sent = String::from_utf8(out).unwrap();
+        assert!(sent.starts_with("\x1b7\x1b[4;3H\x1b_Ga=T,f=32,s=460,v=300,c=46,r=15,i=7310,"));
+        assert!(sent.ends_with("\x1b\\\x1b8"));
+
+        // Nothing changed, nothing sent
+        let mut out = Vec::new();
+        keycaps.show(&mut out, &keyboard, vec![placement]).unwrap();
+        assert!(out.is_empty());
+
+        keycaps.show(&mut out, &keyboard, Vec::new()).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b_Ga=d,d=I,i=7310,q=2\x1b\\");
+    }
+}
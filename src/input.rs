@@ -0,0 +1,268 @@
+/// Single-line text buffer with a movable cursor and readline-style editing
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TextInput {
+    text: String,
+    /// Byte offset of the cursor, always on a char boundary
+    cursor: usize,
+}
+
+impl TextInput {
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Text before and after the cursor
+    pub fn split(&self) -> (&str, &str) {
+        self.text.split_at(self.cursor)
+    }
+
+    /// Replace the whole text, moving the cursor to the end
+    pub fn set(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+    }
+
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.cursor = 0;
+    }
+
+    pub fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    /// Delete the char before the cursor
+    pub fn backspace(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.text.remove(self.cursor);
+        }
+    }
+
+    /// Delete the char under the cursor
+    pub fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    pub fn move_left(&mut self) {
+        if let Some(c) = self.text[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_right(&mut self) {
+        if let Some(c) = self.text[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
+    }
+
+    pub fn move_home(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_end(&mut self) {
+        self.cursor = self.text.len();
+    }
+
+    /// Delete the word before the cursor along with trailing whitespace (Ctrl+W)
+    pub fn delete_word(&mut self) {
+        let before = &self.text[..self.cursor];
+        let trimmed = before.trim_end();
+        let start = trimmed
+            .rfind(char::is_whitespace)
+            .map_or(0, |i| i + trimmed[i..].chars().next().unwrap().len_utf8());
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+    }
+
+    /// Delete everything before the cursor (Ctrl+U)
+    pub fn kill_to_start(&mut self) {
+        self.text.replace_range(..self.cursor, "");
+        self.cursor = 0;
+    }
+}
+
+/// How many past queries are kept
+const HISTORY_LIMIT: usize = 100;
+
+/// Shell-style history of search queries, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    entries: Vec<String>,
+    /// Entry currently recalled into the search box, if browsing
+    position: Option<usize>,
+    /// Query that was being typed before browsing started
+    draft: String,
+}
+
+impl History {
+    pub fn new(mut entries: Vec<String>) -> Self {
+        let excess = entries.len().saturating_sub(HISTORY_LIMIT);
+        entries.drain(..excess);
+        Self {
+            entries,
+            ..Self::default()
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    pub fn is_browsing(&self) -> bool {
+        self.position.is_some()
+    }
+
+    /// The entry recalled into the search box, while browsing
+    pub fn recalled(&self) -> Option<&str> {
+        self.position.map(|position| self.entries[position].as_str())
+    }
+
+    /// Stop browsing without recording anything, e.g. once the recalled query is edited
+    pub fn stop(&mut self) {
+        self.position = None;
+    }
+
+    /// Record a query and stop browsing; refinements of the last query replace it
+    pub fn push(&mut self, query: &str) {
+        self.position = None;
+        let query = query.trim();
+        if query.is_empty() {
+            return;
+        }
+        if self.entries.last().is_some_and(|last| query.starts_with(last.as_str())) {
+            self.entries.pop();
+        }
+        self.entries.retain(|entry| entry != query);
+        self.entries.push(query.to_string());
+        let excess = self.entries.len().saturating_sub(HISTORY_LIMIT);
+        self.entries.drain(..excess);
+    }
+
+    /// Step to an older query, remembering `current` as the draft when browsing starts
+    pub fn previous(&mut self, current: &str) -> Option<&str> {
+        let position = match self.position {
+            Some(0) => return None,
+            Some(pos) => pos - 1,
+            None if self.entries.is_empty() => return None,
+            None => {
+                self.draft = current.to_string();
+                self.entries.len() - 1
+            }
+        };
+        self.position = Some(position);
+        Some(&self.entries[position])
+    }
+
+    /// Step to a newer query, ending with the draft once past the newest entry
+    pub fn next(&mut self) -> Option<&str> {
+        let position = self.position? + 1;
+        if position < self.entries.len() {
+            self.position = Some(position);
+            Some(&self.entries[position])
+        } else {
+            self.position = None;
+            Some(&self.draft)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(text: &str) -> TextInput {
+        let mut input = TextInput::default();
+        input.set(text);
+        input
+    }
+
+    #[test]
+    fn test_insert_mid_text() {
+        let mut input = input("find");
+        input.move_home();
+        input.move_right();
+        input.insert('X');
+        assert_eq!(input.as_str(), "fXind");
+        assert_eq!(input.split(), ("fX", "ind"));
+    }
+
+    #[test]
+    fn test_delete_word() {
+        let mut input = input("git  blame line ");
+        input.delete_word();
+        assert_eq!(input.as_str(), "git  blame ");
+        input.delete_word();
+        input.delete_word();
+        assert_eq!(input.as_str(), "");
+        assert_eq!(input.cursor(), 0);
+    }
+
+    #[test]
+    fn test_kill_to_start_keeps_tail() {
+        let mut input = input("buffer close");
+        for _ in 0.."close".len() {
+            input.move_left();
+        }
+        input.kill_to_start();
+        assert_eq!(input.as_str(), "close");
+        assert_eq!(input.cursor(), 0);
+    }
+
+    #[test]
+    fn test_multibyte_editing() {
+        let mut input = input("a␣b");
+        input.move_left();
+        input.backspace();
+        assert_eq!(input.as_str(), "ab");
+        input.move_left();
+        input.delete();
+        assert_eq!(input.as_str(), "b");
+    }
+
+    #[test]
+    fn test_history_browsing() {
+        let mut history = History::new(vec!["grep".to_string(), "buffer".to_string()]);
+        assert_eq!(history.next(), None);
+
+        assert_eq!(history.previous("fi"), Some("buffer"));
+        assert_eq!(history.previous("buffer"), Some("grep"));
+        assert_eq!(history.previous("grep"), None);
+        assert_eq!(history.next(), Some("buffer"));
+        assert_eq!(history.next(), Some("fi"));
+        assert!(!history.is_browsing());
+
+        assert_eq!(history.previous("fi"), Some("buffer"));
+        assert_eq!(history.recalled(), Some("buffer"));
+        history.stop();
+        assert_eq!(history.recalled(), None);
+        assert_eq!(history.next(), None);
+    }
+
+    #[test]
+    fn test_history_push() {
+        let mut history = History::default();
+        history.push("gi");
+        history.push("git");
+        history.push("  ");
+        history.push("lsp");
+        history.push("git");
+        assert_eq!(history.entries(), ["lsp", "git"]);
+
+        for i in 0..HISTORY_LIMIT + 5 {
+            history.push(&format!("q{}.", i));
+        }
+        assert_eq!(history.entries().len(), HISTORY_LIMIT);
+    }
+}
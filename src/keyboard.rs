@@ -1,8 +1,15 @@
+//! Keyboard layout data and the widget that draws it with keys lit up.
+
 use ratatui::{
-    style::{Color, Style},
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
     text::{Line, Span},
+    widgets::{Block, Paragraph, StatefulWidget, Widget},
 };
-use std::collections::HashMap;
+use crate::commands::{self, Key, KeyFrame};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
 
 /// Colors for each frame in the sequence
 pub const FRAME_COLORS: &[Color] = &[
@@ -16,8 +23,536 @@ pub const FRAME_COLORS: &[Color] = &[
     Color::LightGreen,
 ];
 
+/// Frame colors telling frames apart without red against green, for deuteranopia: the
+/// Okabe-Ito colors
+const DEUTERANOPIA_COLORS: &[Color] = &[
+    Color::Rgb(0xe6, 0x9f, 0x00),
+    Color::Rgb(0x56, 0xb4, 0xe9),
+    Color::Rgb(0xf0, 0xe4, 0x42),
+    Color::Rgb(0xcc, 0x79, 0xa7),
+    Color::Rgb(0x00, 0x9e, 0x73),
+    Color::Rgb(0xd5, 0x5e, 0x00),
+    Color::Rgb(0x64, 0x8f, 0xff),
+    Color::Rgb(0xbb, 0xbb, 0xbb),
+];
+
+/// Frame colors for protanopia, where reds also look dark: the seven Okabe-Ito colors (all
+/// but black), ordered so that the ones protanopes confuse most, the oranges and the
+/// purple and blue, are never next to each other
+const PROTANOPIA_COLORS: &[Color] = &[
+    Color::Rgb(0x56, 0xb4, 0xe9),
+    Color::Rgb(0xe6, 0x9f, 0x00),
+    Color::Rgb(0x00, 0x72, 0xb2),
+    Color::Rgb(0xf0, 0xe4, 0x42),
+    Color::Rgb(0xcc, 0x79, 0xa7),
+    Color::Rgb(0x00, 0x9e, 0x73),
+    Color::Rgb(0xd5, 0x5e, 0x00),
+];
+
+/// Shapes marking each frame in the sequence bar, so the frames don't tell apart by hue alone
+pub const FRAME_SHAPES: &[&str] = &["●", "▲", "■", "◆", "★", "▼", "✚", "◐"];
+
+/// Which colors the frames of a sequence get
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Palette {
+    /// `FRAME_COLORS`
+    #[default]
+    Default,
+    /// Friendly to deuteranopia (green-blind)
+    Deuteranopia,
+    /// Friendly to protanopia (red-blind)
+    Protanopia,
+}
+
+impl Palette {
+    /// The colors of the frames in order, cycled for longer sequences
+    pub fn colors(self) -> &'static [Color] {
+        match self {
+            Palette::Default => FRAME_COLORS,
+            Palette::Deuteranopia => DEUTERANOPIA_COLORS,
+            Palette::Protanopia => PROTANOPIA_COLORS,
+        }
+    }
+
+    /// The color of frame `i`
+    pub fn frame_color(self, i: usize) -> Color {
+        let colors = self.colors();
+        colors[i % colors.len()]
+    }
+}
+
+/// Text styles standing in for `FRAME_COLORS` when drawing without color
+pub const FRAME_MODIFIERS: &[Modifier] = &[
+    Modifier::REVERSED,
+    Modifier::BOLD.union(Modifier::UNDERLINED),
+    Modifier::REVERSED.union(Modifier::UNDERLINED),
+    Modifier::BOLD.union(Modifier::ITALIC),
+    Modifier::REVERSED.union(Modifier::ITALIC),
+    Modifier::UNDERLINED.union(Modifier::ITALIC),
+];
+
+/// One key cap: the id highlights refer to, and its cell text with and without Shift
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyDef {
+    pub id: &'static str,
+    /// Text between the key's borders, padded to its width
+    pub label: &'static str,
+    pub shifted: &'static str,
+}
+
+impl KeyDef {
+    const fn new(id: &'static str, label: &'static str) -> Self {
+        Self {
+            id,
+            label,
+            shifted: label,
+        }
+    }
+
+    const fn shifted(id: &'static str, label: &'static str, shifted: &'static str) -> Self {
+        Self { id, label, shifted }
+    }
+}
+
+/// Rows every layout shares
+const FUNCTION_ROW: &[KeyDef] = &[
+    KeyDef::new("esc", "Esc"),
+    KeyDef::new("f1", "F1"),
+    KeyDef::new("f2", "F2"),
+    KeyDef::new("f3", "F3"),
+    KeyDef::new("f4", "F4"),
+    KeyDef::new("f5", "F5"),
+    KeyDef::new("f6", "F6"),
+    KeyDef::new("f7", "F7"),
+    KeyDef::new("f8", "F8"),
+    KeyDef::new("f9", "F9"),
+    KeyDef::new("f10", " F10"),
+    KeyDef::new("f11", "F11"),
+    KeyDef::new("f12", " F12"),
+];
+
+/// The bottom row as on Linux; `Platform::modifier_row` stands in the others' names
+const MODIFIER_ROW: &[KeyDef] = &[
+    KeyDef::new("ctrl", "Ctrl"),
+    KeyDef::new("super", "Sup"),
+    KeyDef::new("alt", "Alt"),
+    KeyDef::new("space", "      Space     "),
+    KeyDef::new("ralt", "Alt"),
+    KeyDef::new("fn", "Fn "),
+    KeyDef::new("menu", "Mnu"),
+    KeyDef::new("rctrl", "Ct"),
+];
+
+const WINDOWS_MODIFIER_ROW: &[KeyDef] = &[
+    KeyDef::new("ctrl", "Ctrl"),
+    KeyDef::new("super", "Win"),
+    KeyDef::new("alt", "Alt"),
+    KeyDef::new("space", "      Space     "),
+    KeyDef::new("ralt", "Alt"),
+    KeyDef::new("fn", "Fn "),
+    KeyDef::new("menu", "Mnu"),
+    KeyDef::new("rctrl", "Ct"),
+];
+
+/// Mac keyboards put Option before Command on both sides of Space
+const MAC_MODIFIER_ROW: &[KeyDef] = &[
+    KeyDef::new("ctrl", "Ctrl"),
+    KeyDef::new("alt", "Opt"),
+    KeyDef::new("super", "Cmd"),
+    KeyDef::new("space", "      Space     "),
+    KeyDef::new("rsuper", "Cmd"),
+    KeyDef::new("ralt", "Opt"),
+    KeyDef::new("fn", "Fn "),
+    KeyDef::new("rctrl", "Ct"),
+];
+
+const NAV_ROW: &[KeyDef] = &[
+    KeyDef::new("insert", "Ins"),
+    KeyDef::new("delete", "Del"),
+    KeyDef::new("home", "Home "),
+    KeyDef::new("end", "End "),
+    KeyDef::new("pageup", "PgUp"),
+    KeyDef::new("pagedown", "PgDn"),
+    KeyDef::new("left", " ← "),
+    KeyDef::new("up", " ↑ "),
+    KeyDef::new("down", " ↓ "),
+    KeyDef::new("right", " → "),
+];
+
+/// US ANSI layout, top row first. Right-hand modifiers are `rshift`, `ralt` and `rctrl`.
+const US: &[&[KeyDef]] = &[
+    FUNCTION_ROW,
+    &[
+        KeyDef::shifted("`", " `  ", " ~  "),
+        KeyDef::shifted("1", "1 ", "! "),
+        KeyDef::shifted("2", "2 ", "@ "),
+        KeyDef::shifted("3", "3 ", "# "),
+        KeyDef::shifted("4", "4 ", "$ "),
+        KeyDef::shifted("5", "5 ", "% "),
+        KeyDef::shifted("6", "6 ", "^ "),
+        KeyDef::shifted("7", "7 ", "& "),
+        KeyDef::shifted("8", "8 ", "* "),
+        KeyDef::shifted("9", "9 ", "( "),
+        KeyDef::shifted("0", "0 ", ") "),
+        KeyDef::shifted("-", "- ", "_ "),
+        KeyDef::shifted("=", "= ", "+ "),
+        KeyDef::new("backsp", "Bsp"),
+    ],
+    &[
+        KeyDef::new("tab", "Tab  "),
+        KeyDef::shifted("q", "q ", "Q "),
+        KeyDef::shifted("w", "w ", "W "),
+        KeyDef::shifted("e", "e ", "E "),
+        KeyDef::shifted("r", "r ", "R "),
+        KeyDef::shifted("t", "t ", "T "),
+        KeyDef::shifted("y", "y ", "Y "),
+        KeyDef::shifted("u", "u ", "U "),
+        KeyDef::shifted("i", "i ", "I "),
+        KeyDef::shifted("o", "o ", "O "),
+        KeyDef::shifted("p", "p ", "P "),
+        KeyDef::shifted("[", "[ ", "{ "),
+        KeyDef::shifted("]", "] ", "} "),
+        KeyDef::shifted("\\", "\\ ", "| "),
+    ],
+    &[
+        KeyDef::new("caps", "Caps  "),
+        KeyDef::shifted("a", "a ", "A "),
+        KeyDef::shifted("s", "s ", "S "),
+        KeyDef::shifted("d", "d ", "D "),
+        KeyDef::shifted("f", "f ", "F "),
+        KeyDef::shifted("g", "g ", "G "),
+        KeyDef::shifted("h", "h ", "H "),
+        KeyDef::shifted("j", "j ", "J "),
+        KeyDef::shifted("k", "k ", "K "),
+        KeyDef::shifted("l", "l ", "L "),
+        KeyDef::shifted(";", "; ", ": "),
+        KeyDef::shifted("'", "' ", "\" "),
+        KeyDef::new("enter", "Ent "),
+    ],
+    &[
+        KeyDef::new("shift", "Shift  "),
+        KeyDef::shifted("z", "z ", "Z "),
+        KeyDef::shifted("x", "x ", "X "),
+        KeyDef::shifted("c", "c ", "C "),
+        KeyDef::shifted("v", "v ", "V "),
+        KeyDef::shifted("b", "b ", "B "),
+        KeyDef::shifted("n", "n ", "N "),
+        KeyDef::shifted("m", "m ", "M "),
+        KeyDef::shifted(",", ", ", "< "),
+        KeyDef::shifted(".", ". ", "> "),
+        KeyDef::shifted("/", "/ ", "? "),
+        KeyDef::new("rshift", "Shift "),
+    ],
+    MODIFIER_ROW,
+    NAV_ROW,
+];
+
+/// German ISO layout. The ISO Enter spans two rows, so both of its cells use the `enter` id.
+const QWERTZ: &[&[KeyDef]] = &[
+    FUNCTION_ROW,
+    &[
+        KeyDef::shifted("^", " ^  ", " °  "),
+        KeyDef::shifted("1", "1 ", "! "),
+        KeyDef::shifted("2", "2 ", "\" "),
+        KeyDef::shifted("3", "3 ", "§ "),
+        KeyDef::shifted("4", "4 ", "$ "),
+        KeyDef::shifted("5", "5 ", "% "),
+        KeyDef::shifted("6", "6 ", "& "),
+        KeyDef::shifted("7", "7 ", "/ "),
+        KeyDef::shifted("8", "8 ", "( "),
+        KeyDef::shifted("9", "9 ", ") "),
+        KeyDef::shifted("0", "0 ", "= "),
+        KeyDef::shifted("ß", "ß ", "? "),
+        KeyDef::shifted("´", "´ ", "` "),
+        KeyDef::new("backsp", "Bsp"),
+    ],
+    &[
+        KeyDef::new("tab", "Tab  "),
+        KeyDef::shifted("q", "q ", "Q "),
+        KeyDef::shifted("w", "w ", "W "),
+        KeyDef::shifted("e", "e ", "E "),
+        KeyDef::shifted("r", "r ", "R "),
+        KeyDef::shifted("t", "t ", "T "),
+        KeyDef::shifted("z", "z ", "Z "),
+        KeyDef::shifted("u", "u ", "U "),
+        KeyDef::shifted("i", "i ", "I "),
+        KeyDef::shifted("o", "o ", "O "),
+        KeyDef::shifted("p", "p ", "P "),
+        KeyDef::shifted("ü", "ü ", "Ü "),
+        KeyDef::shifted("+", "+ ", "* "),
+        KeyDef::new("enter", "↵ "),
+    ],
+    &[
+        KeyDef::new("caps", "Caps"),
+        KeyDef::shifted("a", "a ", "A "),
+        KeyDef::shifted("s", "s ", "S "),
+        KeyDef::shifted("d", "d ", "D "),
+        KeyDef::shifted("f", "f ", "F "),
+        KeyDef::shifted("g", "g ", "G "),
+        KeyDef::shifted("h", "h ", "H "),
+        KeyDef::shifted("j", "j ", "J "),
+        KeyDef::shifted("k", "k ", "K "),
+        KeyDef::shifted("l", "l ", "L "),
+        KeyDef::shifted("ö", "ö ", "Ö "),
+        KeyDef::shifted("ä", "ä ", "Ä "),
+        KeyDef::shifted("#", "# ", "' "),
+        KeyDef::new("enter", "Ent"),
+    ],
+    &[
+        KeyDef::new("shift", "Shft"),
+        KeyDef::shifted("<", "< ", "> "),
+        KeyDef::shifted("y", "y ", "Y "),
+        KeyDef::shifted("x", "x ", "X "),
+        KeyDef::shifted("c", "c ", "C "),
+        KeyDef::shifted("v", "v ", "V "),
+        KeyDef::shifted("b", "b ", "B "),
+        KeyDef::shifted("n", "n ", "N "),
+        KeyDef::shifted("m", "m ", "M "),
+        KeyDef::shifted(",", ", ", "; "),
+        KeyDef::shifted(".", ". ", ": "),
+        KeyDef::shifted("-", "- ", "_ "),
+        KeyDef::new("rshift", "Shift "),
+    ],
+    MODIFIER_ROW,
+    NAV_ROW,
+];
+
+/// French ISO layout; the number row types digits with Shift
+const AZERTY: &[&[KeyDef]] = &[
+    FUNCTION_ROW,
+    &[
+        KeyDef::shifted("²", " ²  ", " ²  "),
+        KeyDef::shifted("&", "& ", "1 "),
+        KeyDef::shifted("é", "é ", "2 "),
+        KeyDef::shifted("\"", "\" ", "3 "),
+        KeyDef::shifted("'", "' ", "4 "),
+        KeyDef::shifted("(", "( ", "5 "),
+        KeyDef::shifted("-", "- ", "6 "),
+        KeyDef::shifted("è", "è ", "7 "),
+        KeyDef::shifted("_", "_ ", "8 "),
+        KeyDef::shifted("ç", "ç ", "9 "),
+        KeyDef::shifted("à", "à ", "0 "),
+        KeyDef::shifted(")", ") ", "° "),
+        KeyDef::shifted("=", "= ", "+ "),
+        KeyDef::new("backsp", "Bsp"),
+    ],
+    &[
+        KeyDef::new("tab", "Tab  "),
+        KeyDef::shifted("a", "a ", "A "),
+        KeyDef::shifted("z", "z ", "Z "),
+        KeyDef::shifted("e", "e ", "E "),
+        KeyDef::shifted("r", "r ", "R "),
+        KeyDef::shifted("t", "t ", "T "),
+        KeyDef::shifted("y", "y ", "Y "),
+        KeyDef::shifted("u", "u ", "U "),
+        KeyDef::shifted("i", "i ", "I "),
+        KeyDef::shifted("o", "o ", "O "),
+        KeyDef::shifted("p", "p ", "P "),
+        KeyDef::shifted("^", "^ ", "¨ "),
+        KeyDef::shifted("$", "$ ", "£ "),
+        KeyDef::new("enter", "↵ "),
+    ],
+    &[
+        KeyDef::new("caps", "Caps"),
+        KeyDef::shifted("q", "q ", "Q "),
+        KeyDef::shifted("s", "s ", "S "),
+        KeyDef::shifted("d", "d ", "D "),
+        KeyDef::shifted("f", "f ", "F "),
+        KeyDef::shifted("g", "g ", "G "),
+        KeyDef::shifted("h", "h ", "H "),
+        KeyDef::shifted("j", "j ", "J "),
+        KeyDef::shifted("k", "k ", "K "),
+        KeyDef::shifted("l", "l ", "L "),
+        KeyDef::shifted("m", "m ", "M "),
+        KeyDef::shifted("ù", "ù ", "% "),
+        KeyDef::shifted("*", "* ", "µ "),
+        KeyDef::new("enter", "Ent"),
+    ],
+    &[
+        KeyDef::new("shift", "Shft"),
+        KeyDef::shifted("<", "< ", "> "),
+        KeyDef::shifted("w", "w ", "W "),
+        KeyDef::shifted("x", "x ", "X "),
+        KeyDef::shifted("c", "c ", "C "),
+        KeyDef::shifted("v", "v ", "V "),
+        KeyDef::shifted("b", "b ", "B "),
+        KeyDef::shifted("n", "n ", "N "),
+        KeyDef::shifted(",", ", ", "? "),
+        KeyDef::shifted(";", "; ", ". "),
+        KeyDef::shifted(":", ": ", "/ "),
+        KeyDef::shifted("!", "! ", "§ "),
+        KeyDef::new("rshift", "Shift "),
+    ],
+    MODIFIER_ROW,
+    NAV_ROW,
+];
+
+/// Keys typed with the left hand on the US layout; everything else counts as the right hand's
+const US_LEFT_HAND: &[&str] = &[
+    "esc", "f1", "f2", "f3", "f4", "f5", "`", "1", "2", "3", "4", "5", "tab", "q", "w", "e",
+    "r", "t", "caps", "a", "s", "d", "f", "g", "shift", "z", "x", "c", "v", "b",
+];
+
+const QWERTZ_LEFT_HAND: &[&str] = &[
+    "esc", "f1", "f2", "f3", "f4", "f5", "^", "1", "2", "3", "4", "5", "tab", "q", "w", "e",
+    "r", "t", "caps", "a", "s", "d", "f", "g", "shift", "<", "y", "x", "c", "v", "b",
+];
+
+const AZERTY_LEFT_HAND: &[&str] = &[
+    "esc", "f1", "f2", "f3", "f4", "f5", "²", "&", "é", "\"", "'", "(", "tab", "a", "z", "e",
+    "r", "t", "caps", "q", "s", "d", "f", "g", "shift", "<", "w", "x", "c", "v", "b",
+];
+
+/// Characters typed with AltGr (the right Alt key) on the German layout, and their key
+const QWERTZ_ALTGR: &[(char, &str)] = &[
+    ('@', "q"),
+    ('€', "e"),
+    ('{', "7"),
+    ('[', "8"),
+    (']', "9"),
+    ('}', "0"),
+    ('\\', "ß"),
+    ('~', "+"),
+    ('|', "<"),
+];
+
+/// Characters typed with AltGr on the French layout, and their key
+const AZERTY_ALTGR: &[(char, &str)] = &[
+    ('~', "é"),
+    ('#', "\""),
+    ('{', "'"),
+    ('[', "("),
+    ('|', "-"),
+    ('`', "è"),
+    ('\\', "_"),
+    ('@', "à"),
+    (']', ")"),
+    ('}', "="),
+    ('€', "e"),
+];
+
+/// Physical keyboard layouts the art can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    #[default]
+    Us,
+    /// German
+    Qwertz,
+    /// French
+    Azerty,
+}
+
+impl KeyboardLayout {
+    /// Every row, top first, with the modifiers named as on `platform`
+    fn rows(self, platform: Platform) -> Vec<&'static [KeyDef]> {
+        let rows = match self {
+            KeyboardLayout::Us => US,
+            KeyboardLayout::Qwertz => QWERTZ,
+            KeyboardLayout::Azerty => AZERTY,
+        };
+        rows.iter()
+            .map(|&row| if row == MODIFIER_ROW { platform.modifier_row() } else { row })
+            .collect()
+    }
+
+    fn altgr(self) -> &'static [(char, &'static str)] {
+        match self {
+            KeyboardLayout::Us => &[],
+            KeyboardLayout::Qwertz => QWERTZ_ALTGR,
+            KeyboardLayout::Azerty => AZERTY_ALTGR,
+        }
+    }
+
+    fn left_hand(self) -> &'static [&'static str] {
+        match self {
+            KeyboardLayout::Us => US_LEFT_HAND,
+            KeyboardLayout::Qwertz => QWERTZ_LEFT_HAND,
+            KeyboardLayout::Azerty => AZERTY_LEFT_HAND,
+        }
+    }
+}
+
+/// Whose names the modifier keys on the bottom row get
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    /// Super
+    #[default]
+    Linux,
+    /// The Windows key
+    Windows,
+    /// Command and Option
+    Macos,
+}
+
+impl Platform {
+    /// The OS this build of lvcheat runs on
+    pub fn current() -> Self {
+        if cfg!(target_os = "macos") {
+            Platform::Macos
+        } else if cfg!(windows) {
+            Platform::Windows
+        } else {
+            Platform::Linux
+        }
+    }
+
+    fn modifier_row(self) -> &'static [KeyDef] {
+        match self {
+            Platform::Linux => MODIFIER_ROW,
+            Platform::Windows => WINDOWS_MODIFIER_ROW,
+            Platform::Macos => MAC_MODIFIER_ROW,
+        }
+    }
+}
+
+/// Which Shift and Ctrl keys chords light up; Alt is always the left one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModifierHand {
+    /// Always the left-hand ones
+    #[default]
+    Left,
+    /// The ones on the other side from the key, as touch typists press them: right Shift for
+    /// `<S-a>`, left Shift for `<S-l>`
+    Opposite,
+}
+
+/// Which extra key a character needs besides its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Plain,
+    Shift,
+    AltGr,
+}
+
+/// Where a key's cell sits in the rendered art
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPosition {
+    /// Label without padding
+    pub label: &'static str,
+    /// Label shown while Shift is held, without padding
+    pub shifted: &'static str,
+    /// Line of the rendered art
+    pub row: usize,
+    /// Char column of the cell's first character
+    pub col: usize,
+    pub width: usize,
+}
+
 /// Keyboard layout with ASCII art and key mappings
 pub struct Keyboard {
+    pub layout: KeyboardLayout,
+    pub platform: Platform,
+    pub modifier_hand: ModifierHand,
+    /// Colors of the frames of a sequence
+    pub palette: Palette,
+    /// Every key in the layout, by id
+    pub key_positions: HashMap<&'static str, KeyPosition>,
+    /// The key and level that type each character
+    chars: HashMap<char, (&'static str, Level)>,
 }
 
 impl Default for Keyboard {
@@ -28,250 +563,1049 @@ impl Default for Keyboard {
 
 impl Keyboard {
     pub fn new() -> Self {
-        Self {}
+        Self::with_layout(KeyboardLayout::Us)
     }
 
-    /// Get the base keyboard layout as lines (lowercase, shift_active toggles to uppercase)
-    pub fn get_layout_lines(&self, shift_active: bool) -> Vec<&'static str> {
-        if shift_active {
-            vec![
-                "┌───┬──┬──┬──┬──┬──┬──┬──┬──┬──┬────┬───┬────┐",
-                "│Esc│F1│F2│F3│F4│F5│F6│F7│F8│F9│ F10│F11│ F12│",
-                "├───┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┬─┴┬─┴┬─┴┬──┬┴─┬─┴┬───┤",
-                "│ ~  │! │@ │# │$ │% │^ │& │* │( │) │_ │+ │Bsp│",
-                "├────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┤",
-                "│Tab  │Q │W │E │R │T │Y │U │I │O │P │{ │} │| │",
-                "├─────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴──┤",
-                "│Caps  │A │S │D │F │G │H │J │K │L │: │\" │Ent │",
-                "├──────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴────┤",
-                "│Shift  │Z │X │C │V │B │N │M │< │> │? │Shift │",
-                "├────┬──┴┬─┴─┬┴──┴──┴──┴──┴──┴┬─┴─┬┴──┬───┬──┤",
-                "│Ctrl│Sup│Alt│      Space     │Alt│Fn │Mnu│Ct│",
-                "└────┴───┴───┴────────────────┴───┴───┴───┴──┘",
-            ]
-        } else {
-            vec![
-                "┌───┬──┬──┬──┬──┬──┬──┬──┬──┬──┬────┬───┬────┐",
-                "│Esc│F1│F2│F3│F4│F5│F6│F7│F8│F9│ F10│F11│ F12│",
-                "├───┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┬─┴┬─┴┬─┴┬──┬┴─┬─┴┬───┤",
-                "│ `  │1 │2 │3 │4 │5 │6 │7 │8 │9 │0 │- │= │Bsp│",
-                "├────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┤",
-                "│Tab  │q │w │e │r │t │y │u │i │o │p │[ │] │\\ │",
-                "├─────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴──┤",
-                "│Caps  │a │s │d │f │g │h │j │k │l │; │' │Ent │",
-                "├──────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴────┤",
-                "│Shift  │z │x │c │v │b │n │m │, │. │/ │Shift │",
-                "├────┬──┴┬─┴─┬┴──┴──┴──┴──┴──┴┬─┴─┬┴──┬───┬──┤",
-                "│Ctrl│Sup│Alt│      Space     │Alt│Fn │Mnu│Ct│",
-                "└────┴───┴───┴────────────────┴───┴───┴───┴──┘",
-            ]
+    pub fn with_layout(layout: KeyboardLayout) -> Self {
+        Self::with_platform(layout, Platform::default())
+    }
+
+    /// The layout with its modifier keys named as on `platform`
+    pub fn with_platform(layout: KeyboardLayout, platform: Platform) -> Self {
+        let mut key_positions = HashMap::new();
+        let mut chars = HashMap::new();
+        for (r, row) in layout.rows(platform).iter().enumerate() {
+            for (key, col) in row.iter().zip(boundaries(row, false)) {
+                if let Some(c) = single_char(key.label) {
+                    chars.insert(c, (key.id, Level::Plain));
+                }
+                if let Some(c) = single_char(key.shifted).filter(|&c| !chars.contains_key(&c)) {
+                    chars.insert(c, (key.id, Level::Shift));
+                }
+                key_positions.insert(
+                    key.id,
+                    KeyPosition {
+                        label: key.label.trim(),
+                        shifted: key.shifted.trim(),
+                        row: 2 * r + 1,
+                        col: col + 1,
+                        width: text_width(key.label),
+                    },
+                );
+            }
+        }
+        for &(c, id) in layout.altgr() {
+            chars.insert(c, (id, Level::AltGr));
+        }
+        Self {
+            layout,
+            platform,
+            modifier_hand: ModifierHand::default(),
+            palette: Palette::default(),
+            key_positions,
+            chars,
         }
     }
 
-    /// Render keyboard with highlighted keys
-    pub fn render<'a>(&self, highlighted_keys: &[&str]) -> Vec<Line<'a>> {
-        // Check if shift is in highlighted keys
-        let shift_active = highlighted_keys
+    /// Key ids to light up for a frame from the parser, as typed on this layout.
+    /// The parser spells characters the US way (`:` is Shift + `;`); here they are
+    /// looked up again, so on QWERTZ `:` is Shift + `.` and `[` is AltGr + `8`.
+    pub fn frame_ids(&self, frame: &KeyFrame) -> Vec<&'static str> {
+        let mut ids = self.typed_ids(frame);
+        let left_handed = ids
             .iter()
-            .any(|k| k.to_lowercase() == "shift");
-        let layout = self.get_layout_lines(shift_active);
-        let mut result = Vec::new();
-
-        // Colors for highlighting
-        let highlight_style = Style::default().fg(Color::Black).bg(Color::Yellow);
-        let leader_style = Style::default().fg(Color::Black).bg(Color::Cyan);
-        let modifier_style = Style::default().fg(Color::Black).bg(Color::Magenta);
-        let normal_style = Style::default().fg(Color::Gray);
-
-        // Build a set of keys to highlight with their types
-        let mut highlight_map: HashMap<String, Style> = HashMap::new();
-        for key in highlighted_keys {
-            let key_lower = key.to_lowercase();
-            let style = if key_lower == "space" || *key == "Space" {
-                leader_style
-            } else if ["ctrl", "alt", "shift", "super"].contains(&key_lower.as_str()) {
-                modifier_style
-            } else {
-                highlight_style
-            };
-            highlight_map.insert(key_lower, style);
-            // Also add uppercase version for matching
-            highlight_map.insert(key.to_uppercase(), style);
+            .any(|id| !is_modifier(id) && self.layout.left_hand().contains(id));
+        if self.modifier_hand == ModifierHand::Opposite && left_handed {
+            for id in &mut ids {
+                *id = match *id {
+                    "shift" => "rshift",
+                    "ctrl" => "rctrl",
+                    // Alt stays left: the right one is AltGr on ISO layouts, and terminals
+                    // usually only send Meta for the left Option key on Macs
+                    // Only Macs have a second Command key
+                    "super" if self.key_positions.contains_key("rsuper") => "rsuper",
+                    other => other,
+                };
+            }
         }
+        ids
+    }
 
-        for line in layout {
-            let mut spans = Vec::new();
-            let mut current_pos = 0;
-            let chars: Vec<char> = line.chars().collect();
+    /// `frame_ids` with every modifier on the left
+    fn typed_ids(&self, frame: &KeyFrame) -> Vec<&'static str> {
+        let by_name = || frame.keys.iter().filter_map(|k| self.key_name(&k.key)).collect();
 
-            while current_pos < chars.len() {
-                let c = chars[current_pos];
+        let (modifiers, rest): (Vec<&Key>, Vec<&Key>) =
+            frame.keys.iter().partition(|k| k.is_modifier);
+        let [base] = rest.as_slice() else {
+            return by_name();
+        };
+        let Some(c) = single_char(&base.key) else {
+            return by_name();
+        };
+        let shift = modifiers.iter().any(|k| k.key.eq_ignore_ascii_case("shift"));
+        let typed = if shift { commands::shifted(c) } else { c };
+        let Some(&(id, level)) = self.chars.get(&typed) else {
+            return by_name();
+        };
 
-                // Check if this is the start of a key label
-                if c.is_alphanumeric() || c == '`' || c == '-' || c == '=' || c == '[' || c == ']' || c == '\\' || c == ';' || c == '\'' || c == ',' || c == '.' || c == '/' {
-                    // Extract the key label
-                    let start = current_pos;
-                    let mut end = current_pos;
-                    while end < chars.len() && !['│', '┌', '┐', '└', '┘', '├', '┤', '┬', '┴', '┼', '─'].contains(&chars[end]) {
-                        end += 1;
-                    }
+        let mut ids: Vec<&'static str> = modifiers
+            .iter()
+            .filter(|k| !k.key.eq_ignore_ascii_case("shift"))
+            .filter_map(|k| self.key_name(&k.key))
+            .collect();
+        match level {
+            Level::Plain => {}
+            Level::Shift => ids.push("shift"),
+            Level::AltGr => ids.push("ralt"),
+        }
+        // Shift held for something that isn't a character, like Shift + Ctrl + letter
+        if shift && typed == c && level != Level::Shift {
+            ids.push("shift");
+        }
+        ids.push(id);
+        ids
+    }
+
+    /// The key id for a key name from the parser, e.g. `Enter` -> `enter`, `<BS>` -> `backsp`.
+    /// Plain modifier names are the left-hand keys.
+    pub fn key_name(&self, key: &str) -> Option<&'static str> {
+        let lower = key.to_lowercase();
+        let id = match lower.as_str() {
+            "backspace" | "bs" => "backsp",
+            "cr" | "return" => "enter",
+            "escape" => "esc",
+            "leader" => "space",
+            "control" => "ctrl",
+            "meta" => "alt",
+            "del" => "delete",
+            "ins" => "insert",
+            "pgup" => "pageup",
+            "pgdn" => "pagedown",
+            other => other,
+        };
+        self.key_positions.get_key_value(id).map(|(id, _)| *id)
+    }
+
+    /// The key `dx` keys along `id`'s row, or `dy` rows up or down the one nearest the middle
+    /// of `id`; `None` past the edges
+    pub fn neighbor(&self, id: &str, dx: isize, dy: isize) -> Option<&'static str> {
+        let pos = self.key_positions.get(id)?;
+        let row = pos.row.checked_add_signed(2 * dy)?;
+        let mut keys: Vec<(&'static str, &KeyPosition)> = self
+            .key_positions
+            .iter()
+            .filter(|(_, other)| other.row == row)
+            .map(|(id, other)| (*id, other))
+            .collect();
+        keys.sort_by_key(|(_, other)| other.col);
+        // Twice the middle column, to stay in whole numbers
+        let middle = |pos: &KeyPosition| (2 * pos.col + pos.width) as isize;
+        let nearest = keys
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (_, other))| (middle(other) - middle(pos)).abs())
+            .map(|(i, _)| i)?;
+        let i = nearest.checked_add_signed(dx)?;
+        keys.get(i).map(|(id, _)| *id)
+    }
+}
 
-                    let key_str: String = chars[start..end].iter().collect();
-                    let key_trimmed = key_str.trim();
+/// The left-hand id of a right-hand modifier, and any other id as it is
+pub fn unsided(id: &'static str) -> &'static str {
+    match id {
+        "rshift" => "shift",
+        "rctrl" => "ctrl",
+        "ralt" => "alt",
+        "rsuper" => "super",
+        other => other,
+    }
+}
 
-                    // Check if this key should be highlighted
-                    let style = self.find_key_style(key_trimmed, &highlight_map).unwrap_or(normal_style);
+/// The character of a one-character label or key name
+fn single_char(text: &str) -> Option<char> {
+    let mut chars = text.trim().chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
 
-                    spans.push(Span::styled(key_str.clone(), style));
-                    current_pos = end;
-                } else {
-                    // Regular character (borders, spaces)
-                    spans.push(Span::styled(c.to_string(), normal_style));
-                    current_pos += 1;
-                }
-            }
+/// Which keys a `KeyboardWidget` lights up, and how
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardState {
+    /// Key ids to light up, one group per frame
+    pub frames: Vec<Vec<&'static str>>,
+    /// Color of each frame, cycled; when empty, keys are colored by role
+    /// (leader, modifier or other)
+    pub frame_colors: Vec<Color>,
+    /// Force the shifted (`Some(true)`) or plain labels; by default shifted labels
+    /// show while a Shift key is lit
+    pub shifted: Option<bool>,
+    /// Mark lit keys with reverse video, bold and underline instead of color
+    pub monochrome: bool,
+    /// Write each lit key's frame numbers on its top edge, so the order reads without color
+    pub numbered: bool,
+    /// Keys pressed before the sequence rather than in it, like `v` to select text for a
+    /// visual-mode binding: their labels are underlined instead of lit, unless a frame lights
+    /// them too
+    pub precondition: Vec<&'static str>,
+}
 
-            result.push(Line::from(spans));
+impl KeyboardState {
+    /// Light up the keys of a single frame, colored by role
+    pub fn keys(ids: Vec<&'static str>) -> Self {
+        Self {
+            frames: vec![ids],
+            ..Self::default()
         }
+    }
 
-        result
+    /// Light up frame `index` of a sequence alone, in the color it has in the legend
+    pub fn frame(ids: Vec<&'static str>, index: usize) -> Self {
+        let mut frames = vec![Vec::new(); index];
+        frames.push(ids);
+        Self::legend(frames)
     }
 
-    fn find_key_style(&self, key: &str, highlight_map: &HashMap<String, Style>) -> Option<Style> {
-        let key_lower = key.to_lowercase();
+    /// Light up every frame at once, each in its own color
+    pub fn legend(frames: Vec<Vec<&'static str>>) -> Self {
+        Self {
+            frames,
+            frame_colors: FRAME_COLORS.to_vec(),
+            ..Self::default()
+        }
+    }
 
-        // Direct match
-        if let Some(&style) = highlight_map.get(&key_lower) {
-            return Some(style);
+    /// Highlight color of every lit key; a key in several frames takes the last one's
+    pub fn key_colors(&self) -> HashMap<&'static str, Color> {
+        let mut colors = HashMap::new();
+        for (frame_idx, frame_keys) in self.frames.iter().enumerate() {
+            for &id in frame_keys {
+                let color = if self.frame_colors.is_empty() {
+                    role_color(id)
+                } else {
+                    self.frame_colors[frame_idx % self.frame_colors.len()]
+                };
+                colors.insert(id, color);
+            }
         }
+        colors
+    }
 
-        // Check for partial matches (e.g., "Bsp" for "Backsp")
-        let key_mappings = [
-            ("bsp", "backsp"),
-            ("ent", "enter"),
-            ("ct", "ctrl"),
-            ("mnu", "menu"),
-            ("sup", "super"),
-        ];
+    /// Draw without color, see `monochrome`
+    pub fn monochrome(mut self) -> Self {
+        self.monochrome = true;
+        self
+    }
 
-        for (short, full) in key_mappings {
-            if key_lower == short || key_lower.starts_with(short) {
-                if let Some(&style) = highlight_map.get(full) {
-                    return Some(style);
-                }
-            }
+    /// Color frames from `palette` instead of `FRAME_COLORS`, unless they're colored by role
+    pub fn palette(mut self, palette: Palette) -> Self {
+        if !self.frame_colors.is_empty() {
+            self.frame_colors = palette.colors().to_vec();
         }
+        self
+    }
 
-        // Check for single letter keys
-        if key_lower.len() == 1 {
-            if let Some(&style) = highlight_map.get(&key_lower) {
-                return Some(style);
+    /// Mark keys pressed before the sequence, see `precondition`
+    pub fn precondition(mut self, ids: Vec<&'static str>) -> Self {
+        self.precondition = ids;
+        self
+    }
+
+    /// Number the lit keys by frame, see `numbered`
+    pub fn numbered(mut self) -> Self {
+        self.numbered = true;
+        self
+    }
+
+    /// The frames each lit key is pressed in, in order
+    fn key_frames(&self) -> BTreeMap<&'static str, Vec<usize>> {
+        let mut frames: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, frame_keys) in self.frames.iter().enumerate() {
+            for &id in frame_keys {
+                frames.entry(id).or_default().push(i);
             }
         }
+        frames
+    }
 
-        None
+    /// The frames each lit key is pressed in, counting from 1, e.g. `2,3`
+    fn frame_numbers(&self) -> BTreeMap<&'static str, String> {
+        self.key_frames()
+            .into_iter()
+            .map(|(id, frames)| {
+                let numbers: Vec<String> = frames.iter().map(|i| (i + 1).to_string()).collect();
+                (id, numbers.join(","))
+            })
+            .collect()
     }
 
-    /// Render keyboard with all frames shown simultaneously, each with different color
-    pub fn render_legend<'a>(&self, frames: &[Vec<&str>]) -> Vec<Line<'a>> {
-        // Check if any frame contains shift
-        let shift_active = frames
-            .iter()
-            .any(|f| f.iter().any(|k| k.to_lowercase() == "shift"));
-        let layout = self.get_layout_lines(shift_active);
-        let mut result = Vec::new();
-        let normal_style = Style::default().fg(Color::Gray);
-
-        // Build map: key -> frame index (for coloring)
-        let mut key_to_frame: HashMap<String, usize> = HashMap::new();
-        for (frame_idx, frame_keys) in frames.iter().enumerate() {
-            for key in frame_keys {
-                key_to_frame.insert(key.to_lowercase(), frame_idx);
+    /// The style of each frame of a key lit in several, one stripe each, when frames are
+    /// colored individually
+    fn stripes(&self) -> BTreeMap<&'static str, Vec<Style>> {
+        if self.frame_colors.is_empty() {
+            return BTreeMap::new();
+        }
+        let style = |i: usize| {
+            if self.monochrome {
+                Style::default().add_modifier(FRAME_MODIFIERS[i % FRAME_MODIFIERS.len()])
+            } else {
+                let color = self.frame_colors[i % self.frame_colors.len()];
+                Style::default().fg(Color::Black).bg(color)
             }
+        };
+        self.key_frames()
+            .into_iter()
+            .filter(|(_, frames)| frames.len() > 1)
+            .map(|(id, frames)| (id, frames.into_iter().map(style).collect()))
+            .collect()
+    }
+
+    fn styles(&self) -> HashMap<&'static str, Style> {
+        let mut styles = if self.monochrome {
+            self.modifier_styles()
+        } else {
+            self.key_colors()
+                .into_iter()
+                .map(|(id, color)| (id, Style::default().fg(Color::Black).bg(color)))
+                .collect()
+        };
+        let precondition = if self.monochrome {
+            Style::default().add_modifier(Modifier::DIM | Modifier::UNDERLINED)
+        } else {
+            Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        };
+        for &id in &self.precondition {
+            styles.entry(id).or_insert(precondition);
         }
+        styles
+    }
 
-        for line in layout {
-            let mut spans = Vec::new();
-            let mut current_pos = 0;
-            let chars: Vec<char> = line.chars().collect();
+    /// Per-frame modifiers in place of frame colors, or reverse video for every lit key
+    fn modifier_styles(&self) -> HashMap<&'static str, Style> {
+        let mut styles = HashMap::new();
+        for (frame_idx, frame_keys) in self.frames.iter().enumerate() {
+            let modifier = if self.frame_colors.is_empty() {
+                Modifier::REVERSED | Modifier::BOLD
+            } else {
+                FRAME_MODIFIERS[frame_idx % FRAME_MODIFIERS.len()]
+            };
+            for &id in frame_keys {
+                styles.insert(id, Style::default().add_modifier(modifier));
+            }
+        }
+        styles
+    }
+}
 
-            while current_pos < chars.len() {
-                let c = chars[current_pos];
+/// Highlight color for a key when frames aren't colored individually
+fn role_color(id: &str) -> Color {
+    if id == "space" {
+        Color::Cyan
+    } else if is_modifier(id) {
+        Color::Magenta
+    } else {
+        Color::Yellow
+    }
+}
 
-                if c.is_alphanumeric() || c == '`' || c == '-' || c == '=' || c == '[' || c == ']' || c == '\\' || c == ';' || c == '\'' || c == ',' || c == '.' || c == '/' {
-                    let start = current_pos;
-                    let mut end = current_pos;
-                    while end < chars.len() && !['│', '┌', '┐', '└', '┘', '├', '┤', '┬', '┴', '┼', '─'].contains(&chars[end]) {
-                        end += 1;
-                    }
+/// The keyboard art with the keys in a `KeyboardState` lit up
+#[derive(Debug, Clone, Default)]
+pub struct KeyboardWidget<'a> {
+    block: Option<Block<'a>>,
+    layout: KeyboardLayout,
+    platform: Platform,
+    large: bool,
+    labels_only: bool,
+}
 
-                    let key_str: String = chars[start..end].iter().collect();
-                    let key_trimmed = key_str.trim();
+impl<'a> KeyboardWidget<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-                    let style = self.find_frame_style(key_trimmed, &key_to_frame)
-                        .unwrap_or(normal_style);
+    /// Wrap the keyboard in a block, e.g. a bordered and titled one
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
 
-                    spans.push(Span::styled(key_str.clone(), style));
-                    current_pos = end;
-                } else {
-                    spans.push(Span::styled(c.to_string(), normal_style));
-                    current_pos += 1;
-                }
-            }
+    /// Draw this layout instead of US; highlights should come from a `Keyboard` with the same one
+    pub fn layout(mut self, layout: KeyboardLayout) -> Self {
+        self.layout = layout;
+        self
+    }
 
-            result.push(Line::from(spans));
-        }
+    /// Name the modifier keys as on this platform; highlights should come from a `Keyboard`
+    /// with the same one
+    pub fn platform(mut self, platform: Platform) -> Self {
+        self.platform = platform;
+        self
+    }
 
-        result
+    /// Draw keys twice as wide and three lines tall, for screen sharing
+    pub fn large(mut self, large: bool) -> Self {
+        self.large = large;
+        self
     }
 
-    fn find_frame_style(&self, key: &str, key_to_frame: &HashMap<String, usize>) -> Option<Style> {
-        let key_lower = key.to_lowercase();
+    /// Leave out the borders and key backgrounds, to draw the labels over keycap images
+    pub fn labels_only(mut self, labels_only: bool) -> Self {
+        self.labels_only = labels_only;
+        self
+    }
+}
 
-        // Direct match
-        if let Some(&frame_idx) = key_to_frame.get(&key_lower) {
-            let color = FRAME_COLORS[frame_idx % FRAME_COLORS.len()];
-            return Some(Style::default().fg(Color::Black).bg(color));
-        }
+impl StatefulWidget for KeyboardWidget<'_> {
+    type State = KeyboardState;
 
-        // Check for partial matches
-        let key_mappings = [
-            ("bsp", "backsp"),
-            ("ent", "enter"),
-            ("ct", "ctrl"),
-            ("mnu", "menu"),
-            ("sup", "super"),
-        ];
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let styles = state.styles();
+        let shifted = state
+            .shifted
+            .unwrap_or(styles.contains_key("shift") || styles.contains_key("rshift"));
+        let normal_style = if state.monochrome {
+            Style::default()
+        } else {
+            Style::default().fg(Color::Gray)
+        };
+        let rows = self.layout.rows(self.platform);
+        let mut lines = render_rows(&rows, &styles, shifted, normal_style, self.large);
+        if self.labels_only {
+            lines = lines.into_iter().map(bare_labels).collect();
+        }
+        let inner = self.block.as_ref().map_or(area, |block| block.inner(area));
+        let mut paragraph = Paragraph::new(lines);
+        if let Some(block) = self.block {
+            paragraph = paragraph.block(block);
+        }
+        paragraph.render(area, buf);
+        let keys = key_rects(&rows, self.large, inner);
+        if !self.labels_only {
+            render_stripes(&keys, &state.stripes(), buf);
+        }
+        if state.numbered {
+            render_numbers(&keys, &state.frame_numbers(), inner, buf);
+        }
+    }
+}
 
-        for (short, full) in key_mappings {
-            if key_lower == short || key_lower.starts_with(short) {
-                if let Some(&frame_idx) = key_to_frame.get(full) {
-                    let color = FRAME_COLORS[frame_idx % FRAME_COLORS.len()];
-                    return Some(Style::default().fg(Color::Black).bg(color));
-                }
+/// Where each key's inside is drawn, with the art at `area`'s corner. The ISO Enter spans
+/// two rows and is only given the top one.
+fn key_rects(rows: &[&[KeyDef]], large: bool, area: Rect) -> Vec<(&'static str, Rect)> {
+    // Lines each row of keys takes with the border above it
+    let row_height = if large { 4 } else { 2 };
+    let mut rects: Vec<(&str, Rect)> = Vec::new();
+    for (r, row) in rows.iter().enumerate() {
+        for (key, col) in row.iter().zip(boundaries(row, large)) {
+            if rects.iter().any(|(id, _)| *id == key.id) {
+                continue;
             }
+            let rect = Rect::new(
+                area.x + col as u16 + 1,
+                area.y + (r * row_height) as u16 + 1,
+                key_width(key, large) as u16,
+                row_height as u16 - 1,
+            );
+            rects.push((key.id, rect.intersection(area)));
         }
+    }
+    rects
+}
 
-        // Single letter
-        if key_lower.len() == 1 {
-            if let Some(&frame_idx) = key_to_frame.get(&key_lower) {
-                let color = FRAME_COLORS[frame_idx % FRAME_COLORS.len()];
-                return Some(Style::default().fg(Color::Black).bg(color));
+/// Split each key lit in several frames into side-by-side stripes, one per frame in its style.
+/// A key with more frames than columns gets one column each for the first ones and a `+` in
+/// its last column for the rest.
+fn render_stripes(keys: &[(&str, Rect)], stripes: &BTreeMap<&str, Vec<Style>>, buf: &mut Buffer) {
+    for (id, rect) in keys {
+        let Some(styles) = stripes.get(id) else {
+            continue;
+        };
+        let overflow = styles.len() > rect.width as usize;
+        for x in rect.left()..rect.right() {
+            let column = (x - rect.x) as usize;
+            let style = if overflow {
+                styles[column]
+            } else {
+                styles[column * styles.len() / rect.width as usize]
+            };
+            for y in rect.top()..rect.bottom() {
+                let cell = &mut buf[(x, y)];
+                cell.bg = style.bg.unwrap_or(Color::Reset);
+                cell.modifier = style.add_modifier;
             }
         }
+        let last = &mut buf[(rect.right() - 1, rect.top())];
+        if overflow && last.symbol() == " " {
+            last.set_symbol("+");
+        }
+    }
+}
+
+/// Write each key's frame numbers on its top edge. Too many for the edge start on the key's
+/// left corner instead.
+fn render_numbers(
+    keys: &[(&str, Rect)],
+    numbers: &BTreeMap<&str, String>,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    for (id, rect) in keys {
+        let Some(text) = numbers.get(id) else {
+            continue;
+        };
+        let (x, width) = if text.len() > rect.width as usize {
+            (rect.x - 1, rect.width + 1)
+        } else {
+            (rect.x, rect.width)
+        };
+        if rect.y == area.y || rect.is_empty() {
+            continue;
+        }
+        let width = width.min(area.right().saturating_sub(x)) as usize;
+        buf.set_stringn(x, rect.y - 1, text, width, Style::default().add_modifier(Modifier::BOLD));
+    }
+}
+
+fn is_modifier(id: &str) -> bool {
+    let id = id.strip_prefix('r').unwrap_or(id);
+    ["ctrl", "alt", "shift", "super"].contains(&id)
+}
+
+/// Draw every row, styling exactly the cells of the keys in `styles`
+fn render_rows<'a>(
+    rows: &[&[KeyDef]],
+    styles: &HashMap<&str, Style>,
+    shifted: bool,
+    normal_style: Style,
+    large: bool,
+) -> Vec<Line<'a>> {
+    let mut lines = Vec::new();
+
+    for (r, row) in rows.iter().enumerate() {
+        let above = r.checked_sub(1).map(|i| rows[i]);
+        lines.push(Line::styled(border_line(above, Some(row), large), normal_style));
+
+        // Large keys get a blank line above and below the label
+        let blank = |key: &KeyDef| " ".repeat(key_width(key, true));
+        let blank = large.then(|| key_line(row, styles, normal_style, blank));
+        lines.extend(blank.clone());
+        lines.push(key_line(row, styles, normal_style, |key| {
+            let text = if shifted { key.shifted } else { key.label };
+            if !large {
+                return text.to_string();
+            }
+            let label = text.trim();
+            let padding = key_width(key, true) - text_width(label);
+            format!("{}{}{}", " ".repeat(padding / 2), label, " ".repeat(padding - padding / 2))
+        }));
+        lines.extend(blank);
+    }
+    lines.push(Line::styled(border_line(rows.last().copied(), None, large), normal_style));
 
-        None
+    lines
+}
+
+/// A line of the art with its borders blanked out, and labels dark on lit keys and light on
+/// the others, as they'll sit on keycaps
+fn bare_labels(line: Line<'_>) -> Line<'_> {
+    let spans: Vec<Span> = line
+        .spans
+        .into_iter()
+        .map(|span| {
+            let text: String = span
+                .content
+                .chars()
+                .map(|c| if ('─'..='╿').contains(&c) { ' ' } else { c })
+                .collect();
+            let style = match span.style.bg {
+                Some(bg) if bg != Color::Reset => {
+                    Style::default().fg(Color::Black).add_modifier(Modifier::BOLD)
+                }
+                _ => Style::default().fg(Color::White),
+            };
+            Span::styled(text, style)
+        })
+        .collect();
+    Line::from(spans)
+}
+
+/// One line through a row of keys, with `text` filling each key's cell
+fn key_line<'a>(
+    row: &[KeyDef],
+    styles: &HashMap<&str, Style>,
+    normal_style: Style,
+    text: impl Fn(&KeyDef) -> String,
+) -> Line<'a> {
+    let mut spans = vec![Span::styled("│", normal_style)];
+    for key in row {
+        let style = styles.get(key.id).copied().unwrap_or(normal_style);
+        spans.push(Span::styled(text(key), style));
+        spans.push(Span::styled("│", normal_style));
     }
+    Line::from(spans)
+}
+
+/// Terminal columns inside a key's borders
+fn key_width(key: &KeyDef, large: bool) -> usize {
+    let width = text_width(key.label);
+    // Taking in the border after it too keeps the doubled rows' borders lined up
+    if large {
+        2 * width + 1
+    } else {
+        width
+    }
+}
+
+/// Char columns of the vertical borders around each key in a row
+fn boundaries(row: &[KeyDef], large: bool) -> Vec<usize> {
+    let mut cols = vec![0];
+    for key in row {
+        cols.push(cols.last().unwrap() + key_width(key, large) + 1);
+    }
+    cols
+}
+
+/// Terminal columns a label takes; wide characters take two, so `chars().count()` won't do
+fn text_width(text: &str) -> usize {
+    Span::raw(text).width()
+}
+
+/// Horizontal line between two rows, joining the borders of both. A key with the
+/// same id in both rows (the ISO Enter) is left open, as one tall key.
+fn border_line(above: Option<&[KeyDef]>, below: Option<&[KeyDef]>, large: bool) -> String {
+    let up = above.map(|row| boundaries(row, large)).unwrap_or_default();
+    let down = below.map(|row| boundaries(row, large)).unwrap_or_default();
+    let width = up.last().or(down.last()).copied().unwrap_or(0);
+
+    // Whether the line runs from `col` to `col + 1`
+    let segment = |col: usize| {
+        let key_at = |row: Option<&[KeyDef]>, cols: &[usize]| {
+            let i = cols.iter().rposition(|&c| c <= col)?;
+            row?.get(i).map(|key| key.id)
+        };
+        let (a, b) = (key_at(above, &up), key_at(below, &down));
+        col < width && (a.is_none() || a != b)
+    };
+
+    (0..=width)
+        .map(|col| {
+            let left = col > 0 && segment(col - 1);
+            let right = segment(col);
+            match (up.contains(&col), down.contains(&col), left, right) {
+                (false, true, false, true) => '┌',
+                (false, true, true, false) => '┐',
+                (true, false, false, true) => '└',
+                (true, false, true, false) => '┘',
+                (true, true, false, true) => '├',
+                (true, true, true, false) => '┤',
+                (false, true, true, true) => '┬',
+                (true, false, true, true) => '┴',
+                (true, true, true, true) => '┼',
+                (true, true, false, false) => '│',
+                (false, false, false, false) => ' ',
+                _ => '─',
+            }
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    fn draw(mut state: KeyboardState) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(46, 15)).unwrap();
+        terminal
+            .draw(|frame| {
+                frame.render_stateful_widget(KeyboardWidget::new(), frame.area(), &mut state)
+            })
+            .unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    /// A line of the buffer as it reads on screen, skipping the cells wide characters cover
+    fn row_text(buf: &Buffer, y: u16) -> String {
+        let mut text = String::new();
+        let mut x = 0;
+        while x < buf.area.width {
+            let symbol = buf[(x, y)].symbol();
+            text.push_str(symbol);
+            x += text_width(symbol).max(1) as u16;
+        }
+        text
+    }
+
+    /// Label and background of a key's first cell
+    fn key_cell(buf: &Buffer, id: &str) -> (String, Color) {
+        let pos = Keyboard::new().key_positions[id];
+        let cells = (pos.col..pos.col + pos.width).map(|x| &buf[(x as u16, pos.row as u16)]);
+        let label = cells.clone().map(|cell| cell.symbol()).collect::<String>();
+        (label.trim().to_string(), cells.clone().next().unwrap().bg)
+    }
 
     #[test]
     fn test_render_keyboard() {
+        let buf = draw(KeyboardState::keys(vec!["f"]));
+        assert_eq!(key_cell(&buf, "f"), ("f".to_string(), Color::Yellow));
+        assert_eq!(key_cell(&buf, "g").1, Color::Reset);
+    }
+
+    #[test]
+    fn test_generated_art() {
+        let buf = draw(KeyboardState::default());
+        let expected = [
+            "┌───┬──┬──┬──┬──┬──┬──┬──┬──┬──┬────┬───┬────┐",
+            "│Esc│F1│F2│F3│F4│F5│F6│F7│F8│F9│ F10│F11│ F12│",
+            "├───┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┬┴─┬─┴┬───┤",
+            "│ `  │1 │2 │3 │4 │5 │6 │7 │8 │9 │0 │- │= │Bsp│",
+            "├────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┤",
+            "│Tab  │q │w │e │r │t │y │u │i │o │p │[ │] │\\ │",
+            "├─────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴──┤",
+            "│Caps  │a │s │d │f │g │h │j │k │l │; │' │Ent │",
+            "├──────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴────┤",
+            "│Shift  │z │x │c │v │b │n │m │, │. │/ │Shift │",
+            "├────┬──┴┬─┴─┬┴──┴──┴──┴──┴──┴┬─┴─┬┴──┼───┬──┤",
+            "│Ctrl│Sup│Alt│      Space     │Alt│Fn │Mnu│Ct│",
+            "├───┬┴──┬┴───┴┬────┬────┬────┬┴──┬┴──┬┴──┬┴──┤",
+            "│Ins│Del│Home │End │PgUp│PgDn│ ← │ ↑ │ ↓ │ → │",
+            "└───┴───┴─────┴────┴────┴────┴───┴───┴───┴───┘",
+        ];
+        for (y, line) in expected.iter().enumerate() {
+            assert_eq!(row_text(&buf, y as u16), *line);
+        }
+    }
+
+    #[test]
+    fn test_shifted_symbol_highlight() {
+        let buf = draw(KeyboardState::keys(vec!["shift", ";"]));
+        assert_eq!(key_cell(&buf, ";"), (":".to_string(), Color::Yellow));
+
+        let mut state = KeyboardState::keys(vec!["shift", ";"]);
+        state.shifted = Some(false);
+        assert_eq!(key_cell(&draw(state), ";").0, ";");
+    }
+
+    #[test]
+    fn test_left_modifier_only() {
+        let buf = draw(KeyboardState::keys(vec!["shift", "ctrl"]));
+        assert_eq!(key_cell(&buf, "shift").1, Color::Magenta);
+        assert_eq!(key_cell(&buf, "ctrl").1, Color::Magenta);
+        assert_eq!(key_cell(&buf, "rshift").1, Color::Reset);
+        assert_eq!(key_cell(&buf, "rctrl").1, Color::Reset);
+    }
+
+    #[test]
+    fn test_legend_colors() {
+        let buf = draw(KeyboardState::legend(vec![vec!["space"], vec!["f"], vec!["f"]]));
+        assert_eq!(key_cell(&buf, "space").1, FRAME_COLORS[0]);
+        // A key pressed twice starts in its first frame's color, see test_repeated_key_stripes
+        assert_eq!(key_cell(&buf, "f").1, FRAME_COLORS[1]);
+    }
+
+    #[test]
+    fn test_legend_numbers() {
+        let buf = draw(KeyboardState::legend(vec![vec!["space"], vec!["f"], vec!["f"]]).numbered());
+        let top = |id: &str| {
+            let pos = Keyboard::new().key_positions[id];
+            row_text(&buf, pos.row as u16 - 1).chars().skip(pos.col - 1).take(4).collect::<String>()
+        };
+        assert_eq!(top("space"), "┬1──");
+        // Too wide for the key's edge, so it starts on the corner
+        assert_eq!(&top("f")[..3], "2,3");
+        assert!(!row_text(&draw(KeyboardState::legend(vec![vec!["f"]])), 6).contains('1'));
+    }
+
+    #[test]
+    fn test_precondition() {
+        let buf = draw(KeyboardState::legend(vec![vec!["d"]]).precondition(vec!["v", "d"]));
+        let pos = Keyboard::new().key_positions["v"];
+        let cell = &buf[(pos.col as u16, pos.row as u16)];
+        assert_eq!((cell.fg, cell.bg), (Color::Magenta, Color::Reset));
+        assert!(cell.modifier.contains(Modifier::UNDERLINED));
+        // Lit by a frame, the key keeps its frame color
+        assert_eq!(key_cell(&buf, "d").1, FRAME_COLORS[0]);
+    }
+
+    #[test]
+    fn test_palette() {
+        let state = KeyboardState::legend(vec![vec!["space"], vec!["f"]]);
+        let buf = draw(state.palette(Palette::Deuteranopia));
+        assert_eq!(key_cell(&buf, "space").1, DEUTERANOPIA_COLORS[0]);
+        assert_eq!(key_cell(&buf, "f").1, Palette::Deuteranopia.frame_color(1));
+        assert_eq!(Palette::Protanopia.frame_color(7), PROTANOPIA_COLORS[0]);
+        // Keys colored by role stay that way
+        let buf = draw(KeyboardState::keys(vec!["f"]).palette(Palette::Protanopia));
+        assert_eq!(key_cell(&buf, "f").1, Color::Yellow);
+    }
+
+    #[test]
+    fn test_repeated_key_stripes() {
+        let buf = draw(KeyboardState::legend(vec![vec!["space"], vec!["f"], vec!["f"]]));
+        let pos = Keyboard::new().key_positions["f"];
+        let bg = |x: usize| buf[(x as u16, pos.row as u16)].bg;
+        // f is two columns wide, half in each of its frames' colors
+        assert_eq!(bg(pos.col), FRAME_COLORS[1]);
+        assert_eq!(bg(pos.col + 1), FRAME_COLORS[2]);
+        assert_eq!(key_cell(&buf, "space").1, FRAME_COLORS[0]);
+
+        let buf = draw(KeyboardState::legend(vec![vec!["f"], vec!["f"]]).monochrome());
+        let modifier = |x: usize| buf[(x as u16, pos.row as u16)].modifier;
+        assert_eq!(modifier(pos.col), FRAME_MODIFIERS[0]);
+        assert_eq!(modifier(pos.col + 1), FRAME_MODIFIERS[1]);
+
+        // A third frame doesn't fit in two columns, so the second is marked for more
+        let buf = draw(KeyboardState::legend(vec![vec!["f"], vec!["f"], vec!["f"]]));
+        let cell = |x: usize| buf[(x as u16, pos.row as u16)].clone();
+        assert_eq!((cell(pos.col).symbol(), cell(pos.col).bg), ("f", FRAME_COLORS[0]));
+        assert_eq!((cell(pos.col + 1).symbol(), cell(pos.col + 1).bg), ("+", FRAME_COLORS[1]));
+    }
+
+    #[test]
+    fn test_frame_colors() {
+        let buf = draw(KeyboardState::frame(vec!["ctrl", "w"], 1));
+        assert_eq!(key_cell(&buf, "w").1, FRAME_COLORS[1]);
+        assert_eq!(key_cell(&buf, "ctrl").1, FRAME_COLORS[1]);
+    }
+
+    #[test]
+    fn test_monochrome_legend() {
+        let buf = draw(KeyboardState::legend(vec![vec!["space"], vec!["f"]]).monochrome());
+        let cell = |id: &str| {
+            let pos = Keyboard::new().key_positions[id];
+            buf[(pos.col as u16, pos.row as u16)].clone()
+        };
+        assert_eq!(cell("space").modifier, FRAME_MODIFIERS[0]);
+        assert_eq!(cell("f").modifier, FRAME_MODIFIERS[1]);
+        assert!([cell("space"), cell("f"), cell("g")]
+            .iter()
+            .all(|c| c.fg == Color::Reset && c.bg == Color::Reset));
+    }
+
+    #[test]
+    fn test_key_name_lookup() {
+        let kb = Keyboard::new();
+        assert_eq!(kb.key_name("F5"), Some("f5"));
+        assert_eq!(kb.key_name("f12"), Some("f12"));
+        assert_eq!(kb.key_name("Up"), Some("up"));
+        assert_eq!(kb.key_name("PageDown"), Some("pagedown"));
+        assert_eq!(kb.key_name("Backsp"), Some("backsp"));
+        assert_eq!(kb.key_name("Shift"), Some("shift"));
+        assert_eq!(kb.key_name("Hyper"), None);
+        assert_eq!(kb.key_positions["f1"].row, 1);
+        assert_eq!(kb.key_positions["rctrl"].label, "Ct");
+
+        let buf = draw(KeyboardState::keys(vec!["f5"]));
+        assert_eq!(key_cell(&buf, "f5"), ("F5".to_string(), Color::Yellow));
+    }
+
+    #[test]
+    fn test_layout_frames() {
+        let ids = |layout, keys: &str| -> Vec<Vec<&'static str>> {
+            let kb = Keyboard::with_layout(layout);
+            commands::parse_key_string(keys)
+                .iter()
+                .map(|kf| kb.frame_ids(kf))
+                .collect()
+        };
+        assert_eq!(ids(KeyboardLayout::Us, ":")[0], ["shift", ";"]);
+        assert_eq!(ids(KeyboardLayout::Us, "<C-w>"), [vec!["ctrl", "w"]]);
+
+        let qwertz = KeyboardLayout::Qwertz;
+        assert_eq!(ids(qwertz, ":")[0], ["shift", "."]);
+        assert_eq!(ids(qwertz, "[d"), [vec!["ralt", "8"], vec!["d"]]);
+        assert_eq!(ids(qwertz, "zy"), [vec!["z"], vec!["y"]]);
+        assert_eq!(ids(qwertz, "<C-w>"), [vec!["ctrl", "w"]]);
+
+        let azerty = KeyboardLayout::Azerty;
+        assert_eq!(ids(azerty, "1"), [vec!["shift", "&"]]);
+        assert_eq!(ids(azerty, "G"), [vec!["shift", "g"]]);
+        assert_eq!(ids(azerty, "<leader>q"), [vec!["space"], vec!["q"]]);
+    }
+
+    #[test]
+    fn test_opposite_hand_modifiers() {
+        let ids = |layout, keys: &str| -> Vec<&'static str> {
+            let mut kb = Keyboard::with_layout(layout);
+            kb.modifier_hand = ModifierHand::Opposite;
+            kb.frame_ids(&commands::parse_key_string(keys)[0])
+        };
+        assert_eq!(ids(KeyboardLayout::Us, "<C-w>"), ["rctrl", "w"]);
+        assert_eq!(ids(KeyboardLayout::Us, "<C-l>"), ["ctrl", "l"]);
+        assert_eq!(ids(KeyboardLayout::Us, "A"), ["rshift", "a"]);
+        assert_eq!(ids(KeyboardLayout::Us, "<S-h>"), ["shift", "h"]);
+        assert_eq!(ids(KeyboardLayout::Us, "<A-S-Tab>"), ["alt", "rshift", "tab"]);
+        assert_eq!(ids(KeyboardLayout::Qwertz, "<M-a>"), ["alt", "a"]);
+        assert_eq!(ids(KeyboardLayout::Us, "%"), ["rshift", "5"]);
+        // Where Y and Z swap, so do the hands
+        assert_eq!(ids(KeyboardLayout::Qwertz, "Y"), ["rshift", "y"]);
+        assert_eq!(ids(KeyboardLayout::Qwertz, "Z"), ["shift", "z"]);
+        assert_eq!(ids(KeyboardLayout::Azerty, "<C-a>"), ["rctrl", "a"]);
+        assert_eq!(ids(KeyboardLayout::Azerty, "<C-q>"), ["rctrl", "q"]);
+    }
+
+    #[test]
+    fn test_platform_modifiers() {
+        let label = |platform, id| {
+            Keyboard::with_platform(KeyboardLayout::Us, platform).key_positions[id].label
+        };
+        assert_eq!(label(Platform::Linux, "super"), "Sup");
+        assert_eq!(label(Platform::Windows, "super"), "Win");
+        assert_eq!(label(Platform::Macos, "super"), "Cmd");
+        assert_eq!(label(Platform::Macos, "alt"), "Opt");
+
+        let mut mac = Keyboard::with_platform(KeyboardLayout::Us, Platform::Macos);
+        let frame = &commands::parse_key_string("<D-s>")[0];
+        assert_eq!(mac.frame_ids(frame), ["super", "s"]);
+        mac.modifier_hand = ModifierHand::Opposite;
+        assert_eq!(mac.frame_ids(frame), ["rsuper", "s"]);
+
+        let mut terminal = Terminal::new(TestBackend::new(46, 15)).unwrap();
+        terminal
+            .draw(|frame| {
+                let widget = KeyboardWidget::new().platform(Platform::Macos);
+                frame.render_stateful_widget(widget, frame.area(), &mut KeyboardState::default())
+            })
+            .unwrap();
+        assert_eq!(
+            row_text(terminal.backend().buffer(), 11),
+            "│Ctrl│Opt│Cmd│      Space     │Cmd│Opt│Fn │Ct│"
+        );
+    }
+
+    fn draw_layout(layout: KeyboardLayout) -> Vec<String> {
+        let mut terminal = Terminal::new(TestBackend::new(46, 15)).unwrap();
+        terminal
+            .draw(|frame| {
+                let widget = KeyboardWidget::new().layout(layout);
+                frame.render_stateful_widget(widget, frame.area(), &mut KeyboardState::default())
+            })
+            .unwrap();
+        let buf = terminal.backend().buffer();
+        (0..buf.area.height).map(|y| row_text(buf, y)).collect()
+    }
+
+    #[test]
+    fn test_iso_art() {
+        assert_eq!(
+            draw_layout(KeyboardLayout::Qwertz)[3..10],
+            [
+                "│ ^  │1 │2 │3 │4 │5 │6 │7 │8 │9 │0 │ß │´ │Bsp│",
+                "├────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┤",
+                "│Tab  │q │w │e │r │t │z │u │i │o │p │ü │+ │↵ │",
+                "├────┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┘  │",
+                "│Caps│a │s │d │f │g │h │j │k │l │ö │ä │# │Ent│",
+                "├────┼──┼──┼──┼──┼──┼──┼──┼──┼──┼──┼──┼──┴───┤",
+                "│Shft│< │y │x │c │v │b │n │m │, │. │- │Shift │",
+            ]
+        );
+        assert_eq!(
+            draw_layout(KeyboardLayout::Azerty)[3..10],
+            [
+                "│ ²  │& │é │\" │' │( │- │è │_ │ç │à │) │= │Bsp│",
+                "├────┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬─┴┬──┤",
+                "│Tab  │a │z │e │r │t │y │u │i │o │p │^ │$ │↵ │",
+                "├────┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┴─┬┘  │",
+                "│Caps│q │s │d │f │g │h │j │k │l │m │ù │* │Ent│",
+                "├────┼──┼──┼──┼──┼──┼──┼──┼──┼──┼──┼──┼──┴───┤",
+                "│Shft│< │w │x │c │v │b │n │, │; │: │! │Shift │",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rows_align() {
+        for layout in [KeyboardLayout::Us, KeyboardLayout::Qwertz, KeyboardLayout::Azerty] {
+            for platform in [Platform::Linux, Platform::Windows, Platform::Macos] {
+                let widths: Vec<usize> = layout
+                    .rows(platform)
+                    .iter()
+                    .map(|row| boundaries(row, false)[row.len()])
+                    .collect();
+                assert!(widths.iter().all(|&w| w == 45), "{:?}: {:?}", layout, widths);
+            }
+        }
+    }
+
+    #[test]
+    fn test_wide_labels() {
+        // A double-width label and symbols standing in for key names
+        let rows: &[&[KeyDef]] = &[
+            &[KeyDef::new("a", "日本"), KeyDef::new("b", "→ ")],
+            &[KeyDef::new("space", "␣   "), KeyDef::new("c", "c ")],
+        ];
+        let lines = render_rows(rows, &HashMap::new(), false, Style::default(), false);
+        let mut terminal = Terminal::new(TestBackend::new(9, 5)).unwrap();
+        terminal
+            .draw(|frame| frame.render_widget(Paragraph::new(lines), frame.area()))
+            .unwrap();
+        terminal.backend().assert_buffer_lines([
+            "┌────┬──┐",
+            "│日本│→ │",
+            "├────┼──┤",
+            "│␣   │c │",
+            "└────┴──┘",
+        ]);
+    }
+
+    #[test]
+    fn test_labels_only() {
+        let mut state = KeyboardState::keys(vec!["f"]);
+        let mut terminal = Terminal::new(TestBackend::new(46, 15)).unwrap();
+        terminal
+            .draw(|frame| {
+                let widget = KeyboardWidget::new().labels_only(true);
+                frame.render_stateful_widget(widget, frame.area(), &mut state)
+            })
+            .unwrap();
+        let buf = terminal.backend().buffer();
+        assert_eq!(row_text(buf, 0).trim(), "");
+        assert_eq!(row_text(buf, 7), " Caps   a  s  d  f  g  h  j  k  l  ;  '  Ent  ");
+        let (label, bg) = key_cell(buf, "f");
+        assert_eq!((label.as_str(), bg), ("f", Color::Reset));
+        let pos = Keyboard::new().key_positions["f"];
+        assert_eq!(buf[(pos.col as u16, pos.row as u16)].fg, Color::Black);
+    }
+
+    #[test]
+    fn test_large_keys() {
+        let rows: &[&[KeyDef]] = &[&[KeyDef::new("esc", "Esc"), KeyDef::new("f1", "F1")]];
+        let styles = HashMap::from([("f1", Style::default().bg(Color::Yellow))]);
+        let lines = render_rows(rows, &styles, false, Style::default(), true);
+        let text: Vec<String> = lines.iter().map(Line::to_string).collect();
+        assert_eq!(
+            text,
+            [
+                "┌───────┬─────┐",
+                "│       │     │",
+                "│  Esc  │ F1  │",
+                "│       │     │",
+                "└───────┴─────┘",
+            ]
+        );
+        assert_eq!(lines[2].spans[3].style.bg, Some(Color::Yellow));
+
+        // Whole rows double evenly, so their borders still meet
+        for layout in [KeyboardLayout::Us, KeyboardLayout::Qwertz, KeyboardLayout::Azerty] {
+            let rows = layout.rows(Platform::Linux);
+            let lines = render_rows(&rows, &HashMap::new(), false, Style::default(), true);
+            assert_eq!(lines.len(), 29);
+            assert!(lines.iter().all(|line| line.width() == 91), "{:?}", layout);
+        }
+    }
+
+    #[test]
+    fn test_neighbor() {
         let kb = Keyboard::new();
-        let lines = kb.render(&["f", "f"]);
-        assert!(!lines.is_empty());
+        assert_eq!(kb.neighbor("f", 1, 0), Some("g"));
+        assert_eq!(kb.neighbor("f", -1, 0), Some("d"));
+        assert_eq!(kb.neighbor("caps", -1, 0), None);
+        assert_eq!(kb.neighbor("f", 0, -1), Some("r"));
+        assert_eq!(kb.neighbor("f", 0, 1), Some("v"));
+        assert_eq!(kb.neighbor("b", 0, 1), Some("space"));
+        assert_eq!(kb.neighbor("esc", 0, -1), None);
+        assert_eq!(unsided("rshift"), "shift");
     }
 }
@@ -0,0 +1,327 @@
+//! Vim key notation, as command files write it, to frames of keys pressed together and back.
+//!
+//! [`parse`] reads notation the way the keyboard animation does, [`format`] writes frames back
+//! so that parsing them again gives the same frames, and [`normalize`] respells notation
+//! without parsing it.
+
+use crate::commands::{
+    self, is_named_key, leader_frames, mouse_name, KeyFrame, KeyIssue, Leaders,
+};
+
+/// Frames of the keys `keys` presses, e.g. Ctrl+W and then V for `<C-w>v`
+pub fn parse(keys: &str) -> Vec<KeyFrame> {
+    commands::parse_key_string(keys)
+}
+
+/// [`parse`], with `<leader>` and `<localleader>` standing for `leaders`
+pub fn parse_with(keys: &str, leaders: &Leaders) -> Vec<KeyFrame> {
+    commands::parse_key_string_with(keys, leaders)
+}
+
+/// [`parse_with`], or the first problem that would make the frames wrong rather than just
+/// lose something, like an unknown key name
+pub fn parse_checked(keys: &str, leaders: &Leaders) -> Result<Vec<KeyFrame>, KeyIssue> {
+    match commands::check_keys(keys).into_iter().find(|issue| !issue.is_warning()) {
+        Some(issue) => Err(issue),
+        None => Ok(parse_with(keys, leaders)),
+    }
+}
+
+/// `frames` in Vim notation, spelled as [`normalize`] spells it; parsing the result gives
+/// `frames` again
+pub fn format(frames: &[KeyFrame]) -> String {
+    format_with(frames, &Leaders::default())
+}
+
+/// [`format`], writing the keys of `leaders` as `<leader>` and `<localleader>`
+pub fn format_with(frames: &[KeyFrame], leaders: &Leaders) -> String {
+    let named = [
+        (leader_frames(&leaders.leader), "<leader>"),
+        (leader_frames(&leaders.localleader), "<localleader>"),
+    ];
+    // An ex command is typed out, spaces and all, and runs on the Enter parsing adds
+    let ex = frames.first().is_some_and(|frame| frame_notation(frame, false) == ":");
+    let mut out = String::new();
+    let mut rest = frames;
+    // Whether parsing would still read a count or register here
+    let mut prefix = true;
+    while let Some(frame) = rest.first() {
+        match named.iter().find(|(keys, _)| rest.starts_with(keys)) {
+            Some((keys, name)) => {
+                out.push_str(name);
+                rest = &rest[keys.len()..];
+                prefix = false;
+            }
+            None => {
+                let notation = frame_notation(frame, ex);
+                let next = rest.get(1).and_then(|next| frame_notation(next, ex).chars().next());
+                // A `"` typed on its own would pick the register after it
+                if prefix && notation == "\"" && next.is_some_and(commands::is_register) {
+                    out.push_str("<S-'>");
+                } else {
+                    out.push_str(&notation);
+                }
+                prefix &= frame.note.is_some();
+                rest = &rest[1..];
+            }
+        }
+    }
+    let enters = frames.iter().rev().take_while(|frame| frame_notation(frame, ex) == "<CR>");
+    if ex && enters.count() == 1 {
+        out.truncate(out.len() - "<CR>".len());
+    }
+    out
+}
+
+/// One frame in notation: a count or register as typed, a character when that's all it
+/// takes, and `<...>` for the rest
+fn frame_notation(frame: &KeyFrame, ex: bool) -> String {
+    if let Some(note) = &frame.note {
+        if let Some(count) = note.strip_prefix("count: ") {
+            return count.to_string();
+        }
+        if let Some(register) = note.strip_prefix("register: ") {
+            return format!("\"{}", register);
+        }
+    }
+    let modifiers: Vec<&str> =
+        frame.keys.iter().filter(|k| k.is_modifier).map(|k| k.key.as_str()).collect();
+    let Some(base) = frame.keys.iter().find(|k| !k.is_modifier) else {
+        return String::new();
+    };
+    let mut chars = base.key.chars();
+    if let (Some(c), None, false) = (chars.next(), chars.next(), base.is_mouse) {
+        let typed = commands::shifted(c);
+        match modifiers.as_slice() {
+            [] => return c.to_string(),
+            // A bare `<` could start a `<...>` key, so it's written `<lt>` as in `:help <>`
+            ["Shift"] if typed == '<' => return "<lt>".to_string(),
+            ["Shift"] if typed != c => return typed.to_string(),
+            _ => {}
+        }
+    }
+    if ex && modifiers.is_empty() && base.key == "Space" {
+        return " ".to_string();
+    }
+
+    let mut out = String::from("<");
+    for modifier in modifiers {
+        out.push_str(match modifier {
+            "Ctrl" => "C-",
+            "Shift" => "S-",
+            "Alt" => "A-",
+            "Super" => "D-",
+            other => other,
+        });
+    }
+    out.push_str(match base.key.as_str() {
+        "Enter" => "CR",
+        "Backsp" => "BS",
+        other => other,
+    });
+    out.push('>');
+    out
+}
+
+/// Key names as Vim's docs spell them, which notation is normalized to
+const KEY_SPELLINGS: &[&str] = &[
+    "leader", "localleader", "lt", "Space", "CR", "Enter", "Return", "Esc", "Escape", "BS",
+    "Backspace", "Tab", "Up", "Down", "Left", "Right", "Home", "End", "Del", "Delete", "Insert",
+    "PageUp", "PageDown",
+];
+
+/// `keys` with every `<...>` key spelled the usual way, as in `<C-w>` for `<c-W>` or `<CR>`
+/// for `<cr>`: modifiers as single capitals, key names as Vim's docs write them, and the
+/// letter of a Ctrl chord lowercase, since Vim doesn't tell the cases apart. Anything unknown
+/// is left alone, so the keys mean the same before and after.
+pub fn normalize(keys: &str) -> String {
+    let mut out = String::new();
+    let mut rest = keys;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        out.push_str(&rest[..start]);
+        out.push('<');
+        out.push_str(&normalize_special(&rest[start + 1..start + len]));
+        out.push('>');
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The inside of one `<...>` key, normalized; see [`normalize`]
+fn normalize_special(special: &str) -> String {
+    let (modifiers, name) = commands::split_special(special);
+    if name.is_empty() {
+        return special.to_string();
+    }
+    let mut ctrl = false;
+    let mut out = String::new();
+    for modifier in modifiers {
+        let short = commands::modifier(modifier).map_or(modifier, |(letter, _)| letter);
+        ctrl |= short == "C";
+        out.push_str(short);
+        out.push('-');
+    }
+    match spelling(name) {
+        Some(key) => out.push_str(&key),
+        None if ctrl && name.chars().count() == 1 => out.push_str(&name.to_lowercase()),
+        None => out.push_str(name),
+    }
+    out
+}
+
+/// How Vim's docs spell the key called `name` in any case, if it's one with a name
+pub(crate) fn spelling(name: &str) -> Option<String> {
+    let lower = name.to_lowercase();
+    if is_named_key(&lower) && lower.starts_with('f') {
+        return Some(name.to_uppercase());
+    }
+    let spelled = KEY_SPELLINGS.iter().copied().find(|key| key.eq_ignore_ascii_case(name));
+    spelled.or(mouse_name(name)).map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_format() {
+        let cases = [
+            ("<C-w>v", "<C-w>v"),
+            ("<leader>ff", "<leader>ff"),
+            ("<localleader>t", "<localleader>t"),
+            ("gD", "gD"),
+            ("<S-h>", "H"),
+            ("3<C-w>>", "3<C-w>>"),
+            ("\"+yy", "\"+yy"),
+            ("<c-S-TAB>", "<C-S-Tab>"),
+            ("<cr><Esc><bs><Space>", "<CR><Esc><BS><Space>"),
+            ("<F5><PageDown>", "<F5><PageDown>"),
+            ("<2-LeftMouse><C-ScrollWheelUp>", "<2-LeftMouse><C-ScrollWheelUp>"),
+            ("a<b", "a<lt>b"),
+            ("<lt>", "<lt>"),
+            ("<S-,>", "<lt>"),
+            ("\"<S-a>", "<S-'>A"),
+            ("\"ayy", "\"ayy"),
+            (":Lazy", ":Lazy"),
+            (":s/a b/<CR>", ":s/a b/"),
+            (":w<CR><CR>", ":w<CR><CR>"),
+        ];
+        for (keys, formatted) in cases {
+            let frames = parse(keys);
+            assert_eq!(format(&frames), formatted, "{}", keys);
+            assert_eq!(parse(formatted), frames, "{}", keys);
+        }
+
+        let leaders = Leaders {
+            leader: ",".to_string(),
+            localleader: " ".to_string(),
+        };
+        let frames = parse_with("<leader>f<localleader>", &leaders);
+        assert_eq!(format_with(&frames, &leaders), "<leader>f<localleader>");
+        // Space is LazyVim's leader
+        assert_eq!(format(&frames), ",f<leader>");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for cmd in commands::stock_commands().unwrap() {
+            for keys in std::iter::once(&cmd.keys).chain(&cmd.alternatives) {
+                let frames = parse(keys);
+                assert_eq!(parse(&format(&frames)), frames, "{}", keys);
+            }
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("<c-W>v"), "<C-w>v");
+        assert_eq!(normalize("<Leader><TAB><cr>"), "<leader><Tab><CR>");
+        assert_eq!(normalize("<ctrl-up><s-f5><pagedown>"), "<C-Up><S-F5><PageDown>");
+        assert_eq!(normalize("<A-J><S-H>"), "<A-J><S-H>");
+        assert_eq!(normalize("<cmd-s><Super-S><win-s>"), "<D-s><D-S><D-s>");
+        assert_eq!(normalize("<LT>"), "<lt>");
+        assert_eq!(
+            normalize("<c-scrollwheelup><2-leftmouse>"),
+            "<C-ScrollWheelUp><2-LeftMouse>"
+        );
+        // Left alone: already normal, unknown, or not a key at all
+        for keys in ["<leader>ff", "<Foo>", "<C-", "a<b", "<>", "<C-->", "\"+y"] {
+            assert_eq!(normalize(keys), keys);
+        }
+    }
+
+    #[test]
+    fn test_parse_checked() {
+        let leaders = Leaders::default();
+        assert_eq!(parse_checked("<C-w>v", &leaders), Ok(parse("<C-w>v")));
+        assert_eq!(parse_checked("<leader>-", &leaders), Ok(parse("<leader>-")));
+        assert_eq!(parse_checked("<C-", &leaders), Ok(parse("<C-")));
+        assert_eq!(parse_checked("<Foo>", &leaders), Err(KeyIssue::UnknownSpecial("Foo".into())));
+        assert_eq!(parse_checked("<X-w>", &leaders), Err(KeyIssue::UnknownModifier("X".into())));
+        assert_eq!(parse_checked("a<>", &leaders), Err(KeyIssue::EmptySpecial));
+        // Super, as format writes it
+        let super_s = parse_checked("<D-s>", &leaders).unwrap();
+        assert_eq!(super_s, parse("<super-s>"));
+        assert_eq!(parse_checked(&format(&super_s), &leaders), Ok(super_s));
+    }
+
+    /// `name` as it is, lowercase or uppercase; notation ignores the case of key names
+    fn any_case(name: &'static str) -> impl Strategy<Value = String> {
+        prop_oneof![
+            Just(name.to_string()),
+            Just(name.to_lowercase()),
+            Just(name.to_uppercase()),
+        ]
+    }
+
+    /// One key in the notation the parser supports
+    fn key_token() -> impl Strategy<Value = String> {
+        let named = prop::sample::select(vec![
+            "CR", "Esc", "BS", "Tab", "Space", "Up", "Down", "Left", "Right", "Home", "End", "Del",
+            "PageUp", "PageDown", "Insert", "F1", "F12",
+        ]);
+        let mouse = prop::sample::select(vec!["LeftMouse", "2-LeftMouse", "ScrollWheelUp"]);
+        let target = prop_oneof![
+            "[a-z0-9+-]".prop_map(String::from),
+            named.clone().prop_flat_map(any_case),
+        ];
+        let modifiers = prop::sample::subsequence(vec!["C", "S", "A"], 1..=3)
+            .prop_flat_map(|mods| mods.into_iter().map(any_case).collect::<Vec<_>>());
+        prop_oneof![
+            3 => "[ -;=-~]".prop_map(String::from),
+            1 => named.prop_flat_map(any_case).prop_map(|name| format!("<{}>", name)),
+            1 => (modifiers, target)
+                .prop_map(|(mods, key)| format!("<{}-{}>", mods.join("-"), key)),
+            1 => Just("<leader>".to_string()),
+            1 => Just("<localleader>".to_string()),
+            1 => mouse.prop_map(|name| format!("<{}>", name)),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_never_panics(keys in any::<String>()) {
+            let frames = parse(&keys);
+            format(&frames);
+            commands::check_keys(&keys);
+            normalize(&keys);
+        }
+
+        #[test]
+        fn test_supported_grammar(tokens in prop::collection::vec(key_token(), 1..8)) {
+            let keys = tokens.concat();
+            let frames = parse(&keys);
+            let issues = commands::check_keys(&keys);
+            prop_assert!(issues.iter().all(KeyIssue::is_warning), "{:?}: {:?}", keys, issues);
+            let formatted = format(&frames);
+            prop_assert_eq!(&parse(&formatted), &frames, "{:?} as {:?}", keys, formatted);
+            prop_assert_eq!(format(&parse(&formatted)), formatted.clone());
+            prop_assert_eq!(parse(&normalize(&keys)), frames);
+        }
+    }
+}
@@ -0,0 +1,28 @@
+//! Key notation parsing, search and keyboard rendering behind the `lvcheat` cheatsheet.
+//!
+//! - [`commands`] loads the builtin LazyVim keymaps and ex commands (plus the user's
+//!   command file) and parses Vim key notation into frames of simultaneous keypresses.
+//! - [`keynotation`] reads and writes Vim key notation, so frames go back to the keys that
+//!   press them.
+//! - [`search`] ranks commands against a query, with `#tag` filters and key-notation matching.
+//! - [`keyboard`] draws a US keyboard as a ratatui widget with chosen keys lit up.
+//! - [`widget`] puts search, results and keyboard together in one widget to embed.
+//!
+//! ```
+//! use lazyvim_helper::commands::parse_key_string;
+//! use lazyvim_helper::keyboard::{Keyboard, KeyboardState};
+//!
+//! let frames = parse_key_string("<C-w>v");
+//! assert_eq!(frames.len(), 2);
+//!
+//! let keyboard = Keyboard::new();
+//! let lit = frames[0].keys.iter().filter_map(|key| keyboard.key_name(&key.key)).collect();
+//! let state = KeyboardState::keys(lit);
+//! assert_eq!(state.frames[0], ["ctrl", "w"]);
+//! ```
+
+pub mod commands;
+pub mod keyboard;
+pub mod keynotation;
+pub mod search;
+pub mod widget;
@@ -0,0 +1,126 @@
+use crate::changelog::Changelog;
+use crate::commands::{self, Command};
+use crate::workspace::Workspace;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A source the TUI can start without, loaded after the first frame
+#[derive(Debug, Clone)]
+pub enum Job {
+    /// The embedded ex commands, added to the first tab
+    ExCommands,
+    /// A command file opened in a tab, the given one of those configured
+    Tab(usize, PathBuf),
+    /// What the builtin keymaps, as loaded for the first tab, gained or lost since the last
+    /// session
+    Changelog(Vec<Command>),
+}
+
+/// What a job loaded
+pub enum Loaded {
+    ExCommands(anyhow::Result<Vec<Command>>),
+    Tab(usize, anyhow::Result<Box<Workspace>>),
+    /// The changelog, and how many bindings changed
+    Changelog(Changelog, usize),
+}
+
+impl Job {
+    /// Named as in the `--profile-startup` report
+    pub fn name(&self) -> String {
+        match self {
+            Job::ExCommands => "ex commands".to_string(),
+            Job::Tab(_, path) => format!("tab {}", path.display()),
+            Job::Changelog(_) => "changelog".to_string(),
+        }
+    }
+
+    /// Load the source here and now
+    pub fn run(self) -> Loaded {
+        match self {
+            Job::ExCommands => Loaded::ExCommands(commands::ex_commands()),
+            Job::Tab(position, path) => Loaded::Tab(position, Workspace::load(&path).map(Box::new)),
+            Job::Changelog(stock) => {
+                let mut changelog = Changelog::load();
+                let changed = changelog.check(&stock).unwrap_or_else(|error| {
+                    tracing::warn!("failed to record keymap changes: {:#}", error);
+                    0
+                });
+                Loaded::Changelog(changelog, changed)
+            }
+        }
+    }
+}
+
+/// Run `jobs` on a pool of threads, one per core at most, sending each result with the job's
+/// name and how long it took as soon as it's ready
+pub fn spawn(jobs: Vec<Job>) -> Receiver<(Loaded, String, Duration)> {
+    let (sender, receiver) = mpsc::channel();
+    let workers = std::thread::available_parallelism().map_or(1, usize::from).min(jobs.len());
+    let queue = Arc::new(Mutex::new(VecDeque::from(jobs)));
+    for _ in 0..workers {
+        let (queue, sender) = (Arc::clone(&queue), sender.clone());
+        std::thread::spawn(move || {
+            // The lock is let go before the job runs
+            let next = || queue.lock().ok().and_then(|mut queue| queue.pop_front());
+            while let Some(job) = next() {
+                let (name, started) = (job.name(), Instant::now());
+                let loaded = job.run();
+                if sender.send((loaded, name, started.elapsed())).is_err() {
+                    // The app quit before it needed the rest
+                    break;
+                }
+            }
+        });
+    }
+    receiver
+}
+
+/// Where a tab that just loaded goes among the ones already open, so tabs keep their
+/// configured order whichever loads first: after the builtin tab and the `loaded` ones
+/// configured before it
+pub fn tab_slot(position: usize, loaded: &[usize]) -> usize {
+    1 + loaded.iter().filter(|&&other| other < position).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tab_slot() {
+        assert_eq!(tab_slot(1, &[]), 1);
+        assert_eq!(tab_slot(0, &[1]), 1);
+        assert_eq!(tab_slot(2, &[1]), 2);
+        assert_eq!(tab_slot(1, &[0, 2]), 2);
+    }
+
+    #[test]
+    fn test_spawn() {
+        let dir = std::env::temp_dir().join(format!("lvcheat-loader-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (good, bad) = (dir.join("work.json"), dir.join("missing.json"));
+        let entry = r#"[{"keys": "<leader>w", "description": "Work", "category": "general"}]"#;
+        std::fs::write(&good, entry).unwrap();
+
+        let jobs = vec![Job::ExCommands, Job::Tab(0, good), Job::Tab(1, bad)];
+        let mut names: Vec<String> = Vec::new();
+        let mut tabs = Vec::new();
+        for (loaded, name, _) in spawn(jobs) {
+            names.push(name);
+            match loaded {
+                Loaded::ExCommands(commands) => assert!(!commands.unwrap().is_empty()),
+                Loaded::Tab(position, ws) => tabs.push((position, ws.is_ok())),
+                Loaded::Changelog(..) => unreachable!(),
+            }
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+        names.sort();
+        assert_eq!(names.len(), 3);
+        assert_eq!(names[0], "ex commands");
+        tabs.sort();
+        assert_eq!(tabs, [(0, true), (1, false)]);
+    }
+}
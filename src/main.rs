@@ -1,30 +1,828 @@
-mod commands;
-mod keyboard;
-mod search;
+mod browser;
+mod card;
+mod cast;
+mod challenge;
+mod changelog;
+mod cli;
+mod compare;
+mod composer;
+mod config;
+mod debug;
+mod demo;
+mod explain;
+#[cfg(feature = "graphics")]
+mod graphics;
+mod input;
+mod loader;
+mod mouse;
+mod normalize;
+mod nvim;
+mod output;
+mod overrides;
+mod popup;
+mod prefixes;
+#[cfg(unix)]
+mod server;
+mod simulate;
+mod state;
+mod stats;
+mod status;
+mod svg;
+mod theme;
+mod tip;
+mod tmux;
+mod tutorial;
+mod typing;
 mod ui;
+mod undo;
+#[cfg(feature = "update")]
+mod update;
+mod validate;
+mod vimmaps;
+#[cfg(feature = "watch")]
+mod watch;
+mod whichkey;
+mod workspace;
 
-use anyhow::Result;
+use anyhow::{Context, Result};
+use clap::{CommandFactory, Parser};
+use cli::{Cli, CliCommand, ExportFormat};
+use commands::{Category, FileFormat};
 use crossterm::{
+    event::{
+        DisableFocusChange, EnableFocusChange, KeyboardEnhancementFlags,
+        PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
     execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+    terminal::{
+        disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
 };
+// Library modules, imported at the root so the binary's modules keep using `crate::` paths
+use lazyvim_helper::{commands, keyboard, keynotation, search};
 use ratatui::{backend::CrosstermBackend, Terminal};
-use std::io;
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use status::Progress;
 use ui::App;
+use validate::Severity;
 
 fn main() -> Result<()> {
-    // Load commands
-    let commands = commands::load_commands()?;
+    let cli = Cli::parse();
+    let profile = cli.profile.as_deref();
+
+    match cli.command {
+        Some(CliCommand::Validate { ref file, fix }) => run_validate(file, fix, no_color(&cli)),
+        Some(CliCommand::Search { query, json }) => run_search(&query.join(" "), json, profile),
+        Some(CliCommand::Show { ref keys, card }) => run_show(keys, card, no_color(&cli), profile),
+        Some(CliCommand::Play { ref keys, loops }) => {
+            run_play(keys, loops, no_color(&cli), profile)
+        }
+        Some(CliCommand::List { category, json }) => run_list(category, json),
+        Some(CliCommand::Serve { socket }) => run_serve(socket, profile),
+        Some(CliCommand::Import {
+            ref which_key,
+            ref vim_maps,
+        }) => run_import(which_key.as_deref(), vim_maps.as_deref(), no_color(&cli)),
+        Some(CliCommand::Compare { ref file, json }) => run_compare(file, json, no_color(&cli)),
+        Some(CliCommand::Update { url, reset }) => run_update(url, reset),
+        Some(CliCommand::Export { format }) => run_export(format, profile),
+        Some(CliCommand::Tutorial { practice }) => {
+            run_tui(&cli, Start::Tutorial { practice })
+        }
+        Some(CliCommand::Challenge { reverse }) => run_tui(&cli, Start::Challenge { reverse }),
+        Some(CliCommand::Demo { ref script }) => {
+            let script = demo::Script::load(script)?;
+            run_tui(&cli, Start::Demo { script })
+        }
+        Some(CliCommand::Completions { shell }) => run_completions(shell),
+        Some(CliCommand::Manpage) => run_manpage(),
+        None => {
+            let start = if let Some(query) = &cli.popup {
+                Start::Popup {
+                    query: query.join(" "),
+                }
+            } else if cli.tip {
+                Start::Tip
+            } else if cli.fresh {
+                Start::Fresh
+            } else {
+                Start::Session
+            };
+            run_tui(&cli, start)
+        }
+    }
+}
+
+fn run_validate(path: &Path, fix: bool, no_color: bool) -> Result<()> {
+    let format = FileFormat::detect(path)?;
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    let diagnostics = validate::validate(&source, format);
+
+    for d in &diagnostics {
+        eprintln!("{}:{}: {}: {}", path.display(), d.line, d.severity, d.message);
+    }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+
+    let rewrites = normalize::file_rewrites(&source, format);
+    if !rewrites.is_empty() {
+        let color = !no_color && io::stdout().is_terminal();
+        println!("{}", normalize::format_rewrites(&rewrites, color));
+        if fix {
+            let (fixed, applied) = normalize::apply(&source, &rewrites);
+            std::fs::write(path, fixed)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            println!("{}: wrote {} normalized key(s)", path.display(), applied);
+        } else {
+            println!("Run with --fix to write them back");
+        }
+    }
+
+    if errors > 0 {
+        eprintln!("{}: {} error(s), {} warning(s)", path.display(), errors, warnings);
+        std::process::exit(1);
+    }
+
+    println!("{}: ok ({} warning(s))", path.display(), warnings);
+    Ok(())
+}
+
+fn run_search(query: &str, json: bool, profile: Option<&str>) -> Result<()> {
+    let commands = load_commands()?;
+    let config = config::Config::load(profile)?;
+
+    let mut results = config.search_engine().search(&commands, query);
+    let (state, query) = (state::UserState::load()?, search::Query::parse(query));
+    state.boost_results(&mut results, &query, config.usage_boost);
+    state.filter_results(&mut results, &query);
+
+    let results: Vec<_> = results.into_iter().map(|(cmd, score)| (cmd, Some(score))).collect();
+    print_output(&output::format_commands(&results, json)?)
+}
+
+fn run_show(keys: &str, card: bool, no_color: bool, profile: Option<&str>) -> Result<()> {
+    let commands = load_commands()?;
+    let shown: Vec<_> = commands
+        .iter()
+        .filter(|cmd| cmd.keys == keys || cmd.alternatives.iter().any(|alt| alt == keys))
+        .collect();
+    if shown.is_empty() {
+        anyhow::bail!("no command is bound to {:?}; try `lvcheat search {}`", keys, keys);
+    }
+    if !card {
+        let listed: Vec<_> = shown.into_iter().map(|cmd| (cmd, None)).collect();
+        return print_output(&output::format_commands(&listed, false)?);
+    }
+
+    let config = config::Config::load(profile)?;
+    let (keyboard, leaders) = (config.keyboard(), config.leaders());
+    let color = !no_color && io::stdout().is_terminal();
+    let cards: Vec<String> = shown
+        .into_iter()
+        .map(|cmd| {
+            let buf = card::render_card(cmd, &keyboard, &leaders);
+            let lines = if color { cast::ansi_lines(&buf) } else { cast::text_lines(&buf) };
+            lines.join("\n")
+        })
+        .collect();
+    print_output(&cards.join("\n\n"))
+}
+
+fn run_play(keys: &str, loops: u32, no_color: bool, profile: Option<&str>) -> Result<()> {
+    let config = config::Config::load(profile)?;
+    let frames = keynotation::parse_checked(keys, &config.leaders())
+        .with_context(|| format!("can't animate {:?}", keys))?;
+    if frames.is_empty() {
+        anyhow::bail!("no keys to animate in {:?}", keys);
+    }
+    let color = !no_color && io::stdout().is_terminal();
+    let screens: Vec<Vec<String>> = cast::screens(keys, &frames, &config.keyboard())
+        .iter()
+        .map(|buf| if color { cast::ansi_lines(buf) } else { cast::text_lines(buf) })
+        .collect();
+    let frame = Duration::from_millis(config.frame_ms);
+    cast::play(&mut io::stdout().lock(), &screens, loops, frame)?;
+    Ok(())
+}
+
+fn run_list(category: Option<Category>, json: bool) -> Result<()> {
+    let commands = load_commands()?;
+    let listed: Vec<_> = commands
+        .iter()
+        .filter(|cmd| {
+            category
+                .as_ref()
+                .is_none_or(|wanted| cmd.category.as_str().eq_ignore_ascii_case(wanted.as_str()))
+        })
+        .map(|cmd| (cmd, None))
+        .collect();
+    print_output(&output::format_commands(&listed, json)?)
+}
+
+#[cfg(unix)]
+fn run_serve(socket: Option<PathBuf>, profile: Option<&str>) -> Result<()> {
+    let server = server::Server::new(
+        load_commands()?,
+        config::Config::load(profile)?.search_engine(),
+        state::UserState::load()?,
+    );
+    server.listen(&socket.unwrap_or_else(server::default_socket_path))
+}
+
+#[cfg(not(unix))]
+fn run_serve(_socket: Option<PathBuf>, _profile: Option<&str>) -> Result<()> {
+    anyhow::bail!("`lvcheat serve` needs unix sockets, which this platform doesn't support")
+}
+
+fn run_import(which_key: Option<&Path>, vim_maps: Option<&Path>, no_color: bool) -> Result<()> {
+    let mut imported = match (which_key, vim_maps) {
+        (Some(path), _) => whichkey::import(&whichkey::load_dump(path)?),
+        (None, Some(path)) => vimmaps::import(
+            &std::fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?,
+        ),
+        (None, None) => Vec::new(),
+    };
+    let rewrites = normalize::normalize_commands(&mut imported);
+    if !rewrites.is_empty() {
+        let color = !no_color && io::stderr().is_terminal();
+        eprintln!("{}", normalize::format_rewrites(&rewrites, color));
+    }
+    eprintln!("lvcheat: imported {} command(s)", imported.len());
+    let undescribed = imported
+        .iter()
+        .filter(|cmd| cmd.has_tag(vimmaps::UNDESCRIBED_TAG))
+        .count();
+    if undescribed > 0 {
+        eprintln!(
+            "lvcheat: {} command(s) without a description show their mapping instead, tagged #{}",
+            undescribed,
+            vimmaps::UNDESCRIBED_TAG
+        );
+    }
+    print_output(&serde_json::to_string_pretty(&imported)?)
+}
+
+fn run_compare(path: &Path, json: bool, no_color: bool) -> Result<()> {
+    let (mine, problems) = commands::load_valid_commands(path)?;
+    for problem in problems {
+        eprintln!("warning: skipped {}", problem);
+    }
+    let differences = compare::compare(&commands::stock_commands()?, &mine);
+    if json {
+        return print_output(&serde_json::to_string_pretty(&differences)?);
+    }
+    let color = !no_color && io::stdout().is_terminal();
+    print_output(&compare::format_differences(&differences, color))
+}
+
+#[cfg(feature = "update")]
+fn run_update(url: Option<String>, reset: bool) -> Result<()> {
+    if reset {
+        if update::reset()? {
+            println!("Removed the downloaded data; using the built-in keymaps again");
+        } else {
+            println!("Already using the built-in keymaps");
+        }
+        return Ok(());
+    }
+
+    let url = url.as_deref().unwrap_or(update::DEFAULT_URL);
+    let (path, count) = update::update(url)?;
+    let builtin = commands::embedded_commands()?.len();
+    println!(
+        "Saved {} commands to {} (built-in copy has {})",
+        count,
+        path.display(),
+        builtin
+    );
+    let changed = changelog::Changelog::load().check(&commands::stock_commands()?)?;
+    if changed > 0 {
+        println!("{} bindings changed; press F9 in the app to see what's new", changed);
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "update"))]
+fn run_update(_url: Option<String>, _reset: bool) -> Result<()> {
+    anyhow::bail!("this build of lvcheat was compiled without the `update` feature")
+}
+
+fn run_export(format: ExportFormat, profile: Option<&str>) -> Result<()> {
+    match format {
+        ExportFormat::WhichKey => {
+            let commands = load_commands()?;
+            print_output(whichkey::export(&commands).trim_end())
+        }
+        ExportFormat::Cast { keys, output } => {
+            let config = config::Config::load(profile)?;
+            let frames = commands::parse_key_string_with(&keys, &config.leaders());
+            if frames.is_empty() {
+                anyhow::bail!("no keys to animate in {:?}", keys);
+            }
+            let cast = cast::render_cast(&keys, &frames, config.frame_ms, &config.keyboard());
+            write_export(output.as_deref(), &cast)
+        }
+        ExportFormat::Anki { query, output } => {
+            let commands = load_commands()?;
+            let config = config::Config::load(profile)?;
+            let query = query.join(" ");
+            let mut results = config.search_engine().search(&commands, &query);
+            state::UserState::load()?.filter_results(&mut results, &search::Query::parse(&query));
+            let listed: Vec<_> = results.into_iter().map(|(cmd, _)| cmd).collect();
+            write_export(output.as_deref(), &output::format_anki(&listed, &config.leaders()))
+        }
+        ExportFormat::Svg {
+            keys,
+            output,
+            per_frame,
+        } => {
+            let config = config::Config::load(profile)?;
+            let frames = commands::parse_key_string_with(&keys, &config.leaders());
+            if frames.is_empty() {
+                anyhow::bail!("no keys to draw in {:?}", keys);
+            }
+            let keyboard = config.keyboard();
+            match output {
+                Some(path) if per_frame => {
+                    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+                    let images = svg::frame_svgs(&keys, &frames, &keyboard);
+                    for (i, image) in images.iter().enumerate() {
+                        let frame_path = path.with_file_name(format!("{}-{}.svg", stem, i + 1));
+                        write_export(Some(&frame_path), image)?;
+                        println!("{}", frame_path.display());
+                    }
+                    Ok(())
+                }
+                output => {
+                    write_export(output.as_deref(), &svg::legend_svg(&keys, &frames, &keyboard))
+                }
+            }
+        }
+    }
+}
+
+/// Write an exported file, or print it when no path was given
+fn write_export(path: Option<&Path>, contents: &str) -> Result<()> {
+    match path {
+        Some(path) => std::fs::write(path, contents)
+            .with_context(|| format!("failed to write {}", path.display())),
+        None => print_output(contents.trim_end()),
+    }
+}
+
+fn run_completions(shell: clap_complete::Shell) -> Result<()> {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut Cli::command(), "lvcheat", &mut script);
+    print_output(String::from_utf8(script)?.trim_end())
+}
+
+fn run_manpage() -> Result<()> {
+    let mut page = Vec::new();
+    clap_mangen::Man::new(Cli::command()).render(&mut page)?;
+    print_output(String::from_utf8(page)?.trim_end())
+}
+
+/// The commands for a one-off command, with a warning on stderr for each user entry left out
+fn load_commands() -> Result<Vec<commands::Command>> {
+    let overrides = overrides::Overrides::load()?;
+    let (commands, problems, _) = commands::load_commands(|id| overrides.resolution(id))?;
+    for problem in problems {
+        eprintln!("warning: skipped {}", problem);
+    }
+    Ok(commands)
+}
+
+/// Print command output, skipping the blank line an empty text listing would leave.
+/// A closed pipe (e.g. `| head`) isn't an error.
+fn print_output(output: &str) -> Result<()> {
+    if output.is_empty() {
+        return Ok(());
+    }
+    match writeln!(io::stdout(), "{}", output) {
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+        result => Ok(result?),
+    }
+}
+
+/// Whether to draw without color: `--no-color`, or `NO_COLOR` set and non-empty
+/// (https://no-color.org)
+fn no_color(cli: &Cli) -> bool {
+    cli.no_color || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+}
+
+/// How often an attached Neovim is asked for its keymaps
+const NVIM_POLL: Duration = Duration::from_secs(2);
+
+/// Kitty keyboard protocol features asked for where typed keys are checked
+const KEYBOARD_FLAGS: KeyboardEnhancementFlags =
+    KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+        .union(KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS)
+        .union(KeyboardEnhancementFlags::REPORT_EVENT_TYPES)
+        .union(KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES);
+
+/// What the TUI opens on
+enum Start {
+    /// Where the last session left off
+    Session,
+    /// An empty search
+    Fresh,
+    /// A random tip, quitting when it's dismissed
+    Tip,
+    /// The guided tutorial, quitting at its end
+    Tutorial { practice: bool },
+    /// A timed challenge round, asking what bindings do when `reverse`
+    Challenge { reverse: bool },
+    /// Compact, for `tmux display-popup`, searching for `query`
+    Popup { query: String },
+    /// Playing a demo script, quitting at its end
+    Demo { script: demo::Script },
+}
+
+/// The commands piped in for `--stdin`, in whichever format they're written, with the entries
+/// left out as problems
+fn read_piped() -> Result<(Vec<commands::Command>, Vec<commands::Problem>)> {
+    // Keys are read from the terminal instead, which crossterm opens as /dev/tty when stdin
+    // isn't one; other platforms have no such file to turn to
+    if cfg!(not(unix)) {
+        anyhow::bail!("--stdin is only available on Unix-like systems");
+    }
+    if io::stdin().is_terminal() {
+        anyhow::bail!(
+            "--stdin reads commands from a pipe, e.g. `cat mymaps.json | lvcheat --stdin`"
+        );
+    }
+    let mut source = String::new();
+    io::stdin().read_to_string(&mut source).context("failed to read stdin")?;
+    // Fail before reading keys if there's no controlling terminal to read them from
+    std::fs::File::open("/dev/tty").context("--stdin needs a terminal to run in")?;
+
+    let (commands, skipped) = commands::parse_valid_commands(&source, FileFormat::sniff(&source));
+    let path = PathBuf::from("<stdin>");
+    let problems: Vec<commands::Problem> = skipped
+        .into_iter()
+        .map(|(line, message)| commands::Problem {
+            path: path.clone(),
+            line,
+            message,
+        })
+        .collect();
+    if commands.is_empty() {
+        match problems.first() {
+            Some(problem) => anyhow::bail!("no commands on stdin: {}", problem),
+            None => anyhow::bail!("no commands on stdin"),
+        }
+    }
+    let source = commands::Source::File("stdin".to_string());
+    Ok((commands::with_source(commands, source), problems))
+}
+
+/// Hand the terminal to `$VISUAL` or `$EDITOR` (else `vi`) on `path`, then take it back and
+/// reload the commands and overrides the file may have changed
+fn edit_file(
+    terminal: &mut Terminal<CrosstermBackend<io::Stdout>>,
+    path: &Path,
+    enhanced: bool,
+    cli: &Cli,
+    app: &mut App,
+) -> Result<()> {
+    if enhanced {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), DisableFocusChange, LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    // The editor may come with arguments, e.g. `code --wait`
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or("vi");
+    let status = std::process::Command::new(program).args(words).arg(path).status();
+
+    // Take the terminal back before anything can fail, so an error doesn't leave it broken
+    enable_raw_mode()?;
+    execute!(terminal.backend_mut(), EnterAlternateScreen, EnableFocusChange)?;
+    if enhanced {
+        execute!(terminal.backend_mut(), PushKeyboardEnhancementFlags(KEYBOARD_FLAGS))?;
+    }
+    terminal.clear()?;
+
+    match status {
+        Ok(status) if status.success() => reload(cli, app, path),
+        Ok(status) => app.messages.push(format!("{} exited with {}", program, status)),
+        Err(error) => app.messages.push(format!("Failed to run {}: {}", program, error)),
+    }
+    Ok(())
+}
+
+/// The config file's settings, overridden by the command line's
+fn load_config(cli: &Cli) -> Result<config::Config> {
+    let mut config = config::Config::load(cli.profile.as_deref())?;
+    config.animation &= !cli.no_animation;
+    config.plain |= cli.plain;
+    if no_color(cli) {
+        config.theme = theme::Theme::Monochrome;
+    }
+    config.colors = config.colors.resolve();
+    Ok(config)
+}
+
+/// Load the config, overrides and commands again after `changed` was edited, keeping the
+/// session going. A file that no longer loads is reported, and what was loaded before stays.
+/// A `--stdin` session only reloads the config, keeping the commands piped in.
+fn reload(cli: &Cli, app: &mut App, changed: &Path) {
+    if cli.stdin {
+        match load_config(cli) {
+            Ok(config) => {
+                app.set_config(config);
+                app.messages.push(format!("Reloaded {}", changed.display()));
+            }
+            Err(error) => app.messages.push(format!("Failed to reload: {:#}", error)),
+        }
+        return;
+    }
+    let reloaded = load_config(cli).and_then(|config| {
+        let overrides = overrides::Overrides::load()?;
+        let (commands, problems, conflicts) =
+            commands::load_commands(|id| overrides.resolution(id))?;
+        Ok((config, commands, problems, conflicts, overrides))
+    });
+    match reloaded {
+        Ok((config, commands, problems, conflicts, overrides)) => {
+            app.set_config(config);
+            app.reload(commands, problems, conflicts, overrides);
+            app.messages.push(format!("Reloaded {}", changed.display()));
+        }
+        Err(error) => app.messages.push(format!("Failed to reload: {:#}", error)),
+    }
+}
+
+/// Load the tab opened from `changed` again after it was edited. A file that no longer loads
+/// is reported, and the tab keeps what it had.
+#[cfg(feature = "watch")]
+fn reload_tab(app: &mut App, changed: &Path) {
+    match workspace::Workspace::load(changed) {
+        Ok(workspace) => {
+            let problems = !workspace.problems.is_empty();
+            app.reload_tab(workspace);
+            app.messages.push(format!("Reloaded {}", changed.display()));
+            if problems {
+                app.announce_problems();
+            }
+        }
+        Err(error) => app.messages.push(format!("Failed to reload: {:#}", error)),
+    }
+}
+
+/// The user's files a running app reloads when they change: the command files, overrides
+/// and config
+#[cfg(feature = "watch")]
+fn watched_files() -> Result<Vec<PathBuf>> {
+    let dir = commands::config_dir().context("no config directory available")?;
+    let mut files: Vec<PathBuf> =
+        commands::USER_COMMAND_FILES.iter().map(|name| dir.join(name)).collect();
+    files.extend(overrides::Overrides::default_path());
+    files.extend(config::Config::default_path());
+    Ok(files)
+}
+
+/// Put what a loader job loaded into the running app. `loaded_tabs` has the configured
+/// positions of the tabs opened so far, to keep them in order.
+fn apply_loaded(app: &mut App, loaded: loader::Loaded, loaded_tabs: &mut Vec<usize>) {
+    match loaded {
+        loader::Loaded::ExCommands(Ok(commands)) => app.add_commands(commands),
+        loader::Loaded::ExCommands(Err(error)) => {
+            app.messages.push(format!("Failed to load the ex commands: {:#}", error));
+        }
+        loader::Loaded::Tab(position, Ok(tab)) => {
+            let problems = !tab.problems.is_empty();
+            app.insert_tab(loader::tab_slot(position, loaded_tabs), *tab);
+            loaded_tabs.push(position);
+            if problems {
+                app.announce_problems();
+            }
+        }
+        loader::Loaded::Tab(_, Err(error)) => {
+            app.messages.push(format!("Failed to open a tab: {:#}", error));
+        }
+        loader::Loaded::Changelog(changelog, changed) => {
+            app.changelog = changelog;
+            if changed > 0 {
+                app.messages.push(format!(
+                    "{} builtin bindings changed since you last looked · F9: what's new",
+                    changed
+                ));
+            }
+        }
+    }
+}
+
+/// Put the keymaps an attached Neovim listed in their tab, saying how many are new
+fn apply_nvim(app: &mut App, keymaps: Result<Vec<commands::Command>>) {
+    match keymaps {
+        Ok(keymaps) => match app.set_tab(nvim::TAB_NAME, keymaps.clone()) {
+            None => app.messages.push(format!(
+                "{} keymaps from Neovim in the {} tab · Ctrl+→",
+                keymaps.len(),
+                nvim::TAB_NAME
+            )),
+            Some(old) => match nvim::new_keymaps(&old, &keymaps) {
+                0 => app.messages.push("Neovim's keymaps changed".to_string()),
+                new => app.messages.push(format!("{} new keymaps appeared in Neovim", new)),
+            },
+        },
+        Err(error) => app.messages.push(format!("Lost Neovim's keymaps: {:#}", error)),
+    }
+}
+
+#[cfg(feature = "graphics")]
+fn keycap_images_supported() -> bool {
+    graphics::supported()
+}
+
+#[cfg(not(feature = "graphics"))]
+fn keycap_images_supported() -> bool {
+    false
+}
+
+fn run_tui(cli: &Cli, start: Start) -> Result<()> {
+    if cli.debug {
+        let path = debug::log_path().context("no data directory available for the log")?;
+        debug::init_logging(&path)?;
+        tracing::info!(version = env!("CARGO_PKG_VERSION"), "starting");
+    }
+
+    let mut profile = debug::StartupProfile::default();
+    // Load the keymaps, settling conflicts with the builtin ones as chosen before
+    let overrides = overrides::Overrides::load()?;
+    profile.mark("overrides");
+    // The builtin keymaps are loaded once, for the first tab and for the changelog
+    let stock = if cli.stdin { None } else { Some(commands::stock_commands()?) };
+    let (commands, problems, conflicts) = match &stock {
+        None => {
+            let (commands, problems) = read_piped()?;
+            (commands, problems, Vec::new())
+        }
+        Some(stock) => commands::load_keymaps(stock.clone(), |id| overrides.resolution(id))?,
+    };
+    profile.mark("keymaps");
+    tracing::info!(
+        commands = commands.len(),
+        skipped = problems.len(),
+        conflicts = conflicts.len(),
+        "loaded commands"
+    );
+    for problem in &problems {
+        tracing::warn!("skipped {}", problem);
+    }
+    let config = load_config(cli)?;
+    profile.mark("config");
+    let grammar = composer::Grammar::load()?;
+    profile.mark("grammar");
+    let prefixes = prefixes::Prefixes::load()?;
+    profile.mark("prefixes");
+    // A demo plays the same whatever the user has starred, hidden or learned, and a look at
+    // piped commands doesn't count towards any of it
+    let state = match start {
+        Start::Demo { .. } => state::UserState::default(),
+        _ if cli.stdin => state::UserState::default(),
+        _ => state::UserState::load()?,
+    };
+    profile.mark("state");
+    let session = state.session.clone();
+
+    // The first frame only needs the keymaps. The rest load in the background, unless the
+    // start picks among every command or restores an ex command's selection.
+    let tabs = config.tabs.iter().chain(&cli.tabs).cloned().enumerate();
+    let mut jobs = Vec::new();
+    // Piped commands are listed on their own
+    if !cli.stdin {
+        jobs.push(loader::Job::ExCommands);
+    }
+    jobs.extend(tabs.map(|(position, path)| loader::Job::Tab(position, path)));
+    jobs.extend(stock.map(loader::Job::Changelog));
+    let restores_ex = session
+        .as_ref()
+        .and_then(|session| session.selected.as_ref())
+        .is_some_and(|id| id.keys.starts_with(':'));
+    let in_background = cli.select.is_none()
+        && match start {
+            Start::Session => !restores_ex,
+            Start::Fresh | Start::Popup { .. } => true,
+            Start::Tip
+            | Start::Tutorial { .. }
+            | Start::Challenge { .. }
+            | Start::Demo { .. } => false,
+        };
+    let mut demo = match &start {
+        Start::Demo { script } => Some(demo::Demo::new(script, Instant::now())?),
+        _ => None,
+    };
 
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen)?;
+    // Focus changes pause the animation while the terminal is in the background
+    execute!(stdout, EnterAlternateScreen, EnableFocusChange)?;
+    // Where typed keys are checked, the kitty keyboard protocol tells Ctrl+I from Tab and Esc
+    // from Alt chords, and reports Shift with letters reliably. Other terminals keep the legacy
+    // encoding.
+    let checks_typing = matches!(
+        start,
+        Start::Tutorial { practice: true } | Start::Challenge { reverse: false }
+    );
+    let enhanced = checks_typing && supports_keyboard_enhancement().unwrap_or(false);
+    if enhanced {
+        execute!(stdout, PushKeyboardEnhancementFlags(KEYBOARD_FLAGS))?;
+    }
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
 
-    // Create app
-    let mut app = App::new(commands);
+    // Create app, back where the last session left off unless asked not to
+    let keycap_images = config.keycap_images && keycap_images_supported();
+    let mut app = App::new(commands, config, state, overrides, grammar);
+    app.ws.problems = problems;
+    app.conflicts = conflicts;
+    app.prefixes = prefixes;
+    app.announce_problems();
+    app.keycap_images = keycap_images;
+    app.snapshot_file = cli.snapshot_file.clone();
+    app.plain_snapshots = cli.plain_snapshots || no_color(cli);
+    if cli.debug {
+        app.debug = Some(debug::DebugInfo::default());
+    }
+    // Configured positions of the tabs opened so far
+    let mut loaded_tabs = Vec::new();
+    let loading = if in_background {
+        app.loading = Progress::new(jobs.len(), Instant::now());
+        Some(loader::spawn(jobs))
+    } else {
+        for job in jobs {
+            let name = job.name();
+            apply_loaded(&mut app, job.run(), &mut loaded_tabs);
+            profile.mark(&name);
+        }
+        None
+    };
+    match start {
+        Start::Session => {
+            if let Some(session) = session {
+                app.restore_session(&session);
+            }
+            app.show_conflicts();
+        }
+        Start::Fresh => app.show_conflicts(),
+        Start::Tip => {
+            app.tip_only = true;
+            app.show_tip();
+        }
+        Start::Tutorial { practice } => {
+            app.tutorial = tutorial::Tutorial::new(&app.ws.commands, practice);
+            app.should_quit = app.tutorial.is_none();
+        }
+        Start::Challenge { reverse } => app.start_challenge(reverse),
+        Start::Popup { query } => {
+            app.tmux_popup = true;
+            app.ws.query.set(&query);
+            app.update_search();
+        }
+        Start::Demo { .. } => {}
+    }
+    // Deep links from scripts and editors win over the restored session
+    if let Some(category) = &cli.category {
+        app.open_category(category.clone());
+        if app.ws.filtered_results.is_empty() {
+            app.messages.push(format!("No commands in {}", category.as_str()));
+        }
+    }
+    if let Some(keys) = &cli.select {
+        if !app.select_keys(keys) {
+            app.messages.push(format!("No command is bound to {}", keys));
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    let mut keycaps = graphics::Keycaps::default();
+    // Edits made elsewhere are picked up as they're saved; without a watcher they aren't
+    #[cfg(feature = "watch")]
+    let mut watcher = watched_files()
+        .map(|files| files.into_iter().chain(app.tab_paths()).collect())
+        .and_then(watch::FileWatcher::new)
+        .inspect_err(|error| tracing::warn!("not watching the user's files: {:#}", error))
+        .ok();
+    // An attached Neovim is asked for its keymaps every so often, and its tab follows them
+    let nvim_keymaps =
+        cli.nvim.clone().map(|server| nvim::Attachment::new(server).watch(NVIM_POLL));
 
     // Main loop
     while !app.should_quit {
@@ -32,15 +830,98 @@ fn main() -> Result<()> {
         app.tick();
 
         // Draw
+        let drawing = Instant::now();
         terminal.draw(|frame| app.draw(frame))?;
+        if let Some(debug) = &mut app.debug {
+            debug.drawn(drawing.elapsed(), Instant::now());
+        }
+        profile.drawn();
+        #[cfg(feature = "graphics")]
+        keycaps.show(terminal.backend_mut(), &app.keyboard, app.keycaps.take())?;
 
         // Handle input
         app.handle_input()?;
+        if let Some(demo) = &mut demo {
+            app.should_quit |= !demo.advance(&mut app, Instant::now())?;
+            app.wake_at = Some(demo.due());
+        }
+
+        if let Some(loading) = &loading {
+            for (loaded, name, took) in loading.try_iter() {
+                profile.background(&name, took);
+                apply_loaded(&mut app, loaded, &mut loaded_tabs);
+                app.loading.advance();
+                #[cfg(feature = "watch")]
+                if let Some(watcher) = &mut watcher {
+                    for path in app.tab_paths() {
+                        if let Err(error) = watcher.watch(path) {
+                            tracing::warn!("not watching a tab's file: {:#}", error);
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(keymaps) = &nvim_keymaps {
+            for keymaps in keymaps.try_iter() {
+                apply_nvim(&mut app, keymaps);
+            }
+        }
+
+        if app.edit.is_some() && cli.stdin {
+            app.edit = None;
+            let message = "Piped commands can't be edited; F10 is off with --stdin";
+            app.messages.push(message.to_string());
+        }
+        if let Some(path) = app.edit.take() {
+            #[cfg(feature = "graphics")]
+            keycaps.clear(terminal.backend_mut())?;
+            edit_file(&mut terminal, &path, enhanced, cli, &mut app)?;
+            // Already reloaded, so the editor's writes aren't news
+            #[cfg(feature = "watch")]
+            if let Some(watcher) = &mut watcher {
+                watcher.forget();
+            }
+        }
+        #[cfg(feature = "watch")]
+        if let Some(watcher) = &mut watcher {
+            let (tabs, user): (Vec<PathBuf>, Vec<PathBuf>) = watcher
+                .changed()
+                .into_iter()
+                .partition(|changed| app.tab_paths().contains(changed));
+            // One reload picks up every user file
+            if let Some(changed) = user.first() {
+                reload(cli, &mut app, changed);
+            }
+            for changed in tabs {
+                reload_tab(&mut app, &changed);
+            }
+        }
     }
 
     // Restore terminal
+    #[cfg(feature = "graphics")]
+    keycaps.clear(terminal.backend_mut())?;
+    if enhanced {
+        execute!(terminal.backend_mut(), PopKeyboardEnhancementFlags)?;
+    }
     disable_raw_mode()?;
-    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    execute!(terminal.backend_mut(), DisableFocusChange, LeaveAlternateScreen)?;
+
+    // Persist history gathered during the session
+    app.save_state()?;
+
+    if cli.profile_startup {
+        eprintln!("{}", profile.report());
+    }
+
+    if !app.snapshots.is_empty() {
+        print!("{}", app.snapshots.join("\n"));
+    }
+
+    if let Some(idx) = app.picked {
+        // Spelled the usual way, whatever notation the command file uses
+        tmux::set_buffer(&keynotation::format_with(&app.ws.frames[idx], &app.leaders))?;
+    }
 
     Ok(())
 }
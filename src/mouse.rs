@@ -0,0 +1,128 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+/// The mouse drawn beside the keyboard: buttons on top, the wheel between them, and the
+/// horizontal wheel and side buttons below
+const GLYPH: [&str; 7] = [
+    "╭─┬─┬─╮",
+    "│ │▲│ │",
+    "│L│M│R│",
+    "│ │▼│ │",
+    "├─┴─┴─┤",
+    "│◀1 2▶│",
+    "╰─────╯",
+];
+pub const MOUSE_HEIGHT: u16 = GLYPH.len() as u16;
+
+/// A mouse pseudo-key of the sequence, as the glyph shows it
+#[derive(Debug, Clone)]
+pub struct MousePress {
+    /// Vim's name for it, e.g. `2-LeftMouse` or `ScrollWheelUp`
+    pub name: String,
+    /// What goes before its description, like the frame's mark and modifiers held
+    pub prefix: String,
+    pub style: Style,
+}
+
+/// Row and column of the glyph's cells the pseudo-key `name` lights
+fn cells(name: &str) -> &'static [(usize, usize)] {
+    let button = name.trim_start_matches(|c: char| c.is_ascii_digit() || c == '-');
+    match button {
+        "ScrollWheelUp" => &[(1, 3)],
+        "ScrollWheelDown" => &[(3, 3)],
+        "ScrollWheelLeft" => &[(5, 1)],
+        "ScrollWheelRight" => &[(5, 5)],
+        "X1Mouse" => &[(5, 2)],
+        "X2Mouse" => &[(5, 4)],
+        _ if button.starts_with("Left") => &[(1, 1), (2, 1), (3, 1)],
+        _ if button.starts_with("Middle") => &[(2, 3)],
+        _ if button.starts_with("Right") => &[(1, 5), (2, 5), (3, 5)],
+        _ => &[],
+    }
+}
+
+/// What the pseudo-key `name` does in words, e.g. "double-click left" or "scroll up"
+pub fn describe(name: &str) -> String {
+    let (clicks, button) = match name.split_once('-') {
+        Some((count, button)) => (count, button),
+        None => ("1", name),
+    };
+    if let Some(direction) = button.strip_prefix("ScrollWheel") {
+        return format!("scroll {}", direction.to_lowercase());
+    }
+    let click = match clicks {
+        "2" => "double-click",
+        "3" => "triple-click",
+        "4" => "quadruple-click",
+        _ => "click",
+    };
+    let side = |suffix| button.strip_suffix(suffix).map(str::to_lowercase);
+    if let Some(side) = side("Mouse") {
+        match side.as_str() {
+            "x1" => "back button".to_string(),
+            "x2" => "forward button".to_string(),
+            side => format!("{} {}", click, side),
+        }
+    } else if let Some(side) = side("Drag") {
+        format!("drag with {}", side)
+    } else if let Some(side) = side("Release") {
+        format!("release {}", side)
+    } else {
+        name.to_string()
+    }
+}
+
+/// The glyph with each press's parts lit in its style, and what each press does beside it
+pub fn draw_mouse(frame: &mut Frame, area: Rect, presses: &[MousePress]) {
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines: Vec<Line> = GLYPH
+        .iter()
+        .enumerate()
+        .map(|(row, text)| {
+            let spans = text.chars().enumerate().map(|(column, c)| {
+                let lit = presses
+                    .iter()
+                    .rev()
+                    .find(|press| cells(&press.name).contains(&(row, column)));
+                Span::styled(c.to_string(), lit.map_or(dim, |press| press.style))
+            });
+            Line::from(spans.collect::<Vec<_>>())
+        })
+        .collect();
+    for (line, press) in lines.iter_mut().skip(1).zip(presses) {
+        line.spans.push(Span::raw(format!("  {}{}", press.prefix, describe(&press.name))));
+    }
+    frame.render_widget(Paragraph::new(lines), area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe() {
+        assert_eq!(describe("ScrollWheelUp"), "scroll up");
+        assert_eq!(describe("2-LeftMouse"), "double-click left");
+        assert_eq!(describe("RightMouse"), "click right");
+        assert_eq!(describe("MiddleDrag"), "drag with middle");
+        assert_eq!(describe("LeftRelease"), "release left");
+        assert_eq!(describe("X1Mouse"), "back button");
+    }
+
+    #[test]
+    fn test_cells() {
+        for name in crate::commands::MOUSE_KEYS {
+            for &(row, column) in cells(name) {
+                let c = GLYPH[row].chars().nth(column).unwrap();
+                assert!(!"│─╭╮╰╯┬┴├┤".contains(c), "{} lights a border", name);
+            }
+            assert!(!cells(name).is_empty(), "{} lights nothing", name);
+        }
+        assert_eq!(cells("3-LeftMouse"), cells("LeftMouse"));
+    }
+}
@@ -0,0 +1,139 @@
+use crate::commands::{self, Command, FileFormat};
+use crate::keynotation;
+use crossterm::style::Stylize;
+use serde_json::Value;
+
+/// Keys spelled differently from the usual notation, and how they read once normalized
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rewrite {
+    /// Line of the command's entry in its file, unless it came from an import
+    pub line: Option<usize>,
+    pub description: String,
+    pub before: String,
+    pub after: String,
+}
+
+/// Normalize the keys and alternatives of `commands` in place, returning what changed
+pub fn normalize_commands(commands: &mut [Command]) -> Vec<Rewrite> {
+    let mut rewrites = Vec::new();
+    for cmd in commands {
+        for keys in std::iter::once(&mut cmd.keys).chain(&mut cmd.alternatives) {
+            let after = keynotation::normalize(keys);
+            if after != *keys {
+                rewrites.push(Rewrite {
+                    line: None,
+                    description: cmd.description.clone(),
+                    before: std::mem::replace(keys, after.clone()),
+                    after,
+                });
+            }
+        }
+    }
+    rewrites
+}
+
+/// What normalizing would change in a command file's keys and alternatives; nothing for a
+/// file that doesn't parse, which `validate` reports instead
+pub fn file_rewrites(source: &str, format: FileFormat) -> Vec<Rewrite> {
+    let Ok(entries) = commands::raw_entries(source, format) else {
+        return Vec::new();
+    };
+    let mut rewrites = Vec::new();
+    for (line, entry) in &entries {
+        let description = entry.get("description").and_then(Value::as_str).unwrap_or_default();
+        let alternatives = entry.get("alternatives").and_then(Value::as_array);
+        let keys = entry.get("keys").into_iter().chain(alternatives.into_iter().flatten());
+        for before in keys.filter_map(Value::as_str) {
+            let after = keynotation::normalize(before);
+            if after != before {
+                rewrites.push(Rewrite {
+                    line: Some(*line),
+                    description: description.to_string(),
+                    before: before.to_string(),
+                    after,
+                });
+            }
+        }
+    }
+    rewrites
+}
+
+/// `source` with each rewrite made where its keys are written in its entry, leaving the rest
+/// of the file as it was, and how many were made. Keys written with escapes aren't found,
+/// and stay as they were.
+pub fn apply(source: &str, rewrites: &[Rewrite]) -> (String, usize) {
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let (mut out, mut done, mut applied) = (String::new(), 0, 0);
+    for rewrite in rewrites {
+        let line = rewrite.line.unwrap_or(1).saturating_sub(1);
+        let from = line_starts.get(line).copied().unwrap_or(source.len()).max(done);
+        let Some(found) = source[from..].find(&rewrite.before).map(|i| from + i) else {
+            continue;
+        };
+        out.push_str(&source[done..found]);
+        out.push_str(&rewrite.after);
+        done = found + rewrite.before.len();
+        applied += 1;
+    }
+    out.push_str(&source[done..]);
+    (out, applied)
+}
+
+/// Each rewrite as a removed and an added line under where it is, red and green when `color`
+/// is set, with a count at the end
+pub fn format_rewrites(rewrites: &[Rewrite], color: bool) -> String {
+    let mut lines = Vec::new();
+    for rewrite in rewrites {
+        let heading = match rewrite.line {
+            Some(line) => format!("line {} · {}", line, rewrite.description),
+            None => rewrite.description.clone(),
+        };
+        let (before, after) = (format!("  - {}", rewrite.before), format!("  + {}", rewrite.after));
+        if color {
+            lines.push(heading.dark_grey().to_string());
+            lines.push(before.red().to_string());
+            lines.push(after.green().to_string());
+        } else {
+            lines.extend([heading, before, after]);
+        }
+    }
+    lines.push(format!("{} key notation(s) normalized", rewrites.len()));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_rewrites() {
+        let source = r#"[
+  {
+    "keys": "<c-W>v",
+    "description": "Split <c-W>v",
+    "category": "Windows",
+    "alternatives": ["<c-w>V", "<C-w>s"]
+  },
+  { "keys": "<leader>ff", "description": "Find files", "category": "Search" }
+]"#;
+        let rewrites = file_rewrites(source, FileFormat::Json);
+        let changed: Vec<(&str, &str)> =
+            rewrites.iter().map(|r| (r.before.as_str(), r.after.as_str())).collect();
+        assert_eq!(changed, [("<c-W>v", "<C-w>v"), ("<c-w>V", "<C-w>V")]);
+        assert_eq!(rewrites[0].line, Some(2));
+
+        let (fixed, applied) = apply(source, &rewrites);
+        assert_eq!(applied, 2);
+        // Only the keys change, not the same text in the description
+        assert!(fixed.contains(r#""keys": "<C-w>v""#));
+        assert!(fixed.contains(r#""description": "Split <c-W>v""#));
+        assert!(fixed.contains(r#"["<C-w>V", "<C-w>s"]"#));
+        assert!(file_rewrites(&fixed, FileFormat::Json).is_empty());
+
+        let text = format_rewrites(&rewrites, false);
+        assert!(text.starts_with("line 2 · Split <c-W>v\n  - <c-W>v\n  + <C-w>v\n"));
+        assert!(text.ends_with("2 key notation(s) normalized"));
+    }
+}
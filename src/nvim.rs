@@ -0,0 +1,129 @@
+use crate::commands::{self, Command, CommandId, Source};
+use crate::whichkey::{self, DumpEntry};
+use anyhow::Context;
+use std::collections::HashSet;
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// Name of the tab listing an attached Neovim's keymaps
+pub const TAB_NAME: &str = "nvim";
+
+/// Lua run in the attached Neovim, listing its global keymaps in the shape of a which-key dump,
+/// with the leader written as `<leader>`. Keymaps with neither a description nor a right-hand
+/// side are left out. It has no single quotes, to fit in the `luaeval('...')` around it.
+const KEYMAPS_LUA: &str = r#"(function()
+  local leader = vim.fn.keytrans(vim.g.mapleader or "\\")
+  local maps = {}
+  for _, mode in ipairs({ "n", "x", "s", "o", "i", "c", "t" }) do
+    for _, m in ipairs(vim.api.nvim_get_keymap(mode)) do
+      local lhs = vim.fn.keytrans(m.lhsraw or m.lhs)
+      if #lhs > #leader and lhs:sub(1, #leader) == leader then
+        lhs = "<leader>" .. lhs:sub(#leader + 1)
+      end
+      local desc = m.desc or m.rhs
+      if desc and desc ~= "" and not lhs:find("^<Plug>") and not lhs:find("^<SNR>") then
+        table.insert(maps, { lhs = lhs, mode = mode, desc = desc })
+      end
+    end
+  end
+  return vim.json.encode(maps)
+end)()"#;
+
+/// A running Neovim reached through its server address, to list the keymaps of
+pub struct Attachment {
+    server: String,
+}
+
+impl Attachment {
+    pub fn new(server: impl Into<String>) -> Self {
+        Self {
+            server: server.into(),
+        }
+    }
+
+    /// Its keymaps as they are now, asked for with `nvim --server ... --remote-expr`
+    pub fn keymaps(&self) -> anyhow::Result<Vec<Command>> {
+        // A Vim string can't span lines, but the Lua reads the same on one
+        let expr = format!("luaeval('{}')", KEYMAPS_LUA.replace('\n', " "));
+        let output = std::process::Command::new("nvim")
+            .args(["--server", &self.server, "--remote-expr", &expr])
+            .output()
+            .context("failed to run nvim")?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            tracing::warn!("nvim --server {} failed: {}", self.server, stderr.trim());
+            anyhow::bail!("no Neovim at {}", self.server);
+        }
+        parse_keymaps(&String::from_utf8_lossy(&output.stdout))
+    }
+
+    /// Ask for the keymaps every `every` on a thread of its own, sending them whenever they
+    /// differ from the last ones sent, the first time included. A failure is sent once until
+    /// the keymaps come back. Polling stops once the receiver is dropped.
+    pub fn watch(self, every: Duration) -> Receiver<anyhow::Result<Vec<Command>>> {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            let mut last = None;
+            loop {
+                let keymaps = self.keymaps();
+                let seen = keymaps.as_ref().map(|keymaps| fingerprint(keymaps)).ok();
+                if last.as_ref() != Some(&seen) {
+                    last = Some(seen);
+                    if sender.send(keymaps).is_err() {
+                        break;
+                    }
+                }
+                std::thread::sleep(every);
+            }
+        });
+        receiver
+    }
+}
+
+/// What makes two listings of keymaps the same
+fn fingerprint(keymaps: &[Command]) -> Vec<(CommandId, String)> {
+    keymaps.iter().map(|cmd| (cmd.id(), cmd.description.clone())).collect()
+}
+
+/// The commands in what the keymaps Lua returned
+fn parse_keymaps(output: &str) -> anyhow::Result<Vec<Command>> {
+    let entries: Vec<DumpEntry> =
+        serde_json::from_str(output.trim()).context("Neovim didn't list its keymaps")?;
+    let keymaps = whichkey::import(&entries);
+    Ok(commands::with_source(keymaps, Source::File(TAB_NAME.to_string())))
+}
+
+/// How many of the keymaps in `after` weren't among those `before`
+pub fn new_keymaps(before: &[Command], after: &[Command]) -> usize {
+    let before: HashSet<CommandId> = before.iter().map(Command::id).collect();
+    after.iter().filter(|cmd| !before.contains(&cmd.id())).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keymaps() {
+        let output = r#"[
+            {"lhs": "<leader>ff", "mode": "n", "desc": "Find Files"},
+            {"lhs": "<leader>ff", "mode": "n", "desc": "Find Files (again)"},
+            {"lhs": "<leader>cr", "mode": "x", "desc": "Rename"},
+            {"lhs": "gco", "mode": "n", "desc": "Add Comment Below"}
+        ]
+"#;
+        let keymaps = parse_keymaps(output).unwrap();
+        let keys: Vec<&str> = keymaps.iter().map(|cmd| cmd.keys.as_str()).collect();
+        assert_eq!(keys, ["<leader>ff", "<leader>cr", "gco"]);
+        assert_eq!(keymaps[1].mode, commands::Mode::Visual);
+        assert_eq!(keymaps[0].source, Source::File("nvim".to_string()));
+        assert!(parse_keymaps("E5108: Error executing lua").is_err());
+
+        let sourced = parse_keymaps(r#"[{"lhs": "gco", "mode": "n", "desc": "Comment"},
+            {"lhs": "<leader>z", "mode": "n", "desc": "Zen"}]"#)
+        .unwrap();
+        assert_eq!(new_keymaps(&keymaps, &sourced), 1);
+        assert_eq!(new_keymaps(&sourced, &keymaps), 2);
+        assert_ne!(fingerprint(&keymaps), fingerprint(&sourced));
+    }
+}
@@ -0,0 +1,241 @@
+use crate::commands::{self, Category, Command, KeyFrame, Leaders, Source};
+use serde::Serialize;
+use std::fmt::Write;
+
+/// A command as printed by `lvcheat search --json` and `lvcheat list --json`
+#[derive(Debug, Serialize)]
+pub struct CommandOutput<'a> {
+    #[serde(flatten)]
+    command: &'a Command,
+    /// Match score; only present for search results
+    #[serde(skip_serializing_if = "Option::is_none")]
+    score: Option<i64>,
+    source: &'a Source,
+    frames: Vec<KeyFrame>,
+}
+
+impl<'a> CommandOutput<'a> {
+    pub fn new(command: &'a Command, score: Option<i64>) -> Self {
+        Self {
+            command,
+            score,
+            source: &command.source,
+            frames: command.parse_keys(),
+        }
+    }
+}
+
+/// Render commands as a JSON array, or as one aligned text line each
+pub fn format_commands(commands: &[(&Command, Option<i64>)], json: bool) -> anyhow::Result<String> {
+    if json {
+        let entries: Vec<CommandOutput> = commands
+            .iter()
+            .map(|&(command, score)| CommandOutput::new(command, score))
+            .collect();
+        return Ok(serde_json::to_string_pretty(&entries)?);
+    }
+
+    let lines: Vec<String> = commands
+        .iter()
+        .map(|(cmd, _)| {
+            format!(
+                "{:<16} {} {} [{}]",
+                cmd.keys,
+                cmd.mode.short(),
+                cmd.description,
+                cmd.category.as_str()
+            )
+        })
+        .collect();
+    Ok(lines.join("\n"))
+}
+
+/// A Markdown cheatsheet: one table per category, in category order
+pub fn format_markdown(commands: &[&Command]) -> String {
+    let mut md = String::from("# LazyVim cheatsheet\n");
+    for category in Category::ALL {
+        let listed: Vec<_> = commands.iter().filter(|cmd| cmd.category == *category).collect();
+        if listed.is_empty() {
+            continue;
+        }
+        let _ = write!(
+            md,
+            "\n## {}\n\n| Keys | Mode | Description |\n| --- | --- | --- |\n",
+            category.as_str()
+        );
+        for cmd in listed {
+            let mut description = cmd.description.clone();
+            if let Some(mnemonic) = &cmd.mnemonic {
+                let _ = write!(description, " ({})", mnemonic);
+            }
+            let keys: Vec<String> = std::iter::once(&cmd.keys)
+                .chain(&cmd.alternatives)
+                .map(|keys| table_code(keys))
+                .collect();
+            let _ = writeln!(
+                md,
+                "| {} | {} | {} |",
+                keys.join(" or "),
+                cmd.mode.as_str(),
+                description.replace('|', "\\|")
+            );
+        }
+    }
+    md
+}
+
+/// Commands in the command file format, so they can be loaded back as a user command file
+pub fn format_command_file(commands: &[&Command]) -> anyhow::Result<String> {
+    Ok(serde_json::to_string_pretty(commands)? + "\n")
+}
+
+/// An Anki deck as tab-separated notes Anki's File > Import reads: the description on the
+/// front, the keys and the sequence spelled out on the back, and the category as a tag
+pub fn format_anki(commands: &[&Command], leaders: &Leaders) -> String {
+    let mut deck = String::from("#separator:tab\n#html:true\n#tags column:3\n");
+    for cmd in commands {
+        let mut front = html(&cmd.description);
+        if cmd.mode != commands::Mode::Normal {
+            let _ = write!(front, " <i>({})</i>", cmd.mode.as_str());
+        }
+        let keys: Vec<String> = std::iter::once(&cmd.keys)
+            .chain(&cmd.alternatives)
+            .map(|keys| format!("<code>{}</code>", html(keys)))
+            .collect();
+        let mut back = format!(
+            "{}<br>{}",
+            keys.join(" or "),
+            html(&commands::describe_sequence(&cmd.parse_keys_with(leaders)))
+        );
+        if let Some(mnemonic) = &cmd.mnemonic {
+            let _ = write!(back, "<br><i>{}</i>", html(mnemonic));
+        }
+        let tags: Vec<String> = std::iter::once(cmd.category.as_str())
+            .chain(cmd.tags.iter().map(String::as_str))
+            .map(|tag| tag.to_lowercase().replace(char::is_whitespace, "-"))
+            .collect();
+        let _ = writeln!(deck, "{}\t{}\t{}", front, back, tags.join(" "));
+    }
+    deck
+}
+
+/// `text` escaped for an Anki field, where tabs and newlines would end the field or note
+fn html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('\t', " ")
+        .replace('\n', "<br>")
+}
+
+/// `text` as inline code in a Markdown table cell
+fn table_code(text: &str) -> String {
+    // Pipes end the cell even inside code, and backticks need a longer fence
+    let text = text.replace('|', "\\|");
+    if text.contains('`') {
+        format!("`` {} ``", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_output() {
+        let cmd = Command {
+            keys: "<C-w>v".to_string(),
+            description: "Split window right".to_string(),
+            category: Category::Window,
+            ..Default::default()
+        };
+
+        let json = format_commands(&[(&cmd, Some(42))], true).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value[0]["keys"], "<C-w>v");
+        assert_eq!(value[0]["category"], "window");
+        assert_eq!(value[0]["mode"], "normal");
+        assert_eq!(value[0]["score"], 42);
+        assert_eq!(value[0]["source"], "builtin");
+        assert_eq!(value[0]["frames"][0]["keys"][0]["key"], "Ctrl");
+        assert_eq!(value[0]["frames"].as_array().unwrap().len(), 2);
+
+        let text = format_commands(&[(&cmd, None)], false).unwrap();
+        assert_eq!(text, "<C-w>v           N Split window right [Window]");
+    }
+
+    #[test]
+    fn test_markdown() {
+        let split = Command {
+            keys: "<leader>|".to_string(),
+            description: "Split window right".to_string(),
+            category: Category::Window,
+            ..Default::default()
+        };
+        let find = Command {
+            keys: "<leader>ff".to_string(),
+            description: "Find files".to_string(),
+            category: Category::Search,
+            mnemonic: Some("ff = Find Files".to_string()),
+            ..Default::default()
+        };
+        let mark = Command {
+            keys: "`a".to_string(),
+            description: "Jump to mark".to_string(),
+            category: Category::Search,
+            ..Default::default()
+        };
+        assert_eq!(
+            format_markdown(&[&split, &find, &mark]),
+            "# LazyVim cheatsheet\n\
+             \n## Search\n\n\
+             | Keys | Mode | Description |\n\
+             | --- | --- | --- |\n\
+             | `<leader>ff` | normal | Find files (ff = Find Files) |\n\
+             | `` `a `` | normal | Jump to mark |\n\
+             \n## Window\n\n\
+             | Keys | Mode | Description |\n\
+             | --- | --- | --- |\n\
+             | `<leader>\\|` | normal | Split window right |\n"
+        );
+
+        let json = format_command_file(&[&find]).unwrap();
+        let loaded: Vec<Command> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded[0].mnemonic.as_deref(), Some("ff = Find Files"));
+    }
+
+    #[test]
+    fn test_anki() {
+        let find = Command {
+            keys: "<leader>ff".to_string(),
+            alternatives: vec!["<leader><space>".to_string()],
+            description: "Find files".to_string(),
+            category: Category::Search,
+            mnemonic: Some("ff = Find Files".to_string()),
+            ..Default::default()
+        };
+        let indent = Command {
+            keys: ">".to_string(),
+            description: "Indent\tright".to_string(),
+            category: Category::named("Code Editing"),
+            mode: commands::Mode::Visual,
+            tags: vec!["edit".to_string()],
+            ..Default::default()
+        };
+        let deck = format_anki(&[&find, &indent], &Leaders::default());
+        let lines: Vec<&str> = deck.lines().collect();
+        assert_eq!(lines[..3], ["#separator:tab", "#html:true", "#tags column:3"]);
+        assert_eq!(
+            lines[3],
+            "Find files\t<code>&lt;leader&gt;ff</code> or <code>&lt;leader&gt;&lt;space&gt;</code>\
+             <br>Press Space (leader), then f, then f.<br><i>ff = Find Files</i>\tsearch"
+        );
+        assert_eq!(
+            lines[4],
+            "Indent right <i>(visual)</i>\t<code>&gt;</code>\
+             <br>Press Shift and . together.\tcode-editing edit"
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use crate::commands::{self, Command, CommandId, Keep};
+use crate::state::{read_json, write_json};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// A personal note attached to a command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Note {
+    #[serde(flatten)]
+    pub id: CommandId,
+    pub note: String,
+}
+
+/// How a conflict between a user entry and a builtin one was settled
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Resolution {
+    #[serde(flatten)]
+    pub id: CommandId,
+    pub keep: Keep,
+}
+
+/// User-authored annotations layered over the command data, kept in the
+/// config directory (`overrides.json`) so they can be edited by hand
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Overrides {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<Note>,
+    /// Choices made on the conflict resolution screen, applied whenever commands are loaded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub resolutions: Vec<Resolution>,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Overrides {
+    /// Path of the overrides file in the config directory
+    pub fn default_path() -> Option<PathBuf> {
+        commands::config_dir().map(|dir| dir.join("overrides.json"))
+    }
+
+    /// Load overrides from the default location; a broken file is reported rather
+    /// than overwritten, since it's user-edited
+    pub fn load() -> anyhow::Result<Self> {
+        match Self::default_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let mut overrides: Self = read_json(path)?;
+        overrides.path = Some(path.to_path_buf());
+        Ok(overrides)
+    }
+
+    /// Write overrides back to where they were loaded from; a no-op for in-memory overrides
+    pub fn save(&self) -> anyhow::Result<()> {
+        match &self.path {
+            Some(path) => write_json(path, self),
+            None => Ok(()),
+        }
+    }
+
+    pub fn note(&self, cmd: &Command) -> Option<&str> {
+        let id = cmd.id();
+        self.notes
+            .iter()
+            .find(|n| n.id == id)
+            .map(|n| n.note.as_str())
+    }
+
+    /// What to keep when the user entry bound to `id` conflicts with a builtin one
+    pub fn resolution(&self, id: &CommandId) -> Option<Keep> {
+        self.resolutions.iter().find(|r| r.id == *id).map(|r| r.keep)
+    }
+
+    pub fn set_resolution(&mut self, id: CommandId, keep: Keep) {
+        self.resolutions.retain(|r| r.id != id);
+        self.resolutions.push(Resolution { id, keep });
+    }
+
+    /// Set the note for a command; a blank note removes it
+    pub fn set_note(&mut self, cmd: &Command, note: &str) {
+        let id = cmd.id();
+        let note = note.trim();
+        self.notes.retain(|n| n.id != id);
+        if !note.is_empty() {
+            self.notes.push(Note {
+                id,
+                note: note.to_string(),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Category, Mode};
+
+    #[test]
+    fn test_set_and_clear_note() {
+        let cmd = Command {
+            keys: "<leader>e".to_string(),
+            description: "Toggle file explorer".to_string(),
+            category: Category::Navigation,
+            mode: Mode::Normal,
+            ..Default::default()
+        };
+        let mut overrides = Overrides::default();
+
+        overrides.set_note(&cmd, "remapped to <leader>E in my config ");
+        assert_eq!(overrides.note(&cmd), Some("remapped to <leader>E in my config"));
+
+        overrides.set_note(&cmd, "  ");
+        assert_eq!(overrides.note(&cmd), None);
+    }
+
+    #[test]
+    fn test_note_serialization() {
+        let json = r#"{ "notes": [{ "keys": "gd", "note": "Works in visual too" }] }"#;
+        let overrides: Overrides = serde_json::from_str(json).unwrap();
+        assert_eq!(overrides.notes[0].id.mode, Mode::Normal);
+        assert_eq!(overrides.notes[0].note, "Works in visual too");
+    }
+
+    #[test]
+    fn test_resolutions() {
+        let json = r#"{ "resolutions": [{ "keys": "K", "keep": "builtin" }] }"#;
+        let mut overrides: Overrides = serde_json::from_str(json).unwrap();
+        let id = |keys: &str| CommandId {
+            keys: keys.to_string(),
+            mode: Mode::Normal,
+            source: None,
+        };
+        assert_eq!(overrides.resolution(&id("K")), Some(Keep::Builtin));
+        assert_eq!(overrides.resolution(&id("gd")), None);
+
+        overrides.set_resolution(id("K"), Keep::Both);
+        assert_eq!(overrides.resolution(&id("K")), Some(Keep::Both));
+        assert_eq!(overrides.resolutions.len(), 1);
+    }
+}
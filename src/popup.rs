@@ -0,0 +1,456 @@
+use crate::commands::{self, Category, CategoryLayout, Command, Conflict, Mode, Problem};
+use crate::input::TextInput;
+use crate::stats::StatsRange;
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Paragraph},
+    Frame,
+};
+
+/// Modal overlays that capture input until they're dismissed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Popup {
+    /// Editing the note for the command at this index
+    Note { command: usize, input: TextInput },
+    /// Creating a new command for the user commands file
+    NewCommand(CommandForm),
+    /// The usage stats dashboard, with the period its category chart covers
+    Stats(StatsRange),
+    /// Writing the marked commands to a file
+    Export(ExportForm),
+    /// Command file entries that were left out, scrolled down this many
+    Problems(usize),
+    /// Settling the first of the user entries that conflict with builtin ones
+    Conflicts,
+    /// Changes to the builtin keymaps, filtered by a search and scrolled down this many lines
+    WhatsNew { query: TextInput, scroll: usize },
+}
+
+/// File format for exported commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SheetFormat {
+    /// A table per category, to read or print
+    #[default]
+    Markdown,
+    /// The command file format, to load back as a user command file
+    Json,
+    /// Anki's text import format, a flashcard per command
+    Anki,
+}
+
+impl SheetFormat {
+    fn label(self) -> &'static str {
+        match self {
+            SheetFormat::Markdown => "Markdown",
+            SheetFormat::Json => "JSON",
+            SheetFormat::Anki => "Anki deck",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            SheetFormat::Markdown => ".md",
+            SheetFormat::Json => ".json",
+            SheetFormat::Anki => ".txt",
+        }
+    }
+}
+
+/// State of the export dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportForm {
+    pub path: TextInput,
+    pub format: SheetFormat,
+    /// Where the file was written, or why it wasn't
+    pub result: Option<Result<String, String>>,
+}
+
+impl Default for ExportForm {
+    fn default() -> Self {
+        let mut path = TextInput::default();
+        path.set("lvcheat-cheatsheet.md");
+        Self {
+            path,
+            format: SheetFormat::default(),
+            result: None,
+        }
+    }
+}
+
+impl ExportForm {
+    /// Switch to the next format, changing the path's extension along with it
+    pub fn toggle_format(&mut self) {
+        let old = self.format.extension();
+        self.format = match self.format {
+            SheetFormat::Markdown => SheetFormat::Json,
+            SheetFormat::Json => SheetFormat::Anki,
+            SheetFormat::Anki => SheetFormat::Markdown,
+        };
+        if let Some(stem) = self.path.as_str().strip_suffix(old) {
+            let path = format!("{}{}", stem, self.format.extension());
+            self.path.set(&path);
+        }
+        self.result = None;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FormField {
+    #[default]
+    Keys,
+    Description,
+    Category,
+    Mode,
+}
+
+impl FormField {
+    const ALL: [FormField; 4] = [
+        FormField::Keys,
+        FormField::Description,
+        FormField::Category,
+        FormField::Mode,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            FormField::Keys => "Keys",
+            FormField::Description => "Description",
+            FormField::Category => "Category",
+            FormField::Mode => "Mode",
+        }
+    }
+}
+
+/// State of the new-command form
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CommandForm {
+    pub keys: String,
+    pub description: String,
+    pub category: usize,
+    pub mode: usize,
+    pub focus: FormField,
+    pub error: Option<String>,
+}
+
+impl CommandForm {
+    pub fn next_field(&mut self) {
+        let i = FormField::ALL.iter().position(|f| *f == self.focus).unwrap();
+        self.focus = FormField::ALL[(i + 1) % FormField::ALL.len()];
+    }
+
+    pub fn prev_field(&mut self) {
+        let i = FormField::ALL.iter().position(|f| *f == self.focus).unwrap();
+        self.focus = FormField::ALL[(i + FormField::ALL.len() - 1) % FormField::ALL.len()];
+    }
+
+    /// Step the focused choice field forwards or backwards
+    pub fn cycle(&mut self, forward: bool) {
+        let (value, len) = match self.focus {
+            FormField::Category => (&mut self.category, Category::ALL.len()),
+            FormField::Mode => (&mut self.mode, Mode::ALL.len()),
+            _ => return,
+        };
+        *value = if forward {
+            (*value + 1) % len
+        } else {
+            (*value + len - 1) % len
+        };
+    }
+
+    fn text_mut(&mut self) -> Option<&mut String> {
+        match self.focus {
+            FormField::Keys => Some(&mut self.keys),
+            FormField::Description => Some(&mut self.description),
+            _ => None,
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        if let Some(text) = self.text_mut() {
+            text.push(c);
+        }
+    }
+
+    pub fn pop(&mut self) {
+        if let Some(text) = self.text_mut() {
+            text.pop();
+        }
+    }
+
+    /// Build the command, or explain what's missing
+    pub fn to_command(&self) -> Result<Command, String> {
+        let keys = self.keys.trim();
+        let description = self.description.trim();
+        if keys.is_empty() {
+            return Err("keys are required".to_string());
+        }
+        if description.is_empty() {
+            return Err("description is required".to_string());
+        }
+        if let Some(issue) = commands::check_keys(keys)
+            .into_iter()
+            .find(|issue| !issue.is_warning())
+        {
+            return Err(issue.to_string());
+        }
+
+        Ok(Command {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            category: Category::ALL[self.category].clone(),
+            mode: Mode::ALL[self.mode],
+            ..Default::default()
+        })
+    }
+}
+
+/// A rectangle of the given size centered in `area`, clamped to fit
+pub fn centered_rect(width: u16, height: u16, area: Rect) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    let vertical = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length((area.height - height) / 2),
+            Constraint::Length(height),
+            Constraint::Min(0),
+        ])
+        .split(area);
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Length((area.width - width) / 2),
+            Constraint::Length(width),
+            Constraint::Min(0),
+        ])
+        .split(vertical[1])[1]
+}
+
+/// Draw a single text input box with a hint line underneath
+pub fn draw_input(frame: &mut Frame, title: &str, input: &TextInput, hint: &str) {
+    let area = centered_rect(60, 6, frame.area());
+    let (before, _) = input.split();
+    let text = vec![
+        Line::from(input.as_str()),
+        Line::from(""),
+        Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))),
+    ];
+    let widget = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(title),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+
+    let cursor_x = area.x + 1 + Span::raw(before).width() as u16;
+    frame.set_cursor_position((cursor_x.min(area.right() - 2), area.y + 1));
+}
+
+/// Draw the new-command form
+pub fn draw_form(frame: &mut Frame, form: &CommandForm, layout: &CategoryLayout) {
+    let area = centered_rect(60, 10, frame.area());
+    let mut lines = Vec::new();
+
+    for field in FormField::ALL {
+        let focused = field == form.focus;
+        let value = match field {
+            FormField::Keys => form.keys.clone(),
+            FormField::Description => form.description.clone(),
+            FormField::Category => format!("◀ {} ▶", layout.name(&Category::ALL[form.category])),
+            FormField::Mode => format!("◀ {} ▶", Mode::ALL[form.mode].as_str()),
+        };
+        let label_style = if focused {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default().fg(Color::DarkGray)
+        };
+        let mut spans = vec![
+            Span::styled(format!("{:<12}", field.label()), label_style),
+            Span::raw(value),
+        ];
+        if focused && matches!(field, FormField::Keys | FormField::Description) {
+            spans.push(Span::styled("_", Style::default().fg(Color::Gray)));
+        }
+        lines.push(Line::from(spans));
+    }
+
+    lines.push(Line::from(""));
+    match &form.error {
+        Some(error) => lines.push(Line::from(Span::styled(
+            error.clone(),
+            Style::default().fg(Color::Red),
+        ))),
+        None => lines.push(Line::from("")),
+    }
+    lines.push(Line::from(Span::styled(
+        "Tab/↑↓: field · ←→: choose · Enter: save · Esc: cancel",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(" New command "),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+}
+
+/// Draw the export dialog for `count` commands, `which` being `marked` or `listed`
+pub fn draw_export(frame: &mut Frame, form: &ExportForm, count: usize, which: &str) {
+    let area = centered_rect(60, 8, frame.area());
+    let dim = Style::default().fg(Color::DarkGray);
+    let (before, _) = form.path.split();
+    let status = match &form.result {
+        Some(Ok(path)) => Span::styled(
+            format!("Wrote {} commands to {}", count, path),
+            Style::default().fg(Color::Green),
+        ),
+        Some(Err(error)) => Span::styled(error.clone(), Style::default().fg(Color::Red)),
+        None => Span::raw(""),
+    };
+    let lines = vec![
+        Line::from(vec![Span::styled("Format  ", dim), Span::raw(form.format.label())]),
+        Line::from(vec![Span::styled("File    ", dim), Span::raw(form.path.as_str())]),
+        Line::from(""),
+        Line::from(status),
+        Line::from(Span::styled("Tab: format · Enter: write · Esc: close", dim)),
+    ];
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" Export {} {} commands ", count, which)),
+    );
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+
+    let cursor_x = area.x + 9 + Span::raw(before).width() as u16;
+    frame.set_cursor_position((cursor_x.min(area.right() - 2), area.y + 2));
+}
+
+/// List the command file entries left out at load, each with its file, line and reason
+pub fn draw_problems(frame: &mut Frame, problems: &[&Problem], scroll: usize) {
+    let area = centered_rect(90, 20, frame.area());
+    let dim = Style::default().fg(Color::DarkGray);
+    let mut lines = Vec::new();
+    if problems.is_empty() {
+        lines.push(Line::from("Every entry of the command files loaded."));
+    }
+    for problem in problems.iter().skip(scroll) {
+        lines.push(Line::from(Span::styled(
+            format!("{}:{}", problem.path.display(), problem.line),
+            Style::default().fg(Color::Cyan),
+        )));
+        lines.push(Line::from(format!("  {}", problem.message)));
+    }
+    let hint = Line::from(Span::styled("↑↓: scroll · Esc/F6: close", dim));
+    let height = area.height.saturating_sub(3) as usize;
+    lines.truncate(height);
+    lines.resize(height, Line::from(""));
+    lines.push(hint);
+
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" Skipped entries ({}) ", problems.len())),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+}
+
+/// The first conflict between a user entry and a builtin one, with the ways to settle it
+pub fn draw_conflict(frame: &mut Frame, conflict: &Conflict, remaining: usize) {
+    let area = centered_rect(70, 9, frame.area());
+    let dim = Style::default().fg(Color::DarkGray);
+    let side = |name: &str, cmd: &Command| {
+        Line::from(vec![
+            Span::styled(format!("{:<9}", name), dim),
+            Span::raw(cmd.description.clone()),
+        ])
+    };
+    let lines = vec![
+        Line::from(vec![
+            Span::styled(conflict.user.keys.clone(), Style::default().fg(Color::Cyan)),
+            Span::styled(format!(" in {} mode is bound twice:", conflict.user.mode.as_str()), dim),
+        ]),
+        Line::from(""),
+        side(conflict.builtin.source.label(), &conflict.builtin),
+        side(conflict.user.source.label(), &conflict.user),
+        Line::from(""),
+        Line::from(Span::styled(
+            "b: keep builtin · u: keep yours · k: keep both, renamed · Esc: later",
+            dim,
+        )),
+        Line::from(Span::styled("Choices are saved to overrides.json", dim)),
+    ];
+    let widget = Paragraph::new(lines).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Yellow))
+            .title(format!(" Conflicting keymaps ({} left) ", remaining)),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(widget, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_format() {
+        let mut form = ExportForm::default();
+        form.toggle_format();
+        assert_eq!(form.format, SheetFormat::Json);
+        assert_eq!(form.path.as_str(), "lvcheat-cheatsheet.json");
+
+        // A path without the format's extension is left alone
+        form.path.set("notes.txt");
+        form.toggle_format();
+        assert_eq!(form.path.as_str(), "notes.txt");
+
+        // Anki decks are text files, and Markdown comes next
+        assert_eq!(form.format, SheetFormat::Anki);
+        form.toggle_format();
+        assert_eq!(form.format, SheetFormat::Markdown);
+        assert_eq!(form.path.as_str(), "notes.md");
+    }
+
+    #[test]
+    fn test_form_to_command() {
+        let mut form = CommandForm::default();
+        assert!(form.to_command().is_err());
+
+        "<leader>zz".chars().for_each(|c| form.push(c));
+        form.next_field();
+        "Zen mode".chars().for_each(|c| form.push(c));
+        form.next_field();
+        form.cycle(false);
+
+        let cmd = form.to_command().unwrap();
+        assert_eq!(cmd.keys, "<leader>zz");
+        assert_eq!(cmd.description, "Zen mode");
+        assert_eq!(cmd.category, *Category::ALL.last().unwrap());
+        assert_eq!(cmd.mode, Mode::Normal);
+    }
+
+    #[test]
+    fn test_form_rejects_bad_keys() {
+        let form = CommandForm {
+            keys: "<X-w>".to_string(),
+            description: "Broken".to_string(),
+            ..Default::default()
+        };
+        assert!(form.to_command().unwrap_err().contains("unknown modifier"));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::commands;
+use crate::compare::canonical;
+use crate::state::read_json;
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// A key sequence bindings are grouped under, like `<leader>g` for git
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Prefix {
+    pub keys: String,
+    /// What the bindings under it are about; empty in the user's file to drop a builtin one
+    pub name: String,
+}
+
+/// The prefixes in `data/prefixes.json`, with the user's from the config directory
+/// (`prefixes.json`) layered on top
+#[derive(Debug, Clone, Default)]
+pub struct Prefixes(Vec<Prefix>);
+
+impl Prefixes {
+    /// Path of the user's prefix file in the config directory
+    pub fn default_path() -> Option<PathBuf> {
+        commands::config_dir().map(|dir| dir.join("prefixes.json"))
+    }
+
+    pub fn builtin() -> anyhow::Result<Self> {
+        let builtin = serde_json::from_str(include_str!("../data/prefixes.json"))
+            .context("failed to parse embedded prefix data")?;
+        Ok(Self(builtin))
+    }
+
+    /// The builtin prefixes and the user's; a broken user file is reported, since it's
+    /// user-edited
+    pub fn load() -> anyhow::Result<Self> {
+        let mut prefixes = Self::builtin()?;
+        if let Some(path) = Self::default_path() {
+            prefixes.merge(Self::load_from(&path)?);
+        }
+        Ok(prefixes)
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Vec<Prefix>> {
+        read_json(path)
+    }
+
+    /// Add the `user` prefixes, renaming builtin ones with the same keys and dropping those
+    /// the user gave an empty name
+    pub fn merge(&mut self, user: Vec<Prefix>) {
+        for prefix in user {
+            let keys = canonical(&prefix.keys);
+            self.0.retain(|builtin| canonical(&builtin.keys) != keys);
+            if !prefix.name.trim().is_empty() {
+                self.0.push(prefix);
+            }
+        }
+    }
+
+    /// What the bindings under `keys` are about, if it's a known prefix. `<Leader>` and
+    /// `<leader>` are the same, as in Vim.
+    pub fn name(&self, keys: &str) -> Option<&str> {
+        let keys = canonical(keys);
+        self.0
+            .iter()
+            .find(|prefix| canonical(&prefix.keys) == keys)
+            .map(|prefix| prefix.name.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge() {
+        let mut prefixes = Prefixes::builtin().unwrap();
+        assert_eq!(prefixes.name("<Leader>g"), Some("git"));
+        assert_eq!(prefixes.name("<leader><Tab>"), Some("tabs"));
+        assert_eq!(prefixes.name("<leader>ff"), None);
+
+        let user: Vec<Prefix> = serde_json::from_str(
+            r#"[
+                { "keys": "<leader>G", "name": "GitHub" },
+                { "keys": "<leader>g", "name": "version control" },
+                { "keys": "z", "name": "" }
+            ]"#,
+        )
+        .unwrap();
+        prefixes.merge(user);
+        assert_eq!(prefixes.name("<leader>G"), Some("GitHub"));
+        assert_eq!(prefixes.name("<leader>g"), Some("version control"));
+        assert_eq!(prefixes.name("z"), None);
+    }
+}
@@ -1,9 +1,213 @@
-use crate::commands::Command;
+//! Query parsing and weighted ranking of commands.
+
+use crate::commands::{self, Category, CategoryLayout, Command, KeyFrame, Mode};
+use crate::keyboard::{self, Keyboard};
+use crate::keynotation;
 use fuzzy_matcher::skim::SkimMatcherV2;
 use fuzzy_matcher::FuzzyMatcher;
+use nucleo_matcher::pattern::{CaseMatching, Normalization, Pattern};
+use nucleo_matcher::Utf32Str;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::collections::HashMap;
 
-pub struct SearchEngine {
+/// How query text is matched against command fields
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Case-insensitive fuzzy matching
+    #[default]
+    Fuzzy,
+    /// Case-insensitive substring matching
+    Exact,
+    /// Fuzzy matching that turns case-sensitive once the query has an uppercase letter
+    Smartcase,
+    /// Case-insensitive regular expressions; an invalid one is matched as plain text
+    Regex,
+    /// fzf-style fuzzy matching with the nucleo matcher: space-separated words all have to
+    /// match, `^` and `$` anchor one and `'` makes it exact (`!` tokens are the search's own)
+    Nucleo,
+}
+
+impl MatchMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchMode::Fuzzy => "fuzzy",
+            MatchMode::Exact => "exact",
+            MatchMode::Smartcase => "smartcase",
+            MatchMode::Regex => "regex",
+            MatchMode::Nucleo => "nucleo",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            MatchMode::Fuzzy => MatchMode::Exact,
+            MatchMode::Exact => MatchMode::Smartcase,
+            MatchMode::Smartcase => MatchMode::Regex,
+            MatchMode::Regex => MatchMode::Nucleo,
+            MatchMode::Nucleo => MatchMode::Fuzzy,
+        }
+    }
+
+    /// A matcher for `needle` in this mode
+    pub fn matcher(self, needle: &str) -> Box<dyn Matcher> {
+        match self {
+            MatchMode::Fuzzy => Box::new(SkimMatch {
+                matcher: SkimMatcherV2::default(),
+                needle: needle.to_lowercase(),
+                lowercase: true,
+            }),
+            MatchMode::Smartcase => Box::new(SkimMatch {
+                matcher: SkimMatcherV2::default().smart_case(),
+                needle: needle.to_string(),
+                lowercase: false,
+            }),
+            MatchMode::Exact => Box::new(SubstringMatch {
+                needle: needle.to_lowercase(),
+            }),
+            MatchMode::Regex => Box::new(RegexMatch::new(needle)),
+            MatchMode::Nucleo => Box::new(NucleoMatch {
+                matcher: nucleo_matcher::Matcher::default(),
+                pattern: Pattern::parse(needle, CaseMatching::Smart, Normalization::Smart),
+                chars: Vec::new(),
+            }),
+        }
+    }
+}
+
+/// One way of matching query text against the fields of commands, set up for one query
+pub trait Matcher {
+    /// How well `haystack` matches, higher is better; `None` when it doesn't match at all
+    fn score(&mut self, haystack: &str) -> Option<i64>;
+}
+
+/// Fuzzy matching with the skim algorithm
+struct SkimMatch {
     matcher: SkimMatcherV2,
+    needle: String,
+    /// Lowercase haystacks too, matching without regard to case
+    lowercase: bool,
+}
+
+impl Matcher for SkimMatch {
+    fn score(&mut self, haystack: &str) -> Option<i64> {
+        if self.lowercase {
+            self.matcher.fuzzy_match(&haystack.to_lowercase(), &self.needle)
+        } else {
+            self.matcher.fuzzy_match(haystack, &self.needle)
+        }
+    }
+}
+
+/// Case-insensitive substring matching
+struct SubstringMatch {
+    /// Lowercase
+    needle: String,
+}
+
+impl Matcher for SubstringMatch {
+    fn score(&mut self, haystack: &str) -> Option<i64> {
+        substring_score(&haystack.to_lowercase(), &self.needle)
+    }
+}
+
+/// Case-insensitive regular expression matching, scored like a substring match on the first
+/// match
+struct RegexMatch {
+    regex: Regex,
+}
+
+impl RegexMatch {
+    fn new(needle: &str) -> Self {
+        let build = |pattern: &str| RegexBuilder::new(pattern).case_insensitive(true).build();
+        let regex = build(needle)
+            .or_else(|_| build(&regex::escape(needle)))
+            .expect("an escaped pattern is a valid regex");
+        Self { regex }
+    }
+}
+
+impl Matcher for RegexMatch {
+    fn score(&mut self, haystack: &str) -> Option<i64> {
+        let found = self.regex.find(haystack)?;
+        Some(position_score(found.start(), found.len() == haystack.len()))
+    }
+}
+
+/// fzf-style matching with nucleo
+struct NucleoMatch {
+    matcher: nucleo_matcher::Matcher,
+    pattern: Pattern,
+    /// Scratch space for the haystack's characters
+    chars: Vec<char>,
+}
+
+impl Matcher for NucleoMatch {
+    fn score(&mut self, haystack: &str) -> Option<i64> {
+        let haystack = Utf32Str::new(haystack, &mut self.chars);
+        self.pattern.score(haystack, &mut self.matcher).map(i64::from)
+    }
+}
+
+/// Multipliers applied to each field's match score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Weights {
+    pub description: i64,
+    pub keys: i64,
+    pub tags: i64,
+    pub mnemonic: i64,
+    pub category: i64,
+    /// The keys read as a sequence of key presses, so `ctrl w v` finds `<C-w>v`
+    pub frames: i64,
+    /// The plugin doc excerpt, matched on whole words
+    pub doc: i64,
+    /// What each step of a workflow does
+    pub steps: i64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            description: 3,
+            keys: 2,
+            tags: 2,
+            mnemonic: 1,
+            category: 1,
+            frames: 2,
+            doc: 1,
+            steps: 2,
+        }
+    }
+}
+
+impl Weights {
+    /// Defaults for ex commands, where the command name is what people remember
+    pub fn ex() -> Self {
+        Self {
+            description: 2,
+            keys: 3,
+            tags: 1,
+            mnemonic: 1,
+            category: 1,
+            frames: 3,
+            doc: 1,
+            steps: 1,
+        }
+    }
+}
+
+/// Ranks commands against a query with the `Matcher` of its match mode
+pub struct SearchEngine {
+    /// Picks the `Matcher` each search uses
+    pub mode: MatchMode,
+    pub weights: Weights,
+    /// Weights used instead of `weights` for ex commands
+    pub ex_weights: Weights,
+    /// Key presses of every key string indexed, so searches don't parse them again
+    frames: HashMap<String, Vec<String>>,
 }
 
 impl Default for SearchEngine {
@@ -15,38 +219,127 @@ impl Default for SearchEngine {
 impl SearchEngine {
     pub fn new() -> Self {
         Self {
-            matcher: SkimMatcherV2::default(),
+            mode: MatchMode::default(),
+            weights: Weights::default(),
+            ex_weights: Weights::ex(),
+            frames: HashMap::new(),
+        }
+    }
+
+    /// Parse the keys and alternatives of `commands` into key presses once, ahead of searching
+    /// them; commands left out are parsed on every search instead
+    pub fn index(&mut self, commands: &[Command]) {
+        for cmd in commands {
+            for keys in std::iter::once(&cmd.keys).chain(&cmd.alternatives) {
+                if !self.frames.contains_key(keys) {
+                    let tokens = key_tokens(&commands::parse_key_string(keys));
+                    self.frames.insert(keys.clone(), tokens);
+                }
+            }
         }
     }
 
-    /// Search commands by query, returns matches sorted by score (best first)
+    /// Key presses of `keys`, from the index when it's there
+    fn frame_tokens(&self, keys: &str) -> Cow<'_, [String]> {
+        match self.frames.get(keys) {
+            Some(tokens) => Cow::Borrowed(tokens),
+            None => Cow::Owned(key_tokens(&commands::parse_key_string(keys))),
+        }
+    }
+
+    /// Search commands by query, returns matches sorted by score (best first).
+    /// `#tag` tokens restrict results to commands carrying a matching tag, `mode:` tokens to
+    /// commands in those modes, `source:` tokens to commands from those sources and `prefix:`
+    /// tokens to commands whose keys start with them, while `!term` tokens leave out commands
+    /// mentioning the term; filters that need outside state (like `fav:`, `recent:`, `hidden:`
+    /// and `learned:`) are parsed out but left to the caller.
     pub fn search<'a>(&self, commands: &'a [Command], query: &str) -> Vec<(&'a Command, i64)> {
-        if query.is_empty() {
-            // Return all commands with score 0 when query is empty
-            return commands.iter().map(|cmd| (cmd, 0i64)).collect();
+        let query = Query::parse(query);
+        let candidates = commands
+            .iter()
+            .filter(|cmd| query.tags.iter().all(|tag| cmd.has_tag(tag)))
+            .filter(|cmd| query.modes.is_empty() || query.modes.contains(&cmd.mode))
+            .filter(|cmd| {
+                query.sources.is_empty() || query.sources.iter().any(|s| cmd.source.is_named(s))
+            })
+            .filter(|cmd| {
+                query.prefixes.is_empty() || {
+                    let keys = keynotation::normalize(&cmd.keys);
+                    query.prefixes.iter().any(|p| keys.starts_with(p))
+                }
+            })
+            .filter(|cmd| !query.excluded.iter().any(|term| mentions(cmd, term)));
+
+        if query.text.is_empty() {
+            // Return all candidates with score 0 when there's nothing to match
+            return candidates.map(|cmd| (cmd, 0i64)).collect();
         }
 
-        let query_lower = query.to_lowercase();
+        let query_keys = query_key_tokens(&query.text);
+        let mut matcher = self.mode.matcher(&query.text);
         let mut results: Vec<(&Command, i64)> = Vec::new();
 
-        for cmd in commands {
+        for cmd in candidates {
+            let weights = if cmd.is_ex() { self.ex_weights } else { self.weights };
             let mut best_score: Option<i64> = None;
 
             // Search in description (highest weight)
-            if let Some(score) = self.matcher.fuzzy_match(&cmd.description.to_lowercase(), &query_lower) {
-                let weighted = score * 3;
+            if let Some(score) = matcher.score(&cmd.description) {
+                let weighted = score * weights.description;
+                best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
+            }
+
+            // Search in the keys and their alternatives as written
+            let all_keys = || std::iter::once(&cmd.keys).chain(&cmd.alternatives);
+            let literal = all_keys().filter_map(|keys| matcher.score(keys));
+            if let Some(score) = literal.max() {
+                let weighted = score * weights.keys;
                 best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
             }
 
-            // Search in keys
-            if let Some(score) = self.matcher.fuzzy_match(&cmd.keys.to_lowercase(), &query_lower) {
-                let weighted = score * 2;
+            // And as the key presses they stand for
+            let notation =
+                all_keys().filter_map(|keys| notation_score(&self.frame_tokens(keys), &query_keys));
+            if let Some(score) = notation.max() {
+                let weighted = score * weights.frames;
                 best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
             }
 
+            // Search in tags
+            if !cmd.tags.is_empty() {
+                if let Some(score) = matcher.score(&cmd.tags.join(" ")) {
+                    let weighted = score * weights.tags;
+                    best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
+                }
+            }
+
+            // Search in the mnemonic, which repeats the description's words and counts less
+            if let Some(mnemonic) = &cmd.mnemonic {
+                if let Some(score) = matcher.score(mnemonic) {
+                    let weighted = score * weights.mnemonic;
+                    best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
+                }
+            }
+
+            // Search in a workflow's steps, so a recipe turns up for any task along the way
+            let steps = cmd.workflow.iter().filter_map(|step| matcher.score(&step.description));
+            if let Some(score) = steps.max() {
+                let weighted = score * weights.steps;
+                best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
+            }
+
+            // Search in the doc excerpt, for what the plugin does rather than what it's called
+            if let Some(doc) = &cmd.doc {
+                if let Some(score) = doc_score(&doc.text, &query.text) {
+                    let weighted = score * weights.doc;
+                    best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
+                }
+            }
+
             // Search in category
-            if let Some(score) = self.matcher.fuzzy_match(&cmd.category.as_str().to_lowercase(), &query_lower) {
-                best_score = Some(best_score.map_or(score, |s| s.max(score)));
+            if let Some(score) = matcher.score(cmd.category.as_str()) {
+                let weighted = score * weights.category;
+                best_score = Some(best_score.map_or(weighted, |s| s.max(weighted)));
             }
 
             if let Some(score) = best_score {
@@ -55,16 +348,269 @@ impl SearchEngine {
         }
 
         // Sort by score descending
-        results.sort_by(|a, b| b.1.cmp(&a.1));
+        results.sort_by_key(|r| std::cmp::Reverse(r.1));
         results
     }
+}
+
+/// Whether any field the search matches contains `term`, which is lowercase: the description,
+/// keys, tags, mnemonic, workflow steps, doc excerpt or category
+fn mentions(cmd: &Command, term: &str) -> bool {
+    let keys = std::iter::once(&cmd.keys).chain(&cmd.alternatives).map(String::as_str);
+    let steps = cmd.workflow.iter().map(|step| step.description.as_str());
+    std::iter::once(cmd.description.as_str())
+        .chain(keys)
+        .chain(cmd.tags.iter().map(String::as_str))
+        .chain(cmd.mnemonic.as_deref())
+        .chain(steps)
+        .chain(cmd.doc.as_ref().map(|doc| doc.text.as_str()))
+        .chain([cmd.category.as_str()])
+        .any(|field| field.to_lowercase().contains(term))
+}
+
+/// How many of `results` are in each category, in the order of `layout` and leaving out the
+/// empty and hidden ones; one pass over the results, however many categories there are
+pub fn category_counts<'a>(
+    results: impl IntoIterator<Item = &'a Command>,
+    layout: &CategoryLayout,
+) -> Vec<(Category, usize)> {
+    let mut counts: HashMap<&Category, usize> = HashMap::new();
+    for cmd in results {
+        *counts.entry(&cmd.category).or_default() += 1;
+    }
+    layout
+        .in_order(counts.keys().copied())
+        .into_iter()
+        .filter_map(|category| counts.get(&category).map(|&count| (category.clone(), count)))
+        .collect()
+}
+
+/// Score of a doc excerpt that has every word of `query` starting one of its words. Excerpts
+/// are long enough for a fuzzy match to find almost anything in them, so they're matched on
+/// words whatever the match mode.
+fn doc_score(text: &str, query: &str) -> Option<i64> {
+    let text = text.to_lowercase();
+    let words: Vec<&str> = text.split(|c: char| !c.is_alphanumeric()).collect();
+    let query = query.to_lowercase();
+    let mut terms = query.split_whitespace().peekable();
+    terms.peek()?;
+    terms
+        .all(|term| words.iter().any(|word| !word.is_empty() && word.starts_with(term)))
+        .then_some(DOC_SCORE)
+}
+
+/// What a doc excerpt match scores before weighting, below most matches of the other fields
+const DOC_SCORE: i64 = 20;
+
+/// Score a substring match, favouring whole-field and leading matches
+fn substring_score(haystack: &str, needle: &str) -> Option<i64> {
+    let pos = haystack.find(needle)?;
+    Some(position_score(pos, haystack.len() == needle.len()))
+}
+
+/// Score of a match starting at byte `pos`, which may cover the `whole` field
+fn position_score(pos: usize, whole: bool) -> i64 {
+    let pos = pos as i64;
+    let mut score = 100 - pos.min(50);
+    if pos == 0 {
+        score += 50;
+    }
+    if whole {
+        score += 100;
+    }
+    score
+}
+
+/// Normalized names for each frame, e.g. `<C-w>v` -> ["ctrl-w", "v"]
+fn key_tokens(frames: &[KeyFrame]) -> Vec<String> {
+    frames
+        .iter()
+        .map(|frame| {
+            let names: Vec<String> = frame
+                .keys
+                .iter()
+                .map(|key| if key.is_leader { "space".to_string() } else { key.key.to_lowercase() })
+                .collect();
+            names.join("-")
+        })
+        .collect()
+}
+
+/// Read free text as a key sequence, so `ctrl w`, `c-w` and `spc f f` use the same tokens as
+/// `<C-w>` and `<leader>ff`
+fn query_key_tokens(text: &str) -> Vec<String> {
+    let mut notation = String::new();
+    let mut words = text.split_whitespace();
+
+    while let Some(word) = words.next() {
+        let lower = word.to_lowercase();
+        let modifier = match lower.as_str() {
+            "ctrl" | "control" => Some("C"),
+            "alt" | "meta" => Some("A"),
+            "shift" => Some("S"),
+            _ => None,
+        };
+        match (modifier, lower.as_str()) {
+            (Some(modifier), _) => match words.next() {
+                Some(target) => notation.push_str(&format!("<{}-{}>", modifier, target)),
+                None => notation.push_str(word),
+            },
+            (None, "spc" | "space" | "leader") => notation.push_str("<leader>"),
+            (None, "esc" | "tab" | "cr" | "enter" | "bs") => notation.push_str(&format!("<{}>", lower)),
+            (None, _) => match word.split_once(['-', '+']) {
+                Some((modifier, target))
+                    if !target.is_empty() && is_modifier_name(&modifier.to_lowercase()) =>
+                {
+                    notation.push_str(&format!("<{}-{}>", modifier, target));
+                }
+                _ => notation.push_str(word),
+            },
+        }
+    }
 
+    key_tokens(&commands::parse_key_string(&notation))
+}
+
+fn is_modifier_name(name: &str) -> bool {
+    matches!(
+        name,
+        "c" | "ctrl" | "control" | "a" | "alt" | "m" | "meta" | "s" | "shift"
+    )
+}
+
+/// Score a run of query key tokens found inside a command's key tokens
+fn notation_score(keys: &[String], query: &[String]) -> Option<i64> {
+    if query.is_empty() || query.len() > keys.len() {
+        return None;
+    }
+    let pos = keys.windows(query.len()).position(|window| window == query)? as i64;
+    let mut score = 100 - (pos * 10).min(50);
+    if pos == 0 {
+        score += 50;
+    }
+    if keys.len() == query.len() {
+        score += 100;
+    }
+    Some(score)
+}
+
+/// A key focused on the keyboard, keeping the commands that press it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyFilter {
+    /// Key id on the keyboard; either side's modifier counts for a right-hand one
+    pub key: &'static str,
+    /// Only count the key in a sequence's first frame
+    pub first_frame: bool,
+}
+
+impl KeyFilter {
+    /// Whether `frames`, as typed on `keyboard`, press the key
+    pub fn matches(&self, frames: &[KeyFrame], keyboard: &Keyboard) -> bool {
+        let key = keyboard::unsided(self.key);
+        let checked = if self.first_frame { 1 } else { frames.len() };
+        frames
+            .iter()
+            .take(checked)
+            .flat_map(|kf| keyboard.frame_ids(kf))
+            .any(|id| keyboard::unsided(id) == key)
+    }
+}
+
+/// A search query split into free text and filter tokens
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Query {
+    /// Text that is fuzzy-matched against command fields
+    pub text: String,
+    /// `#tag` tokens; every one must match a tag prefix
+    pub tags: Vec<String>,
+    /// `fav:` token; only favorites are shown
+    pub favorites: bool,
+    /// `recent:` token; only recently viewed commands are shown, most recent first
+    pub recent: bool,
+    /// `hidden:` token; show the hidden commands instead of the visible ones
+    pub hidden: bool,
+    /// `learned:` shows only commands marked as learned, `unlearned:` only the rest
+    pub learned: Option<bool>,
+    /// `mode:visual` (or `mode:v`) tokens; commands bound in any of them are shown
+    pub modes: Vec<Mode>,
+    /// `source:user` tokens; commands loaded from any of them are shown
+    pub sources: Vec<String>,
+    /// `prefix:<leader>g` tokens, normalized; commands whose keys start with any of them, however
+    /// either spells its keys, are shown
+    pub prefixes: Vec<String>,
+    /// `!close` tokens, lowercased; commands mentioning any of them are left out
+    pub excluded: Vec<String>,
+}
+
+impl Query {
+    /// Split filter tokens like `#tag` and `fav:` out of the search text
+    pub fn parse(input: &str) -> Self {
+        let mut query = Query::default();
+        let mut rest = Vec::new();
+
+        for token in input.split_whitespace() {
+            match token {
+                "fav:" => query.favorites = true,
+                "recent:" => query.recent = true,
+                "hidden:" => query.hidden = true,
+                "learned:" => query.learned = Some(true),
+                "unlearned:" => query.learned = Some(false),
+                _ => {
+                    if let Some(tag) = token.strip_prefix('#').filter(|tag| !tag.is_empty()) {
+                        query.tags.push(tag.to_string());
+                    } else if let Some(mode) = token.strip_prefix("mode:").and_then(Mode::named) {
+                        query.modes.push(mode);
+                    } else if let Some(source) =
+                        token.strip_prefix("source:").filter(|source| !source.is_empty())
+                    {
+                        query.sources.push(source.to_string());
+                    } else if let Some(prefix) =
+                        token.strip_prefix("prefix:").filter(|prefix| !prefix.is_empty())
+                    {
+                        query.prefixes.push(keynotation::normalize(prefix));
+                    } else if let Some(term) = excluded_term(token, &rest) {
+                        query.excluded.push(term.to_lowercase());
+                    } else {
+                        rest.push(token);
+                    }
+                }
+            }
+        }
+
+        query.text = rest.join(" ");
+        query
+    }
+
+    /// The filter tokens of `input`, leaving out the text it searches for
+    pub fn filters_only(input: &str) -> String {
+        let mut text = Vec::new();
+        let mut filters = Vec::new();
+        for token in input.split_whitespace() {
+            // A `!` token's meaning depends on the text before it
+            let filter = Query::parse(token).text.is_empty()
+                && (!token.starts_with('!') || excluded_term(token, &text).is_some());
+            if filter {
+                filters.push(token);
+            } else {
+                text.push(token);
+            }
+        }
+        filters.join(" ")
+    }
+}
+
+/// The term of a `!term` token, which leaves out the commands mentioning it: `!` then a word,
+/// unless the `text` before it has an ex command whose shell command it is, as in `:r !ls`
+fn excluded_term<'a>(token: &'a str, text: &[&str]) -> Option<&'a str> {
+    let term = token.strip_prefix('!')?;
+    let word = term.starts_with(|c: char| c.is_alphanumeric() || c == '_');
+    (word && !text.iter().any(|token| token.starts_with(':'))).then_some(term)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::commands::{Category, Mode};
+    use crate::commands::{Category, Doc, Source, WorkflowStep};
 
     fn sample_commands() -> Vec<Command> {
         vec![
@@ -73,24 +619,30 @@ mod tests {
                 description: "Find files".to_string(),
                 category: Category::Search,
                 mode: Mode::Normal,
+                tags: vec!["picker".to_string(), "beginner".to_string()],
+                ..Default::default()
             },
             Command {
                 keys: "<leader>fg".to_string(),
                 description: "Live grep".to_string(),
                 category: Category::Search,
                 mode: Mode::Normal,
+                tags: vec!["picker".to_string()],
+                ..Default::default()
             },
             Command {
                 keys: "gd".to_string(),
                 description: "Go to definition".to_string(),
                 category: Category::Lsp,
                 mode: Mode::Normal,
+                ..Default::default()
             },
             Command {
                 keys: "<leader>gg".to_string(),
                 description: "Open LazyGit".to_string(),
                 category: Category::Git,
                 mode: Mode::Normal,
+                ..Default::default()
             },
         ]
     }
@@ -105,6 +657,30 @@ mod tests {
         assert_eq!(results[0].0.keys, "<leader>ff");
     }
 
+    #[test]
+    fn test_search_by_mnemonic() {
+        let engine = SearchEngine::new();
+        let commands = vec![
+            Command {
+                keys: "<leader>sg".to_string(),
+                description: "Grep (root dir)".to_string(),
+                mnemonic: Some("sg = Search Grep".to_string()),
+                ..Default::default()
+            },
+            Command {
+                keys: "<leader>/".to_string(),
+                description: "Search grep".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let results = engine.search(&commands, "search grep");
+        assert_eq!(results.len(), 2);
+        // A description match outranks the same words in a mnemonic
+        assert_eq!(results[0].0.keys, "<leader>/");
+        assert_eq!(results[1].0.keys, "<leader>sg");
+    }
+
     #[test]
     fn test_search_by_keys() {
         let engine = SearchEngine::new();
@@ -125,6 +701,33 @@ mod tests {
         assert!(!results.is_empty());
     }
 
+    #[test]
+    fn test_category_counts() {
+        let engine = SearchEngine::new();
+        let mut commands = sample_commands();
+        commands[3].category = Category::Custom("Testing".to_string());
+        let results = engine.search(&commands, "");
+        let layout = CategoryLayout::default();
+        let counts = category_counts(results.iter().map(|(cmd, _)| *cmd), &layout);
+        assert_eq!(
+            counts,
+            [
+                (Category::Search, 2),
+                (Category::Lsp, 1),
+                (Category::Custom("Testing".to_string()), 1),
+            ]
+        );
+        assert!(category_counts(&[], &layout).is_empty());
+
+        let layout = CategoryLayout {
+            order: vec![Category::Lsp],
+            hidden: vec![Category::Search],
+            ..Default::default()
+        };
+        let counts = category_counts(results.iter().map(|(cmd, _)| *cmd), &layout);
+        assert_eq!(counts, [(Category::Lsp, 1), (Category::Custom("Testing".to_string()), 1)]);
+    }
+
     #[test]
     fn test_empty_query() {
         let engine = SearchEngine::new();
@@ -134,4 +737,362 @@ mod tests {
         assert_eq!(results.len(), commands.len());
     }
 
+    #[test]
+    fn test_tag_filter() {
+        let engine = SearchEngine::new();
+        let commands = sample_commands();
+
+        let results = engine.search(&commands, "#picker");
+        assert_eq!(results.len(), 2);
+
+        let results = engine.search(&commands, "#beg");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.keys, "<leader>ff");
+
+        let results = engine.search(&commands, "#picker grep");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.keys, "<leader>fg");
+    }
+
+    #[test]
+    fn test_search_alternatives() {
+        let mut commands = sample_commands();
+        commands.push(Command {
+            keys: "<S-h>".to_string(),
+            description: "Previous buffer".to_string(),
+            alternatives: vec!["[b".to_string()],
+            ..Default::default()
+        });
+        let engine = SearchEngine::new();
+        let results = engine.search(&commands, "[b");
+        assert_eq!(results[0].0.keys, "<S-h>");
+    }
+
+    #[test]
+    fn test_mode_filter() {
+        let mut commands = sample_commands();
+        commands.push(Command {
+            keys: "gd".to_string(),
+            description: "Select definition".to_string(),
+            mode: Mode::Visual,
+            ..Default::default()
+        });
+        let engine = SearchEngine::new();
+
+        let results = engine.search(&commands, "definition");
+        assert_eq!(results.len(), 2);
+        let results = engine.search(&commands, "mode:v definition");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.description, "Select definition");
+        let results = engine.search(&commands, "mode:normal mode:visual gd");
+        assert_eq!(results.len(), 2);
+
+        // Not a mode: searched as text
+        assert_eq!(Query::parse("mode:sideways").text, "mode:sideways");
+        assert_eq!(Query::filters_only("mode:v gd fav: mode:sideways"), "mode:v fav:");
+    }
+
+    #[test]
+    fn test_source_filter() {
+        let mut commands = sample_commands();
+        commands[1].source = Source::User;
+        commands[2].source = Source::File("packs/work.json".to_string());
+        let engine = SearchEngine::new();
+
+        let results = engine.search(&commands, "source:user");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.keys, "<leader>fg");
+        assert_eq!(engine.search(&commands, "source:builtin").len(), 2);
+        assert_eq!(engine.search(&commands, "source:file").len(), 1);
+        assert_eq!(engine.search(&commands, "source:Work source:user").len(), 2);
+        assert_eq!(engine.search(&commands, "source:packs/work.json").len(), 1);
+        assert_eq!(engine.search(&commands, "source:work.json").len(), 1);
+        assert!(engine.search(&commands, "source:other/work.json").is_empty());
+    }
+
+    #[test]
+    fn test_search_docs() {
+        let mut commands = sample_commands();
+        commands[3].doc = Some(Doc {
+            plugin: "lazygit.nvim".to_string(),
+            text: "Opens LazyGit in a floating window to stage hunks and commit.".to_string(),
+        });
+        let engine = SearchEngine::new();
+
+        let results = engine.search(&commands, "stage commit");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.keys, "<leader>gg");
+        // Fuzzy matches of the other fields aren't found in the excerpt's letters
+        assert!(engine.search(&commands, "fwsh").is_empty());
+        assert_eq!(doc_score("Opens LazyGit", "lazy"), Some(DOC_SCORE));
+        assert_eq!(doc_score("Opens LazyGit", "git"), None);
+    }
+
+    #[test]
+    fn test_prefix_filter() {
+        let commands = sample_commands();
+        let engine = SearchEngine::new();
+
+        let results = engine.search(&commands, "prefix:<leader>f");
+        let keys: Vec<&str> = results.iter().map(|(cmd, _)| cmd.keys.as_str()).collect();
+        assert_eq!(keys, ["<leader>ff", "<leader>fg"]);
+        assert_eq!(engine.search(&commands, "prefix:<Leader>f").len(), 2);
+        let results = engine.search(&commands, "prefix:<leader>f grep");
+        assert_eq!(results.len(), 1);
+        assert_eq!(engine.search(&commands, "prefix:g prefix:<leader>g").len(), 2);
+        assert_eq!(Query::parse("prefix:").text, "prefix:");
+    }
+
+    #[test]
+    fn test_excluded_terms() {
+        let mut commands = sample_commands();
+        commands.push(Command {
+            keys: "<leader>fG".to_string(),
+            description: "Live grep (cwd)".to_string(),
+            mode: Mode::Visual,
+            ..Default::default()
+        });
+        let engine = SearchEngine::new();
+
+        let results = engine.search(&commands, "grep !CWD");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.keys, "<leader>fg");
+        // Keys and tags count as mentions too, and filters still apply
+        let keys = |query| {
+            let results = engine.search(&commands, query);
+            results.iter().map(|(cmd, _)| cmd.keys.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(keys("!leader !picker"), ["gd"]);
+        assert_eq!(keys("mode:v !cwd"), Vec::<String>::new());
+        assert_eq!(keys("prefix:<leader>f !files"), ["<leader>fg", "<leader>fG"]);
+        // So do the other fields the search looks in, like the category
+        assert_eq!(keys("!search"), ["gd", "<leader>gg", "<leader>fG"]);
+
+        let query = Query::parse("buffer !close !");
+        assert_eq!(query.text, "buffer !");
+        assert_eq!(query.excluded, ["close"]);
+        assert_eq!(Query::filters_only("buffer !close"), "!close");
+        // Only a word after the `!`, and not the shell command of an ex command
+        assert_eq!(Query::parse("!<leader>").text, "!<leader>");
+        let query = Query::parse(":w !sudo");
+        assert_eq!(query.text, ":w !sudo");
+        assert!(query.excluded.is_empty());
+        assert_eq!(Query::filters_only(":r !ls #git"), "#git");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = Query::parse("fav: #picker  find files recent: hidden:");
+        assert!(query.favorites);
+        assert!(query.recent);
+        assert!(query.hidden);
+        assert_eq!(query.tags, vec!["picker"]);
+        assert_eq!(query.text, "find files");
+        assert_eq!(query.learned, None);
+
+        assert_eq!(Query::parse("unlearned: lsp").learned, Some(false));
+        assert_eq!(Query::parse("learned:").learned, Some(true));
+    }
+
+    #[test]
+    fn test_fav_token_not_matched() {
+        let engine = SearchEngine::new();
+        let commands = sample_commands();
+
+        let results = engine.search(&commands, "fav:");
+        assert_eq!(results.len(), commands.len());
+    }
+
+    #[test]
+    fn test_search_by_tag_text() {
+        let engine = SearchEngine::new();
+        let commands = sample_commands();
+
+        let results = engine.search(&commands, "picker");
+        assert!(results.iter().any(|(cmd, _)| cmd.keys == "<leader>fg"));
+    }
+
+    #[test]
+    fn test_exact_mode() {
+        let mut engine = SearchEngine::new();
+        engine.mode = MatchMode::Exact;
+        let commands = sample_commands();
+
+        let results = engine.search(&commands, "gd");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].0.keys, "gd");
+
+        let results = engine.search(&commands, "GREP");
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn test_smartcase_mode() {
+        let mut engine = SearchEngine::new();
+        engine.mode = MatchMode::Smartcase;
+        let commands = sample_commands();
+
+        assert_eq!(engine.search(&commands, "lazygit").len(), 1);
+        assert_eq!(engine.search(&commands, "LazyGit").len(), 1);
+        assert!(engine.search(&commands, "LAZYGIT").is_empty());
+    }
+
+    #[test]
+    fn test_regex_mode() {
+        let mut engine = SearchEngine::new();
+        engine.mode = MatchMode::Regex;
+        let commands = sample_commands();
+
+        let results = engine.search(&commands, "^g[dr]$");
+        let keys: Vec<&str> = results.iter().map(|(cmd, _)| cmd.keys.as_str()).collect();
+        assert_eq!(keys, ["gd"]);
+        // Not a valid regex, so it's looked for as typed
+        assert!(engine.search(&commands, "grep (").is_empty());
+        assert_eq!(engine.search(&commands, "LAZYGIT").len(), 1);
+    }
+
+    #[test]
+    fn test_nucleo_mode() {
+        let mut engine = SearchEngine::new();
+        engine.mode = MatchMode::Nucleo;
+        let commands = sample_commands();
+
+        assert_eq!(engine.search(&commands, "lzgt")[0].0.keys, "<leader>gg");
+        assert!(engine.search(&commands, "lazygit !lazy").is_empty());
+        assert!(engine.search(&commands, "LazyGIT").is_empty());
+    }
+
+    #[test]
+    fn test_key_notation_queries() {
+        let engine = SearchEngine::new();
+        let commands = vec![
+            Command {
+                keys: "<C-w>v".to_string(),
+                description: "Split window vertically".to_string(),
+                category: Category::Window,
+                ..Default::default()
+            },
+            Command {
+                keys: "<leader>ff".to_string(),
+                description: "Find files".to_string(),
+                category: Category::Search,
+                ..Default::default()
+            },
+        ];
+
+        for query in ["c-w", "ctrl w", "C-W v", "<C-w>v"] {
+            let results = engine.search(&commands, query);
+            assert_eq!(results[0].0.keys, "<C-w>v", "query {:?}", query);
+        }
+        for query in ["spc f f", "leader ff", "space ff"] {
+            let results = engine.search(&commands, query);
+            assert_eq!(results[0].0.keys, "<leader>ff", "query {:?}", query);
+        }
+    }
+
+    #[test]
+    fn test_frames_field() {
+        let commands = vec![Command {
+            keys: "<leader>sg".to_string(),
+            description: "Grep".to_string(),
+            ..Default::default()
+        }];
+        let mut engine = SearchEngine::new();
+        let unindexed = engine.search(&commands, "space s g")[0].1;
+        engine.index(&commands);
+        assert_eq!(engine.frames["<leader>sg"], ["space", "s", "g"]);
+        assert_eq!(engine.search(&commands, "space s g")[0].1, unindexed);
+        assert!(unindexed > 0);
+
+        // Read only as key presses, so it goes with the field
+        engine.weights.frames = 0;
+        assert_eq!(engine.search(&commands, "space s g")[0].1, 0);
+    }
+
+    #[test]
+    fn test_search_workflow_steps() {
+        let engine = SearchEngine::new();
+        let step = |keys: &str, description: &str| WorkflowStep {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            app: None,
+        };
+        let commands = vec![
+            Command {
+                keys: "gd<leader>cr".to_string(),
+                description: "Rename a symbol project-wide".to_string(),
+                workflow: vec![
+                    step("gd", "Go to the definition"),
+                    step("<leader>cr", "Rename every reference"),
+                ],
+                ..Default::default()
+            },
+            Command {
+                keys: "gr".to_string(),
+                description: "Find all references".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let results = engine.search(&commands, "rename");
+        assert_eq!(results.len(), 1);
+        let results = engine.search(&commands, "reference");
+        let keys: Vec<&str> = results.iter().map(|(cmd, _)| cmd.keys.as_str()).collect();
+        // A step counts less than a description
+        assert_eq!(keys, ["gr", "gd<leader>cr"]);
+    }
+
+    #[test]
+    fn test_custom_weights() {
+        let mut engine = SearchEngine::new();
+        engine.weights = Weights {
+            description: 0,
+            ..Weights::default()
+        };
+        let commands = sample_commands();
+
+        let results = engine.search(&commands, "find");
+        assert!(!results.is_empty());
+        assert!(results.iter().all(|(_, score)| *score == 0));
+    }
+
+    #[test]
+    fn test_ex_weights() {
+        let mut engine = SearchEngine::new();
+        engine.ex_weights = Weights {
+            keys: 0,
+            description: 0,
+            ..Weights::ex()
+        };
+        let commands = vec![
+            Command {
+                keys: ":Lazy".to_string(),
+                description: "Open the plugin manager".to_string(),
+                ..Default::default()
+            },
+            Command {
+                keys: "<leader>l".to_string(),
+                description: "Lazy".to_string(),
+                ..Default::default()
+            },
+        ];
+
+        let results = engine.search(&commands, "lazy");
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0.keys, "<leader>l");
+        assert_eq!(results[1].1, 0);
+    }
+
+    #[test]
+    fn test_key_filter() {
+        let keyboard = Keyboard::new();
+        let frames = commands::parse_key_string("<leader>gG");
+        let filter = |key, first_frame| KeyFilter { key, first_frame };
+        assert!(filter("space", true).matches(&frames, &keyboard));
+        assert!(filter("g", false).matches(&frames, &keyboard));
+        assert!(!filter("g", true).matches(&frames, &keyboard));
+        // `G` is typed with Shift, on either side
+        assert!(filter("rshift", false).matches(&frames, &keyboard));
+        assert!(!filter("f", false).matches(&frames, &keyboard));
+    }
 }
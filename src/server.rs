@@ -0,0 +1,231 @@
+use crate::commands::{Category, Command, Mode};
+use crate::output::CommandOutput;
+use crate::search::{Query, SearchEngine};
+use crate::state::UserState;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Results returned for a search without an explicit `limit`
+const DEFAULT_LIMIT: usize = 50;
+
+/// One line of the socket protocol, e.g. `{"cmd": "search", "query": "lsp"}`
+#[derive(Debug, Deserialize)]
+#[serde(tag = "cmd", rename_all = "lowercase", deny_unknown_fields)]
+enum Request {
+    /// Commands matching a query, best first
+    Search { query: String, limit: Option<usize> },
+    /// The command with exactly these keys and mode
+    Get {
+        keys: String,
+        #[serde(default)]
+        mode: Mode,
+    },
+    /// Every category with its number of commands
+    Categories,
+}
+
+#[derive(Debug, Serialize)]
+struct CategoryCount {
+    label: String,
+    name: Category,
+    count: usize,
+}
+
+/// Everything a query needs, loaded once when the server starts
+pub struct Server {
+    commands: Vec<Command>,
+    engine: SearchEngine,
+    state: UserState,
+}
+
+impl Server {
+    pub fn new(commands: Vec<Command>, mut engine: SearchEngine, state: UserState) -> Self {
+        engine.index(&commands);
+        Self {
+            commands,
+            engine,
+            state,
+        }
+    }
+
+    /// Answer one request line with one response line
+    pub fn respond(&self, line: &str) -> String {
+        let response = match serde_json::from_str::<Request>(line) {
+            Ok(request) => json!({ "ok": true, "result": self.handle(request) }),
+            Err(err) => json!({ "ok": false, "error": format!("bad request: {}", err) }),
+        };
+        response.to_string()
+    }
+
+    fn handle(&self, request: Request) -> Value {
+        match request {
+            Request::Search { query, limit } => {
+                let mut results = self.engine.search(&self.commands, &query);
+                self.state
+                    .filter_results(&mut results, &Query::parse(&query));
+                let entries: Vec<CommandOutput> = results
+                    .into_iter()
+                    .take(limit.unwrap_or(DEFAULT_LIMIT))
+                    .map(|(cmd, score)| CommandOutput::new(cmd, Some(score)))
+                    .collect();
+                json!(entries)
+            }
+            Request::Get { keys, mode } => {
+                let found = self
+                    .commands
+                    .iter()
+                    .find(|cmd| cmd.keys == keys && cmd.mode == mode)
+                    .map(|cmd| CommandOutput::new(cmd, None));
+                json!(found)
+            }
+            Request::Categories => {
+                let used = self.commands.iter().map(|cmd| &cmd.category);
+                let counts: Vec<CategoryCount> = Category::in_order(used)
+                    .into_iter()
+                    .map(|category| CategoryCount {
+                        label: category.as_str().to_string(),
+                        count: self
+                            .commands
+                            .iter()
+                            .filter(|cmd| cmd.category == category)
+                            .count(),
+                        name: category,
+                    })
+                    .collect();
+                json!(counts)
+            }
+        }
+    }
+
+    /// Serve requests on a unix socket until the process is killed, one thread per client
+    pub fn listen(self, path: &Path) -> anyhow::Result<()> {
+        let listener = bind(path)?;
+        eprintln!("lvcheat: listening on {}", path.display());
+
+        let server = Arc::new(self);
+        for stream in listener.incoming() {
+            let stream = stream.context("failed to accept connection")?;
+            let server = Arc::clone(&server);
+            std::thread::spawn(move || {
+                // A client hanging up mid-request only ends its own connection
+                let _ = server.serve_client(stream);
+            });
+        }
+        Ok(())
+    }
+
+    fn serve_client(&self, stream: UnixStream) -> std::io::Result<()> {
+        let mut writer = stream.try_clone()?;
+        for line in BufReader::new(stream).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            writeln!(writer, "{}", self.respond(&line))?;
+        }
+        Ok(())
+    }
+}
+
+/// Default socket path: `$XDG_RUNTIME_DIR/lvcheat.sock`, or the temp dir without one
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join("lvcheat.sock")
+}
+
+/// Bind the socket, replacing a stale one left by a server that didn't shut down cleanly
+fn bind(path: &Path) -> anyhow::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            anyhow::bail!("a server is already listening on {}", path.display());
+        }
+        std::fs::remove_file(path)
+            .with_context(|| format!("failed to remove stale socket {}", path.display()))?;
+    }
+    UnixListener::bind(path).with_context(|| format!("failed to bind {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn server() -> Server {
+        let cmd = |keys: &str, description: &str, category| Command {
+            keys: keys.to_string(),
+            description: description.to_string(),
+            category,
+            ..Default::default()
+        };
+        Server::new(
+            vec![
+                cmd("<leader>ff", "Find files", Category::Search),
+                cmd("<leader>gg", "Lazygit", Category::Git),
+                cmd("<leader>sg", "Grep", Category::Search),
+            ],
+            SearchEngine::new(),
+            UserState::default(),
+        )
+    }
+
+    fn respond(server: &Server, line: &str) -> Value {
+        serde_json::from_str(&server.respond(line)).unwrap()
+    }
+
+    #[test]
+    fn test_requests() {
+        let server = server();
+
+        let search = respond(&server, r#"{"cmd": "search", "query": "find", "limit": 1}"#);
+        assert_eq!(search["ok"], true);
+        assert_eq!(search["result"].as_array().unwrap().len(), 1);
+        assert_eq!(search["result"][0]["keys"], "<leader>ff");
+
+        let get = respond(&server, r#"{"cmd": "get", "keys": "<leader>gg"}"#);
+        assert_eq!(get["result"]["description"], "Lazygit");
+        assert_eq!(get["result"]["frames"].as_array().unwrap().len(), 3);
+        let missing = respond(
+            &server,
+            r#"{"cmd": "get", "keys": "<leader>gg", "mode": "insert"}"#,
+        );
+        assert!(missing["result"].is_null());
+
+        let categories = respond(&server, r#"{"cmd": "categories"}"#);
+        let search_count = categories["result"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|c| c["name"] == "search")
+            .unwrap();
+        assert_eq!(search_count["count"], 2);
+
+        let bad = respond(&server, r#"{"cmd": "delete"}"#);
+        assert_eq!(bad["ok"], false);
+        assert!(bad["error"].as_str().unwrap().starts_with("bad request"));
+    }
+
+    #[test]
+    fn test_socket_roundtrip() {
+        let path = std::env::temp_dir().join(format!("lvcheat-test-{}.sock", std::process::id()));
+        let listener = bind(&path).unwrap();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            server().serve_client(stream).unwrap();
+        });
+
+        let mut client = UnixStream::connect(&path).unwrap();
+        writeln!(client, r#"{{"cmd": "get", "keys": "<leader>sg"}}"#).unwrap();
+        client.shutdown(std::net::Shutdown::Write).unwrap();
+        let mut lines = BufReader::new(client).lines();
+        let reply: Value = serde_json::from_str(&lines.next().unwrap().unwrap()).unwrap();
+        assert_eq!(reply["result"]["description"], "Grep");
+
+        handle.join().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
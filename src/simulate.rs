@@ -0,0 +1,1015 @@
+use crate::commands::{self, Diagram, KeyFrame, Mode};
+
+/// Code the simulation runs on when a command has no diagram of its own
+const SAMPLE: &[&str] = &[
+    "fn total(price: u32, qty: u32) -> u32 {",
+    "    let sum = price * qty;",
+    "    println!(\"sum = {}\", sum);",
+    "",
+    "    sum",
+    "}",
+];
+const SAMPLE_CURSOR: (usize, usize) = (1, 8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SimMode {
+    Normal,
+    Insert,
+    Visual,
+    VisualLine,
+}
+
+impl SimMode {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SimMode::Normal => "NORMAL",
+            SimMode::Insert => "INSERT",
+            SimMode::Visual => "VISUAL",
+            SimMode::VisualLine => "V-LINE",
+        }
+    }
+}
+
+/// The sample buffer as it looks after one animation frame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snapshot {
+    pub lines: Vec<String>,
+    /// Cursor as (row, col) in chars
+    pub cursor: (usize, usize),
+    pub mode: SimMode,
+    /// Selected flat char range while in visual mode, end exclusive
+    pub selection: Option<(usize, usize)>,
+    /// Keys typed towards a command that isn't complete yet
+    pub pending: String,
+}
+
+impl Snapshot {
+    /// Flat index of the first char of each line
+    pub fn line_offsets(&self) -> Vec<usize> {
+        let mut offset = 0;
+        self.lines
+            .iter()
+            .map(|line| {
+                let start = offset;
+                offset += line.chars().count() + 1;
+                start
+            })
+            .collect()
+    }
+}
+
+/// Play a command's frames against sample text, returning the buffer after each frame.
+/// Returns `None` when the keys use anything the interpreter doesn't understand.
+pub fn simulate(
+    frames: &[KeyFrame],
+    mode: Mode,
+    diagram: Option<&Diagram>,
+) -> Option<Vec<Snapshot>> {
+    if frames.is_empty() || !matches!(mode, Mode::Normal | Mode::Visual) {
+        return None;
+    }
+
+    // Single-line diagrams make a better stage, unless they fake blank lines with ¶
+    let mut sim = match diagram {
+        Some(diagram) if !diagram.text.contains('¶') => {
+            Sim::new(&[&diagram.text], (0, diagram.cursor))
+        }
+        _ => Sim::new(SAMPLE, SAMPLE_CURSOR),
+    };
+    if mode == Mode::Visual {
+        sim.mode = SimMode::Visual;
+        sim.anchor = sim.cursor;
+    }
+
+    let mut snapshots = Vec::new();
+    for frame in frames {
+        for key in frame_keys(frame)? {
+            sim.press(key)?;
+        }
+        snapshots.push(sim.snapshot());
+    }
+    Some(snapshots)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SimKey {
+    Char(char),
+    Esc,
+}
+
+/// Keys a frame stands for, or `None` for chords the interpreter can't play
+fn frame_keys(frame: &KeyFrame) -> Option<Vec<SimKey>> {
+    if let Some(note) = &frame.note {
+        if let Some(count) = note.strip_prefix("count: ") {
+            return Some(count.chars().map(SimKey::Char).collect());
+        }
+        if let Some(register) = note.strip_prefix("register: ") {
+            return Some(
+                std::iter::once('"')
+                    .chain(register.chars())
+                    .map(SimKey::Char)
+                    .collect(),
+            );
+        }
+    }
+
+    let shift = frame.keys.iter().any(|k| k.is_modifier && k.key == "Shift");
+    let mut keys = frame.keys.iter().filter(|k| !k.is_modifier);
+    let key = keys.next()?;
+    if keys.next().is_some()
+        || key.is_leader
+        || frame.keys.iter().any(|k| k.is_modifier && k.key != "Shift")
+    {
+        return None;
+    }
+
+    match key.key.as_str() {
+        "Esc" => Some(vec![SimKey::Esc]),
+        name => {
+            let mut chars = name.chars();
+            let c = chars.next()?;
+            if chars.next().is_some() {
+                return None;
+            }
+            let c = if shift { commands::shifted(c) } else { c };
+            Some(vec![SimKey::Char(c)])
+        }
+    }
+}
+
+/// How far a motion reaches and how an operator should treat it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Span {
+    start: usize,
+    /// Exclusive
+    end: usize,
+    linewise: bool,
+}
+
+enum Parse {
+    /// Where the cursor lands and what an operator would cover
+    Motion(usize, Span),
+    /// A text object; selects or operates on the whole span
+    Object(Span),
+    /// Valid keys that found nothing to act on, which Vim answers with a beep
+    Nothing,
+    NeedMore,
+}
+
+#[derive(Debug, Clone)]
+struct Register {
+    text: String,
+    linewise: bool,
+}
+
+/// A tiny Vim: enough motions, text objects and operators to demonstrate common commands
+struct Sim {
+    text: Vec<char>,
+    cursor: usize,
+    mode: SimMode,
+    anchor: usize,
+    pending: String,
+    register: Option<Register>,
+    undo: Vec<(Vec<char>, usize)>,
+}
+
+impl Sim {
+    fn new(lines: &[&str], (row, col): (usize, usize)) -> Self {
+        let text: Vec<char> = lines.join("\n").chars().collect();
+        let mut sim = Self {
+            text,
+            cursor: 0,
+            mode: SimMode::Normal,
+            anchor: 0,
+            pending: String::new(),
+            register: None,
+            undo: Vec::new(),
+        };
+        // A diagram's cursor may sit past its text, where it marks the end of the line
+        let start = sim.line_start(row).min(sim.text.len());
+        sim.cursor = (start + col).min(sim.line_last(start));
+        sim
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        let text: String = self.text.iter().collect();
+        let lines: Vec<String> = text.split('\n').map(str::to_string).collect();
+        let row = self.text[..self.cursor]
+            .iter()
+            .filter(|&&c| c == '\n')
+            .count();
+        let col = self.cursor - self.line_start(row);
+        let selection = match self.mode {
+            SimMode::Visual | SimMode::VisualLine => {
+                self.visual_span().map(|span| (span.start, span.end))
+            }
+            _ => None,
+        };
+        Snapshot {
+            lines,
+            cursor: (row, col),
+            mode: self.mode,
+            selection,
+            pending: self.pending.clone(),
+        }
+    }
+
+    fn press(&mut self, key: SimKey) -> Option<()> {
+        match (self.mode, key) {
+            (SimMode::Insert, SimKey::Esc) => {
+                self.mode = SimMode::Normal;
+                if self.cursor > self.line_start_of(self.cursor) {
+                    self.cursor -= 1;
+                }
+                Some(())
+            }
+            (SimMode::Insert, SimKey::Char(c)) => {
+                self.text.insert(self.cursor, c);
+                self.cursor += 1;
+                Some(())
+            }
+            (_, SimKey::Esc) => {
+                self.pending.clear();
+                self.mode = SimMode::Normal;
+                Some(())
+            }
+            (_, SimKey::Char(c)) => {
+                self.pending.push(c);
+                let pending = self.pending.clone();
+                if self.execute(&pending)? {
+                    self.pending.clear();
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// Run the pending keys; `Some(false)` means more keys are needed
+    fn execute(&mut self, keys: &str) -> Option<bool> {
+        // Registers only matter for pasting, so `"a` is accepted and dropped
+        let keys = match keys.strip_prefix('"') {
+            Some(rest) => match rest.chars().next() {
+                Some(c) => &rest[c.len_utf8()..],
+                None => return Some(false),
+            },
+            None => keys,
+        };
+        let (count, keys) = match split_count(keys) {
+            Some(split) => split,
+            None => return Some(false),
+        };
+
+        if matches!(self.mode, SimMode::Visual | SimMode::VisualLine) {
+            return self.execute_visual(keys, count);
+        }
+
+        let mut chars = keys.chars();
+        let first = chars.next()?;
+        let rest = chars.as_str();
+
+        match first {
+            'd' | 'c' | 'y' => self.execute_operator(first, rest, count),
+            'x' => self.apply_simple('d', "l", count),
+            'X' => self.apply_simple('d', "h", count),
+            'D' => self.apply_simple('d', "$", 1),
+            'C' => self.apply_simple('c', "$", 1),
+            's' => self.apply_simple('c', "l", count),
+            'i' => self.enter_insert(self.cursor),
+            'a' => self.enter_insert((self.cursor + 1).min(self.line_end_of(self.cursor))),
+            'I' => self.enter_insert(self.first_non_blank(self.cursor)),
+            'A' => self.enter_insert(self.line_end_of(self.cursor)),
+            'o' | 'O' => {
+                self.save_undo();
+                let at = if first == 'o' {
+                    self.line_end_of(self.cursor)
+                } else {
+                    self.line_start_of(self.cursor)
+                };
+                self.text.insert(at, '\n');
+                self.cursor = if first == 'o' { at + 1 } else { at };
+                self.mode = SimMode::Insert;
+                Some(true)
+            }
+            'v' | 'V' => {
+                self.anchor = self.cursor;
+                self.mode = if first == 'v' {
+                    SimMode::Visual
+                } else {
+                    SimMode::VisualLine
+                };
+                Some(true)
+            }
+            'p' | 'P' => self.paste(first == 'p'),
+            'u' => {
+                let (text, cursor) = self.undo.pop()?;
+                self.text = text;
+                self.cursor = cursor;
+                Some(true)
+            }
+            'J' => {
+                let end = self.line_end_of(self.cursor);
+                if end >= self.text.len() {
+                    return Some(true);
+                }
+                self.save_undo();
+                let next = self.first_non_blank(end + 1);
+                self.text.splice(end..next, [' ']);
+                self.cursor = end;
+                Some(true)
+            }
+            '~' => {
+                let c = *self.text.get(self.cursor)?;
+                self.save_undo();
+                self.text[self.cursor] = if c.is_uppercase() {
+                    c.to_ascii_lowercase()
+                } else {
+                    c.to_ascii_uppercase()
+                };
+                self.cursor = (self.cursor + 1).min(self.line_last(self.cursor));
+                Some(true)
+            }
+            _ => match self.parse_motion(keys, count, false)? {
+                Parse::Motion(target, _) => {
+                    self.cursor = target;
+                    Some(true)
+                }
+                Parse::Object(_) | Parse::Nothing => Some(true),
+                Parse::NeedMore => Some(false),
+            },
+        }
+    }
+
+    fn execute_operator(&mut self, op: char, rest: &str, count: usize) -> Option<bool> {
+        // A count can also sit between the operator and the motion, as in `d2w`
+        let (inner, rest) = match split_count(rest) {
+            Some(split) => split,
+            None => return Some(false),
+        };
+        let count = count * inner;
+        if rest.is_empty() {
+            return Some(false);
+        }
+
+        // Doubled operators (`dd`, `yy`, `cc`) work on whole lines
+        if rest.len() == 1 && rest.starts_with(op) {
+            let start = self.line_start_of(self.cursor);
+            let mut end = self.cursor;
+            for _ in 1..count {
+                let line_end = self.line_end_of(end);
+                if line_end < self.text.len() {
+                    end = line_end + 1;
+                }
+            }
+            let end = self.line_end_of(end);
+            self.apply(
+                op,
+                Span {
+                    start,
+                    end,
+                    linewise: true,
+                },
+            );
+            return Some(true);
+        }
+
+        match self.parse_motion(rest, count, true)? {
+            Parse::Motion(_, span) | Parse::Object(span) => {
+                self.apply(op, span);
+                Some(true)
+            }
+            Parse::Nothing => Some(true),
+            Parse::NeedMore => Some(false),
+        }
+    }
+
+    fn apply_simple(&mut self, op: char, motion: &str, count: usize) -> Option<bool> {
+        if let Parse::Motion(_, span) = self.parse_motion(motion, count, true)? {
+            if span.start < span.end {
+                self.apply(op, span);
+            }
+        }
+        Some(true)
+    }
+
+    fn execute_visual(&mut self, keys: &str, count: usize) -> Option<bool> {
+        match keys {
+            "d" | "x" | "y" | "c" => {
+                let span = self.visual_span()?;
+                let op = if keys == "x" {
+                    'd'
+                } else {
+                    keys.chars().next()?
+                };
+                self.mode = SimMode::Normal;
+                self.apply(op, span);
+                Some(true)
+            }
+            _ => match self.parse_motion(keys, count, true)? {
+                Parse::Motion(target, _) => {
+                    self.cursor = target;
+                    Some(true)
+                }
+                // Text objects grow the selection to cover the object
+                Parse::Object(span) => {
+                    self.anchor = span.start;
+                    self.cursor = span.end.saturating_sub(1).max(span.start);
+                    Some(true)
+                }
+                Parse::Nothing => Some(true),
+                Parse::NeedMore => Some(false),
+            },
+        }
+    }
+
+    fn visual_span(&self) -> Option<Span> {
+        let (start, end) = (self.anchor.min(self.cursor), self.anchor.max(self.cursor));
+        Some(match self.mode {
+            SimMode::VisualLine => Span {
+                start: self.line_start_of(start),
+                end: self.line_end_of(end),
+                linewise: true,
+            },
+            _ => Span {
+                start,
+                end: (end + 1).min(self.text.len()),
+                linewise: false,
+            },
+        })
+    }
+
+    /// Run an operator over a span
+    fn apply(&mut self, op: char, span: Span) {
+        let Span {
+            start,
+            mut end,
+            linewise,
+        } = span;
+        self.register = Some(Register {
+            text: self.text[start..end].iter().collect(),
+            linewise,
+        });
+
+        match op {
+            // `yy` leaves the cursor alone; charwise yanks move to the start
+            'y' if linewise => {}
+            'y' => self.cursor = start,
+            'c' => {
+                self.save_undo();
+                self.text.drain(start..end);
+                self.cursor = start;
+                self.mode = SimMode::Insert;
+            }
+            _ => {
+                self.save_undo();
+                // Take the line break too, so the lines below move up
+                let mut start = start;
+                if linewise {
+                    if end < self.text.len() {
+                        end += 1;
+                    } else {
+                        start = start.saturating_sub(1);
+                    }
+                }
+                self.text.drain(start..end);
+                self.cursor = if linewise {
+                    self.first_non_blank(self.line_start_of(start.min(self.text.len())))
+                } else {
+                    start.min(self.line_last(start.min(self.text.len())))
+                };
+            }
+        }
+    }
+
+    fn paste(&mut self, after: bool) -> Option<bool> {
+        let register = self.register.clone()?;
+        self.save_undo();
+        if register.linewise {
+            let (at, text) = if after {
+                let end = self.line_end_of(self.cursor);
+                (end, format!("\n{}", register.text))
+            } else {
+                (
+                    self.line_start_of(self.cursor),
+                    format!("{}\n", register.text),
+                )
+            };
+            self.text.splice(at..at, text.chars());
+            let row_start = if after { at + 1 } else { at };
+            self.cursor = self.first_non_blank(row_start);
+        } else {
+            let at = if after {
+                (self.cursor + 1).min(self.line_end_of(self.cursor))
+            } else {
+                self.cursor
+            };
+            let len = register.text.chars().count();
+            self.text.splice(at..at, register.text.chars());
+            self.cursor = at + len.saturating_sub(1);
+        }
+        Some(true)
+    }
+
+    fn enter_insert(&mut self, at: usize) -> Option<bool> {
+        self.save_undo();
+        self.cursor = at;
+        self.mode = SimMode::Insert;
+        Some(true)
+    }
+
+    fn save_undo(&mut self) {
+        self.undo.push((self.text.clone(), self.cursor));
+    }
+
+    /// Parse a motion or (when `objects` is set) a text object.
+    /// `None` means the keys aren't something the interpreter knows.
+    fn parse_motion(&self, keys: &str, count: usize, objects: bool) -> Option<Parse> {
+        let mut chars = keys.chars();
+        let first = chars.next()?;
+        let arg = chars.next();
+        if chars.next().is_some() {
+            return None;
+        }
+        let from = self.cursor;
+        let exclusive = |to: usize| Span {
+            start: from.min(to),
+            end: from.max(to),
+            linewise: false,
+        };
+        let inclusive = |to: usize| Span {
+            start: from.min(to),
+            end: (from.max(to) + 1).min(self.text.len()),
+            linewise: false,
+        };
+        let linewise = |to: usize| Span {
+            start: self.line_start_of(from.min(to)),
+            end: self.line_end_of(from.max(to)),
+            linewise: true,
+        };
+        let repeat = |step: &dyn Fn(usize) -> usize| (0..count).fold(from, |pos, _| step(pos));
+
+        let motion = match (first, arg) {
+            ('h', None) => {
+                let to = repeat(&|pos| {
+                    if pos > self.line_start_of(pos) {
+                        pos - 1
+                    } else {
+                        pos
+                    }
+                });
+                Parse::Motion(to, exclusive(to))
+            }
+            ('l', None) => {
+                let to = repeat(&|pos| (pos + 1).min(self.line_end_of(pos)));
+                let target = to.min(self.line_last(from));
+                Parse::Motion(target, exclusive(to))
+            }
+            ('j' | 'k', None) => {
+                let down = first == 'j';
+                let to = repeat(&|pos| self.vertical(pos, down));
+                Parse::Motion(to, linewise(to))
+            }
+            ('0', None) => {
+                let to = self.line_start_of(from);
+                Parse::Motion(to, exclusive(to))
+            }
+            ('^', None) => {
+                let to = self.first_non_blank(from);
+                Parse::Motion(to, exclusive(to))
+            }
+            ('$', None) => {
+                let to = self.line_last(from);
+                Parse::Motion(to, inclusive(to))
+            }
+            ('w', None) => {
+                let mut to = repeat(&|pos| self.next_word_start(pos));
+                if objects && self.class(from) != Class::Space {
+                    // `cw`/`dw` stop at the end of the line rather than wrapping
+                    to = to.min(self.line_end_of(from));
+                }
+                Parse::Motion(to.min(self.text.len().saturating_sub(1)), exclusive(to))
+            }
+            ('e', None) => {
+                let to = repeat(&|pos| self.word_end(pos));
+                Parse::Motion(to, inclusive(to))
+            }
+            ('b', None) => {
+                let to = repeat(&|pos| self.prev_word_start(pos));
+                Parse::Motion(to, exclusive(to))
+            }
+            ('G', None) => {
+                let to = self.first_non_blank(self.line_start_of(self.text.len()));
+                Parse::Motion(to, linewise(to))
+            }
+            ('g', None) | ('f' | 't' | 'F' | 'T', None) => Parse::NeedMore,
+            ('g', Some('g')) => {
+                let to = self.first_non_blank(0);
+                Parse::Motion(to, linewise(to))
+            }
+            ('}' | '{', None) => {
+                let forward = first == '}';
+                let to = repeat(&|pos| self.paragraph(pos, forward));
+                Parse::Motion(to, exclusive(to))
+            }
+            ('f' | 't', Some(c)) => {
+                let line_end = self.line_end_of(from);
+                match self.text[(from + 1).min(line_end)..line_end]
+                    .iter()
+                    .position(|&x| x == c)
+                {
+                    Some(offset) => {
+                        let found = from + 1 + offset;
+                        let to = if first == 'f' { found } else { found - 1 };
+                        Parse::Motion(to, inclusive(to))
+                    }
+                    None => Parse::Nothing,
+                }
+            }
+            ('F' | 'T', Some(c)) => {
+                let line_start = self.line_start_of(from);
+                match self.text[line_start..from].iter().rposition(|&x| x == c) {
+                    Some(offset) => {
+                        let found = line_start + offset;
+                        let to = if first == 'F' { found } else { found + 1 };
+                        Parse::Motion(to, exclusive(to))
+                    }
+                    None => Parse::Nothing,
+                }
+            }
+            ('i' | 'a', None) if objects => Parse::NeedMore,
+            ('i' | 'a', Some(object)) if objects && TEXT_OBJECTS.contains(object) => {
+                match self.text_object(first == 'a', object) {
+                    Some(span) => Parse::Object(span),
+                    None => Parse::Nothing,
+                }
+            }
+            _ => return None,
+        };
+        Some(motion)
+    }
+
+    fn text_object(&self, around: bool, object: char) -> Option<Span> {
+        let charwise = |start, end| Span {
+            start,
+            end,
+            linewise: false,
+        };
+        match object {
+            'w' => {
+                let class = self.class(self.cursor);
+                let mut start = self.cursor;
+                while start > 0 && self.class(start - 1) == class && self.text[start - 1] != '\n' {
+                    start -= 1;
+                }
+                let mut end = self.cursor;
+                while end < self.text.len() && self.class(end) == class && self.text[end] != '\n' {
+                    end += 1;
+                }
+                if around {
+                    while end < self.text.len() && self.text[end] == ' ' {
+                        end += 1;
+                    }
+                }
+                Some(charwise(start, end))
+            }
+            '(' | ')' | 'b' => self.pair('(', ')', around),
+            '{' | '}' | 'B' => self.pair('{', '}', around),
+            '[' | ']' => self.pair('[', ']', around),
+            '"' | '\'' | '`' => {
+                let line_start = self.line_start_of(self.cursor);
+                let line_end = self.line_end_of(self.cursor);
+                if line_start == line_end {
+                    return None;
+                }
+                let open = line_start
+                    + self.text[line_start..=self.cursor.min(line_end.saturating_sub(1))]
+                        .iter()
+                        .rposition(|&c| c == object)?;
+                let close = open
+                    + 1
+                    + self.text[open + 1..line_end]
+                        .iter()
+                        .position(|&c| c == object)?;
+                Some(if around {
+                    charwise(open, close + 1)
+                } else {
+                    charwise(open + 1, close)
+                })
+            }
+            'p' => {
+                let mut start = self.line_start_of(self.cursor);
+                while start > 0 && !self.is_blank_line(start - 1) {
+                    start = self.line_start_of(start - 1);
+                }
+                let mut end = self.line_end_of(self.cursor);
+                while end < self.text.len() && !self.is_blank_line(end + 1) {
+                    end = self.line_end_of(end + 1);
+                }
+                if around && end < self.text.len() && self.is_blank_line(end + 1) {
+                    end = self.line_end_of(end + 1);
+                }
+                Some(Span {
+                    start,
+                    end,
+                    linewise: true,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// The innermost `open`/`close` pair around the cursor
+    fn pair(&self, open: char, close: char, around: bool) -> Option<Span> {
+        let mut depth = 0;
+        let mut start = None;
+        for i in (0..=self.cursor.min(self.text.len().saturating_sub(1))).rev() {
+            match self.text[i] {
+                c if c == close && i != self.cursor => depth += 1,
+                c if c == open => {
+                    if depth == 0 {
+                        start = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+        }
+        let start = start?;
+        let mut depth = 0;
+        let mut end = None;
+        for i in start + 1..self.text.len() {
+            match self.text[i] {
+                c if c == open => depth += 1,
+                c if c == close => {
+                    if depth == 0 {
+                        end = Some(i);
+                        break;
+                    }
+                    depth -= 1;
+                }
+                _ => {}
+            }
+        }
+        let end = end?;
+        Some(if around {
+            Span {
+                start,
+                end: end + 1,
+                linewise: false,
+            }
+        } else {
+            Span {
+                start: start + 1,
+                end,
+                linewise: false,
+            }
+        })
+    }
+
+    fn class(&self, pos: usize) -> Class {
+        match self.text.get(pos) {
+            Some(c) if c.is_alphanumeric() || *c == '_' => Class::Word,
+            Some(c) if c.is_whitespace() => Class::Space,
+            Some(_) => Class::Punct,
+            None => Class::Space,
+        }
+    }
+
+    fn next_word_start(&self, mut pos: usize) -> usize {
+        let class = self.class(pos);
+        if class != Class::Space {
+            while pos < self.text.len() && self.class(pos) == class {
+                pos += 1;
+            }
+        }
+        while pos < self.text.len() && self.class(pos) == Class::Space {
+            pos += 1;
+        }
+        pos
+    }
+
+    fn word_end(&self, mut pos: usize) -> usize {
+        pos += 1;
+        while pos < self.text.len() && self.class(pos) == Class::Space {
+            pos += 1;
+        }
+        let class = self.class(pos);
+        while pos + 1 < self.text.len() && self.class(pos + 1) == class {
+            pos += 1;
+        }
+        pos.min(self.text.len().saturating_sub(1))
+    }
+
+    fn prev_word_start(&self, mut pos: usize) -> usize {
+        pos = pos.saturating_sub(1);
+        while pos > 0 && self.class(pos) == Class::Space {
+            pos -= 1;
+        }
+        let class = self.class(pos);
+        while pos > 0 && self.class(pos - 1) == class {
+            pos -= 1;
+        }
+        pos
+    }
+
+    fn paragraph(&self, pos: usize, forward: bool) -> usize {
+        let mut line = self.line_start_of(pos);
+        loop {
+            if forward {
+                let end = self.line_end_of(line);
+                if end >= self.text.len() {
+                    return self.line_last(line);
+                }
+                line = end + 1;
+            } else {
+                if line == 0 {
+                    return 0;
+                }
+                line = self.line_start_of(line - 1);
+            }
+            if self.is_blank_line(line) {
+                return line;
+            }
+        }
+    }
+
+    fn vertical(&self, pos: usize, down: bool) -> usize {
+        let col = pos - self.line_start_of(pos);
+        let start = if down {
+            let end = self.line_end_of(pos);
+            if end >= self.text.len() {
+                return pos;
+            }
+            end + 1
+        } else {
+            let start = self.line_start_of(pos);
+            if start == 0 {
+                return pos;
+            }
+            self.line_start_of(start - 1)
+        };
+        (start + col).min(self.line_last(start))
+    }
+
+    fn is_blank_line(&self, pos: usize) -> bool {
+        self.line_start_of(pos) == self.line_end_of(pos)
+    }
+
+    fn line_start(&self, row: usize) -> usize {
+        let mut pos = 0;
+        for _ in 0..row {
+            pos = self.line_end_of(pos) + 1;
+        }
+        pos
+    }
+
+    fn line_start_of(&self, pos: usize) -> usize {
+        let pos = pos.min(self.text.len());
+        self.text[..pos]
+            .iter()
+            .rposition(|&c| c == '\n')
+            .map_or(0, |i| i + 1)
+    }
+
+    /// Index of the line break ending the line (or the end of the text)
+    fn line_end_of(&self, pos: usize) -> usize {
+        let pos = pos.min(self.text.len());
+        self.text[pos..]
+            .iter()
+            .position(|&c| c == '\n')
+            .map_or(self.text.len(), |i| pos + i)
+    }
+
+    /// Last char of the line, where the cursor rests after `$`
+    fn line_last(&self, pos: usize) -> usize {
+        let start = self.line_start_of(pos);
+        self.line_end_of(pos).saturating_sub(1).max(start)
+    }
+
+    fn first_non_blank(&self, pos: usize) -> usize {
+        let start = self.line_start_of(pos);
+        let end = self.line_end_of(pos);
+        self.text[start..end]
+            .iter()
+            .position(|c| !c.is_whitespace())
+            .map_or(start, |i| start + i)
+    }
+}
+
+/// Text objects the interpreter knows how to select
+const TEXT_OBJECTS: &str = "w()b{}B[]\"'`p";
+
+/// Split a leading count off pending keys; `None` while only digits have been typed
+fn split_count(keys: &str) -> Option<(usize, &str)> {
+    let digits = keys.chars().take_while(char::is_ascii_digit).count();
+    // `0` on its own is the start-of-line motion, never a count
+    if digits == 0 || keys.starts_with('0') {
+        return Some((1, keys));
+    }
+    if digits == keys.len() {
+        return None;
+    }
+    Some((keys[..digits].parse().ok()?, &keys[digits..]))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Word,
+    Punct,
+    Space,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse_key_string;
+
+    fn run(keys: &str) -> Snapshot {
+        simulate(&parse_key_string(keys), Mode::Normal, None)
+            .unwrap()
+            .pop()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_delete_line() {
+        let snapshot = run("dd");
+        assert_eq!(snapshot.lines.len(), SAMPLE.len() - 1);
+        assert_eq!(snapshot.lines[1], SAMPLE[2]);
+        assert_eq!(snapshot.cursor, (1, 4));
+    }
+
+    #[test]
+    fn test_change_inner_word() {
+        let snapshot = run("ciw");
+        assert_eq!(snapshot.lines[1], "    let  = price * qty;");
+        assert_eq!(snapshot.mode, SimMode::Insert);
+        assert_eq!(snapshot.cursor, (1, 8));
+    }
+
+    #[test]
+    fn test_motions_and_counts() {
+        assert_eq!(run("w").cursor, (1, 12));
+        assert_eq!(run("2w").cursor, (1, 14));
+        assert_eq!(run("$").cursor, (1, 25));
+        assert_eq!(run("G").cursor, (5, 0));
+        assert_eq!(run("}").cursor, (3, 0));
+        assert_eq!(run("f*").cursor, (1, 20));
+        assert_eq!(run("3x").lines[1], "    let  = price * qty;");
+        assert_eq!(run("d2w").lines[1], "    let price * qty;");
+    }
+
+    #[test]
+    fn test_frames_and_pending() {
+        let snapshots = simulate(&parse_key_string("di("), Mode::Normal, None).unwrap();
+        assert_eq!(snapshots.len(), 3);
+        assert_eq!(snapshots[1].pending, "di");
+
+        // There are no parentheses around the cursor, so `di(` changes nothing
+        assert_eq!(snapshots[2].lines, SAMPLE);
+
+        let snapshot = run("yyp");
+        assert_eq!(snapshot.lines[2], SAMPLE[1]);
+    }
+
+    #[test]
+    fn test_visual_text_object() {
+        let diagram = Diagram {
+            text: "call(foo, bar)".to_string(),
+            cursor: 6,
+            start: 5,
+            end: 13,
+            caption: None,
+        };
+        let snapshots = simulate(&parse_key_string("i("), Mode::Visual, Some(&diagram)).unwrap();
+        assert_eq!(snapshots.last().unwrap().selection, Some((5, 13)));
+    }
+
+    #[test]
+    fn test_cursor_past_the_diagram() {
+        let diagram = Diagram {
+            text: "foo bar".to_string(),
+            cursor: 7,
+            start: 4,
+            end: 7,
+            caption: None,
+        };
+        let snapshots = simulate(&parse_key_string("x"), Mode::Normal, Some(&diagram)).unwrap();
+        assert_eq!(snapshots[0].lines, ["foo ba"]);
+        assert_eq!(snapshots[0].cursor, (0, 5));
+
+        let empty = Diagram {
+            text: String::new(),
+            ..diagram
+        };
+        let snapshots = simulate(&parse_key_string("l"), Mode::Normal, Some(&empty)).unwrap();
+        assert_eq!(snapshots[0].cursor, (0, 0));
+    }
+
+    #[test]
+    fn test_unsupported_keys() {
+        assert!(simulate(&parse_key_string("<leader>ff"), Mode::Normal, None).is_none());
+        assert!(simulate(&parse_key_string("<C-w>v"), Mode::Normal, None).is_none());
+        assert!(simulate(&parse_key_string("gd"), Mode::Normal, None).is_none());
+    }
+}
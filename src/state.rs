@@ -0,0 +1,475 @@
+use crate::commands::{data_dir, Command, CommandId};
+use crate::search::Query;
+use crate::stats::Stats;
+use anyhow::Context;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Read a JSON file, falling back to the default value if it doesn't exist
+pub(crate) fn read_json<T: DeserializeOwned + Default>(path: &Path) -> anyhow::Result<T> {
+    if !path.exists() {
+        return Ok(T::default());
+    }
+    let data = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&data).with_context(|| format!("failed to parse {}", path.display()))
+}
+
+/// Write a value as pretty JSON, creating parent directories as needed. It goes to a temporary
+/// file first, so that a write cut short leaves the old file whole.
+pub(crate) fn write_json<T: Serialize>(path: &Path, value: &T) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let data = serde_json::to_string_pretty(value)?;
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    std::fs::write(&tmp, data).with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to replace {}", path.display()))
+}
+
+/// How many recently viewed commands are remembered
+const RECENT_LIMIT: usize = 50;
+
+/// Views after which a command gets the whole search boost, see `UserState::boost_results`
+const VIEWS_FOR_FULL_BOOST: usize = 10;
+
+/// How often a command was viewed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ViewCount {
+    #[serde(flatten)]
+    pub id: CommandId,
+    pub count: usize,
+}
+
+/// Per-user data that survives between sessions
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct UserState {
+    #[serde(default)]
+    pub favorites: Vec<CommandId>,
+    /// Commands listed above the results whatever the search, in the order they were pinned
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pinned: Vec<CommandId>,
+    /// Recently viewed commands, most recent first
+    #[serde(default)]
+    pub recent: Vec<CommandId>,
+    /// How often each command was viewed, to rank the usual ones higher
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub view_counts: Vec<ViewCount>,
+    /// Commands left out of search results
+    #[serde(default)]
+    pub hidden: Vec<CommandId>,
+    /// Commands marked as learned
+    #[serde(default)]
+    pub learned: Vec<CommandId>,
+    /// Search queries, oldest first; only kept when `save_history` is enabled
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<String>,
+    /// Where the last session left off
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session: Option<Session>,
+    #[serde(default)]
+    pub stats: Stats,
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+/// The search, view and selection of a session, restored on the next launch
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub query: String,
+    /// Queries narrowed with Ctrl+F, outermost first
+    pub filters: Vec<String>,
+    pub view_mode: ViewMode,
+    pub section: Section,
+    pub list_mode: ListMode,
+    pub sort: SortMode,
+    /// The selected command, unless a category header was selected
+    pub selected: Option<CommandId>,
+}
+
+/// Which keyboards show the selected command
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ViewMode {
+    #[default]
+    Animation,
+    Legend,
+    /// Animation and legend keyboards together, where the terminal has room for both
+    Both,
+}
+
+impl ViewMode {
+    pub fn toggle(&mut self) {
+        *self = self.next();
+    }
+
+    pub(crate) fn next(self) -> Self {
+        match self {
+            ViewMode::Animation => ViewMode::Legend,
+            ViewMode::Legend => ViewMode::Both,
+            ViewMode::Both => ViewMode::Animation,
+        }
+    }
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            ViewMode::Animation => "Animation",
+            ViewMode::Legend => "Legend",
+            ViewMode::Both => "Both",
+        }
+    }
+}
+
+/// How the results list is laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListMode {
+    /// Score-sorted matches
+    #[default]
+    Flat,
+    /// Matches bucketed under category headers
+    Grouped,
+}
+
+/// What the results are ordered by, after the search has picked them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// Best match first, or the order of the command files with nothing typed
+    #[default]
+    Relevance,
+    /// Fewest frames first, then fewest keys held down, keeping the best match first among
+    /// bindings as easy to press
+    Complexity,
+}
+
+/// Which dataset the results list shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Section {
+    #[default]
+    Keymaps,
+    /// `:` commands like `:Lazy`, shown without the keyboard animation
+    ExCommands,
+}
+
+impl Section {
+    pub fn toggle(&mut self) {
+        *self = match self {
+            Section::Keymaps => Section::ExCommands,
+            Section::ExCommands => Section::Keymaps,
+        };
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            Section::Keymaps => "Commands",
+            Section::ExCommands => "Ex commands",
+        }
+    }
+
+    pub(crate) fn contains(&self, cmd: &Command) -> bool {
+        cmd.is_ex() == (*self == Section::ExCommands)
+    }
+}
+
+impl UserState {
+    /// Load state from the default location, starting fresh if it's missing. An unreadable
+    /// file is an error rather than a fresh start, which would overwrite it on exit.
+    pub fn load() -> anyhow::Result<Self> {
+        match data_dir() {
+            Some(dir) => Self::load_from(&dir.join("state.json")),
+            None => Ok(Self::default()),
+        }
+    }
+
+    pub fn load_from(path: &Path) -> anyhow::Result<Self> {
+        let mut state: Self = read_json(path)?;
+        state.path = Some(path.to_path_buf());
+        Ok(state)
+    }
+
+    /// Write state back to where it was loaded from; a no-op for in-memory state
+    pub fn save(&self) -> anyhow::Result<()> {
+        match &self.path {
+            Some(path) => write_json(path, self),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_favorite(&self, cmd: &Command) -> bool {
+        let id = cmd.id();
+        self.favorites.contains(&id)
+    }
+
+    /// Star or unstar a command, returning whether it is now a favorite
+    pub fn toggle_favorite(&mut self, cmd: &Command) -> bool {
+        let id = cmd.id();
+        if let Some(pos) = self.favorites.iter().position(|f| *f == id) {
+            self.favorites.remove(pos);
+            false
+        } else {
+            self.favorites.push(id);
+            true
+        }
+    }
+
+    /// Pin or unpin a command, returning whether it is now pinned; `None` when it isn't and
+    /// `limit` commands are pinned already
+    pub fn toggle_pinned(&mut self, cmd: &Command, limit: usize) -> Option<bool> {
+        let id = cmd.id();
+        if let Some(pos) = self.pinned.iter().position(|p| *p == id) {
+            self.pinned.remove(pos);
+            Some(false)
+        } else if self.pinned.len() < limit {
+            self.pinned.push(id);
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_hidden(&self, cmd: &Command) -> bool {
+        let id = cmd.id();
+        self.hidden.contains(&id)
+    }
+
+    /// Hide or unhide a command, returning whether it is now hidden
+    pub fn toggle_hidden(&mut self, cmd: &Command) -> bool {
+        let id = cmd.id();
+        if let Some(pos) = self.hidden.iter().position(|h| *h == id) {
+            self.hidden.remove(pos);
+            false
+        } else {
+            self.hidden.push(id);
+            true
+        }
+    }
+
+    pub fn is_learned(&self, cmd: &Command) -> bool {
+        let id = cmd.id();
+        self.learned.contains(&id)
+    }
+
+    /// Mark or unmark a command as learned, returning whether it is now learned
+    pub fn toggle_learned(&mut self, cmd: &Command) -> bool {
+        let id = cmd.id();
+        if let Some(pos) = self.learned.iter().position(|l| *l == id) {
+            self.learned.remove(pos);
+            false
+        } else {
+            self.learned.push(id);
+            true
+        }
+    }
+
+    /// Learned and total commands among `commands`
+    pub fn progress<'a>(&self, commands: impl IntoIterator<Item = &'a Command>) -> (usize, usize) {
+        commands.into_iter().fold((0, 0), |(learned, total), cmd| {
+            (learned + usize::from(self.is_learned(cmd)), total + 1)
+        })
+    }
+
+    /// Move a command to the front of the recently viewed list and count the view
+    pub fn record_view(&mut self, cmd: &Command) {
+        let id = cmd.id();
+        match self.view_counts.iter_mut().find(|v| v.id == id) {
+            Some(views) => views.count += 1,
+            None => self.view_counts.push(ViewCount {
+                id: id.clone(),
+                count: 1,
+            }),
+        }
+        self.recent.retain(|r| *r != id);
+        self.recent.insert(0, id);
+        self.recent.truncate(RECENT_LIMIT);
+    }
+
+    /// How many times a command was viewed
+    pub fn view_count(&self, cmd: &Command) -> usize {
+        let id = cmd.id();
+        self.view_counts.iter().find(|v| v.id == id).map_or(0, |v| v.count)
+    }
+
+    /// Raise the scores of favorites and often viewed commands by up to `percent` and sort the
+    /// results again, so the usual lookups come first among close matches. A favorite, or a
+    /// command viewed `VIEWS_FOR_FULL_BOOST` times, gets all of it. An empty query's results
+    /// keep their order.
+    pub fn boost_results(&self, results: &mut [(&Command, i64)], query: &Query, percent: u32) {
+        if percent == 0 || query.text.is_empty() {
+            return;
+        }
+        let full = VIEWS_FOR_FULL_BOOST as i64;
+        for (cmd, score) in results.iter_mut() {
+            let share = if self.is_favorite(cmd) {
+                full
+            } else {
+                self.view_count(cmd).min(VIEWS_FOR_FULL_BOOST) as i64
+            };
+            *score += *score * i64::from(percent) * share / (100 * full);
+        }
+        results.sort_by_key(|r| std::cmp::Reverse(r.1));
+    }
+
+    /// Position in the recently viewed list (0 = most recent)
+    pub fn recent_rank(&self, cmd: &Command) -> Option<usize> {
+        let id = cmd.id();
+        self.recent.iter().position(|r| *r == id)
+    }
+
+    /// Apply the query's `hidden:`, `fav:`, `learned:` and `recent:` filters to search results
+    pub fn filter_results(&self, results: &mut Vec<(&Command, i64)>, query: &Query) {
+        results.retain(|(cmd, _)| self.is_hidden(cmd) == query.hidden);
+        results.retain(|(cmd, _)| !query.favorites || self.is_favorite(cmd));
+        if let Some(learned) = query.learned {
+            results.retain(|(cmd, _)| self.is_learned(cmd) == learned);
+        }
+        if query.recent {
+            results.retain(|(cmd, _)| self.recent_rank(cmd).is_some());
+            results.sort_by_key(|(cmd, _)| self.recent_rank(cmd));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Category, Mode};
+
+    fn command(keys: &str) -> Command {
+        Command {
+            keys: keys.to_string(),
+            description: "Test".to_string(),
+            category: Category::General,
+            mode: Mode::Normal,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_toggle_pinned() {
+        let mut state = UserState::default();
+        let (ff, gg, sg) = (command("<leader>ff"), command("<leader>gg"), command("<leader>sg"));
+        assert_eq!(state.toggle_pinned(&ff, 2), Some(true));
+        assert_eq!(state.toggle_pinned(&gg, 2), Some(true));
+        assert_eq!(state.toggle_pinned(&sg, 2), None);
+        assert_eq!(state.toggle_pinned(&ff, 2), Some(false));
+        assert_eq!(state.toggle_pinned(&sg, 2), Some(true));
+        assert_eq!(state.pinned, [gg.id(), sg.id()]);
+    }
+
+    #[test]
+    fn test_toggle_favorite() {
+        let mut state = UserState::default();
+        let cmd = command("gd");
+
+        assert!(state.toggle_favorite(&cmd));
+        assert!(state.is_favorite(&cmd));
+        assert!(!state.toggle_favorite(&cmd));
+        assert!(!state.is_favorite(&cmd));
+    }
+
+    #[test]
+    fn test_toggle_hidden() {
+        let mut state = UserState::default();
+        let cmd = command("<leader>gg");
+
+        assert!(state.toggle_hidden(&cmd));
+        assert!(state.is_hidden(&cmd));
+        assert!(!state.toggle_hidden(&cmd));
+        assert!(state.hidden.is_empty());
+    }
+
+    #[test]
+    fn test_learned() {
+        let mut state = UserState::default();
+        let commands = [command("gd"), command("gr"), command("K")];
+        assert!(state.toggle_learned(&commands[0]));
+        assert!(state.is_learned(&commands[0]));
+        assert_eq!(state.progress(&commands), (1, 3));
+
+        let mut results: Vec<(&Command, i64)> = commands.iter().map(|cmd| (cmd, 0)).collect();
+        state.filter_results(&mut results, &Query::parse("unlearned:"));
+        let keys: Vec<&str> = results.iter().map(|(cmd, _)| cmd.keys.as_str()).collect();
+        assert_eq!(keys, ["gr", "K"]);
+
+        assert!(!state.toggle_learned(&commands[0]));
+        assert_eq!(state.progress(&commands), (0, 3));
+    }
+
+    #[test]
+    fn test_record_view() {
+        let mut state = UserState::default();
+        state.record_view(&command("gd"));
+        state.record_view(&command("K"));
+        state.record_view(&command("gd"));
+
+        assert_eq!(state.recent.len(), 2);
+        assert_eq!(state.recent_rank(&command("gd")), Some(0));
+        assert_eq!(state.recent_rank(&command("K")), Some(1));
+        assert_eq!(state.recent_rank(&command("gr")), None);
+
+        for i in 0..RECENT_LIMIT + 5 {
+            state.record_view(&command(&i.to_string()));
+        }
+        assert_eq!(state.recent.len(), RECENT_LIMIT);
+    }
+
+    #[test]
+    fn test_boost_results() {
+        let mut state = UserState::default();
+        let commands = [command("gd"), command("gr"), command("gI")];
+        for _ in 0..VIEWS_FOR_FULL_BOOST {
+            state.record_view(&commands[2]);
+        }
+        state.toggle_favorite(&commands[1]);
+        assert_eq!(state.view_count(&commands[2]), VIEWS_FOR_FULL_BOOST);
+
+        let ranked = |percent, text: &str| {
+            let mut results = vec![(&commands[0], 100), (&commands[1], 95), (&commands[2], 80)];
+            state.boost_results(&mut results, &Query::parse(text), percent);
+            results.iter().map(|(cmd, score)| (cmd.keys.as_str(), *score)).collect::<Vec<_>>()
+        };
+        assert_eq!(ranked(10, "g"), [("gr", 104), ("gd", 100), ("gI", 88)]);
+        assert_eq!(ranked(0, "g"), [("gd", 100), ("gr", 95), ("gI", 80)]);
+        assert_eq!(ranked(10, ""), [("gd", 100), ("gr", 95), ("gI", 80)]);
+    }
+
+    #[test]
+    fn test_save_and_reload() {
+        let path = std::env::temp_dir()
+            .join(format!("lvcheat-state-{}", std::process::id()))
+            .join("state.json");
+
+        let mut state = UserState::load_from(&path).unwrap();
+        state.toggle_favorite(&command("<leader>ff"));
+        state.save().unwrap();
+
+        let reloaded = UserState::load_from(&path).unwrap();
+        assert!(reloaded.is_favorite(&command("<leader>ff")));
+        assert_eq!(reloaded.session, None);
+
+        let mut state = reloaded;
+        let session = Session {
+            query: "#git".to_string(),
+            filters: vec!["fav:".to_string()],
+            view_mode: ViewMode::Legend,
+            selected: Some(command("<leader>gg").id()),
+            ..Session::default()
+        };
+        state.session = Some(session.clone());
+        state.save().unwrap();
+        assert_eq!(UserState::load_from(&path).unwrap().session, Some(session));
+        assert!(!path.with_extension("json.tmp").exists());
+
+        // A file that doesn't parse is reported, not taken for an empty state
+        std::fs::write(&path, "{\"favorites\": [").unwrap();
+        assert!(UserState::load_from(&path).is_err());
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,440 @@
+use crate::commands::{Category, CategoryLayout, CommandId};
+use crate::popup::centered_rect;
+use crate::ui;
+use ratatui::{
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Bar, BarChart, BarGroup, Block, Borders, Clear, Paragraph},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Days shown in the dashboard's daily chart
+const CHART_DAYS: u64 = 14;
+/// Categories shown in the dashboard's category chart
+const CHART_CATEGORIES: usize = 8;
+/// Challenge results kept in the high score table
+const HIGH_SCORES: usize = 10;
+/// Days before a binding comes up for review again, by its box: a miss puts it in the first,
+/// and each right answer moves it on to the next
+const REVIEW_DAYS: &[u64] = &[0, 1, 3, 7, 16, 35];
+/// How likely a binding never asked for is to come up, next to one due in its last box (1)
+const NEW_WEIGHT: u32 = 4;
+
+/// Local usage counters, kept with the rest of the user state and never sent anywhere
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    /// Commands viewed, by day (days since the Unix epoch, UTC) and category
+    pub days: BTreeMap<u64, BTreeMap<Category, usize>>,
+    /// Best timed challenge results of each direction, highest first within it
+    pub high_scores: Vec<HighScore>,
+    /// When each binding asked for in a challenge is next due, in each direction
+    pub reviews: Vec<Review>,
+}
+
+/// Where a binding stands in the spaced review of one direction of the challenge
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Review {
+    #[serde(flatten)]
+    pub id: CommandId,
+    /// Asked for in reverse, by its keys
+    #[serde(default)]
+    pub reverse: bool,
+    /// Index into [`REVIEW_DAYS`]
+    pub level: usize,
+    /// Day it's next due
+    pub due: u64,
+}
+
+/// One timed challenge round
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HighScore {
+    pub score: u32,
+    /// Bindings typed right
+    pub hits: usize,
+    /// Share of keypresses that were right, in percent
+    pub accuracy: u32,
+    pub day: u64,
+    /// Played in reverse, picking what keys do; ranked apart from rounds typing the keys
+    #[serde(default)]
+    pub reverse: bool,
+}
+
+impl Stats {
+    pub fn record_view(&mut self, category: Category, day: u64) {
+        *self.days.entry(day).or_default().entry(category).or_default() += 1;
+    }
+
+    pub fn views_on(&self, day: u64) -> usize {
+        self.days.get(&day).map_or(0, |counts| counts.values().sum())
+    }
+
+    pub fn total_views(&self) -> usize {
+        self.days.values().flat_map(|counts| counts.values()).sum()
+    }
+
+    /// Consecutive days with a lookup, ending today. A streak that ended yesterday
+    /// still counts, so it doesn't read 0 before the first lookup of the day.
+    pub fn streak(&self, today: u64) -> usize {
+        let start = if self.days.contains_key(&today) {
+            today
+        } else {
+            today.saturating_sub(1)
+        };
+        (0..=start)
+            .rev()
+            .take_while(|day| self.days.contains_key(day))
+            .count()
+    }
+
+    /// Longest run of consecutive days with a lookup
+    pub fn best_streak(&self) -> usize {
+        let mut best = 0;
+        let mut run = 0;
+        let mut previous = None;
+        for &day in self.days.keys() {
+            run = if previous == Some(day.wrapping_sub(1)) { run + 1 } else { 1 };
+            best = best.max(run);
+            previous = Some(day);
+        }
+        best
+    }
+
+    /// Views per category from `since` on, most viewed first
+    pub fn categories(&self, since: u64) -> Vec<(Category, usize)> {
+        let mut totals: BTreeMap<Category, usize> = BTreeMap::new();
+        for counts in self.days.range(since..).map(|(_, counts)| counts) {
+            for (category, &count) in counts {
+                *totals.entry(category.clone()).or_default() += count;
+            }
+        }
+        let mut totals: Vec<_> = totals.into_iter().collect();
+        totals.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+        totals
+    }
+
+    /// Best results of the rounds played in one direction, highest first
+    pub fn high_scores(&self, reverse: bool) -> impl Iterator<Item = &HighScore> {
+        self.high_scores.iter().filter(move |best| best.reverse == reverse)
+    }
+
+    /// Add a challenge result if it's among the best of its direction; its place in that
+    /// direction's table if so, from 0
+    /// Move a binding's review in one direction on after a right answer on `day`, or back to the
+    /// start after a miss or a skip
+    pub fn record_review(&mut self, id: CommandId, reverse: bool, right: bool, day: u64) {
+        let at = self.reviews.iter().position(|r| r.id == id && r.reverse == reverse);
+        let review = match at {
+            Some(at) => &mut self.reviews[at],
+            None => {
+                self.reviews.push(Review {
+                    id,
+                    reverse,
+                    level: 0,
+                    due: day,
+                });
+                self.reviews.last_mut().unwrap()
+            }
+        };
+        review.level = if right { (review.level + 1).min(REVIEW_DAYS.len() - 1) } else { 0 };
+        review.due = day + REVIEW_DAYS[review.level];
+    }
+
+    /// How likely a binding is to come up next in one direction of the challenge: most when
+    /// it's due and was missed lately, least when it isn't due yet
+    pub fn review_weight(&self, id: &CommandId, reverse: bool, today: u64) -> u32 {
+        match self.reviews.iter().find(|r| r.id == *id && r.reverse == reverse) {
+            None => NEW_WEIGHT,
+            Some(review) if review.due <= today => 2 * (REVIEW_DAYS.len() - review.level) as u32,
+            Some(_) => 1,
+        }
+    }
+
+    pub fn record_score(&mut self, score: HighScore) -> Option<usize> {
+        // Positions in the whole list of the scores in the same direction
+        let same: Vec<usize> = (0..self.high_scores.len())
+            .filter(|&i| self.high_scores[i].reverse == score.reverse)
+            .collect();
+        // Ties go to the earlier round
+        let rank = same
+            .iter()
+            .position(|&i| self.high_scores[i].score < score.score)
+            .unwrap_or(same.len());
+        if rank >= HIGH_SCORES {
+            return None;
+        }
+        let at = same.get(rank).copied().unwrap_or(self.high_scores.len());
+        self.high_scores.insert(at, score);
+        // The direction's table had room for one less
+        if same.len() == HIGH_SCORES {
+            let last = same[HIGH_SCORES - 1] + 1;
+            self.high_scores.remove(last);
+        }
+        Some(rank)
+    }
+}
+
+/// Period the dashboard's category chart covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StatsRange {
+    #[default]
+    Week,
+    Month,
+    AllTime,
+}
+
+impl StatsRange {
+    pub fn next(self) -> Self {
+        match self {
+            StatsRange::Week => StatsRange::Month,
+            StatsRange::Month => StatsRange::AllTime,
+            StatsRange::AllTime => StatsRange::Week,
+        }
+    }
+
+    pub fn previous(self) -> Self {
+        self.next().next()
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            StatsRange::Week => "last 7 days",
+            StatsRange::Month => "last 30 days",
+            StatsRange::AllTime => "all time",
+        }
+    }
+
+    /// First day in the range
+    fn since(self, today: u64) -> u64 {
+        match self {
+            StatsRange::Week => today.saturating_sub(6),
+            StatsRange::Month => today.saturating_sub(29),
+            StatsRange::AllTime => 0,
+        }
+    }
+}
+
+/// Days since the Unix epoch, UTC
+pub fn today() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs() / 86_400)
+}
+
+/// Month and day of a day number, e.g. `(10, 16)`
+pub fn month_day(day: u64) -> (u64, u64) {
+    // Days to civil date, shifted so years start in March (Howard Hinnant's algorithm)
+    let z = day + 719_468;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    (m, d)
+}
+
+/// Draw the stats dashboard over the screen
+pub fn draw_dashboard(
+    frame: &mut Frame,
+    stats: &Stats,
+    range: StatsRange,
+    today: u64,
+    layout: &CategoryLayout,
+) {
+    let area = centered_rect(72, 28, frame.area());
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(" Stats ");
+    let inner = block.inner(area);
+    frame.render_widget(Clear, area);
+    frame.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Length(10),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .split(inner);
+
+    let dim = Style::default().fg(Color::DarkGray);
+    let summary = Line::from(vec![
+        Span::styled("Streak: ", dim),
+        Span::raw(format!("{} days", stats.streak(today))),
+        Span::styled(format!(" (best {})", stats.best_streak()), dim),
+        Span::styled(" · Today: ", dim),
+        Span::raw(stats.views_on(today).to_string()),
+        Span::styled(" · All time: ", dim),
+        Span::raw(stats.total_views().to_string()),
+        Span::styled(" commands viewed", dim),
+    ]);
+    frame.render_widget(Paragraph::new(summary), chunks[0]);
+
+    let days: Vec<Bar> = (today.saturating_sub(CHART_DAYS - 1)..=today)
+        .map(|day| {
+            let style = if day == today { Color::Yellow } else { Color::Cyan };
+            Bar::default()
+                .value(stats.views_on(day) as u64)
+                .label(Line::from(format!("{:02}", month_day(day).1)))
+                .style(Style::default().fg(style))
+        })
+        .collect();
+    let (month, day) = month_day(today);
+    let daily = BarChart::default()
+        .block(Block::default().title(format!(
+            "Viewed per day, through {:02}-{:02}",
+            month, day
+        )))
+        .data(BarGroup::default().bars(&days))
+        .bar_width(3)
+        .bar_gap(1);
+    frame.render_widget(daily, chunks[1]);
+
+    let categories = stats.categories(range.since(today));
+    let bars: Vec<Bar> = categories
+        .iter()
+        .take(CHART_CATEGORIES)
+        .map(|(category, count)| {
+            Bar::default()
+                .value(*count as u64)
+                .label(Line::from(format!("{:<10}", layout.name(category))))
+                .style(Style::default().fg(ui::color(layout.color(category))))
+        })
+        .collect();
+    let title = format!("Most looked-up categories, {}", range.label());
+    if bars.is_empty() {
+        let empty = Paragraph::new(Span::styled("Nothing viewed yet", dim))
+            .block(Block::default().title(title));
+        frame.render_widget(empty, chunks[2]);
+    } else {
+        let by_category = BarChart::default()
+            .block(Block::default().title(title))
+            .direction(Direction::Horizontal)
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(1)
+            .bar_gap(0);
+        frame.render_widget(by_category, chunks[2]);
+    }
+
+    frame.render_widget(
+        Paragraph::new(Span::styled("←→: category range · Esc: close", dim)),
+        chunks[3],
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streaks() {
+        let mut stats = Stats::default();
+        for day in [10, 11, 12, 20, 21] {
+            stats.record_view(Category::Git, day);
+        }
+        stats.record_view(Category::Lsp, 21);
+
+        assert_eq!(stats.streak(21), 2);
+        // Still running until a day passes without a lookup
+        assert_eq!(stats.streak(22), 2);
+        assert_eq!(stats.streak(23), 0);
+        assert_eq!(stats.best_streak(), 3);
+        assert_eq!(stats.views_on(21), 2);
+        assert_eq!(stats.total_views(), 6);
+    }
+
+    #[test]
+    fn test_categories() {
+        let mut stats = Stats::default();
+        stats.record_view(Category::Lsp, 1);
+        stats.record_view(Category::Lsp, 1);
+        stats.record_view(Category::Git, 1);
+        stats.record_view(Category::Git, 30);
+        stats.record_view(Category::Git, 31);
+
+        assert_eq!(stats.categories(0), [(Category::Git, 3), (Category::Lsp, 2)]);
+        assert_eq!(StatsRange::Week.since(31), 25);
+        assert_eq!(stats.categories(25), [(Category::Git, 2)]);
+    }
+
+    #[test]
+    fn test_reviews() {
+        let id = |keys: &str| CommandId {
+            keys: keys.to_string(),
+            mode: Default::default(),
+            source: None,
+        };
+        let mut stats = Stats::default();
+        assert_eq!(stats.review_weight(&id("gd"), false, 10), NEW_WEIGHT);
+
+        stats.record_review(id("gd"), false, true, 10);
+        stats.record_review(id("gd"), false, true, 11);
+        assert_eq!((stats.reviews[0].level, stats.reviews[0].due), (2, 14));
+        assert_eq!(stats.review_weight(&id("gd"), false, 13), 1);
+        assert_eq!(stats.review_weight(&id("gd"), false, 14), 8);
+        // The other direction is scheduled on its own
+        assert_eq!(stats.review_weight(&id("gd"), true, 14), NEW_WEIGHT);
+
+        // A miss is due again the same day, ahead of everything else
+        stats.record_review(id("gd"), false, false, 14);
+        assert_eq!((stats.reviews[0].level, stats.reviews[0].due), (0, 14));
+        assert_eq!(stats.review_weight(&id("gd"), false, 14), 12);
+        assert_eq!(stats.reviews.len(), 1);
+    }
+
+    #[test]
+    fn test_record_score() {
+        let score = |score| HighScore {
+            score,
+            hits: 1,
+            accuracy: 100,
+            day: 1,
+            reverse: false,
+        };
+        let mut stats = Stats::default();
+        assert_eq!(stats.record_score(score(50)), Some(0));
+        assert_eq!(stats.record_score(score(80)), Some(0));
+        assert_eq!(stats.record_score(score(50)), Some(2));
+        for _ in 0..7 {
+            stats.record_score(score(60));
+        }
+        assert_eq!(stats.high_scores.len(), HIGH_SCORES);
+        // The table is full of better scores
+        assert_eq!(stats.record_score(score(40)), None);
+        assert_eq!(stats.record_score(score(70)), Some(1));
+        assert_eq!(stats.high_scores.last().unwrap().score, 50);
+
+        // Reverse rounds have a table of their own
+        let reverse = HighScore {
+            reverse: true,
+            ..score(10)
+        };
+        assert_eq!(stats.record_score(reverse), Some(0));
+        assert_eq!(stats.high_scores(false).count(), HIGH_SCORES);
+        assert_eq!(stats.high_scores(true).count(), 1);
+        assert_eq!(stats.record_score(score(90)), Some(0));
+        assert_eq!(stats.high_scores(false).last().unwrap().score, 60);
+        assert_eq!(stats.high_scores(true).next().unwrap().score, 10);
+
+        // Scores saved before rounds had a direction were typed
+        let old: HighScore =
+            serde_json::from_str(r#"{"score": 5, "hits": 1, "accuracy": 90, "day": 2}"#).unwrap();
+        assert!(!old.reverse);
+    }
+
+    #[test]
+    fn test_month_day() {
+        assert_eq!(month_day(0), (1, 1));
+        // 2024-02-29 and 2026-10-16
+        assert_eq!(month_day(19_782), (2, 29));
+        assert_eq!(month_day(20_742), (10, 16));
+    }
+}
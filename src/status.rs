@@ -0,0 +1,131 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// How long each message stays in the status bar
+const MESSAGE_TIME: Duration = Duration::from_millis(2500);
+/// Messages waiting beyond this many are dropped, oldest first, so the bar never lags far
+/// behind what was just done
+const MAX_QUEUED: usize = 3;
+
+/// Short-lived messages for the status bar, shown one after another
+#[derive(Debug, Clone, Default)]
+pub struct Messages {
+    queue: VecDeque<String>,
+    /// When the front message went up, once it has
+    shown_at: Option<Instant>,
+}
+
+impl Messages {
+    pub fn push(&mut self, message: impl Into<String>) {
+        self.queue.push_back(message.into());
+        // Keep the one showing, drop the stalest of the rest
+        while self.queue.len() > MAX_QUEUED {
+            self.queue.remove(1);
+        }
+    }
+
+    /// Time out the message showing and put up the next one
+    pub fn tick(&mut self, now: Instant) {
+        if self
+            .shown_at
+            .is_some_and(|at| now.saturating_duration_since(at) >= MESSAGE_TIME)
+        {
+            self.queue.pop_front();
+            self.shown_at = None;
+        }
+        if self.shown_at.is_none() && !self.queue.is_empty() {
+            self.shown_at = Some(now);
+        }
+    }
+
+    pub fn current(&self) -> Option<&str> {
+        self.queue.front().map(String::as_str)
+    }
+}
+
+/// Spinner frames, one per [`SPINNER_STEP`]
+const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+const SPINNER_STEP: Duration = Duration::from_millis(100);
+
+/// Sources still loading in the background, counted off as they're merged in
+#[derive(Debug, Clone, Default)]
+pub struct Progress {
+    done: usize,
+    total: usize,
+    started: Option<Instant>,
+}
+
+impl Progress {
+    pub fn new(total: usize, now: Instant) -> Self {
+        Self {
+            done: 0,
+            total,
+            started: Some(now),
+        }
+    }
+
+    /// Count off a source that was merged in
+    pub fn advance(&mut self) {
+        self.done = (self.done + 1).min(self.total);
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done >= self.total
+    }
+
+    /// A spinner and how many sources are in, like `⠙ loading 1/3`, until they all are
+    pub fn label(&self, now: Instant) -> Option<String> {
+        let started = self.started.filter(|_| !self.is_done())?;
+        let step = now.saturating_duration_since(started).as_millis() / SPINNER_STEP.as_millis();
+        let spinner = SPINNER[step as usize % SPINNER.len()];
+        Some(format!("{} loading {}/{}", spinner, self.done, self.total))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_messages() {
+        let start = Instant::now();
+        let mut messages = Messages::default();
+        assert_eq!(messages.current(), None);
+
+        messages.push("Saved favorite");
+        messages.push("Marked for export");
+        messages.tick(start);
+        assert_eq!(messages.current(), Some("Saved favorite"));
+        messages.tick(start + MESSAGE_TIME / 2);
+        assert_eq!(messages.current(), Some("Saved favorite"));
+        messages.tick(start + MESSAGE_TIME);
+        assert_eq!(messages.current(), Some("Marked for export"));
+        messages.tick(start + MESSAGE_TIME * 2);
+        assert_eq!(messages.current(), None);
+
+        for i in 0..5 {
+            messages.push(i.to_string());
+        }
+        let mut shown = Vec::new();
+        for i in 0..5 {
+            messages.tick(start + MESSAGE_TIME * (3 + i));
+            shown.extend(messages.current().map(str::to_string));
+        }
+        assert_eq!(shown, ["0", "3", "4"]);
+    }
+
+    #[test]
+    fn test_progress() {
+        let start = Instant::now();
+        assert_eq!(Progress::default().label(start), None);
+
+        let mut progress = Progress::new(2, start);
+        assert_eq!(progress.label(start).as_deref(), Some("⠋ loading 0/2"));
+        progress.advance();
+        assert_eq!(progress.label(start + SPINNER_STEP).as_deref(), Some("⠙ loading 1/2"));
+        progress.advance();
+        progress.advance();
+        assert!(progress.is_done());
+        assert_eq!(progress.label(start), None);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::commands::KeyFrame;
+use crate::keyboard::{Keyboard, KeyboardState};
+use crate::theme;
+use ratatui::{style::Color, text::Span};
+use std::fmt::Write;
+
+/// Pixels per character column of the keyboard art
+const CELL_WIDTH: f32 = 10.0;
+/// Pixels per line of the keyboard art
+const LINE_HEIGHT: f32 = 14.0;
+const MARGIN: f32 = 12.0;
+const TITLE_HEIGHT: f32 = 24.0;
+const FOOTER_HEIGHT: f32 = 32.0;
+
+/// One image with every frame lit in its legend color and the sequence spelled out below
+pub fn legend_svg(title: &str, frames: &[KeyFrame], keyboard: &Keyboard) -> String {
+    let state = KeyboardState::legend(frame_ids(keyboard, frames)).palette(keyboard.palette);
+    let pills: Vec<(String, Color)> = frames
+        .iter()
+        .enumerate()
+        .map(|(i, kf)| {
+            let text = match &kf.note {
+                Some(note) => format!("[{}]", note),
+                None => kf.label(),
+            };
+            (text, keyboard.palette.frame_color(i))
+        })
+        .collect();
+    render(keyboard, title, &state, "Sequence:", &pills)
+}
+
+/// One image per frame, lit like the animation view in the frame's legend color
+pub fn frame_svgs(title: &str, frames: &[KeyFrame], keyboard: &Keyboard) -> Vec<String> {
+    frame_ids(keyboard, frames)
+        .into_iter()
+        .zip(frames)
+        .enumerate()
+        .map(|(i, (ids, kf))| {
+            let caption = format!("Frame {}/{}:", i + 1, frames.len());
+            let text = kf.note.clone().unwrap_or_else(|| kf.label());
+            let state = KeyboardState::frame(ids, i).palette(keyboard.palette);
+            let color = keyboard.palette.frame_color(i);
+            render(keyboard, title, &state, &caption, &[(text, color)])
+        })
+        .collect()
+}
+
+fn frame_ids(keyboard: &Keyboard, frames: &[KeyFrame]) -> Vec<Vec<&'static str>> {
+    frames.iter().map(|kf| keyboard.frame_ids(kf)).collect()
+}
+
+/// Width of the keyboard art in columns and lines, without the surrounding block: up to the
+/// border right of the widest row and the one under the last
+fn art_size(keyboard: &Keyboard) -> (f32, f32) {
+    let positions = keyboard.key_positions.values();
+    let columns = positions.clone().map(|pos| pos.col + pos.width + 1).max();
+    let lines = positions.map(|pos| pos.row + 2).max();
+    (columns.unwrap_or(0) as f32, lines.unwrap_or(0) as f32)
+}
+
+fn render(
+    keyboard: &Keyboard,
+    title: &str,
+    state: &KeyboardState,
+    caption: &str,
+    pills: &[(String, Color)],
+) -> String {
+    let (columns, lines) = art_size(keyboard);
+    let width = columns * CELL_WIDTH + 2.0 * MARGIN;
+    let height = TITLE_HEIGHT + lines * LINE_HEIGHT + FOOTER_HEIGHT + 2.0 * MARGIN;
+    let mut svg = String::new();
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}" font-family="monospace" font-size="11">"#,
+        w = width,
+        h = height
+    );
+    let _ = writeln!(
+        svg,
+        r##"<rect width="100%" height="100%" fill="#ffffff"/>"##
+    );
+    let _ = writeln!(
+        svg,
+        r##"<text x="{}" y="{}" font-size="14" font-weight="bold" fill="#222222">{}</text>"##,
+        MARGIN,
+        MARGIN + 14.0,
+        escape(title)
+    );
+
+    let colors = state.key_colors();
+    let shifted = state
+        .shifted
+        .unwrap_or(colors.contains_key("shift") || colors.contains_key("rshift"));
+    let top = MARGIN + TITLE_HEIGHT;
+
+    // Sorted so the output is stable
+    let mut keys: Vec<_> = keyboard.key_positions.iter().collect();
+    keys.sort_by_key(|(_, pos)| (pos.row, pos.col));
+    for (id, pos) in keys {
+        // From the middle of the left border to the middle of the right one
+        let x = MARGIN + (pos.col as f32 - 0.5) * CELL_WIDTH + 1.5;
+        let y = top + (pos.row as f32 - 0.5) * LINE_HEIGHT + 1.5;
+        let w = (pos.width as f32 + 1.0) * CELL_WIDTH - 3.0;
+        let h = 2.0 * LINE_HEIGHT - 3.0;
+        let fill = colors.get(id).map_or("#f2f2f2".to_string(), |&c| hex(c));
+        let label = if shifted { pos.shifted } else { pos.label };
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{}" y="{}" width="{}" height="{}" rx="3" fill="{}" stroke="#888888"/>"##,
+            x, y, w, h, fill
+        );
+        let _ = writeln!(
+            svg,
+            r##"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central" fill="#111111">{}</text>"##,
+            x + w / 2.0,
+            y + h / 2.0,
+            escape(label)
+        );
+    }
+
+    let footer_y = top + lines * LINE_HEIGHT + FOOTER_HEIGHT / 2.0;
+    let _ = writeln!(
+        svg,
+        r##"<text x="{}" y="{}" dominant-baseline="central" fill="#555555">{}</text>"##,
+        MARGIN,
+        footer_y,
+        escape(caption)
+    );
+    let mut x = MARGIN + (Span::raw(caption).width() as f32 + 1.0) * 7.0;
+    for (text, color) in pills {
+        // Terminal columns, so wide characters get room for two
+        let w = (Span::raw(text.as_str()).width() as f32 + 2.0) * 7.0;
+        let _ = writeln!(
+            svg,
+            r##"<rect x="{}" y="{}" width="{}" height="18" rx="9" fill="{}"/>"##,
+            x,
+            footer_y - 9.0,
+            w,
+            hex(*color)
+        );
+        let _ = writeln!(
+            svg,
+            r##"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central" fill="#000000">{}</text>"##,
+            x + w / 2.0,
+            footer_y,
+            escape(text)
+        );
+        x += w + 6.0;
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn hex(color: Color) -> String {
+    let (r, g, b) = theme::rgb(color);
+    format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse_key_string;
+    use crate::ui::{KEYBOARD_HEIGHT, KEYBOARD_WIDTH};
+
+    #[test]
+    fn test_legend_svg() {
+        let svg = legend_svg("<C-w>v", &parse_key_string("<C-w>v"), &Keyboard::new());
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.trim_end().ends_with("</svg>"));
+        assert!(svg.contains("&lt;C-w&gt;v"));
+        // Ctrl and w in the first frame color, v in the second
+        assert_eq!(svg.matches(r##"fill="#e5e510" stroke"##).count(), 2);
+        assert_eq!(svg.matches(r##"fill="#0dbc79" stroke"##).count(), 1);
+        assert!(svg.contains(">Ctrl+W</text>"));
+    }
+
+    #[test]
+    fn test_art_size() {
+        // The art the keyboard widget draws inside its block
+        let (columns, lines) = (KEYBOARD_WIDTH - 2, KEYBOARD_HEIGHT - 2);
+        assert_eq!(art_size(&Keyboard::new()), (columns as f32, lines as f32));
+    }
+
+    #[test]
+    fn test_frame_svgs() {
+        let frames = parse_key_string("<leader>P");
+        let svgs = frame_svgs("<leader>P", &frames, &Keyboard::new());
+        assert_eq!(svgs.len(), 2);
+        assert!(svgs[0].contains("Frame 1/2:"));
+        // Space lit in the first frame's color, then Shift + p in the second frame's, which
+        // shows shifted labels
+        assert!(svgs[0].contains(r##"fill="#e5e510" stroke"##));
+        assert!(svgs[1].contains(r##"fill="#0dbc79" stroke"##));
+        assert!(svgs[1].contains(">!</text>"));
+        assert!(!svgs[0].contains(">!</text>"));
+    }
+}
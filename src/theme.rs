@@ -0,0 +1,312 @@
+use crate::commands;
+use ratatui::{
+    buffer::Buffer,
+    style::{Color, Modifier},
+};
+use serde::Deserialize;
+
+/// How the drawn screen is colored, applied as a last pass over the frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    #[default]
+    Default,
+    /// Bright colors only, with dark grays lifted to white
+    HighContrast,
+    /// No color at all: highlights become reverse video (`NO_COLOR`, `--no-color`)
+    Monochrome,
+}
+
+impl Theme {
+    pub fn is_monochrome(self) -> bool {
+        self == Theme::Monochrome
+    }
+
+    /// Restyle every cell of a finished frame
+    pub fn apply(self, buf: &mut Buffer) {
+        if self == Theme::Default {
+            return;
+        }
+        let area = buf.area;
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = &mut buf[(x, y)];
+                match self {
+                    Theme::Default => {}
+                    Theme::HighContrast => {
+                        if is_set(cell.bg) {
+                            // Selection and chips: bright background, black text
+                            cell.bg = bright(cell.bg);
+                            cell.fg = Color::Black;
+                        } else if is_set(cell.fg) {
+                            cell.fg = bright(cell.fg);
+                        }
+                    }
+                    Theme::Monochrome => {
+                        if is_set(cell.bg) {
+                            cell.modifier.insert(Modifier::REVERSED);
+                        }
+                        cell.fg = Color::Reset;
+                        cell.bg = Color::Reset;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// How many colors the terminal can show
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+pub enum ColorSupport {
+    /// Decide from `COLORTERM` and `TERM`
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    /// 256 colors or truecolor: everything is drawn as is
+    #[serde(rename = "full")]
+    Full,
+    /// 16 colors, where a dark gray background can be the same as black
+    #[serde(rename = "16")]
+    Ansi16,
+    /// 8 colors, as on the Linux console: no bright variants at all
+    #[serde(rename = "8")]
+    Ansi8,
+}
+
+impl ColorSupport {
+    /// Replace `Auto` with what the environment says
+    pub fn resolve(self) -> Self {
+        match self {
+            ColorSupport::Auto => Self::from_env(
+                std::env::var("COLORTERM").ok().as_deref(),
+                std::env::var("TERM").ok().as_deref(),
+            ),
+            other => other,
+        }
+    }
+
+    fn from_env(colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if matches!(colorterm, Some("truecolor" | "24bit")) {
+            return ColorSupport::Full;
+        }
+        match term.unwrap_or_default() {
+            term if term.contains("256color") || term.contains("direct") => ColorSupport::Full,
+            "linux" | "ansi" | "cons25" | "dumb" | "" => ColorSupport::Ansi8,
+            term if term.starts_with("vt") => ColorSupport::Ansi8,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    /// Swap colors the terminal can't show for ones it can
+    pub fn apply(self, buf: &mut Buffer) {
+        if matches!(self, ColorSupport::Auto | ColorSupport::Full) {
+            return;
+        }
+        let area = buf.area;
+        for y in area.top()..area.bottom() {
+            for x in area.left()..area.right() {
+                let cell = &mut buf[(x, y)];
+                let (fg, bg) = (cell.fg, cell.bg);
+                cell.fg = named(fg);
+                cell.bg = named(bg);
+                // The grays are whatever the terminal's own palette makes them, often the same as
+                // its background, light or dark: selection rows turn blue, and dim text takes the
+                // default foreground, faint where the terminal can
+                if is_neutral(bg) {
+                    cell.bg = Color::Blue;
+                } else if !is_set(bg) && is_neutral(fg) {
+                    cell.fg = Color::Reset;
+                    if fg == Color::DarkGray {
+                        cell.modifier.insert(Modifier::DIM);
+                    }
+                }
+                if self == ColorSupport::Ansi8 {
+                    cell.fg = dim(cell.fg);
+                    cell.bg = dim(cell.bg);
+                }
+            }
+        }
+    }
+}
+
+/// Terminal colors as the usual xterm-like palette, for output drawn outside the terminal
+pub fn rgb(color: Color) -> (u8, u8, u8) {
+    match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Black => (0x00, 0x00, 0x00),
+        Color::Red => (0xcd, 0x31, 0x31),
+        Color::Green => (0x0d, 0xbc, 0x79),
+        Color::Yellow => (0xe5, 0xe5, 0x10),
+        Color::Blue => (0x24, 0x72, 0xc8),
+        Color::Magenta => (0xbc, 0x3f, 0xbc),
+        Color::Cyan => (0x11, 0xa8, 0xcd),
+        Color::Gray => (0xd0, 0xd0, 0xd0),
+        Color::DarkGray => (0x66, 0x66, 0x66),
+        Color::LightRed => (0xf1, 0x4c, 0x4c),
+        Color::LightGreen => (0x23, 0xd1, 0x8b),
+        Color::LightYellow => (0xf5, 0xf5, 0x43),
+        Color::LightBlue => (0x3b, 0x8e, 0xea),
+        Color::LightMagenta => (0xd6, 0x70, 0xd6),
+        Color::LightCyan => (0x29, 0xb8, 0xdb),
+        Color::Indexed(index) => indexed_rgb(index),
+        Color::White | Color::Reset => (0xff, 0xff, 0xff),
+    }
+}
+
+/// The sixteen named colors, in the order of their ANSI indices
+const NAMED: &[Color] = &[
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::Gray,
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::LightYellow,
+    Color::LightBlue,
+    Color::LightMagenta,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// Named stand-ins for indexed colors the app picks itself, where the nearest named color
+/// would clash: the Recipes orange is nearest light red, which is Git's
+const INDEXED_FALLBACKS: &[(u8, Color)] = &[(commands::RECIPES_ORANGE, Color::Yellow)];
+
+/// One of the 256 indexed colors as the usual xterm palette has it
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+    const LEVELS: [u8; 6] = [0x00, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+    match index {
+        0..=15 => rgb(NAMED[index as usize]),
+        16..=231 => {
+            let cube = index - 16;
+            let level = |i: u8| LEVELS[i as usize];
+            (level(cube / 36), level(cube / 6 % 6), level(cube % 6))
+        }
+        _ => {
+            let gray = 8 + 10 * (index - 232);
+            (gray, gray, gray)
+        }
+    }
+}
+
+/// The named color nearest an RGB or indexed one, such as a colorblind-friendly frame color
+fn named(color: Color) -> Color {
+    let (r, g, b) = match color {
+        Color::Rgb(r, g, b) => (r, g, b),
+        Color::Indexed(index) => {
+            match INDEXED_FALLBACKS.iter().find(|(fallback, _)| *fallback == index) {
+                Some(&(_, named)) => return named,
+                None => indexed_rgb(index),
+            }
+        }
+        other => return other,
+    };
+    let distance = |named: &Color| {
+        let (nr, ng, nb) = rgb(*named);
+        let d = |a: u8, b: u8| (a as i32 - b as i32).pow(2);
+        d(r, nr) + d(g, ng) + d(b, nb)
+    };
+    NAMED.iter().copied().min_by_key(distance).unwrap_or(color)
+}
+
+/// The 8-color equivalent of a color
+fn dim(color: Color) -> Color {
+    match color {
+        Color::LightRed => Color::Red,
+        Color::LightGreen => Color::Green,
+        Color::LightYellow => Color::Yellow,
+        Color::LightBlue => Color::Blue,
+        Color::LightMagenta => Color::Magenta,
+        Color::LightCyan => Color::Cyan,
+        Color::DarkGray | Color::White => Color::Gray,
+        other => other,
+    }
+}
+
+/// The grays and white, named rather than picked by RGB
+fn is_neutral(color: Color) -> bool {
+    matches!(color, Color::Gray | Color::DarkGray | Color::White)
+}
+
+fn is_set(color: Color) -> bool {
+    color != Color::Reset
+}
+
+fn bright(color: Color) -> Color {
+    match color {
+        Color::Red => Color::LightRed,
+        Color::Green => Color::LightGreen,
+        Color::Yellow => Color::LightYellow,
+        Color::Blue => Color::LightBlue,
+        Color::Magenta => Color::LightMagenta,
+        Color::Cyan => Color::LightCyan,
+        Color::Gray | Color::DarkGray => Color::White,
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{layout::Rect, style::Style};
+
+    #[test]
+    fn test_apply() {
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 1));
+        buf.set_string(0, 0, "a", Style::default().fg(Color::DarkGray));
+        buf.set_string(1, 0, "b", Style::default().fg(Color::Black).bg(Color::Cyan));
+
+        let mut high = buf.clone();
+        Theme::HighContrast.apply(&mut high);
+        assert_eq!(high[(0, 0)].fg, Color::White);
+        assert_eq!(high[(1, 0)].bg, Color::LightCyan);
+
+        Theme::Monochrome.apply(&mut buf);
+        assert_eq!((buf[(0, 0)].fg, buf[(0, 0)].modifier), (Color::Reset, Modifier::empty()));
+        assert_eq!(buf[(1, 0)].modifier, Modifier::REVERSED);
+        assert_eq!(buf[(1, 0)].bg, Color::Reset);
+    }
+
+    #[test]
+    fn test_color_support() {
+        let detect = ColorSupport::from_env;
+        assert_eq!(detect(Some("truecolor"), Some("xterm")), ColorSupport::Full);
+        assert_eq!(detect(None, Some("xterm-256color")), ColorSupport::Full);
+        assert_eq!(detect(None, Some("xterm")), ColorSupport::Ansi16);
+        assert_eq!(detect(None, Some("linux")), ColorSupport::Ansi8);
+        assert_eq!(detect(None, None), ColorSupport::Ansi8);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 1, 1));
+        buf.set_string(0, 0, "a", Style::default().fg(Color::LightGreen).bg(Color::DarkGray));
+        let mut basic = buf.clone();
+        ColorSupport::Ansi16.apply(&mut buf);
+        assert_eq!((buf[(0, 0)].fg, buf[(0, 0)].bg), (Color::LightGreen, Color::Blue));
+        ColorSupport::Ansi8.apply(&mut basic);
+        assert_eq!((basic[(0, 0)].fg, basic[(0, 0)].bg), (Color::Green, Color::Blue));
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 3, 1));
+        buf.set_string(0, 0, "a", Style::default().fg(Color::DarkGray));
+        buf.set_string(1, 0, "b", Style::default().fg(Color::Gray));
+        buf.set_string(2, 0, "c", Style::default().fg(Color::Black).bg(Color::Gray));
+        ColorSupport::Ansi16.apply(&mut buf);
+        assert_eq!((buf[(0, 0)].fg, buf[(0, 0)].modifier), (Color::Reset, Modifier::DIM));
+        assert_eq!((buf[(1, 0)].fg, buf[(1, 0)].modifier), (Color::Reset, Modifier::empty()));
+        assert_eq!((buf[(2, 0)].fg, buf[(2, 0)].bg), (Color::Black, Color::Blue));
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 1, 1));
+        buf.set_string(0, 0, "a", Style::default().bg(Color::Rgb(0xf0, 0xe4, 0x42)));
+        ColorSupport::Ansi16.apply(&mut buf);
+        assert_eq!(buf[(0, 0)].bg, Color::LightYellow);
+
+        let mut buf = Buffer::empty(Rect::new(0, 0, 2, 1));
+        buf.set_string(0, 0, "a", Style::default().fg(Color::Indexed(commands::RECIPES_ORANGE)));
+        buf.set_string(1, 0, "b", Style::default().fg(Color::Indexed(27)));
+        ColorSupport::Ansi16.apply(&mut buf);
+        assert_eq!((buf[(0, 0)].fg, buf[(1, 0)].fg), (Color::Yellow, Color::Blue));
+    }
+}
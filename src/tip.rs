@@ -0,0 +1,126 @@
+use crate::commands::Command;
+use crate::state::UserState;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Pseudo-random numbers for picking tips; xorshift is plenty for that
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Xorshift never leaves zero
+        Self(seed.max(1))
+    }
+
+    /// Seeded from the clock, so every launch starts somewhere else
+    pub fn from_time() -> Self {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos() as u64);
+        Self::new(nanos)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// An index picked with probability proportional to its weight, if any weight is non-zero
+    pub fn weighted(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: u64 = weights.iter().map(|&w| u64::from(w)).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut target = self.next() % total;
+        weights.iter().position(|&w| {
+            let w = u64::from(w);
+            if target < w {
+                true
+            } else {
+                target -= w;
+                false
+            }
+        })
+    }
+}
+
+/// How strongly a command is favored as a tip: never viewed most, learned least, hidden never
+fn weight(state: &UserState, cmd: &Command) -> u32 {
+    if state.is_hidden(cmd) {
+        0
+    } else if state.is_learned(cmd) {
+        1
+    } else if state.recent_rank(cmd).is_some() {
+        3
+    } else {
+        6
+    }
+}
+
+/// Index of a random command to show as a tip, other than `current`
+pub fn pick(
+    commands: &[Command],
+    state: &UserState,
+    rng: &mut Rng,
+    current: Option<usize>,
+) -> Option<usize> {
+    let weights: Vec<u32> = commands
+        .iter()
+        .enumerate()
+        .map(|(i, cmd)| if Some(i) == current { 0 } else { weight(state, cmd) })
+        .collect();
+    rng.weighted(&weights)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{Category, Mode};
+
+    fn command(keys: &str) -> Command {
+        Command {
+            keys: keys.to_string(),
+            description: "Test".to_string(),
+            category: Category::General,
+            mode: Mode::Normal,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_weighted() {
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.weighted(&[]), None);
+        assert_eq!(rng.weighted(&[0, 0]), None);
+        for _ in 0..100 {
+            assert_eq!(rng.weighted(&[0, 5, 0]), Some(1));
+        }
+
+        let mut counts = [0; 2];
+        for _ in 0..1000 {
+            counts[rng.weighted(&[1, 9]).unwrap()] += 1;
+        }
+        assert!(counts[1] > counts[0] * 4, "{:?}", counts);
+    }
+
+    #[test]
+    fn test_pick() {
+        let commands = [command("gd"), command("gr"), command("K")];
+        let mut state = UserState::default();
+        state.toggle_hidden(&commands[1]);
+        state.toggle_learned(&commands[2]);
+
+        let mut rng = Rng::new(7);
+        let mut picked = [0; 3];
+        for _ in 0..300 {
+            picked[pick(&commands, &state, &mut rng, None).unwrap()] += 1;
+        }
+        assert_eq!(picked[1], 0);
+        assert!(picked[0] > picked[2], "{:?}", picked);
+
+        // Never the tip already showing
+        assert_eq!(pick(&commands, &state, &mut rng, Some(0)), Some(2));
+    }
+}
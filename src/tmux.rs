@@ -0,0 +1,14 @@
+use anyhow::{Context, Result};
+use std::process::Command;
+
+/// Put `text` in tmux's paste buffer, ready for `prefix + ]`
+pub fn set_buffer(text: &str) -> Result<()> {
+    let status = Command::new("tmux")
+        .args(["set-buffer", "--", text])
+        .status()
+        .context("failed to run tmux")?;
+    if !status.success() {
+        anyhow::bail!("`tmux set-buffer` failed ({})", status);
+    }
+    Ok(())
+}
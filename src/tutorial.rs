@@ -0,0 +1,172 @@
+use crate::commands::{Command, KeyFrame, Mode};
+use crate::typing::Typing;
+use crossterm::event::KeyEvent;
+
+/// One lesson: a builtin binding and how to remember it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Step {
+    /// Keys of a normal-mode command in the builtin data
+    pub keys: &'static str,
+    pub mnemonic: &'static str,
+}
+
+/// The essentials, in the order a new LazyVim user needs them
+pub const STEPS: &[Step] = &[
+    Step {
+        keys: "<leader>ff",
+        mnemonic: "Space is the leader key, and almost every LazyVim binding starts with it. \
+                   f is for find, and the second f for files.",
+    },
+    Step {
+        keys: "<leader>fr",
+        mnemonic: "Find, recent: the files you opened last, across projects.",
+    },
+    Step {
+        keys: "<leader>/",
+        mnemonic: "/ searches the buffer in Vim, so leader + / searches every file in the project.",
+    },
+    Step {
+        keys: "<leader>e",
+        mnemonic: "e for explorer: the file tree. Press it again to close it.",
+    },
+    Step {
+        keys: "<S-l>",
+        mnemonic: "Shift + l goes right, to the next buffer in the bufferline, like the l motion.",
+    },
+    Step {
+        keys: "<S-h>",
+        mnemonic: "And Shift + h goes left, to the previous buffer.",
+    },
+    Step {
+        keys: "<leader>bd",
+        mnemonic: "b for buffer, d for delete: closes the file but keeps the window layout.",
+    },
+    Step {
+        keys: "<C-h>",
+        mnemonic: "Ctrl + h/j/k/l moves between windows, in the same directions as the cursor.",
+    },
+    Step {
+        keys: "<leader>|",
+        mnemonic: "| looks like the line the split draws: a new window on the right. \
+                   leader + - splits below.",
+    },
+    Step {
+        keys: "gd",
+        mnemonic: "g for go, d for definition. Ctrl + o jumps back to where you were.",
+    },
+    Step {
+        keys: "K",
+        mnemonic: "K looks up the word under the cursor, as it opened man pages in classic Vim; \
+                   here it's the language server's documentation.",
+    },
+    Step {
+        keys: "<leader>ca",
+        mnemonic: "c for code, a for actions: quick fixes and refactorings from the language \
+                   server.",
+    },
+    Step {
+        keys: "<leader>cr",
+        mnemonic: "Code, rename: renames the symbol everywhere it's used.",
+    },
+    Step {
+        keys: "gcc",
+        mnemonic: "gc is the comment operator. Doubled, like dd or yy, it works on the whole line.",
+    },
+    Step {
+        keys: "<leader>gg",
+        mnemonic: "g for git, twice: opens LazyGit.",
+    },
+    Step {
+        keys: "<leader>sk",
+        mnemonic: "s for search, k for keymaps: when a binding slips your mind inside Neovim.",
+    },
+];
+
+/// Progress through `STEPS`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tutorial {
+    /// Each step with its command, by index into the app's commands
+    steps: Vec<(usize, &'static Step)>,
+    current: usize,
+    /// Whether each step has to be typed before moving on
+    pub practice: bool,
+    /// Progress typing the current step
+    pub typing: Typing,
+}
+
+impl Tutorial {
+    /// The lessons whose commands are in `commands`; `None` if there are none
+    pub fn new(commands: &[Command], practice: bool) -> Option<Self> {
+        let steps: Vec<_> = STEPS
+            .iter()
+            .filter_map(|step| {
+                let idx = commands
+                    .iter()
+                    .position(|cmd| cmd.keys == step.keys && cmd.mode == Mode::Normal)?;
+                Some((idx, step))
+            })
+            .collect();
+        if steps.is_empty() {
+            return None;
+        }
+        Some(Self {
+            steps,
+            current: 0,
+            practice,
+            typing: Typing::default(),
+        })
+    }
+
+    /// Index of the current step's command
+    pub fn command(&self) -> usize {
+        self.steps[self.current].0
+    }
+
+    pub fn step(&self) -> &'static Step {
+        self.steps[self.current].1
+    }
+
+    /// 1-based position and number of steps, e.g. `(3, 16)`
+    pub fn position(&self) -> (usize, usize) {
+        (self.current + 1, self.steps.len())
+    }
+
+    /// Whether the current step can be left: always, unless it still has to be typed
+    pub fn is_done(&self, frames: &[KeyFrame]) -> bool {
+        !self.practice || self.typing.is_done(frames)
+    }
+
+    /// Go to the next step; `false` after the last one
+    pub fn advance(&mut self) -> bool {
+        if self.current + 1 >= self.steps.len() {
+            return false;
+        }
+        self.current += 1;
+        self.typing = Typing::default();
+        true
+    }
+
+    pub fn back(&mut self) {
+        self.current = self.current.saturating_sub(1);
+        self.typing = Typing::default();
+    }
+
+    /// Check a keypress against the current step
+    pub fn press(&mut self, frames: &[KeyFrame], event: KeyEvent) {
+        self.typing.press(frames, event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::embedded_commands;
+
+    #[test]
+    fn test_steps_exist() {
+        let commands = embedded_commands().unwrap();
+        let tutorial = Tutorial::new(&commands, false).unwrap();
+        assert_eq!(tutorial.position(), (1, STEPS.len()));
+        assert_eq!(commands[tutorial.command()].keys, "<leader>ff");
+    }
+}
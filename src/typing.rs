@@ -0,0 +1,157 @@
+use crate::commands::{self, KeyFrame};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A keypress reduced to what a key notation frame can express. Shift is folded into
+/// characters (`G`, `|`), and kept only for named keys (`<S-Tab>`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Press {
+    ctrl: bool,
+    alt: bool,
+    shift: bool,
+    /// A typed character, or a lowercase key name like `space` or `enter`
+    key: String,
+}
+
+impl Press {
+    /// What typing `frame` looks like, if it is a single keypress
+    fn expected(frame: &KeyFrame) -> Option<Self> {
+        let has = |name: &str| frame.keys.iter().any(|k| k.is_modifier && k.key == name);
+        let mut bases = frame.keys.iter().filter(|k| !k.is_modifier);
+        let (Some(base), None) = (bases.next(), bases.next()) else {
+            return None;
+        };
+        let mut chars = base.key.chars();
+        let (key, shift) = match (chars.next(), chars.next()) {
+            (Some(c), None) if has("Shift") => (commands::shifted(c).to_string(), false),
+            (Some(c), None) => (c.to_string(), false),
+            _ => (base.key.to_lowercase(), has("Shift")),
+        };
+        Some(Self {
+            ctrl: has("Ctrl"),
+            alt: has("Alt"),
+            shift,
+            key,
+        })
+    }
+
+    fn pressed(event: KeyEvent) -> Option<Self> {
+        let mut shift = event.modifiers.contains(KeyModifiers::SHIFT);
+        let key = match event.code {
+            KeyCode::Char(' ') => "space".to_string(),
+            // The kitty keyboard protocol can report Shift + h as `h` with Shift held
+            KeyCode::Char(c) if shift && c.is_ascii_lowercase() => {
+                shift = false;
+                c.to_ascii_uppercase().to_string()
+            }
+            KeyCode::Char(c) => {
+                shift = false;
+                c.to_string()
+            }
+            KeyCode::Enter => "enter".to_string(),
+            KeyCode::Esc => "esc".to_string(),
+            KeyCode::Backspace => "backsp".to_string(),
+            KeyCode::Tab => "tab".to_string(),
+            KeyCode::BackTab => {
+                shift = true;
+                "tab".to_string()
+            }
+            KeyCode::Up => "up".to_string(),
+            KeyCode::Down => "down".to_string(),
+            KeyCode::Left => "left".to_string(),
+            KeyCode::Right => "right".to_string(),
+            KeyCode::F(n) => format!("f{}", n),
+            _ => return None,
+        };
+        Some(Self {
+            ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+            alt: event.modifiers.contains(KeyModifiers::ALT),
+            shift,
+            key,
+        })
+    }
+}
+
+/// Progress typing a key sequence, one keypress at a time
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Typing {
+    /// Frames typed correctly so far
+    pub typed: usize,
+    /// The last key didn't match, so typing started over
+    pub missed: bool,
+}
+
+impl Typing {
+    pub fn is_done(&self, frames: &[KeyFrame]) -> bool {
+        self.typed >= frames.len()
+    }
+
+    /// Check a keypress against the next frame of `frames`, starting over on a wrong one.
+    /// Returns whether it matched.
+    pub fn press(&mut self, frames: &[KeyFrame], event: KeyEvent) -> bool {
+        let Some(frame) = frames.get(self.typed) else {
+            return false;
+        };
+        let pressed = Press::pressed(event);
+        self.missed = pressed.is_none() || pressed != Press::expected(frame);
+        self.typed = if self.missed { 0 } else { self.typed + 1 };
+        !self.missed
+    }
+}
+
+/// Whether every frame of `frames` is a single keypress `Typing` can check
+pub fn is_typeable(frames: &[KeyFrame]) -> bool {
+    frames.iter().all(|frame| Press::expected(frame).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::parse_key_string;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    fn types(keys: &str, events: &[KeyEvent]) -> bool {
+        let frames = parse_key_string(keys);
+        let mut typing = Typing::default();
+        for &event in events {
+            typing.press(&frames, event);
+        }
+        typing.is_done(&frames) && !typing.missed
+    }
+
+    #[test]
+    fn test_press() {
+        let none = KeyModifiers::NONE;
+        let shift = KeyModifiers::SHIFT;
+        let space = key(KeyCode::Char(' '), none);
+        let f = key(KeyCode::Char('f'), none);
+        assert!(types("<leader>ff", &[space, f, f]));
+        assert!(!types("<leader>ff", &[space, f]));
+        assert!(types("<S-h>", &[key(KeyCode::Char('H'), shift)]));
+        assert!(types("K", &[key(KeyCode::Char('K'), shift)]));
+        assert!(types("<leader>|", &[space, key(KeyCode::Char('|'), shift)]));
+        assert!(types("<C-h>", &[key(KeyCode::Char('h'), KeyModifiers::CONTROL)]));
+        assert!(!types("<C-h>", &[key(KeyCode::Char('h'), none)]));
+        assert!(types("<S-Tab>", &[key(KeyCode::BackTab, shift)]));
+        // As the kitty keyboard protocol reports them, with and without alternate keys
+        assert!(types("H", &[key(KeyCode::Char('h'), shift)]));
+        let ctrl_shift = KeyModifiers::CONTROL | shift;
+        assert!(types("<C-S-h>", &[key(KeyCode::Char('h'), ctrl_shift)]));
+        assert!(types("<C-S-h>", &[key(KeyCode::Char('H'), KeyModifiers::CONTROL)]));
+
+        // A wrong key starts the sequence over
+        let g = key(KeyCode::Char('g'), none);
+        let d = key(KeyCode::Char('d'), none);
+        assert!(!types("gd", &[g, g, d]));
+        assert!(types("gd", &[g, g, g, d]));
+    }
+
+    #[test]
+    fn test_typeable() {
+        assert!(is_typeable(&parse_key_string("<leader>gg")));
+        // A register is Shift + ' and then the register's key, two keypresses in one frame
+        assert!(!is_typeable(&parse_key_string("\"ayy")));
+    }
+}
@@ -1,449 +1,4287 @@
-use crate::commands::{Command, KeyFrame};
-use crate::keyboard::{Keyboard, FRAME_COLORS};
-use crate::search::SearchEngine;
-use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crate::browser::{self, Card};
+use crate::card;
+use crate::cast;
+use crate::challenge::{Challenge, Outcome};
+use crate::changelog::{self, Changelog};
+use crate::commands::{
+    self, Category, Command, CommandId, Conflict, Diagram, Hue, KeyFrame, Keep, Leaders, Mode,
+    Problem, Source,
+};
+use crate::composer::{self, Composer, Grammar};
+use crate::config::Config;
+use crate::debug::{self, DebugInfo};
+use crate::explain::{self, Meaning};
+#[cfg(feature = "graphics")]
+use crate::graphics;
+use crate::input::{History, TextInput};
+use crate::keyboard::{Keyboard, KeyboardState, KeyboardWidget, FRAME_MODIFIERS, FRAME_SHAPES};
+use crate::keynotation;
+use crate::mouse::{self, MousePress, MOUSE_HEIGHT};
+use crate::overrides::Overrides;
+use crate::output;
+use crate::popup::{self, CommandForm, ExportForm, Popup, SheetFormat};
+use crate::prefixes::Prefixes;
+use crate::search::{self, KeyFilter, Query, SearchEngine};
+use crate::simulate::{self, Snapshot};
+use crate::state::{ListMode, Section, Session, SortMode, UserState, ViewMode};
+use crate::stats::{self, StatsRange};
+use crate::status::{Messages, Progress};
+use crate::undo::{Undo, UndoStack};
+use crate::tip::{self, Rng};
+use crate::tutorial::Tutorial;
+use crate::workspace::{Crumb, Workspace};
+use anyhow::Context;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph},
+    widgets::{
+        Block, Borders, List, ListItem, ListState, Paragraph, Scrollbar, ScrollbarOrientation,
+        ScrollbarState, Wrap,
+    },
     Frame,
 };
+use std::cell::Cell;
+#[cfg(feature = "graphics")]
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::time::{Duration, Instant};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+const VIEW_THRESHOLD_MS: u64 = 1000; // Selection dwell time that counts as viewing a command
+pub(crate) const KEYBOARD_WIDTH: u16 = 48; // Keyboard art plus borders
+pub(crate) const KEYBOARD_HEIGHT: u16 = 17;
+const DETAIL_MIN_WIDTH: u16 = 30;
+/// Results list width, inside its borders, below which each command takes two lines: the keys
+/// on top and the description under them
+const TWO_LINE_ROWS_BELOW: u16 = 60;
+/// The large keyboard of the zoomed view: every key doubles in width and triples in height
+const ZOOMED_KEYBOARD_WIDTH: u16 = 2 * KEYBOARD_WIDTH - 3;
+const ZOOMED_KEYBOARD_HEIGHT: u16 = 2 * KEYBOARD_HEIGHT - 3;
+/// Height the search box, results, keyboard, sequence bar and status bar need between them.
+/// Shorter terminals show the sequence as a row of chips instead of the keyboard, and a
+/// `--popup` shows only its sequence bar.
+const KEYBOARD_MIN_HEIGHT: u16 = 3 + 8 + KEYBOARD_HEIGHT + 2;
+/// Lines the row of chips takes: a box around each key
+const CHIPS_HEIGHT: u16 = 3;
+/// Width of the keys column in one-line result rows; longer keys are cut short with `…`
+const KEYS_COLUMN: usize = 16;
+/// Lines the sequence bar wraps onto before the rest is cut short with `…`
+const LEGEND_MAX_ROWS: usize = 3;
+/// Longest the metronome's pulse shows after a frame change, in milliseconds
+const PULSE_MS: u64 = 150;
+/// Longest wait for input while the terminal is in the background, with nothing to animate
+const BACKGROUND_TICK_MS: u64 = 2000;
+/// Where a command came from, in the list's badges and the details
+const SOURCE_COLOR: Color = Color::LightBlue;
+
+/// Where the keyboards go, for `ViewMode::Both`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Arrangement {
+    Single,
+    SideBySide,
+    Stacked,
+}
 
-const FRAME_DURATION_MS: u64 = 500; // Animation speed
+/// Border color of the focused pane
+const FOCUS_COLOR: Color = Color::LightCyan;
 
+/// The pane that pane-specific keys act on, cycled with Tab and Shift+Tab
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
-pub enum ViewMode {
+pub enum Focus {
+    /// Typing edits the query and the arrows move the selection, as everywhere
     #[default]
-    Animation,
-    Legend,
+    Search,
+    /// j/k and g/G move the selection too; other letters go back to the search
+    Results,
+    /// The arrows and Enter open the key explorer
+    Keyboard,
+    /// Arrows, paging and Ctrl+E/Ctrl+Y scroll the detail pane
+    Detail,
 }
 
-impl ViewMode {
-    pub fn toggle(&mut self) {
-        *self = match self {
-            ViewMode::Animation => ViewMode::Legend,
-            ViewMode::Legend => ViewMode::Animation,
-        };
+impl Focus {
+    /// In the order Tab visits them
+    const ALL: [Focus; 4] = [Focus::Search, Focus::Results, Focus::Keyboard, Focus::Detail];
+
+    fn bit(self) -> u8 {
+        1 << self as u8
     }
 }
 
+/// A row in the results list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResultRow {
+    /// Category section header with its number of matches
+    Header(Category, usize),
+    /// Index into `Workspace::commands`
+    Command(usize),
+    /// A pinned command, listed above the rest whatever the search
+    Pinned(usize),
+}
+
 pub struct App {
-    pub query: String,
-    pub commands: Vec<Command>,
-    pub filtered_results: Vec<usize>,
-    pub selected_index: usize,
+    /// The workspace in the active tab
+    pub ws: Workspace,
+    /// Every open tab; the active one's slot holds an empty placeholder while it's in `ws`
+    tabs: Vec<Workspace>,
+    /// Index into `tabs` of the active tab
+    tab: usize,
+    pub history: History,
+    /// Visible rows in the results list as of the last draw, for paging
+    pub list_height: Cell<usize>,
+    /// Cards side by side on the start screen as of the last draw, for moving up and down
+    pub card_columns: Cell<usize>,
     pub search_engine: SearchEngine,
     pub keyboard: Keyboard,
+    /// What `<leader>` and `<localleader>` are drawn as
+    pub leaders: Leaders,
+    pub state: UserState,
+    pub overrides: Overrides,
+    /// User entries conflicting with builtin ones that haven't been settled yet (F8)
+    pub conflicts: Vec<Conflict>,
+    /// Bindings the builtin keymaps gained, changed or lost between sessions (F9)
+    pub changelog: Changelog,
+    pub popup: Option<Popup>,
+    /// Operator + motion picker, shown in place of the results while open
+    pub composer: Option<Composer>,
+    /// Key focused on the keyboard by the key explorer (F7), keeping the results that press it
+    pub explorer: Option<KeyFilter>,
+    pub grammar: Grammar,
+    /// Names of the key sequences bindings are grouped under, like `<leader>g` for git
+    pub prefixes: Prefixes,
+    /// Command shown as a tip in place of the results, standing in for the selection
+    pub tip: Option<usize>,
+    /// Started with `--tip`: leaving the tip quits
+    pub tip_only: bool,
+    rng: Rng,
+    /// Guided lessons shown in place of the results; leaving them quits
+    pub tutorial: Option<Tutorial>,
+    /// Timed challenge shown in place of the results; leaving it quits
+    pub challenge: Option<Challenge>,
+    /// Started with `--popup`: a compact layout, and Enter picks the selection and quits
+    pub tmux_popup: bool,
+    /// Command picked with Enter in `--popup` mode
+    pub picked: Option<usize>,
+    /// File to open in `$EDITOR` once the event loop gets to it (F10)
+    pub edit: Option<PathBuf>,
+    /// Hides, unstars and note edits to take back with Ctrl+Z
+    undo: UndoStack,
+    /// Sources still loading in the background, shown with a spinner in the status bar
+    pub loading: Progress,
+    /// Where legend snapshots (Shift+F4) are appended; unset, they're printed on exit
+    pub snapshot_file: Option<PathBuf>,
+    /// Leave the colors out of legend snapshots
+    pub plain_snapshots: bool,
+    /// Legend snapshots to print once the terminal is restored
+    pub snapshots: Vec<String>,
+    /// Transient messages for the status bar
+    pub messages: Messages,
+    pub config: Config,
     pub should_quit: bool,
+    /// Wait for input no later than this, for a demo's next beat
+    pub wake_at: Option<Instant>,
     // Animation state
     pub current_frame: usize,
-    pub last_frame_time: Instant,
+    /// When the animation of `cached_frames` started; the frame showing follows from it
+    pub animation_start: Instant,
+    /// When the terminal lost focus, while it's in the background; the animation holds still
+    paused_at: Option<Instant>,
+    /// Frames the animation had moved on when the metronome last beat
+    beat: Option<u128>,
     pub cached_frames: Vec<KeyFrame>,
+    /// Which of the selected command's alternatives is animated instead of its keys
+    pub alternative: Option<usize>,
+    /// `]x` / `[x` bindings animate their twin right after their own keys
+    pub both_directions: bool,
+    /// Sample buffer after each cached frame, when the keys can be simulated
+    pub simulation: Option<Vec<Snapshot>>,
     pub last_selected: Option<usize>,
+    pub selected_at: Instant,
+    /// When the selection last moved, while it moves too quickly for each step to be animated
+    settling: Option<Instant>,
+    pub view_recorded: bool,
     // View mode
     pub view_mode: ViewMode,
+    /// The keyboard fills the screen with large keys, for screen sharing and teaching
+    pub zoomed: bool,
+    /// The detail pane explains what each key of the sequence stands for
+    pub explaining: bool,
+    /// A line under the animation says what the frame showing does, in words
+    pub narrating: bool,
+    /// Lines of the detail pane scrolled past, for long doc excerpts and notes
+    pub detail_scroll: u16,
+    pub focus: Focus,
+    /// The panes the last draw showed, one [`Focus::bit`] each, as only those can be focused
+    panes_shown: Cell<u8>,
+    /// Rows of the detail pane as of the last draw, and how many fit, for scrolling
+    detail_lines: Cell<(u16, u16)>,
+    /// Draw keycap images under the keyboard labels; the terminal shows kitty graphics
+    pub keycap_images: bool,
+    /// Timings for the `--debug` overlay, when started with it
+    pub debug: Option<DebugInfo>,
+    /// Keycap images for the keyboards in the last frame drawn
+    #[cfg(feature = "graphics")]
+    pub keycaps: RefCell<Vec<graphics::Placement>>,
 }
 
 impl App {
-    pub fn new(commands: Vec<Command>) -> Self {
-        let filtered_results: Vec<usize> = (0..commands.len()).collect();
-        Self {
-            query: String::new(),
-            commands,
-            filtered_results,
-            selected_index: 0,
-            search_engine: SearchEngine::new(),
-            keyboard: Keyboard::new(),
+    pub fn new(
+        commands: Vec<Command>,
+        config: Config,
+        state: UserState,
+        overrides: Overrides,
+        grammar: Grammar,
+    ) -> Self {
+        let history = if config.save_history {
+            History::new(state.history.clone())
+        } else {
+            History::default()
+        };
+        let mut app = Self {
+            ws: Workspace::new("LazyVim", commands),
+            tabs: vec![Workspace::default()],
+            tab: 0,
+            history,
+            list_height: Cell::new(0),
+            card_columns: Cell::new(1),
+            search_engine: config.search_engine(),
+            keyboard: config.keyboard(),
+            leaders: config.leaders(),
+            state,
+            overrides,
+            conflicts: Vec::new(),
+            changelog: Changelog::default(),
+            popup: None,
+            composer: None,
+            explorer: None,
+            grammar,
+            prefixes: Prefixes::default(),
+            tip: None,
+            tip_only: false,
+            rng: Rng::from_time(),
+            tutorial: None,
+            challenge: None,
+            tmux_popup: false,
+            picked: None,
+            edit: None,
+            undo: UndoStack::default(),
+            loading: Progress::default(),
+            snapshot_file: None,
+            plain_snapshots: false,
+            snapshots: Vec::new(),
+            messages: Messages::default(),
+            config,
             should_quit: false,
+            wake_at: None,
             current_frame: 0,
-            last_frame_time: Instant::now(),
+            animation_start: Instant::now(),
+            beat: None,
             cached_frames: Vec::new(),
+            alternative: None,
+            both_directions: false,
+            simulation: None,
             last_selected: None,
+            selected_at: Instant::now(),
+            settling: None,
+            paused_at: None,
+            view_recorded: false,
             view_mode: ViewMode::default(),
+            zoomed: false,
+            explaining: false,
+            narrating: false,
+            detail_scroll: 0,
+            focus: Focus::default(),
+            panes_shown: Cell::new(0),
+            detail_lines: Cell::new((0, 0)),
+            keycap_images: false,
+            debug: None,
+            #[cfg(feature = "graphics")]
+            keycaps: RefCell::default(),
+        };
+        if !app.config.animation {
+            app.view_mode = ViewMode::Legend;
         }
+        app.narrating = app.config.narrate;
+        app.search_engine.index(&app.ws.commands);
+        app.ws.parse_frames(&app.leaders);
+        app.refresh_results();
+        app
     }
 
     pub fn update_search(&mut self) {
-        let results = self.search_engine.search(&self.commands, &self.query);
-        self.filtered_results = results
-            .into_iter()
-            .map(|(cmd, _)| {
-                self.commands
-                    .iter()
-                    .position(|c| std::ptr::eq(c, cmd))
-                    .unwrap()
-            })
-            .collect();
-        self.selected_index = 0;
+        // Editing a recalled query ends the recall, and with it Ctrl+N stepping through history
+        if self.history.recalled().is_some_and(|entry| entry != self.ws.query.as_str()) {
+            self.history.stop();
+        }
+        self.refresh_results();
+        // The best match rather than the pinned commands above it
+        let pinned = self.ws.pinned.len();
+        self.ws.selected_index = if self.ws.rows.len() > pinned { pinned } else { 0 };
         self.reset_animation();
     }
 
-    pub fn selected_command(&self) -> Option<&Command> {
-        self.filtered_results
-            .get(self.selected_index)
-            .and_then(|&idx| self.commands.get(idx))
+    /// Add the current query to the search history
+    fn remember_query(&mut self) {
+        self.history.push(self.ws.query.as_str());
     }
 
-    fn reset_animation(&mut self) {
-        self.current_frame = 0;
-        self.last_frame_time = Instant::now();
-        self.cached_frames = self
-            .selected_command()
-            .map(|cmd| cmd.parse_keys())
-            .unwrap_or_default();
-        self.last_selected = self.filtered_results.get(self.selected_index).copied();
+    /// Replace the query with an older or newer history entry
+    fn recall_history(&mut self, older: bool) {
+        let entry = if older {
+            self.history.previous(self.ws.query.as_str())
+        } else {
+            self.history.next()
+        };
+        if let Some(entry) = entry.map(str::to_string) {
+            self.ws.query.set(&entry);
+            self.update_search();
+        }
     }
 
-    pub fn tick(&mut self) {
-        // Check if selection changed
-        let current_selected = self.filtered_results.get(self.selected_index).copied();
-        if current_selected != self.last_selected {
-            self.reset_animation();
+    /// Switch to the next match mode and re-run the query
+    fn cycle_match_mode(&mut self) {
+        self.search_engine.mode = self.search_engine.mode.next();
+        self.update_search();
+        self.messages
+            .push(format!("Match mode: {}", self.search_engine.mode.as_str()));
+    }
+
+    /// Pick up where an earlier session left off: its search, view and selected command
+    pub fn restore_session(&mut self, session: &Session) {
+        self.ws.query.set(&session.query);
+        self.ws.set_filters(&session.filters);
+        self.ws.section = session.section;
+        self.ws.list_mode = session.list_mode;
+        self.ws.sort = session.sort;
+        if self.config.animation {
+            self.view_mode = session.view_mode;
         }
+        self.refresh_results();
+        let selected = session.selected.as_ref().and_then(|id| {
+            self.ws.rows.iter().position(|row| {
+                matches!(row, ResultRow::Command(idx) if self.ws.commands[*idx].id() == *id)
+            })
+        });
+        self.ws.selected_index = selected.unwrap_or(0);
+        self.reset_animation();
+    }
 
-        // Advance animation frame
-        if !self.cached_frames.is_empty()
-            && self.last_frame_time.elapsed() >= Duration::from_millis(FRAME_DURATION_MS)
+    /// Write user state at the end of the session, including search history if enabled
+    pub fn save_state(&mut self) -> anyhow::Result<()> {
+        // A `--tip`, `--popup`, tutorial or challenge run leaves the last real session alone
+        if !self.tip_only && !self.tmux_popup && self.tutorial.is_none() && self.challenge.is_none()
         {
-            self.current_frame = (self.current_frame + 1) % self.cached_frames.len();
-            self.last_frame_time = Instant::now();
+            self.tip = None;
+            // Sessions restore into the first tab
+            self.select_tab(0);
+            self.state.session = Some(Session {
+                query: self.ws.query.as_str().to_string(),
+                filters: self.ws.filters().into_iter().map(str::to_string).collect(),
+                view_mode: self.view_mode,
+                section: self.ws.section,
+                list_mode: self.ws.list_mode,
+                sort: self.ws.sort,
+                selected: self.selected_command().map(Command::id),
+            });
         }
+        self.remember_query();
+        self.state.history = if self.config.save_history {
+            self.history.entries().to_vec()
+        } else {
+            Vec::new()
+        };
+        self.state.save()
     }
 
-    pub fn handle_input(&mut self) -> anyhow::Result<()> {
-        if event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key) = event::read()? {
-                match key.code {
-                    KeyCode::Esc => {
-                        if self.query.is_empty() {
-                            self.should_quit = true;
-                        } else {
-                            self.query.clear();
-                            self.update_search();
-                        }
-                    }
-                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.should_quit = true;
-                    }
-                    KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                        self.view_mode.toggle();
-                    }
-                    KeyCode::Char(c) => {
-                        self.query.push(c);
-                        self.update_search();
-                    }
-                    KeyCode::Backspace => {
-                        self.query.pop();
-                        self.update_search();
-                    }
-                    KeyCode::Down | KeyCode::Tab => {
-                        if !self.filtered_results.is_empty() {
-                            self.selected_index =
-                                (self.selected_index + 1) % self.filtered_results.len();
-                        }
-                    }
-                    KeyCode::Up | KeyCode::BackTab => {
-                        if !self.filtered_results.is_empty() {
-                            self.selected_index = if self.selected_index == 0 {
-                                self.filtered_results.len() - 1
-                            } else {
-                                self.selected_index - 1
-                            };
-                        }
-                    }
-                    _ => {}
-                }
+    /// Open a workspace loaded after startup as the tab at `slot`, shifting the later ones
+    pub fn insert_tab(&mut self, slot: usize, mut workspace: Workspace) {
+        self.search_engine.index(&workspace.commands);
+        workspace.parse_frames(&self.leaders);
+        let slot = slot.min(self.tabs.len());
+        self.tabs.insert(slot, workspace);
+        if slot <= self.tab {
+            self.tab += 1;
+        }
+    }
+
+    /// Swap the commands of the tab named `name` for `commands`, keeping its selection where
+    /// it still can, and return the ones it had. Without such a tab, one is opened last.
+    pub fn set_tab(&mut self, name: &str, commands: Vec<Command>) -> Option<Vec<Command>> {
+        let Some(tab) = (0..self.tabs.len()).find(|&tab| self.tab_name(tab) == name) else {
+            self.insert_tab(self.tabs.len(), Workspace::new(name, commands));
+            return None;
+        };
+        Some(self.replace_commands(tab, commands))
+    }
+
+    /// The files the open tabs were loaded from
+    #[cfg(feature = "watch")]
+    pub fn tab_paths(&self) -> Vec<PathBuf> {
+        (0..self.tabs.len())
+            .filter_map(|tab| self.tab_workspace(tab).path.clone())
+            .collect()
+    }
+
+    /// Swap the commands and problems of the tab loaded from `workspace`'s file for the ones
+    /// it was loaded with again, keeping its selection where it still can
+    #[cfg(feature = "watch")]
+    pub fn reload_tab(&mut self, workspace: Workspace) {
+        let path = &workspace.path;
+        let Some(tab) = (0..self.tabs.len()).find(|&i| self.tab_workspace(i).path == *path) else {
+            return;
+        };
+        self.replace_commands(tab, workspace.commands);
+        let ws = if tab == self.tab { &mut self.ws } else { &mut self.tabs[tab] };
+        ws.problems = workspace.problems;
+    }
+
+    /// Swap the commands of `tab` for `commands`, keeping its selection where it still can,
+    /// and return the ones it had
+    fn replace_commands(&mut self, tab: usize, commands: Vec<Command>) -> Vec<Command> {
+        let selected = self.selected_command().map(Command::id);
+        let ws = if tab == self.tab { &mut self.ws } else { &mut self.tabs[tab] };
+        let marked: Vec<CommandId> = ws.marked.iter().map(|&idx| ws.commands[idx].id()).collect();
+        let old = std::mem::replace(&mut ws.commands, commands);
+        ws.marked = marked
+            .iter()
+            .filter_map(|id| ws.commands.iter().position(|cmd| cmd.id() == *id))
+            .collect();
+        ws.parse_frames(&self.leaders);
+        self.search_engine.index(&ws.commands);
+        // Other tabs are searched again when switched to
+        if tab == self.tab {
+            self.refresh_results();
+            let row = selected.as_ref().and_then(|id| {
+                self.ws.rows.iter().position(|row| {
+                    matches!(row, ResultRow::Command(idx) if self.ws.commands[*idx].id() == *id)
+                })
+            });
+            self.ws.selected_index = row.unwrap_or(0).min(self.ws.rows.len().saturating_sub(1));
+            if self.selected_command().map(Command::id) != selected {
+                self.reset_animation();
             }
         }
-        Ok(())
+        old
     }
 
-    pub fn draw(&self, frame: &mut Frame) {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(1)
-            .constraints([
-                Constraint::Length(3),  // Search input
-                Constraint::Min(8),     // Results list
-                Constraint::Length(15), // Keyboard
-            ])
-            .split(frame.area());
+    /// Entries left out of every tab's command files, the active tab's first
+    fn problems(&self) -> Vec<&Problem> {
+        let others = self.tabs.iter().enumerate().filter(|&(i, _)| i != self.tab);
+        self.ws
+            .problems
+            .iter()
+            .chain(others.flat_map(|(_, ws)| &ws.problems))
+            .collect()
+    }
 
-        self.draw_search_input(frame, chunks[0]);
-        self.draw_results_list(frame, chunks[1]);
-        self.draw_keyboard(frame, chunks[2]);
+    /// Tell, in the status bar, how many entries of the command files were left out
+    pub fn announce_problems(&mut self) {
+        let count = self.problems().len();
+        if count > 0 {
+            self.messages
+                .push(format!("Skipped {} invalid command entries · F6: details", count));
+        }
     }
 
-    fn draw_search_input(&self, frame: &mut Frame, area: Rect) {
-        let input = Paragraph::new(Line::from(vec![
-            Span::styled("Search: ", Style::default().fg(Color::Yellow)),
-            Span::raw(&self.query),
-            Span::styled(
-                "_",
-                Style::default()
-                    .fg(Color::Gray)
-                    .add_modifier(Modifier::SLOW_BLINK),
-            ),
-        ]))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("LazyVim Helper (Esc to quit)"),
-        );
-        frame.render_widget(input, area);
+    fn tab_name(&self, tab: usize) -> &str {
+        &self.tab_workspace(tab).name
     }
 
-    fn draw_results_list(&self, frame: &mut Frame, area: Rect) {
-        let results_count = self.filtered_results.len();
-        let title = format!("Commands ({} results)", results_count);
-        let list_height = area.height.saturating_sub(2) as usize;
-        let mut start = 0usize;
+    fn tab_workspace(&self, tab: usize) -> &Workspace {
+        if tab == self.tab {
+            &self.ws
+        } else {
+            &self.tabs[tab]
+        }
+    }
 
-        if list_height > 0 && results_count > list_height {
-            let half = list_height / 2;
-            if self.selected_index > half {
-                start = self.selected_index - half;
-            }
-            let max_start = results_count - list_height;
-            if start > max_start {
-                start = max_start;
-            }
+    /// Make `tab` the active workspace, putting the current one back in its slot
+    fn select_tab(&mut self, tab: usize) {
+        if tab == self.tab || tab >= self.tabs.len() {
+            return;
         }
+        std::mem::swap(&mut self.ws, &mut self.tabs[self.tab]);
+        self.tab = tab;
+        std::mem::swap(&mut self.ws, &mut self.tabs[tab]);
+    }
 
-        let end = if list_height == 0 {
-            start
+    /// Switch to the next or previous tab, wrapping around
+    fn cycle_tab(&mut self, forward: bool) {
+        let count = self.tabs.len();
+        if count < 2 {
+            return;
+        }
+        let tab = if forward {
+            (self.tab + 1) % count
         } else {
-            (start + list_height).min(results_count)
+            (self.tab + count - 1) % count
         };
+        self.select_tab(tab);
+        // Favorites or hidden commands may have changed in another tab
+        self.refresh_results();
+        self.reset_animation();
+    }
 
-        let items: Vec<ListItem> = (start..end)
-            .map(|i| {
-                let cmd_idx = self.filtered_results[i];
-                let cmd = &self.commands[cmd_idx];
-                let style = if i == self.selected_index {
-                    Style::default()
-                        .bg(Color::DarkGray)
-                        .add_modifier(Modifier::BOLD)
-                } else {
-                    Style::default()
-                };
-
-                let content = Line::from(vec![
-                    Span::styled(format!("{:16}", cmd.keys), style.fg(Color::Cyan)),
-                    Span::styled(" │ ", style.fg(Color::DarkGray)),
-                    Span::styled(&cmd.description, style),
-                    Span::styled(" │ ", style.fg(Color::DarkGray)),
-                    Span::styled(
-                        format!("[{}]", cmd.category.as_str()),
-                        style.fg(Color::Yellow),
-                    ),
-                ]);
+    /// Re-run the current query, keeping the selection in range
+    fn refresh_results(&mut self) {
+        let started = Instant::now();
+        let mut levels = self.ws.filters();
+        levels.push(self.ws.query.as_str());
 
-                ListItem::new(content)
+        // Results are ranked by the innermost level that actually orders them
+        let ranking = levels
+            .iter()
+            .rposition(|level| {
+                let query = Query::parse(level);
+                !query.text.is_empty() || query.recent
             })
-            .collect();
+            .unwrap_or(levels.len() - 1);
 
-        let list = List::new(items)
-            .block(Block::default().borders(Borders::ALL).title(title))
-            .highlight_style(Style::default().bg(Color::DarkGray));
+        let mut results = self.matching(levels[ranking]);
+        for (i, level) in levels.iter().enumerate() {
+            if i != ranking {
+                let keep: HashSet<usize> = self.matching(level).into_iter().collect();
+                results.retain(|idx| keep.contains(idx));
+            }
+        }
 
-        let mut state = ListState::default();
-        if results_count > 0 && list_height > 0 {
-            state.select(Some(self.selected_index.saturating_sub(start)));
+        let pinned = self.pinned_results(&levels);
+        if let Some(filter) = &self.explorer {
+            results.retain(|&idx| filter.matches(&self.ws.frames[idx], &self.keyboard));
+        }
+        let counted = results.iter().map(|&idx| &self.ws.commands[idx]);
+        self.ws.category_counts = search::category_counts(counted, &self.config.categories);
+        if let Some(category) = self.ws.entered() {
+            results.retain(|&idx| self.ws.commands[idx].category == *category);
+        }
+        if self.ws.sort == SortMode::Complexity {
+            results.sort_by_key(|&idx| self.ws.complexity[idx]);
         }
+        self.ws.pinned = pinned;
+        self.ws.filtered_results = results;
+        self.rebuild_rows();
 
-        frame.render_stateful_widget(list, area, &mut state);
+        let took = started.elapsed();
+        tracing::debug!(
+            query = self.ws.query.as_str(),
+            results = self.ws.filtered_results.len(),
+            ?took,
+            "searched"
+        );
+        if let Some(debug) = &mut self.debug {
+            debug.search.record(took);
+        }
     }
 
-    fn draw_keyboard(&self, frame: &mut Frame, area: Rect) {
-        match self.view_mode {
-            ViewMode::Animation => self.draw_keyboard_animation(frame, area),
-            ViewMode::Legend => self.draw_keyboard_legend(frame, area),
+    /// Indices of the pinned commands, in the order they were pinned, that the filters of
+    /// `levels`, the opened category and the key explorer keep, whatever text is searched for
+    fn pinned_results(&self, levels: &[&str]) -> Vec<usize> {
+        let mut pinned: Vec<usize> = self
+            .state
+            .pinned
+            .iter()
+            .filter_map(|id| self.ws.commands.iter().position(|cmd| cmd.id() == *id))
+            .collect();
+        if pinned.is_empty() {
+            return pinned;
+        }
+        for level in levels {
+            let keep: HashSet<usize> =
+                self.matching(&Query::filters_only(level)).into_iter().collect();
+            pinned.retain(|idx| keep.contains(idx));
+        }
+        if let Some(category) = self.ws.entered() {
+            pinned.retain(|&idx| self.ws.commands[idx].category == *category);
         }
+        if let Some(filter) = &self.explorer {
+            pinned.retain(|&idx| filter.matches(&self.ws.frames[idx], &self.keyboard));
+        }
+        pinned
     }
 
-    fn draw_keyboard_animation(&self, frame: &mut Frame, area: Rect) {
-        let highlighted_keys = self.get_current_frame_keys();
-        let kb_lines = self.keyboard.render(&highlighted_keys);
+    /// Indices of commands matching one query, best first
+    fn matching(&self, input: &str) -> Vec<usize> {
+        let query = Query::parse(input);
+        let mut results = self.search_engine.search(&self.ws.commands, input);
+        let categories = &self.config.categories;
+        results.retain(|(cmd, _)| {
+            self.ws.section.contains(cmd) && !categories.is_hidden(&cmd.category)
+        });
+        self.state.boost_results(&mut results, &query, self.config.usage_boost);
+        self.state.filter_results(&mut results, &query);
+        results
+            .into_iter()
+            .map(|(cmd, _)| {
+                self.ws.commands
+                    .iter()
+                    .position(|c| std::ptr::eq(c, cmd))
+                    .unwrap()
+            })
+            .collect()
+    }
 
-        let title = if let Some(cmd) = self.selected_command() {
-            let total_frames = self.cached_frames.len();
-            if total_frames > 1 {
-                format!(
-                    " {} [frame {}/{}] ",
-                    cmd.keys,
-                    self.current_frame + 1,
-                    total_frames
-                )
-            } else {
-                format!(" {} ", cmd.keys)
+    /// Switch between keymaps and ex commands, keeping the query
+    fn toggle_section(&mut self) {
+        self.ws.section.toggle();
+        self.update_search();
+    }
+
+    /// Keep the current results and start a new query within them
+    fn narrow(&mut self) {
+        if self.ws.query.as_str().trim().is_empty() {
+            return;
+        }
+        self.remember_query();
+        let filter = self.ws.query.as_str().trim().to_string();
+        self.ws.trail.push(Crumb::Filter(filter));
+        self.ws.query.clear();
+        self.update_search();
+    }
+
+    /// Back out of the last level of the trail; a narrowed query comes back for editing, and
+    /// leaving the last category goes back to the start screen, on its card
+    fn go_back(&mut self) {
+        if let Some(Crumb::Filter(filter)) = self.ws.trail.pop() {
+            self.ws.query.set(&filter);
+        }
+        self.update_search();
+    }
+
+    /// Lay out `filtered_results` as rows, keeping the selected row where possible
+    fn rebuild_rows(&mut self) {
+        let selected = self.ws.rows.get(self.ws.selected_index).cloned();
+
+        let pinned = self.ws.pinned.iter().map(|&idx| ResultRow::Pinned(idx));
+        self.ws.rows = pinned.collect();
+        self.ws.rows.extend(match self.ws.list_mode {
+            ListMode::Flat => self
+                .ws
+                .filtered_results
+                .iter()
+                .map(|&idx| ResultRow::Command(idx))
+                .collect(),
+            ListMode::Grouped => {
+                let mut rows = Vec::new();
+                let used = self.ws.commands.iter().map(|cmd| &cmd.category);
+                for category in self.config.categories.in_order(used) {
+                    let members: Vec<usize> = self
+                        .ws
+                        .filtered_results
+                        .iter()
+                        .copied()
+                        .filter(|&idx| self.ws.commands[idx].category == category)
+                        .collect();
+                    if members.is_empty() {
+                        continue;
+                    }
+                    let collapsed = self.ws.collapsed.contains(&category);
+                    rows.push(ResultRow::Header(category, members.len()));
+                    if !collapsed {
+                        rows.extend(members.into_iter().map(ResultRow::Command));
+                    }
+                }
+                rows
             }
-        } else {
-            String::new()
+        });
+
+        let same_row = |row: &ResultRow| match (row, &selected) {
+            (ResultRow::Header(a, _), Some(ResultRow::Header(b, _))) => a == b,
+            (row, Some(selected)) => row == selected,
+            _ => false,
         };
+        self.ws.selected_index = match self.ws.rows.iter().position(same_row) {
+            Some(pos) => pos,
+            None => self.ws.selected_index.min(self.ws.rows.len().saturating_sub(1)),
+        };
+    }
 
-        let kb_widget = Paragraph::new(kb_lines).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!("Keyboard{} (Ctrl+V: Legend)", title)),
-        );
+    /// Whether the start screen's category cards replace the results: nothing typed or
+    /// narrowed, no category open, and the plain keymap list otherwise
+    fn showing_start(&self) -> bool {
+        self.config.start_screen
+            && !self.tmux_popup
+            && self.explorer.is_none()
+            && self.ws.trail.is_empty()
+            && self.ws.query.is_empty()
+            && self.ws.section == Section::Keymaps
+            && self.ws.list_mode == ListMode::Flat
+    }
 
-        frame.render_widget(kb_widget, area);
+    fn start_cards(&self) -> Vec<Card> {
+        browser::cards(&self.ws.commands, &self.ws.filtered_results, &self.config.categories)
     }
 
-    fn draw_keyboard_legend(&self, frame: &mut Frame, area: Rect) {
-        // Split area for keyboard and legend bar
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .constraints([Constraint::Min(13), Constraint::Length(1)])
-            .split(area);
+    /// Move between the start screen's cards and open one; other keys are left to the search
+    fn handle_start_key(&mut self, key: KeyEvent) -> bool {
+        if !key.modifiers.is_empty() && key.modifiers != KeyModifiers::SHIFT {
+            return false;
+        }
+        let count = self.start_cards().len();
+        let columns = self.card_columns.get();
+        let (dx, dy) = match key.code {
+            KeyCode::Left => (-1, 0),
+            KeyCode::Right => (1, 0),
+            KeyCode::Up => (0, -1),
+            KeyCode::Down => (0, 1),
+            KeyCode::Tab => {
+                self.ws.card = (self.ws.card + 1) % count.max(1);
+                return true;
+            }
+            KeyCode::BackTab => {
+                self.ws.card = (self.ws.card + count.max(1) - 1) % count.max(1);
+                return true;
+            }
+            KeyCode::Home => {
+                self.ws.card = 0;
+                return true;
+            }
+            KeyCode::End => {
+                self.ws.card = count.saturating_sub(1);
+                return true;
+            }
+            KeyCode::Enter => {
+                self.enter_category();
+                return true;
+            }
+            _ => return false,
+        };
+        self.ws.card = browser::step(self.ws.card, count, columns, dx, dy);
+        true
+    }
 
-        // Get all frames as key lists
-        let all_frames: Vec<Vec<&str>> = self
-            .cached_frames
-            .iter()
-            .map(|kf| {
-                kf.keys
-                    .iter()
-                    .filter_map(|k| Self::key_to_static(&k.key))
-                    .collect()
-            })
-            .collect();
+    /// List the commands of the highlighted card's category alone
+    fn enter_category(&mut self) {
+        let Some(card) = self.start_cards().into_iter().nth(self.ws.card) else {
+            return;
+        };
+        self.open_category(card.category);
+    }
 
-        let kb_lines = self.keyboard.render_legend(&all_frames);
+    /// List the commands of `category` alone, as if its card was opened
+    pub fn open_category(&mut self, category: Category) {
+        self.ws.set_entered(Some(category));
+        self.update_search();
+    }
 
-        let title = self
-            .selected_command()
-            .map(|cmd| format!(" {} ", cmd.keys))
-            .unwrap_or_default();
+    /// List only the `n`th category of the counts above the results, or all of them again
+    /// for 0 or the one already listed
+    fn pick_counted_category(&mut self, n: usize) {
+        let picked = n
+            .checked_sub(1)
+            .and_then(|i| self.ws.category_counts.get(i))
+            .map(|(category, _)| category.clone());
+        if n > 0 && picked.is_none() {
+            return;
+        }
+        match picked.filter(|category| self.ws.entered() != Some(category)) {
+            Some(category) => self.open_category(category),
+            None => {
+                self.ws.set_entered(None);
+                self.update_search();
+            }
+        }
+    }
 
-        let kb_widget = Paragraph::new(kb_lines).block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(format!("Keyboard{} (Ctrl+V: Animation)", title)),
-        );
+    /// Whether the results are for a search, which gets a count of them per category
+    fn showing_counts(&self) -> bool {
+        let searched = !self.ws.query.is_empty() || !self.ws.filters().is_empty();
+        searched && !self.ws.category_counts.is_empty()
+    }
 
-        frame.render_widget(kb_widget, chunks[0]);
+    /// The results per category, e.g. `Git 4 · LSP 7 · Search 12`, with the listed one
+    /// highlighted, and how to pick one
+    fn category_count_line(&self) -> Line<'static> {
+        let dim = Style::default().fg(Color::DarkGray);
+        let entered = self.ws.entered();
+        let mut spans = Vec::new();
+        for (i, (category, count)) in self.ws.category_counts.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::styled(" · ", dim));
+            }
+            let mut style = Style::default().fg(color(self.config.categories.color(category)));
+            if entered == Some(category) {
+                style = style.add_modifier(Modifier::REVERSED | Modifier::BOLD);
+            }
+            let name = self.config.categories.name(category);
+            spans.push(Span::styled(format!("{} {}", name, count), style));
+        }
+        let hint = match entered {
+            Some(_) => "  (Alt+1-9: filter, Alt+0: all)",
+            None => "  (Alt+1-9: filter)",
+        };
+        spans.push(Span::styled(hint, dim));
+        Line::from(spans)
+    }
 
-        // Draw legend bar showing sequence
-        let legend_spans = self.build_legend_bar();
-        let legend = Paragraph::new(Line::from(legend_spans));
-        frame.render_widget(legend, chunks[1]);
+    fn toggle_list_mode(&mut self) {
+        self.ws.list_mode = match self.ws.list_mode {
+            ListMode::Flat => ListMode::Grouped,
+            ListMode::Grouped => ListMode::Flat,
+        };
+        self.rebuild_rows();
     }
 
-    fn build_legend_bar(&self) -> Vec<Span<'static>> {
-        let mut spans = Vec::new();
-        spans.push(Span::styled("Sequence: ", Style::default().fg(Color::Gray)));
+    fn toggle_sort(&mut self) {
+        self.ws.sort = match self.ws.sort {
+            SortMode::Relevance => SortMode::Complexity,
+            SortMode::Complexity => SortMode::Relevance,
+        };
+        self.update_search();
+    }
 
-        for (i, kf) in self.cached_frames.iter().enumerate() {
-            let color = FRAME_COLORS[i % FRAME_COLORS.len()];
+    /// Collapse or expand the category whose header is selected
+    fn toggle_collapsed(&mut self) {
+        let Some(ResultRow::Header(category, _)) = self.ws.rows.get(self.ws.selected_index) else {
+            return;
+        };
+        let category = category.clone();
+        if let Some(pos) = self.ws.collapsed.iter().position(|c| *c == category) {
+            self.ws.collapsed.remove(pos);
+        } else {
+            self.ws.collapsed.push(category);
+        }
+        self.rebuild_rows();
+    }
 
-            // Build key representation for this frame
-            let keys_str: String = kf
-                .keys
-                .iter()
-                .map(|k| {
-                    if k.key == "Space" {
-                        "␣".to_string()
-                    } else if k.key.len() > 1 {
-                        k.key.clone()
-                    } else {
-                        k.key.to_uppercase()
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join("+");
+    /// Position in the results list of the row for `commands[idx]`
+    fn row_of(&self, idx: usize) -> Option<usize> {
+        self.ws.rows.iter().position(|row| *row == ResultRow::Command(idx))
+    }
 
-            spans.push(Span::styled(
-                format!(" {} ", keys_str),
-                Style::default().fg(Color::Black).bg(color),
-            ));
+    /// Move the highlight through the selected command's "See also" list; moving up past
+    /// the first entry leaves the list
+    fn move_related(&mut self, delta: isize) {
+        let count = self
+            .selected_command()
+            .map_or(0, |cmd| cmd.related(&self.ws.commands).len());
+        if count == 0 {
+            return;
+        }
+        self.ws.related_index = match (self.ws.related_index, delta) {
+            (None, d) if d > 0 => Some(0),
+            (None, _) => None,
+            (Some(i), d) => i.checked_add_signed(d).map(|i| i.min(count - 1)),
+        };
+    }
 
-            if i < self.cached_frames.len() - 1 {
-                spans.push(Span::styled(" → ", Style::default().fg(Color::DarkGray)));
-            }
+    /// Select the highlighted "See also" command, clearing the search if it's filtered out
+    fn follow_related(&mut self) {
+        let target = self
+            .selected_command()
+            .zip(self.ws.related_index)
+            .and_then(|(cmd, i)| cmd.related(&self.ws.commands).get(i).copied());
+        self.ws.related_index = None;
+        if let Some(target) = target {
+            self.select_command(target);
         }
+    }
 
-        spans
+    /// Select the command at `target`, clearing the search or opening its category if they
+    /// keep it out of the list
+    fn select_command(&mut self, target: usize) {
+        let cmd = &self.ws.commands[target];
+        if !self.ws.section.contains(cmd) {
+            self.ws.section.toggle();
+        }
+        let category = cmd.category.clone();
+        self.ws.collapsed.retain(|c| *c != category);
+        if self.ws.entered().is_some_and(|entered| *entered != category) {
+            self.ws.set_entered(Some(category.clone()));
+        }
+        self.refresh_results();
+        if self.row_of(target).is_none() {
+            self.remember_query();
+            self.ws.query.clear();
+            self.ws.set_filters(&[]);
+            self.refresh_results();
+        }
+        // Rows aren't listed under the start screen's cards
+        if self.showing_start() {
+            self.open_category(category);
+        }
+        // Still missing when it's hidden
+        if let Some(row) = self.row_of(target) {
+            self.ws.selected_index = row;
+        }
     }
 
-    fn get_current_frame_keys(&self) -> Vec<&'static str> {
-        if self.cached_frames.is_empty() {
-            return Vec::new();
+    /// Select the command bound to `keys`, or with them as an alternative; false if there's
+    /// none
+    pub fn select_keys(&mut self, keys: &str) -> bool {
+        let target = self
+            .ws
+            .commands
+            .iter()
+            .position(|cmd| cmd.keys == keys || cmd.alternatives.iter().any(|alt| alt == keys));
+        if let Some(target) = target {
+            self.select_command(target);
         }
+        target.is_some()
+    }
+
+    /// Select the `n`th command listed, counting from 0 and skipping headers; false if fewer
+    /// are listed
+    pub fn select_result(&mut self, n: usize) -> bool {
+        let row = self
+            .ws
+            .rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !matches!(row, ResultRow::Header(..)))
+            .nth(n)
+            .map(|(i, _)| i);
+        if let Some(row) = row {
+            self.ws.selected_index = row;
+        }
+        row.is_some()
+    }
+
+    /// Move the selection by `delta` rows, stopping at either end
+    fn move_selection(&mut self, delta: isize) {
+        if self.ws.rows.is_empty() {
+            return;
+        }
+        let last = self.ws.rows.len() - 1;
+        self.ws.selected_index = self.ws.selected_index.saturating_add_signed(delta).min(last);
+    }
 
-        let current = &self.cached_frames[self.current_frame];
-        let mut result = Vec::new();
-
-        for key in &current.keys {
-            if let Some(static_key) = Self::key_to_static(&key.key) {
-                result.push(static_key);
-            }
-        }
-
-        result
-    }
-
-    fn key_to_static(key: &str) -> Option<&'static str> {
-        match key.to_lowercase().as_str() {
-            "space" => Some("Space"),
-            "ctrl" => Some("Ctrl"),
-            "alt" => Some("Alt"),
-            "shift" => Some("Shift"),
-            "enter" => Some("Enter"),
-            "esc" => Some("Esc"),
-            "tab" => Some("Tab"),
-            "backsp" => Some("Backsp"),
-            "a" => Some("a"),
-            "b" => Some("b"),
-            "c" => Some("c"),
-            "d" => Some("d"),
-            "e" => Some("e"),
-            "f" => Some("f"),
-            "g" => Some("g"),
-            "h" => Some("h"),
-            "i" => Some("i"),
-            "j" => Some("j"),
-            "k" => Some("k"),
-            "l" => Some("l"),
-            "m" => Some("m"),
-            "n" => Some("n"),
-            "o" => Some("o"),
-            "p" => Some("p"),
-            "q" => Some("q"),
-            "r" => Some("r"),
-            "s" => Some("s"),
-            "t" => Some("t"),
-            "u" => Some("u"),
-            "v" => Some("v"),
-            "w" => Some("w"),
-            "x" => Some("x"),
-            "y" => Some("y"),
-            "z" => Some("z"),
-            "0" => Some("0"),
-            "1" => Some("1"),
-            "2" => Some("2"),
-            "3" => Some("3"),
-            "4" => Some("4"),
-            "5" => Some("5"),
-            "6" => Some("6"),
-            "7" => Some("7"),
-            "8" => Some("8"),
-            "9" => Some("9"),
-            "/" => Some("/"),
-            "." => Some("."),
-            "," => Some(","),
-            ";" => Some(";"),
-            "'" => Some("'"),
-            "[" => Some("["),
-            "]" => Some("]"),
-            "\\" => Some("\\"),
-            "-" => Some("-"),
-            "=" => Some("="),
-            "`" => Some("`"),
+    fn page_size(&self) -> isize {
+        self.list_height.get().max(1) as isize
+    }
+
+    /// Index into `commands` of the selected row, if it's a command
+    pub fn selected_command_index(&self) -> Option<usize> {
+        // The keyboard would give the answer away, unless it's the question
+        if let Some(challenge) = &self.challenge {
+            let asking = challenge.reverse && challenge.result.is_none();
+            return asking.then(|| challenge.command());
+        }
+        if let Some(tutorial) = &self.tutorial {
+            return Some(tutorial.command());
+        }
+        if self.tip.is_some() {
+            return self.tip;
+        }
+        // The highlighted card stands in with its first command
+        if self.showing_start() {
+            let cards = self.start_cards();
+            return cards.get(self.ws.card).and_then(|card| card.featured.first().copied());
+        }
+        match self.ws.rows.get(self.ws.selected_index) {
+            Some(ResultRow::Command(idx) | ResultRow::Pinned(idx)) => Some(*idx),
             _ => None,
         }
     }
-}
+
+    fn toggle_favorite(&mut self) {
+        let Some(idx) = self.selected_command_index() else {
+            return;
+        };
+        let favorite = self.state.toggle_favorite(&self.ws.commands[idx]);
+        self.messages.push(if favorite { "Saved favorite" } else { "Removed favorite" });
+        if !favorite {
+            self.undo.push(Undo::Unfavorite(self.ws.commands[idx].clone()));
+        }
+        self.save_user_state();
+        self.refresh_results();
+    }
+
+    fn toggle_pinned(&mut self) {
+        let Some(idx) = self.selected_command_index() else {
+            return;
+        };
+        let limit = self.config.max_pinned;
+        match self.state.toggle_pinned(&self.ws.commands[idx], limit) {
+            Some(true) => self.messages.push("Pinned above the results".to_string()),
+            Some(false) => self.messages.push("Unpinned".to_string()),
+            None => {
+                let full = format!("{} commands pinned already; unpin one first", limit);
+                self.messages.push(full);
+                return;
+            }
+        }
+        self.save_user_state();
+        self.refresh_results();
+    }
+
+    fn toggle_hidden(&mut self) {
+        let Some(idx) = self.selected_command_index() else {
+            return;
+        };
+        let hidden = self.state.toggle_hidden(&self.ws.commands[idx]);
+        self.messages.push(if hidden {
+            "Hidden: search hidden: to see it again · Ctrl+Z: undo"
+        } else {
+            "No longer hidden"
+        });
+        if hidden {
+            self.undo.push(Undo::Hide(self.ws.commands[idx].clone()));
+        }
+        self.save_user_state();
+        self.refresh_results();
+    }
+
+    /// Take back the last hide, unstar or note edit
+    fn undo(&mut self) {
+        let Some(action) = self.undo.pop() else {
+            self.messages.push("Nothing to undo");
+            return;
+        };
+        self.messages.push(action.describe());
+        match &action {
+            Undo::Hide(cmd) => {
+                if self.state.is_hidden(cmd) {
+                    self.state.toggle_hidden(cmd);
+                }
+                self.save_user_state();
+            }
+            Undo::Unfavorite(cmd) => {
+                if !self.state.is_favorite(cmd) {
+                    self.state.toggle_favorite(cmd);
+                }
+                self.save_user_state();
+            }
+            Undo::Note(cmd, note) => {
+                self.overrides.set_note(cmd, note);
+                // A failed write shouldn't end the session
+                if let Err(error) = self.overrides.save() {
+                    self.messages.push(format!("Failed to save: {:#}", error));
+                }
+            }
+        }
+        self.refresh_results();
+    }
+
+    fn toggle_learned(&mut self) {
+        let Some(idx) = self.selected_command_index() else {
+            return;
+        };
+        let learned = self.state.toggle_learned(&self.ws.commands[idx]);
+        self.messages.push(if learned { "Marked as learned" } else { "No longer learned" });
+        self.save_user_state();
+        self.refresh_results();
+    }
+
+    /// Save favorites and the like right away; a failed write shouldn't end the session,
+    /// so it only shows in the status bar
+    fn save_user_state(&mut self) {
+        if let Err(error) = self.state.save() {
+            self.messages.push(format!("Failed to save: {:#}", error));
+        }
+    }
+
+    /// Learned counts for the selected row's category and the whole section,
+    /// e.g. "LSP 12/19 · all 40/259"
+    fn learned_progress(&self) -> Option<String> {
+        let category = match self.ws.rows.get(self.ws.selected_index)? {
+            ResultRow::Header(category, _) => category,
+            ResultRow::Command(idx) | ResultRow::Pinned(idx) => &self.ws.commands[*idx].category,
+        };
+        let section: Vec<&Command> = self
+            .ws
+            .commands
+            .iter()
+            .filter(|cmd| self.ws.section.contains(cmd))
+            .collect();
+        let (learned, total) = self
+            .state
+            .progress(section.iter().copied().filter(|cmd| cmd.category == *category));
+        let (all_learned, all_total) = self.state.progress(section);
+        Some(format!(
+            " ✓ {} {}/{} · all {}/{} ",
+            self.config.categories.name(category),
+            learned,
+            total,
+            all_learned,
+            all_total
+        ))
+    }
+
+    fn open_note_editor(&mut self) {
+        let Some(idx) = self.selected_command_index() else {
+            return;
+        };
+        let mut input = TextInput::default();
+        input.set(self.overrides.note(&self.ws.commands[idx]).unwrap_or_default());
+        self.popup = Some(Popup::Note {
+            command: idx,
+            input,
+        });
+    }
+
+    fn handle_popup_key(&mut self, key: KeyEvent) {
+        match &mut self.popup {
+            Some(Popup::Note { command, input }) => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Enter => {
+                    let (command, input) = (*command, input.as_str().to_string());
+                    let cmd = &self.ws.commands[command];
+                    let old = self.overrides.note(cmd).unwrap_or_default().to_string();
+                    if !old.is_empty() && old != input {
+                        self.undo.push(Undo::Note(cmd.clone(), old));
+                    }
+                    self.overrides.set_note(cmd, &input);
+                    // A failed write shouldn't end the session
+                    match self.overrides.save() {
+                        Ok(()) if input.is_empty() => self.messages.push("Removed note"),
+                        Ok(()) => self.messages.push("Saved note"),
+                        Err(error) => self.messages.push(format!("Failed to save: {:#}", error)),
+                    }
+                    self.popup = None;
+                }
+                KeyCode::Backspace => input.backspace(),
+                KeyCode::Delete => input.delete(),
+                KeyCode::Left => input.move_left(),
+                KeyCode::Right => input.move_right(),
+                KeyCode::Home => input.move_home(),
+                KeyCode::End => input.move_end(),
+                KeyCode::Char(c) => input.insert(c),
+                _ => {}
+            },
+            Some(Popup::NewCommand(form)) => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Enter => match form.to_command() {
+                    Ok(cmd) => self.add_command(cmd),
+                    Err(error) => form.error = Some(error),
+                },
+                KeyCode::Tab | KeyCode::Down => form.next_field(),
+                KeyCode::BackTab | KeyCode::Up => form.prev_field(),
+                KeyCode::Left => form.cycle(false),
+                KeyCode::Right => form.cycle(true),
+                KeyCode::Backspace => form.pop(),
+                KeyCode::Char(c) => form.push(c),
+                _ => {}
+            },
+            Some(Popup::Stats(range)) => match key.code {
+                KeyCode::Esc | KeyCode::F(2) => self.popup = None,
+                KeyCode::Left => *range = range.previous(),
+                KeyCode::Right => *range = range.next(),
+                _ => {}
+            },
+            Some(Popup::Export(form)) => match key.code {
+                KeyCode::Esc => self.popup = None,
+                KeyCode::Enter if matches!(form.result, Some(Ok(_))) => self.popup = None,
+                KeyCode::Enter => {
+                    let (path, format) = (form.path.as_str().trim().to_string(), form.format);
+                    let result = self
+                        .export_commands(&path, format)
+                        .map_err(|error| format!("{:#}", error));
+                    if let Some(Popup::Export(form)) = &mut self.popup {
+                        form.result = Some(result);
+                    }
+                }
+                KeyCode::Tab => form.toggle_format(),
+                KeyCode::Backspace => form.path.backspace(),
+                KeyCode::Delete => form.path.delete(),
+                KeyCode::Left => form.path.move_left(),
+                KeyCode::Right => form.path.move_right(),
+                KeyCode::Home => form.path.move_home(),
+                KeyCode::End => form.path.move_end(),
+                KeyCode::Char(c) => form.path.insert(c),
+                _ => {}
+            },
+            Some(Popup::Conflicts) => match key.code {
+                KeyCode::Esc | KeyCode::F(8) => self.popup = None,
+                KeyCode::Char('b') => self.resolve_conflict(Keep::Builtin),
+                KeyCode::Char('u') => self.resolve_conflict(Keep::User),
+                KeyCode::Char('k') => self.resolve_conflict(Keep::Both),
+                _ => {}
+            },
+            Some(Popup::Problems(scroll)) => match key.code {
+                KeyCode::Esc | KeyCode::F(6) => self.popup = None,
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down => {
+                    let last = self.problems().len().saturating_sub(1);
+                    if let Some(Popup::Problems(scroll)) = &mut self.popup {
+                        *scroll = (*scroll + 1).min(last);
+                    }
+                }
+                _ => {}
+            },
+            Some(Popup::WhatsNew { query, scroll }) => match key.code {
+                KeyCode::Esc | KeyCode::F(9) => self.popup = None,
+                KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                KeyCode::Down | KeyCode::PageDown => {
+                    let last = changelog::lines(&self.changelog.releases, query.as_str()).len();
+                    let step = if key.code == KeyCode::Down { 1 } else { 10 };
+                    *scroll = (*scroll + step).min(last.saturating_sub(1));
+                }
+                KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+                KeyCode::Backspace => {
+                    query.backspace();
+                    *scroll = 0;
+                }
+                KeyCode::Delete => {
+                    query.delete();
+                    *scroll = 0;
+                }
+                KeyCode::Left => query.move_left(),
+                KeyCode::Right => query.move_right(),
+                KeyCode::Home => query.move_home(),
+                KeyCode::End => query.move_end(),
+                KeyCode::Char(c) => {
+                    query.insert(c);
+                    *scroll = 0;
+                }
+                _ => {}
+            },
+            None => {}
+        }
+    }
+
+    /// Take a text snapshot of the selected command's legend, as `lvcheat show --card` draws
+    /// it, and append it to `snapshot_file` or keep it to print on exit
+    fn snapshot_legend(&mut self) {
+        let Some(cmd) = self.selected_command() else {
+            self.messages.push("No command selected to snapshot");
+            return;
+        };
+        let mut cmd = cmd.clone();
+        if let Some(keys) = self.animated_keys() {
+            cmd.keys = keys;
+        }
+        let buf = card::render_card(&cmd, &self.keyboard, &self.leaders);
+        let lines = if self.plain_snapshots {
+            cast::text_lines(&buf)
+        } else {
+            cast::ansi_lines(&buf)
+        };
+        let snapshot = lines.join("\n") + "\n";
+        let Some(path) = &self.snapshot_file else {
+            self.snapshots.push(snapshot);
+            self.messages.push(format!("Legend of {} will be printed on exit", cmd.keys));
+            return;
+        };
+        let written = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| writeln!(file, "{}", snapshot));
+        match written {
+            Ok(()) => self
+                .messages
+                .push(format!("Legend of {} appended to {}", cmd.keys, path.display())),
+            Err(error) => self
+                .messages
+                .push(format!("Couldn't write {}: {}", path.display(), error)),
+        }
+    }
+
+    /// Ask for the user command file, or `overrides.json`, to be opened in `$EDITOR`. A command
+    /// file is created as `commands.json` if there's none yet.
+    fn request_edit(&mut self, overrides: bool) {
+        let path = if overrides {
+            Overrides::default_path()
+        } else {
+            commands::user_commands_path()
+                .or_else(|| commands::config_dir().map(|dir| dir.join("commands.json")))
+        };
+        match path {
+            Some(path) => self.edit = Some(path),
+            None => self.messages.push("No config directory available"),
+        }
+    }
+
+    /// Apply a config reloaded after an edit. The match mode stays as it is, since the config
+    /// only picks the one to start with.
+    pub fn set_config(&mut self, config: Config) {
+        let mode = self.search_engine.mode;
+        self.search_engine = config.search_engine();
+        self.search_engine.mode = mode;
+        self.keyboard = config.keyboard();
+        self.leaders = config.leaders();
+        if !config.animation {
+            self.view_mode = ViewMode::Legend;
+        }
+        self.config = config;
+        // The explored key may not be on the new keyboard
+        let positions = &self.keyboard.key_positions;
+        if self.explorer.is_some_and(|explorer| !positions.contains_key(explorer.key)) {
+            self.explorer = None;
+        }
+        for ws in std::iter::once(&mut self.ws).chain(&mut self.tabs) {
+            self.search_engine.index(&ws.commands);
+            ws.parse_frames(&self.leaders);
+        }
+        self.refresh_results();
+        self.reset_animation();
+    }
+
+    /// Swap in the builtin tab's commands and the overrides freshly loaded after an edit,
+    /// keeping the search, selection and marks
+    pub fn reload(
+        &mut self,
+        commands: Vec<Command>,
+        problems: Vec<Problem>,
+        conflicts: Vec<Conflict>,
+        overrides: Overrides,
+    ) {
+        let selected = self.selected_command().map(Command::id);
+        let ws = if self.tab == 0 { &mut self.ws } else { &mut self.tabs[0] };
+        let marked: Vec<CommandId> = ws.marked.iter().map(|&idx| ws.commands[idx].id()).collect();
+        ws.commands = commands;
+        ws.problems = problems;
+        ws.marked = marked
+            .iter()
+            .filter_map(|id| ws.commands.iter().position(|cmd| cmd.id() == *id))
+            .collect();
+        ws.parse_frames(&self.leaders);
+        self.search_engine.index(&ws.commands);
+        self.overrides = overrides;
+        // Conflicts put off with Esc aren't asked about again on every save
+        let new_conflict = conflicts.iter().any(|conflict| {
+            let id = conflict.user.id();
+            !self.conflicts.iter().any(|old| old.user.id() == id)
+        });
+        self.conflicts = conflicts;
+        self.tip = None;
+        self.refresh_results();
+        let row = selected.and_then(|id| {
+            self.ws.rows.iter().position(|row| {
+                matches!(row, ResultRow::Command(idx) if self.ws.commands[*idx].id() == id)
+            })
+        });
+        self.ws.selected_index = row.unwrap_or(0).min(self.ws.rows.len().saturating_sub(1));
+        self.reset_animation();
+        self.announce_problems();
+        if new_conflict {
+            self.show_conflicts();
+        }
+    }
+
+    /// Add commands loaded after startup to the builtin tab, leaving out any bound the same as
+    /// one it has, and re-run the search to count them in
+    pub fn add_commands(&mut self, commands: Vec<Command>) {
+        let selected = self.selected_command().map(Command::id);
+        let ws = if self.tab == 0 { &mut self.ws } else { &mut self.tabs[0] };
+        let bound: HashSet<CommandId> = ws.commands.iter().map(Command::id).collect();
+        ws.commands.extend(commands.into_iter().filter(|cmd| !bound.contains(&cmd.id())));
+        ws.parse_frames(&self.leaders);
+        self.search_engine.index(&ws.commands);
+        self.refresh_results();
+        let row = selected.as_ref().and_then(|id| {
+            self.ws.rows.iter().position(|row| {
+                matches!(row, ResultRow::Command(idx) if self.ws.commands[*idx].id() == *id)
+            })
+        });
+        self.ws.selected_index = row.unwrap_or(0).min(self.ws.rows.len().saturating_sub(1));
+        if self.selected_command().map(Command::id) != selected {
+            self.reset_animation();
+        }
+    }
+
+    /// Open the conflict resolution screen if there's anything left to settle
+    pub fn show_conflicts(&mut self) {
+        if !self.conflicts.is_empty() {
+            self.popup = Some(Popup::Conflicts);
+        }
+    }
+
+    /// Settle the first conflict, saving the choice for later sessions
+    fn resolve_conflict(&mut self, keep: Keep) {
+        if self.conflicts.is_empty() {
+            return;
+        }
+        let conflict = self.conflicts.remove(0);
+        // The builtin keymaps are in the first tab
+        let ws = if self.tab == 0 { &mut self.ws } else { &mut self.tabs[0] };
+        commands::resolve_conflict(&mut ws.commands, &conflict, keep);
+        ws.parse_frames(&self.leaders);
+        self.overrides.set_resolution(conflict.user.id(), keep);
+        if let Err(error) = self.overrides.save() {
+            self.messages.push(format!("Failed to save the choice: {}", error));
+        }
+        if self.conflicts.is_empty() {
+            self.popup = None;
+            self.messages.push("Every conflict settled");
+        }
+        self.refresh_results();
+        self.reset_animation();
+    }
+
+    /// Mark or unmark the selected command for export
+    fn toggle_marked(&mut self) {
+        let Some(idx) = self.selected_command_index() else {
+            return;
+        };
+        if let Some(pos) = self.ws.marked.iter().position(|&marked| marked == idx) {
+            self.ws.marked.remove(pos);
+            self.messages.push("Unmarked");
+        } else {
+            self.ws.marked.push(idx);
+            self.messages
+                .push(format!("Marked for export ({})", self.ws.marked.len()));
+        }
+    }
+
+    /// What an export writes: the marked commands, or every listed result when none are
+    fn export_set(&self) -> Vec<usize> {
+        if self.ws.marked.is_empty() {
+            self.ws.filtered_results.clone()
+        } else {
+            self.ws.marked.clone()
+        }
+    }
+
+    /// Write the marked or listed commands to `path`, returning where they went
+    fn export_commands(&self, path: &str, format: SheetFormat) -> anyhow::Result<String> {
+        let exported: Vec<&Command> = self
+            .export_set()
+            .into_iter()
+            .map(|idx| &self.ws.commands[idx])
+            .collect();
+        if exported.is_empty() {
+            anyhow::bail!("Nothing to export: no command matches the search");
+        }
+        let contents = match format {
+            SheetFormat::Markdown => output::format_markdown(&exported),
+            SheetFormat::Json => output::format_command_file(&exported)?,
+            SheetFormat::Anki => output::format_anki(&exported, &self.leaders),
+        };
+        let path = match path.strip_prefix("~/") {
+            Some(rest) => dirs::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(path),
+        };
+        std::fs::write(&path, contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        Ok(path.display().to_string())
+    }
+
+    /// Save a new command to the user file and add it to the builtin tab, which the file
+    /// belongs to, selecting it there if that's the tab showing
+    fn add_command(&mut self, cmd: Command) {
+        if let Err(error) = commands::append_user_command(&cmd) {
+            if let Some(Popup::NewCommand(form)) = &mut self.popup {
+                form.error = Some(format!("Failed to save: {}", error));
+            }
+            return;
+        }
+
+        let id = cmd.id();
+        let keys = cmd.keys.clone();
+        let added = Command {
+            source: Source::User,
+            ..cmd
+        };
+        let ws = if self.tab == 0 { &mut self.ws } else { &mut self.tabs[0] };
+        commands::merge_commands(&mut ws.commands, vec![added]);
+        ws.parse_frames(&self.leaders);
+        self.search_engine.index(&ws.commands);
+        self.popup = None;
+        if self.tab != 0 {
+            self.messages.push(format!("Added {} to the main tab", keys));
+            return;
+        }
+        self.messages.push(format!("Added {}", keys));
+        self.ws.query.clear();
+        self.update_search();
+        if let Some(pos) = self.ws.rows.iter().position(|row| match row {
+            ResultRow::Command(idx) => self.ws.commands[*idx].id() == id,
+            ResultRow::Header(..) | ResultRow::Pinned(_) => false,
+        }) {
+            self.ws.selected_index = pos;
+        }
+    }
+
+    pub fn selected_command(&self) -> Option<&Command> {
+        self.selected_command_index()
+            .and_then(|idx| self.ws.commands.get(idx))
+    }
+
+    /// Keys shown on the keyboard: the composed command while composing, else the selection's
+    /// keys or the alternative picked with Alt+←→
+    fn animated_keys(&self) -> Option<String> {
+        match &self.composer {
+            Some(composer) => composer.keys(&self.grammar),
+            None => self.selected_command().map(|cmd| {
+                let keys = self.alternative.and_then(|i| cmd.alternatives.get(i));
+                match (keys, self.played_twin()) {
+                    (Some(keys), _) => keys.clone(),
+                    (None, Some(twin)) => format!("{}{}", cmd.keys, self.ws.commands[twin].keys),
+                    (None, None) => cmd.keys.clone(),
+                }
+            }),
+        }
+    }
+
+    /// The selected command's bracket twin, when both directions are animated
+    fn played_twin(&self) -> Option<usize> {
+        let cmd = self.selected_command().filter(|_| self.both_directions)?;
+        cmd.twin(&self.ws.commands)
+    }
+
+    /// Select the other half of the selected `]x` / `[x` pair
+    fn jump_to_twin(&mut self) {
+        let twin = self.selected_command().and_then(|cmd| cmd.twin(&self.ws.commands));
+        match twin {
+            Some(twin) => self.select_command(twin),
+            None => self.messages.push("Only ]x and [x pairs have a twin"),
+        }
+    }
+
+    fn toggle_both_directions(&mut self) {
+        self.both_directions = !self.both_directions;
+        self.messages.push(if self.both_directions {
+            "Bracket pairs play both directions"
+        } else {
+            "Bracket pairs play one direction"
+        });
+        self.restart_animation();
+    }
+
+    /// Keys to press before the sequence on the keyboard: `v`, to select text for a
+    /// visual-mode binding
+    fn mode_precondition(&self) -> Vec<&'static str> {
+        match self.animated_mode() {
+            Some(Mode::Visual) => self.keyboard.key_name("v").into_iter().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    /// A Vim-style banner under the keyboard for bindings that only work in a mode other than
+    /// normal
+    fn mode_banner(&self) -> Option<Line<'static>> {
+        let (mode, text) = match self.animated_mode()? {
+            Mode::Visual => (Mode::Visual, " -- VISUAL -- select text first "),
+            Mode::Insert => (Mode::Insert, " -- INSERT -- works while typing "),
+            Mode::Terminal => (Mode::Terminal, " -- TERMINAL -- in a terminal buffer "),
+            Mode::OperatorPending => (Mode::OperatorPending, " after an operator (d, c, y...) "),
+            Mode::Select => (Mode::Select, " -- SELECT -- in a snippet placeholder "),
+            Mode::Normal | Mode::Command => return None,
+        };
+        let style = Style::default().fg(color(mode.color())).add_modifier(Modifier::BOLD);
+        Some(Line::styled(text, style))
+    }
+
+    /// The mode of the binding on the keyboard; composed commands are normal-mode ones
+    fn animated_mode(&self) -> Option<Mode> {
+        match &self.composer {
+            Some(_) => None,
+            None => self.selected_command().map(|cmd| cmd.mode),
+        }
+    }
+
+    /// Cycle through animation, legend and both; without animation the legend stays
+    fn toggle_view(&mut self) {
+        if self.config.animation {
+            self.view_mode.toggle();
+        }
+    }
+
+    /// Show another random command as a tip, favoring ones not viewed or learned yet
+    pub fn show_tip(&mut self) {
+        self.tip = tip::pick(&self.ws.commands, &self.state, &mut self.rng, self.tip).or(self.tip);
+        // Nothing to suggest at all
+        if self.tip.is_none() && self.tip_only {
+            self.should_quit = true;
+        }
+    }
+
+    fn handle_tip_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_learned();
+            }
+            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_favorite();
+            }
+            KeyCode::Char(' ' | 'n') | KeyCode::Enter | KeyCode::Right => self.show_tip(),
+            // With --tip any other key is done with it; in the app only Esc and F3 are
+            _ if self.tip_only => self.should_quit = true,
+            KeyCode::Esc | KeyCode::F(3) => self.tip = None,
+            _ => {}
+        }
+    }
+
+    /// The zoomed keyboard still steps through the results, so a session can go on without
+    /// leaving it
+    fn handle_zoomed_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::F(5) | KeyCode::Esc => self.zoomed = false,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.toggle_view();
+            }
+            KeyCode::Down | KeyCode::Tab | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::BackTab | KeyCode::Char('k') => self.move_selection(-1),
+            _ => {}
+        }
+    }
+
+    fn handle_tutorial_key(&mut self, key: KeyEvent) {
+        let Some(tutorial) = &mut self.tutorial else {
+            return;
+        };
+        let frames = self.ws.frames[tutorial.command()].clone();
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Esc => self.should_quit = true,
+            KeyCode::Left => tutorial.back(),
+            // Until the step is typed, every key is an attempt at it
+            _ if !tutorial.is_done(&frames) => tutorial.press(&frames, key),
+            KeyCode::Char(' ' | 'n') | KeyCode::Enter | KeyCode::Right => {
+                // Past the last step is the end of the tutorial
+                self.should_quit = !tutorial.advance();
+            }
+            KeyCode::Char('p') => tutorial.back(),
+            _ => {}
+        }
+    }
+
+    /// Start a new challenge round, showing keys to recall what they do when `reverse`, quitting
+    /// if there's nothing to ask for
+    pub fn start_challenge(&mut self, reverse: bool) {
+        self.challenge = Challenge::new(
+            &self.ws.commands,
+            &self.ws.frames,
+            &self.state,
+            reverse,
+            &mut self.rng,
+            Instant::now(),
+            stats::today(),
+        );
+        self.should_quit = self.challenge.is_none();
+    }
+
+    /// Schedule the challenge's answers so far for review, each in the round's direction
+    fn record_reviews(&mut self) {
+        let Some(challenge) = &mut self.challenge else {
+            return;
+        };
+        let today = stats::today();
+        for (i, right) in challenge.answered.drain(..) {
+            let id = self.ws.commands[i].id();
+            self.state.stats.record_review(id, challenge.reverse, right, today);
+        }
+    }
+
+    fn handle_challenge_key(&mut self, key: KeyEvent) {
+        let Some(challenge) = &mut self.challenge else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Esc => self.should_quit = true,
+            KeyCode::Enter if challenge.result.is_some() => {
+                let reverse = challenge.reverse;
+                self.start_challenge(reverse);
+            }
+            _ if challenge.result.is_some() => {}
+            KeyCode::Tab => challenge.skip(&mut self.rng, Instant::now()),
+            KeyCode::Char(c @ '1'..='9') if challenge.reverse => {
+                let n = c as usize - '1' as usize;
+                challenge.answer(n, &mut self.rng, Instant::now());
+            }
+            _ if challenge.reverse => {}
+            _ => challenge.press(key, &mut self.rng, Instant::now()),
+        }
+        self.record_reviews();
+    }
+
+    fn toggle_composer(&mut self) {
+        self.composer = match self.composer {
+            Some(_) => None,
+            None => Some(Composer::default()),
+        };
+        self.reset_animation();
+    }
+
+    fn handle_composer_key(&mut self, key: KeyEvent) {
+        let Some(composer) = &mut self.composer else {
+            return;
+        };
+        match key.code {
+            KeyCode::Esc => self.composer = None,
+            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.composer = None
+            }
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Tab | KeyCode::BackTab => {
+                composer.toggle_focus()
+            }
+            KeyCode::Down | KeyCode::Char('j') => composer.move_selection(&self.grammar, 1),
+            KeyCode::Up | KeyCode::Char('k') => composer.move_selection(&self.grammar, -1),
+            _ => return,
+        }
+        self.reset_animation();
+    }
+
+    /// Open the key explorer on the selected command's first key, or close it
+    fn toggle_explorer(&mut self) {
+        self.explorer = match self.explorer {
+            Some(_) => None,
+            None => {
+                let first_key = self
+                    .selected_command_index()
+                    .and_then(|idx| self.ws.frames[idx].first())
+                    .and_then(|kf| self.keyboard.frame_ids(kf).last().copied());
+                self.focus = Focus::Keyboard;
+                Some(KeyFilter {
+                    key: first_key.unwrap_or("space"),
+                    first_frame: false,
+                })
+            }
+        };
+        self.update_search();
+    }
+
+    fn handle_explorer_key(&mut self, key: KeyEvent) {
+        let Some(explorer) = &mut self.explorer else {
+            return;
+        };
+        let (dx, dy) = match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                self.should_quit = true;
+                return;
+            }
+            KeyCode::Esc | KeyCode::F(7) => {
+                self.toggle_explorer();
+                return;
+            }
+            KeyCode::Tab => {
+                explorer.first_frame = !explorer.first_frame;
+                self.update_search();
+                return;
+            }
+            KeyCode::Char('n') | KeyCode::PageDown => {
+                self.move_selection(1);
+                return;
+            }
+            KeyCode::Char('p') | KeyCode::PageUp => {
+                self.move_selection(-1);
+                return;
+            }
+            KeyCode::Left | KeyCode::Char('h') => (-1, 0),
+            KeyCode::Right | KeyCode::Char('l') => (1, 0),
+            KeyCode::Up | KeyCode::Char('k') => (0, -1),
+            KeyCode::Down | KeyCode::Char('j') => (0, 1),
+            _ => return,
+        };
+        if let Some(id) = self.keyboard.neighbor(explorer.key, dx, dy) {
+            explorer.key = id;
+            self.update_search();
+        }
+    }
+
+    fn reset_animation(&mut self) {
+        self.ws.related_index = None;
+        self.alternative = None;
+        self.detail_scroll = 0;
+        self.restart_animation();
+    }
+
+    /// Animate the selected command's next alternative, or its previous one for a negative
+    /// `delta`, coming back to its own keys after the last
+    fn cycle_alternative(&mut self, delta: isize) {
+        let count = self.selected_command().map_or(0, |cmd| cmd.alternatives.len());
+        if count == 0 || self.composer.is_some() {
+            return;
+        }
+        // Slot 0 is the command's keys, then one per alternative
+        let slot = self.alternative.map_or(0, |i| i as isize + 1) + delta;
+        self.alternative = (slot.rem_euclid(count as isize + 1) as usize).checked_sub(1);
+        self.restart_animation();
+    }
+
+    /// Parse the animated keys and play them from the start
+    fn restart_animation(&mut self) {
+        self.current_frame = 0;
+        self.animation_start = Instant::now();
+        if self.paused_at.is_some() {
+            self.paused_at = Some(self.animation_start);
+        }
+        self.beat = None;
+        self.cached_frames = match &self.composer {
+            Some(composer) => composer
+                .keys(&self.grammar)
+                .map(|keys| commands::parse_key_string(&keys))
+                .unwrap_or_default(),
+            // Alternatives and twins are parsed as they're picked, the command's own keys only
+            // once
+            None => match self.alternative.or(self.played_twin()) {
+                Some(_) => self
+                    .animated_keys()
+                    .map(|keys| commands::parse_key_string_with(&keys, &self.leaders))
+                    .unwrap_or_default(),
+                None => self
+                    .selected_command_index()
+                    .and_then(|idx| self.ws.frames.get(idx))
+                    .cloned()
+                    .unwrap_or_default(),
+            },
+        };
+        tracing::debug!(keys = ?self.animated_keys(), frames = ?self.cached_frames, "animating");
+        // Without animation, rest on the finished sequence (a fully typed command line,
+        // the simulation's end result)
+        if !self.config.animation {
+            self.current_frame = self.cached_frames.len().saturating_sub(1);
+        }
+        self.simulation = match (&self.composer, self.selected_command()) {
+            _ if !self.config.simulation => None,
+            (Some(_), _) => simulate::simulate(&self.cached_frames, Mode::Normal, None),
+            (None, Some(cmd)) => {
+                simulate::simulate(&self.cached_frames, cmd.mode, cmd.diagram.as_ref())
+            }
+            (None, None) => None,
+        };
+        self.last_selected = self.selected_command_index();
+        self.selected_at = Instant::now();
+        self.settling = None;
+        self.view_recorded = false;
+    }
+
+    /// Leave the keyboard unlit under the new selection's keys until the selection settles,
+    /// rather than parse and animate every command scrolled past
+    fn preview_selection(&mut self) {
+        self.ws.related_index = None;
+        self.alternative = None;
+        self.detail_scroll = 0;
+        self.current_frame = 0;
+        self.cached_frames.clear();
+        self.simulation = None;
+        self.last_selected = self.selected_command_index();
+        self.settling = Some(Instant::now());
+    }
+
+    fn settle(&self) -> Duration {
+        Duration::from_millis(self.config.settle_ms)
+    }
+
+    pub fn tick(&mut self) {
+        self.messages.tick(Instant::now());
+        if let Some(challenge) = &mut self.challenge {
+            if challenge.time_left(Instant::now()).is_zero() {
+                challenge.finish(&mut self.state.stats, stats::today());
+            }
+        }
+
+        // Check if selection changed. A move soon after the last one only previews the keys,
+        // so holding an arrow key doesn't restart the animation on every row.
+        let current_selected = self.selected_command_index();
+        if current_selected != self.last_selected {
+            if self.settling.is_some() || self.selected_at.elapsed() < self.settle() {
+                self.preview_selection();
+            } else {
+                self.reset_animation();
+            }
+        }
+        if self.settling.is_some_and(|at| at.elapsed() >= self.settle()) {
+            self.reset_animation();
+        }
+
+        // Count the selection as viewed once it has been looked at for a moment
+        if !self.view_recorded
+            && self.composer.is_none()
+            && !self.showing_start()
+            && self.selected_at.elapsed() >= Duration::from_millis(VIEW_THRESHOLD_MS)
+        {
+            if let Some(idx) = self.selected_command_index() {
+                self.state.record_view(&self.ws.commands[idx]);
+                self.state
+                    .stats
+                    .record_view(self.ws.commands[idx].category.clone(), stats::today());
+                // The query led somewhere, so it's worth recalling later
+                if !self.history.is_browsing() {
+                    self.remember_query();
+                }
+            }
+            self.view_recorded = true;
+        }
+
+        // The frame showing follows from the time since the animation started, so a slow
+        // draw or a long tick skips ahead instead of slowing the animation down
+        if self.config.animation && !self.cached_frames.is_empty() && self.paused_at.is_none() {
+            let frames = self.animation_start.elapsed().as_millis() / self.frame_ms() as u128;
+            self.current_frame = (frames % self.cached_frames.len() as u128) as usize;
+            // A single frame never changes, so it gets no beat
+            let beat = self.beat != Some(frames) && self.cached_frames.len() > 1;
+            if beat && self.config.metronome.rings() {
+                ring_bell();
+            }
+            self.beat = Some(frames);
+        }
+    }
+
+    /// Hold the animation still while the terminal is in the background, so a cheatsheet left
+    /// open in another pane doesn't keep redrawing, and go on from the same frame once it's back
+    fn set_terminal_focused(&mut self, focused: bool) {
+        match (focused, self.paused_at) {
+            (false, None) => self.paused_at = Some(Instant::now()),
+            (true, Some(at)) => {
+                self.animation_start += at.elapsed();
+                self.paused_at = None;
+            }
+            _ => {}
+        }
+    }
+
+    fn frame_ms(&self) -> u64 {
+        self.config.frame_ms.max(1)
+    }
+
+    /// How long the metronome's pulse shows after each frame change
+    fn pulse_ms(&self) -> u64 {
+        (self.frame_ms() / 3).clamp(1, PULSE_MS)
+    }
+
+    /// Whether the metronome's pulse is showing, just after the animation moved on a frame
+    fn pulsing(&self) -> bool {
+        let into_frame = self.animation_start.elapsed().as_millis() % self.frame_ms() as u128;
+        self.config.metronome.pulses()
+            && self.config.animation
+            && self.paused_at.is_none()
+            && self.cached_frames.len() > 1
+            && into_frame < self.pulse_ms() as u128
+    }
+
+    /// How long to wait for input: a tick, or less when the next animation frame is due sooner,
+    /// or longer while the terminal is in the background
+    fn poll_timeout(&self) -> Duration {
+        let tick = Duration::from_millis(self.config.tick_ms.max(1));
+        if self.paused_at.is_some() {
+            return tick.max(Duration::from_millis(BACKGROUND_TICK_MS));
+        }
+        if let Some(at) = self.settling {
+            return tick.min(self.settle().saturating_sub(at.elapsed()));
+        }
+        if !self.config.animation || self.cached_frames.len() < 2 {
+            return tick;
+        }
+        let frame_ms = self.frame_ms() as u128;
+        let into_frame = (self.animation_start.elapsed().as_millis() % frame_ms) as u64;
+        // Wake up to take the pulse down too
+        let next = if self.pulsing() { self.pulse_ms() } else { frame_ms as u64 };
+        tick.min(Duration::from_millis(next - into_frame))
+    }
+
+    pub fn handle_input(&mut self) -> anyhow::Result<()> {
+        let mut timeout = self.poll_timeout();
+        if let Some(wake_at) = self.wake_at {
+            timeout = timeout.min(wake_at.saturating_duration_since(Instant::now()));
+        }
+        if event::poll(timeout)? {
+            self.handle_event(event::read()?)?;
+        }
+        Ok(())
+    }
+
+    /// Act on one terminal event, as read by `handle_input` or made up by a test
+    pub fn handle_event(&mut self, event: Event) -> anyhow::Result<()> {
+        match event {
+            Event::FocusLost => self.set_terminal_focused(false),
+            Event::FocusGained => self.set_terminal_focused(true),
+            _ => {}
+        }
+        if let Event::Key(key) = event {
+            // The kitty keyboard protocol also reports releases and lone modifiers; only
+            // presses of actual keys are acted on
+            if key.kind == KeyEventKind::Release || matches!(key.code, KeyCode::Modifier(_)) {
+                return Ok(());
+            }
+            tracing::debug!(code = ?key.code, modifiers = ?key.modifiers, "key");
+            if let Some(debug) = &mut self.debug {
+                debug.event_at = Some(Instant::now());
+                if key.code == KeyCode::F(12) {
+                    debug.shown = !debug.shown;
+                    return Ok(());
+                }
+            }
+            if self.popup.is_some() {
+                self.handle_popup_key(key);
+                return Ok(());
+            }
+            if self.composer.is_some() {
+                self.handle_composer_key(key);
+                return Ok(());
+            }
+            if self.challenge.is_some() {
+                self.handle_challenge_key(key);
+                return Ok(());
+            }
+            if self.tutorial.is_some() {
+                self.handle_tutorial_key(key);
+                return Ok(());
+            }
+            if self.tip.is_some() {
+                self.handle_tip_key(key);
+                return Ok(());
+            }
+            if self.zoomed {
+                self.handle_zoomed_key(key);
+                return Ok(());
+            }
+            if self.explorer.is_some() {
+                self.handle_explorer_key(key);
+                return Ok(());
+            }
+            if self.showing_start() && self.handle_start_key(key) {
+                return Ok(());
+            }
+            let handled = match self.focus {
+                _ if !self.is_shown(self.focus) => false,
+                Focus::Search => false,
+                Focus::Results => self.handle_results_key(key),
+                Focus::Keyboard => self.handle_keyboard_key(key),
+                Focus::Detail => self.handle_detail_key(key),
+            };
+            if handled {
+                return Ok(());
+            }
+
+            match key.code {
+                KeyCode::Down if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.move_related(1);
+                }
+                KeyCode::Up if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.move_related(-1);
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.cycle_alternative(1);
+                }
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.cycle_alternative(-1);
+                }
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.jump_to_twin();
+                }
+                KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.toggle_both_directions();
+                }
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.toggle_pinned();
+                }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.toggle_sort();
+                }
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.narrating = !self.narrating;
+                }
+                KeyCode::Char(c @ '0'..='9') if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.pick_counted_category(c as usize - '0' as usize);
+                }
+                KeyCode::Esc if self.ws.related_index.is_some() => {
+                    self.ws.related_index = None;
+                }
+                KeyCode::Esc => {
+                    if !self.ws.query.is_empty() {
+                        self.remember_query();
+                        self.ws.query.clear();
+                        self.update_search();
+                    } else if !self.ws.trail.is_empty() {
+                        self.go_back();
+                    } else {
+                        self.should_quit = true;
+                    }
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.should_quit = true;
+                }
+                KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_tab(false);
+                }
+                KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_tab(true);
+                }
+                KeyCode::Char('v') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_view();
+                }
+                KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_favorite();
+                }
+                KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.open_note_editor();
+                }
+                KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_hidden();
+                }
+                KeyCode::Char('z') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.undo();
+                }
+                KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.recall_history(true);
+                }
+                // Ctrl+N steps forward right after Ctrl+P or Ctrl+N recalled a query, as long
+                // as it isn't edited, and adds a command otherwise
+                KeyCode::Char('n')
+                    if key.modifiers.contains(KeyModifiers::CONTROL)
+                        && self.history.is_browsing() =>
+                {
+                    self.recall_history(false);
+                }
+                KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.popup = Some(Popup::NewCommand(CommandForm::default()));
+                }
+                KeyCode::Char('d') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.move_selection(self.page_size() / 2);
+                }
+                // Readline kill; the focused results take it for half a page up instead
+                KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    let killed = self.ws.query.cursor() > 0;
+                    self.ws.query.kill_to_start();
+                    // Searching again for the same query would lose the selection
+                    if killed {
+                        self.update_search();
+                    }
+                }
+                KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.ws.query.delete_word();
+                    self.update_search();
+                }
+                KeyCode::Char('a') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.ws.query.move_home();
+                }
+                KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.ws.query.move_end();
+                }
+                KeyCode::Char('f') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.narrow();
+                }
+                KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_composer();
+                }
+                KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.cycle_match_mode();
+                }
+                KeyCode::Char('g') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_list_mode();
+                }
+                KeyCode::Char('k') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_section();
+                }
+                KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_marked();
+                }
+                KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    self.toggle_learned();
+                }
+                KeyCode::Char(c) => {
+                    self.ws.query.insert(c);
+                    self.update_search();
+                }
+                KeyCode::F(2) => self.popup = Some(Popup::Stats(StatsRange::default())),
+                KeyCode::F(3) => self.show_tip(),
+                KeyCode::F(4) if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                    self.snapshot_legend();
+                }
+                KeyCode::F(1) => self.explaining = !self.explaining,
+                KeyCode::F(4) => self.popup = Some(Popup::Export(ExportForm::default())),
+                KeyCode::F(5) => self.zoomed = true,
+                KeyCode::F(6) => self.popup = Some(Popup::Problems(0)),
+                KeyCode::F(7) => self.toggle_explorer(),
+                KeyCode::F(8) if self.conflicts.is_empty() => {
+                    self.messages.push("No conflicts with the builtin keymaps");
+                }
+                KeyCode::F(8) => self.show_conflicts(),
+                KeyCode::F(10) => {
+                    self.request_edit(key.modifiers.contains(KeyModifiers::SHIFT));
+                }
+                KeyCode::F(9) => {
+                    self.popup = Some(Popup::WhatsNew {
+                        query: TextInput::default(),
+                        scroll: 0,
+                    });
+                }
+                KeyCode::Backspace => {
+                    self.ws.query.backspace();
+                    self.update_search();
+                }
+                KeyCode::Delete => {
+                    self.ws.query.delete();
+                    self.update_search();
+                }
+                KeyCode::Left => self.ws.query.move_left(),
+                KeyCode::Right => self.ws.query.move_right(),
+                KeyCode::Enter if self.ws.related_index.is_some() => self.follow_related(),
+                KeyCode::Enter if self.tmux_popup && self.selected_command().is_some() => {
+                    self.picked = self.selected_command_index();
+                    self.should_quit = true;
+                }
+                KeyCode::Enter => self.toggle_collapsed(),
+                KeyCode::PageDown if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.scroll_detail(3);
+                }
+                KeyCode::PageUp if key.modifiers.contains(KeyModifiers::ALT) => {
+                    self.scroll_detail(-3);
+                }
+                KeyCode::PageDown => self.move_selection(self.page_size()),
+                KeyCode::PageUp => self.move_selection(-self.page_size()),
+                KeyCode::Home => self.ws.selected_index = 0,
+                KeyCode::End => self.ws.selected_index = self.ws.rows.len().saturating_sub(1),
+                KeyCode::Tab => self.cycle_focus(true),
+                KeyCode::BackTab => self.cycle_focus(false),
+                KeyCode::Down if !self.ws.rows.is_empty() => {
+                    self.ws.selected_index = (self.ws.selected_index + 1) % self.ws.rows.len();
+                }
+                KeyCode::Up if !self.ws.rows.is_empty() => {
+                    self.ws.selected_index = if self.ws.selected_index == 0 {
+                        self.ws.rows.len() - 1
+                    } else {
+                        self.ws.selected_index - 1
+                    };
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, frame: &mut Frame) {
+        self.panes_shown.set(0);
+        if self.zoomed {
+            self.draw_zoomed(frame);
+            return;
+        }
+        // A tmux popup is small, so it drops the margin and, when short, shows just the
+        // sequence bar in place of the keyboard. Other short terminals get a row of chips.
+        let margin = u16::from(!self.tmux_popup);
+        let compact = frame.area().height < KEYBOARD_MIN_HEIGHT + 2 * margin;
+        let arrangement = if compact {
+            Arrangement::Single
+        } else {
+            self.arrangement(frame.area())
+        };
+        // The sequence bar goes under the keyboard, on as many lines as it wraps onto
+        let legend_height = self.legend_height(KEYBOARD_WIDTH);
+        let keyboard_height = match arrangement {
+            _ if compact && self.tmux_popup => 1,
+            _ if compact => CHIPS_HEIGHT,
+            Arrangement::Stacked => 2 * (KEYBOARD_HEIGHT + legend_height),
+            _ => KEYBOARD_HEIGHT + legend_height,
+        };
+        let keyboard_width = match arrangement {
+            Arrangement::SideBySide => 2 * KEYBOARD_WIDTH,
+            _ => KEYBOARD_WIDTH,
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(margin)
+            .constraints([
+                Constraint::Length(3),  // Search input
+                Constraint::Min(if compact { 3 } else { 8 }), // Results list
+                Constraint::Length(keyboard_height), // Keyboard
+                Constraint::Length(1),  // Status bar
+            ])
+            .split(frame.area());
+
+        self.draw_search_input(frame, chunks[0]);
+        if let Some(challenge) = &self.challenge {
+            self.draw_challenge(frame, chunks[1], challenge);
+        } else {
+            match (&self.composer, &self.tutorial, self.tip) {
+                (Some(composer), _, _) => {
+                    composer::draw_picker(frame, chunks[1], &self.grammar, composer)
+                }
+                (None, Some(tutorial), _) => self.draw_tutorial(frame, chunks[1], tutorial),
+                (None, None, Some(idx)) => self.draw_tip(frame, chunks[1], idx),
+                (None, None, None) if self.showing_start() => self.draw_start(frame, chunks[1]),
+                (None, None, None) => self.draw_results_list(frame, chunks[1]),
+            }
+        }
+
+        // Detail pane beside the keyboard when there's room for it
+        if compact && self.tmux_popup {
+            let spans = fit_spans(self.build_legend_bar(None).concat(), chunks[2].width as usize);
+            frame.render_widget(Paragraph::new(Line::from(spans)), chunks[2]);
+        } else if compact && self.config.plain {
+            self.draw_spoken_sequence(frame, chunks[2]);
+        } else if compact {
+            let animating = self.config.animation && self.view_mode != ViewMode::Legend;
+            let current = animating.then_some(self.current_frame);
+            let chips = self.build_chips(current, chunks[2].width as usize);
+            frame.render_widget(Paragraph::new(chips), chunks[2]);
+        } else if chunks[2].width >= keyboard_width + DETAIL_MIN_WIDTH {
+            let bottom = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Length(keyboard_width), Constraint::Min(0)])
+                .split(chunks[2]);
+            self.draw_keyboard(frame, bottom[0]);
+            // Mouse pseudo-keys light a mouse under the detail pane
+            let presses = self.mouse_presses();
+            let side = if presses.is_empty() || bottom[1].height < 2 * MOUSE_HEIGHT {
+                bottom[1]
+            } else {
+                let side = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([Constraint::Min(0), Constraint::Length(MOUSE_HEIGHT)])
+                    .split(bottom[1]);
+                mouse::draw_mouse(frame, side[1], &presses);
+                side[0]
+            };
+            // Ex commands show the command line filling in as the keys are typed
+            let typing = self.composer.is_none()
+                && !self.cached_frames.is_empty()
+                && self.selected_command().is_some_and(Command::is_ex);
+            match self.current_snapshot() {
+                Some(snapshot) => {
+                    let height = snapshot.lines.len() as u16 + 2;
+                    let right = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(0), Constraint::Length(height)])
+                        .split(side);
+                    self.draw_detail(frame, right[0]);
+                    draw_simulation(frame, right[1], snapshot);
+                }
+                None if typing => {
+                    let right = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Min(0), Constraint::Length(3)])
+                        .split(side);
+                    self.draw_detail(frame, right[0]);
+                    let typed = self.current_frame.min(self.cached_frames.len() - 1);
+                    draw_command_line(frame, right[1], &self.cached_frames[..=typed]);
+                }
+                None => self.draw_detail(frame, side),
+            }
+        } else {
+            self.draw_keyboard(frame, chunks[2]);
+        }
+
+        self.draw_status_bar(frame, chunks[3]);
+
+        match &self.popup {
+            Some(Popup::Note { command, input }) => {
+                let title = format!(" Note for {} ", self.ws.commands[*command].keys);
+                popup::draw_input(frame, &title, input, "Enter: save (empty removes) · Esc: cancel");
+            }
+            Some(Popup::NewCommand(form)) => popup::draw_form(frame, form, &self.config.categories),
+            Some(Popup::Stats(range)) => {
+                stats::draw_dashboard(
+                    frame,
+                    &self.state.stats,
+                    *range,
+                    stats::today(),
+                    &self.config.categories,
+                )
+            }
+            Some(Popup::Export(form)) => {
+                let which = if self.ws.marked.is_empty() { "listed" } else { "marked" };
+                popup::draw_export(frame, form, self.export_set().len(), which)
+            }
+            Some(Popup::Problems(scroll)) => popup::draw_problems(frame, &self.problems(), *scroll),
+            Some(Popup::Conflicts) => {
+                if let Some(conflict) = self.conflicts.first() {
+                    popup::draw_conflict(frame, conflict, self.conflicts.len());
+                }
+            }
+            Some(Popup::WhatsNew { query, scroll }) => {
+                changelog::draw_whats_new(frame, &self.changelog.releases, query, *scroll)
+            }
+            None => {}
+        }
+
+        if let Some(debug) = self.debug.as_ref().filter(|debug| debug.shown) {
+            debug::draw_overlay(
+                frame,
+                debug,
+                self.animated_keys().as_deref(),
+                &self.cached_frames,
+                self.current_frame,
+                &self.keyboard,
+            );
+        }
+
+        self.config.theme.apply(frame.buffer_mut());
+        self.config.colors.apply(frame.buffer_mut());
+    }
+
+    /// Just the keyboard, large when it fits, over the status bar
+    fn draw_zoomed(&self, frame: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(u16::from(!self.tmux_popup))
+            .constraints([Constraint::Min(0), Constraint::Length(1)])
+            .split(frame.area());
+        let area = chunks[0];
+
+        if self.config.plain {
+            self.draw_spoken_sequence(frame, area);
+        } else {
+            let (width, height) = if self.large_keyboard_fits(area) {
+                (ZOOMED_KEYBOARD_WIDTH, ZOOMED_KEYBOARD_HEIGHT)
+            } else {
+                (KEYBOARD_WIDTH, KEYBOARD_HEIGHT)
+            };
+            let height = height + self.legend_height(width);
+            let area = popup::centered_rect(width, height, area);
+            match self.view_mode {
+                ViewMode::Legend => self.draw_keyboard_legend(frame, area, " (F5: back)"),
+                _ => self.draw_keyboard_animation(frame, area, " (F5: back)"),
+            }
+        }
+        self.draw_status_bar(frame, chunks[1]);
+
+        self.config.theme.apply(frame.buffer_mut());
+        self.config.colors.apply(frame.buffer_mut());
+    }
+
+    /// Whether the zoomed view has room for the large keyboard and its sequence bar
+    fn large_keyboard_fits(&self, area: Rect) -> bool {
+        self.zoomed && area.width >= ZOOMED_KEYBOARD_WIDTH && area.height > ZOOMED_KEYBOARD_HEIGHT
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: Rect) {
+        if let Some(composer) = &self.composer {
+            self.draw_composition(frame, area, composer);
+            return;
+        }
+
+        let block = Block::default().borders(Borders::ALL).title("Details");
+        // A reverse challenge asks for what's written here
+        let Some(cmd) = self.selected_command().filter(|_| self.challenge.is_none()) else {
+            frame.render_widget(block, area);
+            return;
+        };
+
+        if self.explaining {
+            self.draw_explain(frame, area, cmd);
+            return;
+        }
+
+        let label = Style::default().fg(Color::DarkGray);
+        let mut lines = vec![
+            Line::from(Span::styled(
+                cmd.description.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Keys:     ", label),
+                Span::styled(cmd.keys.clone(), Style::default().fg(Color::Cyan)),
+            ]),
+        ];
+        if cmd.is_workflow() {
+            lines.extend(self.workflow_lines(cmd));
+        }
+        if !cmd.alternatives.is_empty() {
+            // The one on the keyboard is highlighted
+            let mut or_press = vec![Span::styled("Or press: ", label)];
+            for (i, keys) in cmd.alternatives.iter().enumerate() {
+                let mut style = Style::default().fg(Color::Cyan);
+                if self.alternative == Some(i) {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                if i > 0 {
+                    or_press.push(Span::raw(", "));
+                }
+                or_press.push(Span::styled(keys.clone(), style));
+            }
+            or_press.push(Span::styled("  (Alt+←→ to animate)", label));
+            lines.push(Line::from(or_press));
+        }
+        if let Some(twin) = cmd.twin(&self.ws.commands) {
+            let other = &self.ws.commands[twin];
+            let both = if self.both_directions { "one way" } else { "both ways" };
+            lines.push(Line::from(vec![
+                Span::styled("Twin:     ", label),
+                Span::styled(other.keys.clone(), Style::default().fg(Color::Cyan)),
+                Span::raw(format!(" {}", other.description)),
+                Span::styled(format!("  (Alt+T: go, Alt+B: play {})", both), label),
+            ]));
+        }
+        lines.extend([
+            Line::from(vec![
+                Span::styled("Category: ", label),
+                self.category_span(&cmd.category),
+            ]),
+            Line::from(vec![
+                Span::styled("Source:   ", label),
+                Span::styled(cmd.source.label().to_string(), Style::default().fg(SOURCE_COLOR)),
+            ]),
+            Line::from(vec![
+                Span::styled("Mode:     ", label),
+                mode_chip(cmd.mode),
+                Span::raw(format!(" {}", cmd.mode.as_str())),
+            ]),
+        ]);
+        // The same keys bound in other modes, where they may do something else
+        for idx in cmd.other_modes(&self.ws.commands) {
+            let other = &self.ws.commands[idx];
+            let mut also = vec![
+                Span::styled("  also    ", label),
+                mode_chip(other.mode),
+                Span::raw(format!(" {}", other.mode.as_str())),
+            ];
+            if other.description != cmd.description {
+                also.push(Span::styled(format!(": {}", other.description), label));
+            }
+            lines.push(Line::from(also));
+        }
+        if !cmd.tags.is_empty() {
+            let tags: Vec<String> = cmd.tags.iter().map(|t| format!("#{}", t)).collect();
+            lines.push(Line::from(vec![
+                Span::styled("Tags:     ", label),
+                Span::raw(tags.join(" ")),
+            ]));
+        }
+        if let Some(mnemonic) = &cmd.mnemonic {
+            lines.push(Line::from(vec![
+                Span::styled("Remember: ", label),
+                Span::styled(mnemonic.clone(), Style::default().fg(Color::Yellow)),
+            ]));
+        }
+        let related = cmd.related(&self.ws.commands);
+        // A workflow's step commands are listed with its steps
+        let linked = cmd.step_commands(&self.ws.commands).iter().flatten().count();
+        if related.len() > linked {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("See also (Alt+↑↓, Enter to go)", label)));
+            for (i, &idx) in related.iter().enumerate().skip(linked) {
+                let other = &self.ws.commands[idx];
+                let (marker, style) = if self.ws.related_index == Some(i) {
+                    ("▶ ", Style::default().add_modifier(Modifier::REVERSED))
+                } else {
+                    ("  ", Style::default())
+                };
+                lines.push(Line::from(vec![
+                    Span::raw(marker),
+                    Span::styled(other.keys.clone(), style.fg(Color::Cyan)),
+                    Span::styled(format!(" {}", other.description), style),
+                ]));
+            }
+        }
+        if let Some(diagram) = &cmd.diagram {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("Example", label)));
+            lines.extend(diagram_lines(diagram));
+        }
+        if let Some(note) = self.overrides.note(cmd) {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled("📝 Note", label)));
+            lines.push(Line::from(note.to_string()));
+        }
+        if let Some(doc) = &cmd.doc {
+            lines.push(Line::from(""));
+            lines.push(Line::from(Span::styled(format!("📖 From {}", doc.plugin), label)));
+            lines.push(Line::from(doc.text.clone()));
+        }
+
+        frame.render_widget(self.scrolled_detail(lines, "Details", area), area);
+    }
+
+    /// A workflow's steps in order, the one the keyboard is animating marked and those before
+    /// it checked off
+    fn workflow_lines(&self, cmd: &Command) -> Vec<Line<'static>> {
+        let label = Style::default().fg(Color::DarkGray);
+        // Alternatives and twins animate other keys
+        let animating = self.alternative.is_none() && self.played_twin().is_none();
+        let mut end = 0;
+        let current = cmd.step_frames(&self.leaders).iter().position(|frames| {
+            end += frames.len();
+            self.current_frame < end
+        });
+        let current = current.filter(|_| animating);
+
+        let mut heading = match current {
+            Some(step) => format!("Workflow, step {} of {}", step + 1, cmd.workflow.len()),
+            None => format!("Workflow of {} steps", cmd.workflow.len()),
+        };
+        // Steps running a listed command lead to it, first in the "See also" order
+        let links = cmd.step_commands(&self.ws.commands);
+        if links.iter().any(Option::is_some) {
+            heading.push_str(" (Alt+↑↓, Enter to go)");
+        }
+        let mut lines = vec![Line::from(""), Line::from(Span::styled(heading, label))];
+        let mut link = 0;
+        for (i, step) in cmd.workflow.iter().enumerate() {
+            let (marker, mut style) = match current {
+                Some(at) if i < at => ("✓", label),
+                Some(at) if i == at => ("▶", Style::default().add_modifier(Modifier::BOLD)),
+                _ => (" ", Style::default()),
+            };
+            if links[i].is_some() {
+                if self.ws.related_index == Some(link) {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                link += 1;
+            }
+            let mut line = vec![
+                Span::styled(format!("{} {}. ", marker, i + 1), style),
+                Span::styled(format!("{:<12} ", step.keys), style.fg(Color::Cyan)),
+                Span::styled(step.description.clone(), style),
+            ];
+            if let Some(app) = &step.app {
+                line.push(Span::styled(format!("  in {}", app), label));
+            }
+            lines.push(Line::from(line));
+        }
+        lines.push(Line::from(""));
+        lines
+    }
+
+    /// The detail pane's `lines` scrolled by `detail_scroll`, in a block that says how far and
+    /// stands out while focused
+    fn scrolled_detail<'a>(&self, lines: Vec<Line<'a>>, title: &str, area: Rect) -> Paragraph<'a> {
+        let border = self.pane_border(Focus::Detail);
+        // Rows once wrapped, roughly, since words that don't fit move down whole
+        let inner = area.width.saturating_sub(2).max(1) as usize;
+        let rows: usize = lines.iter().map(|line| line.width().div_ceil(inner).max(1)).sum();
+        self.detail_lines.set((rows as u16, area.height.saturating_sub(2)));
+        // Scrolling stops with the last line at the top
+        let scroll = self.detail_scroll.min(rows.saturating_sub(1) as u16);
+        let focused = self.focus == Focus::Detail;
+        let title = match (scroll, focused) {
+            (0, false) => title.to_string(),
+            (0, true) => format!("{} (↑↓ Ctrl+E/Y: scroll · Esc: search)", title),
+            (_, false) => format!("{} ↑{} (Alt+PgUp/PgDn)", title, scroll),
+            (_, true) => format!("{} ↑{} (↑↓ Ctrl+E/Y: scroll · Esc: search)", title, scroll),
+        };
+        let block = Block::default().borders(Borders::ALL).border_style(border).title(title);
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0))
+            .block(block)
+    }
+
+    /// Scroll the detail pane down `delta` lines, or up for a negative one, stopping with its
+    /// last line at the top
+    fn scroll_detail(&mut self, delta: i32) {
+        let (lines, _) = self.detail_lines.get();
+        let last = (i32::from(lines) - 1).max(0);
+        self.detail_scroll = (i32::from(self.detail_scroll) + delta).clamp(0, last) as u16;
+    }
+
+    /// Whether the last draw showed `pane`
+    fn is_shown(&self, pane: Focus) -> bool {
+        self.panes_shown.get() & pane.bit() != 0
+    }
+
+    /// Note that `pane` is on screen, returning the style for its border: highlighted when
+    /// it has the focus
+    fn pane_border(&self, pane: Focus) -> Style {
+        self.panes_shown.set(self.panes_shown.get() | pane.bit());
+        if self.focus == pane {
+            Style::default().fg(FOCUS_COLOR)
+        } else {
+            Style::default()
+        }
+    }
+
+    /// Focus the next pane on screen, or the previous one
+    fn cycle_focus(&mut self, forward: bool) {
+        let count = Focus::ALL.len();
+        let current = Focus::ALL.iter().position(|&pane| pane == self.focus).unwrap_or(0);
+        let step = if forward { 1 } else { count - 1 };
+        self.focus = (1..count)
+            .map(|i| Focus::ALL[(current + i * step) % count])
+            .find(|&pane| self.is_shown(pane))
+            .unwrap_or(Focus::Search);
+    }
+
+    /// Keys the focused results list takes: vi-style moves, Ctrl+D and Ctrl+U by half a page,
+    /// and Esc back to the search. Other letters return the focus to the search and are typed
+    /// into it.
+    fn handle_results_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers == KeyModifiers::CONTROL;
+        match key.code {
+            KeyCode::Char('d') if ctrl => self.move_selection(self.page_size() / 2),
+            KeyCode::Char('u') if ctrl => self.move_selection(-(self.page_size() / 2)),
+            _ if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => {
+                return false
+            }
+            KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Char('g') => self.ws.selected_index = 0,
+            KeyCode::Char('G') => {
+                self.ws.selected_index = self.ws.rows.len().saturating_sub(1)
+            }
+            KeyCode::Esc => self.focus = Focus::Search,
+            KeyCode::Char(_) => {
+                self.focus = Focus::Search;
+                return false;
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keys the focused keyboard takes: the arrows, vi-style moves and Enter open the key
+    /// explorer on the selected command's first key, and Esc goes back to the search
+    fn handle_keyboard_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Enter => {
+                self.toggle_explorer()
+            }
+            KeyCode::Char('h' | 'j' | 'k' | 'l') => self.toggle_explorer(),
+            KeyCode::Esc => self.focus = Focus::Search,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Keys the focused detail pane takes: scrolling, Tab and Shift+Tab on to the next or
+    /// previous pane shown, and Esc back to the search. Returns false for the rest, which act
+    /// as usual.
+    fn handle_detail_key(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let page = self.detail_lines.get().1.saturating_sub(1).max(1) as i32;
+        match key.code {
+            KeyCode::Down => self.scroll_detail(1),
+            KeyCode::Up => self.scroll_detail(-1),
+            KeyCode::Char('e') if ctrl => self.scroll_detail(1),
+            KeyCode::Char('y') if ctrl => self.scroll_detail(-1),
+            KeyCode::PageDown => self.scroll_detail(page),
+            KeyCode::PageUp => self.scroll_detail(-page),
+            KeyCode::Char('d') if ctrl => self.scroll_detail(page / 2),
+            KeyCode::Char('u') if ctrl => self.scroll_detail(-page / 2),
+            KeyCode::Home => self.detail_scroll = 0,
+            KeyCode::End => {
+                let (rows, visible) = self.detail_lines.get();
+                self.detail_scroll = rows.saturating_sub(visible);
+            }
+            KeyCode::Tab => self.cycle_focus(true),
+            KeyCode::BackTab => self.cycle_focus(false),
+            KeyCode::Esc => self.focus = Focus::Search,
+            _ => return false,
+        }
+        true
+    }
+
+    /// Each key of the selected command with what it stands for, in the frame colors
+    fn draw_explain(&self, frame: &mut Frame, area: Rect, cmd: &Command) {
+        let label = Style::default().fg(Color::DarkGray);
+        let frames = self
+            .selected_command_index()
+            .and_then(|idx| self.ws.frames.get(idx))
+            .map_or(&[][..], Vec::as_slice);
+        let mut lines = vec![
+            Line::from(Span::styled(
+                cmd.description.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+        ];
+        for (i, step) in explain::explain(cmd, frames, &self.prefixes).into_iter().enumerate() {
+            let chip = format!(" {} {} ", frame_mark(i), step.keys);
+            let padding = " ".repeat(12usize.saturating_sub(Span::raw(chip.as_str()).width()));
+            let mut line = vec![
+                Span::styled(chip, self.frame_style(i)),
+                Span::raw(padding),
+                Span::styled("— ", label),
+            ];
+            line.extend(match step.meaning {
+                Meaning::Leader => vec![Span::raw("leader")],
+                Meaning::Prefix(name) => vec![
+                    Span::styled(name, Style::default().fg(Color::Yellow)),
+                    Span::styled(" prefix", label),
+                ],
+                Meaning::Note(note) => vec![Span::styled(note, label)],
+                Meaning::Word(word) => vec![Span::raw(word)],
+                Meaning::Action(action) => vec![Span::raw(action)],
+                Meaning::Unknown => vec![Span::styled("…", label)],
+            });
+            lines.push(Line::from(line));
+        }
+        if let Some(mnemonic) = &cmd.mnemonic {
+            lines.push(Line::from(""));
+            lines.push(Line::from(vec![
+                Span::styled("Remember: ", label),
+                Span::styled(mnemonic.clone(), Style::default().fg(Color::Yellow)),
+            ]));
+        }
+
+        frame.render_widget(self.scrolled_detail(lines, "Explain (F1: details)", area), area);
+    }
+
+    /// Sample buffer state for the frame currently highlighted on the keyboard
+    fn current_snapshot(&self) -> Option<&Snapshot> {
+        self.simulation.as_ref()?.get(self.current_frame)
+    }
+
+    /// Explain each half of the composed command
+    fn draw_composition(&self, frame: &mut Frame, area: Rect, composer: &Composer) {
+        let block = Block::default().borders(Borders::ALL).title("Composition");
+        let Some((operator, motion)) = composer.parts(&self.grammar) else {
+            frame.render_widget(block, area);
+            return;
+        };
+
+        let label = Style::default().fg(Color::DarkGray);
+        let keys = Style::default().fg(Color::Cyan);
+        let lines = vec![
+            Line::from(vec![
+                Span::styled(format!("{}{}", operator.keys, motion.keys), keys),
+                Span::styled(
+                    format!("  {} {}", operator.name, motion.name),
+                    Style::default().add_modifier(Modifier::BOLD),
+                ),
+            ]),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Operator: ", label),
+                Span::styled(operator.keys.clone(), keys),
+                Span::raw(format!(" {}", operator.name)),
+            ]),
+            Line::from(operator.explain.clone()),
+            Line::from(""),
+            Line::from(vec![
+                Span::styled("Motion:   ", label),
+                Span::styled(motion.keys.clone(), keys),
+                Span::raw(format!(" {}", motion.name)),
+            ]),
+            Line::from(motion.explain.clone()),
+            Line::from(""),
+            Line::from(Span::styled("Press . to repeat it elsewhere", label)),
+        ];
+
+        let detail = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(block);
+        frame.render_widget(detail, area);
+    }
+
+    fn draw_search_input(&self, frame: &mut Frame, area: Rect) {
+        let mut spans = vec![Span::styled("Search: ", Style::default().fg(Color::Yellow))];
+        let (before, _) = self.ws.query.split();
+        let prefix_width: usize = spans.iter().map(Span::width).sum();
+        let cursor_x = area.x + 1 + (prefix_width + Span::raw(before).width()) as u16;
+        spans.push(Span::raw(self.ws.query.as_str()));
+
+        let hint = if self.tmux_popup {
+            "Enter: copy to tmux · Esc: quit"
+        } else {
+            "Esc to quit"
+        };
+        let title = format!(
+            "LazyVim Helper ({}) [{}]",
+            hint,
+            self.search_engine.mode.as_str()
+        );
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.pane_border(Focus::Search))
+            .title(title);
+        if self.tabs.len() > 1 {
+            block = block.title(Line::from(self.tab_bar()).right_aligned());
+        }
+        let input = Paragraph::new(Line::from(spans)).block(block);
+        frame.render_widget(input, area);
+
+        // Popups draw their own input, so only place the terminal cursor when none is open
+        if self.popup.is_none() {
+            frame.set_cursor_position((cursor_x.min(area.right() - 2), area.y + 1));
+        }
+    }
+
+    /// Tab names for the search box border, the active one highlighted
+    fn tab_bar(&self) -> Vec<Span<'_>> {
+        let mut spans = Vec::new();
+        for tab in 0..self.tabs.len() {
+            let style = if tab == self.tab {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+            spans.push(Span::styled(format!(" {} ", self.tab_name(tab)), style));
+            spans.push(Span::raw(" "));
+        }
+        spans.push(Span::styled("Ctrl+←/→ ", Style::default().fg(Color::DarkGray)));
+        spans
+    }
+
+    /// The latest message, or else the active filters and a few hints, with the sort order and
+    /// position in the results on the right
+    fn draw_status_bar(&self, frame: &mut Frame, area: Rect) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let browsing = self.composer.is_none()
+            && self.tip.is_none()
+            && self.tutorial.is_none()
+            && self.challenge.is_none();
+
+        let mut right = Vec::new();
+        if let Some(label) = self.loading.label(Instant::now()) {
+            right.push(Span::styled(format!("{} · ", label), dim));
+        }
+        if browsing {
+            let queries: Vec<Query> = self
+                .ws
+                .filters()
+                .into_iter()
+                .chain([self.ws.query.as_str()])
+                .map(Query::parse)
+                .collect();
+            let order = if self.ws.sort == SortMode::Complexity {
+                "simplest first"
+            } else if self.ws.list_mode == ListMode::Grouped {
+                "by category"
+            } else if queries.iter().any(|query| query.recent) {
+                "recent first"
+            } else if queries.iter().any(|query| !query.text.is_empty()) {
+                "best match"
+            } else {
+                "default order"
+            };
+            let position = self.ws.rows[..self.ws.rows.len().min(self.ws.selected_index + 1)]
+                .iter()
+                .filter(|row| matches!(row, ResultRow::Command(_)))
+                .count();
+            right.push(Span::styled(order, dim));
+            right.push(Span::styled(
+                format!(" · {}/{} ", position, self.ws.filtered_results.len()),
+                dim,
+            ));
+        }
+
+        let mut left = Vec::new();
+        if let Some(message) = self.messages.current() {
+            left.push(Span::styled(
+                format!(" {} ", message),
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+            ));
+        } else if browsing {
+            let filters = self.active_filters();
+            if !filters.is_empty() {
+                left.push(Span::styled(" Filters:", dim));
+                for filter in filters {
+                    left.push(Span::raw(" "));
+                    left.push(Span::styled(filter, Style::default().fg(Color::Cyan)));
+                }
+                left.push(Span::styled(" · ", dim));
+            } else {
+                left.push(Span::raw(" "));
+            }
+            let hints = if self.explorer.is_some() {
+                "←↓↑→/hjkl: key · Tab: first/any · n/p: results · F7/Esc: back"
+            } else if self.zoomed {
+                "↑↓: other commands · Ctrl+V: view · F5/Esc: back"
+            } else if self.tmux_popup {
+                "Enter: copy · Ctrl+B: favorite · Esc: quit"
+            } else {
+                "Ctrl+B: favorite · Ctrl+S: mark · Ctrl+L: learned · F2: stats · F3: tip"
+            };
+            left.push(Span::styled(hints, dim));
+        }
+
+        let right_width: usize = right.iter().map(Span::width).sum();
+        let left = fit_spans(left, (area.width as usize).saturating_sub(right_width + 1));
+        let layout = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(0), Constraint::Length(right_width as u16)])
+            .split(area);
+        frame.render_widget(Paragraph::new(Line::from(left)), layout[0]);
+        frame.render_widget(Paragraph::new(Line::from(right)), layout[1]);
+    }
+
+    /// Filter tokens and narrowing levels in effect, e.g. `["#lsp", "fav:", "›2"]`
+    fn active_filters(&self) -> Vec<String> {
+        let mut active = Vec::new();
+        let filters = self.ws.filters();
+        for &level in filters.iter().chain([&self.ws.query.as_str()]) {
+            let query = Query::parse(level);
+            active.extend(query.tags.iter().map(|tag| format!("#{}", tag)));
+            active.extend(query.modes.iter().map(|mode| format!("mode:{}", mode.as_str())));
+            active.extend(query.sources.iter().map(|source| format!("source:{}", source)));
+            active.extend(query.excluded.iter().map(|term| format!("!{}", term)));
+            active.extend(query.prefixes.iter().map(|prefix| match self.prefixes.name(prefix) {
+                Some(name) => format!("prefix:{} ({})", prefix, name),
+                None => format!("prefix:{}", prefix),
+            }));
+            let tokens = [
+                (query.favorites, "fav:"),
+                (query.recent, "recent:"),
+                (query.hidden, "hidden:"),
+                (query.learned == Some(true), "learned:"),
+                (query.learned == Some(false), "unlearned:"),
+            ];
+            for (on, token) in tokens {
+                if on && !active.iter().any(|filter| filter == token) {
+                    active.push(token.to_string());
+                }
+            }
+        }
+        if let Some(explorer) = &self.explorer {
+            let position = self.keyboard.key_positions.get(explorer.key);
+            active.push(format!("key:{}", position.map_or(explorer.key, |pos| pos.label)));
+        }
+        if !filters.is_empty() {
+            active.push(format!("›{}", filters.len()));
+        }
+        active
+    }
+
+    /// A tip in place of the results list
+    fn draw_tip(&self, frame: &mut Frame, area: Rect, idx: usize) {
+        let cmd = &self.ws.commands[idx];
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut lines = vec![
+            Line::from(Span::styled(
+                cmd.description.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from(vec![
+                mode_chip(cmd.mode),
+                Span::raw(" "),
+                Span::styled(cmd.keys.clone(), Style::default().fg(Color::Cyan)),
+                Span::styled(" · ", dim),
+                self.category_span(&cmd.category),
+            ]),
+        ];
+        if let Some(note) = self.overrides.note(cmd) {
+            lines.push(Line::from(Span::styled(
+                format!("📝 {}", note),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        if self.state.is_learned(cmd) {
+            lines.push(Line::from(Span::styled(
+                "✓ learned",
+                Style::default().fg(Color::Green),
+            )));
+        }
+        lines.push(Line::from(""));
+        let leave = if self.tip_only { "any other key: quit" } else { "Esc: back" };
+        lines.push(Line::from(Span::styled(
+            format!("Space: another tip · Ctrl+L: learned · {}", leave),
+            dim,
+        )));
+
+        let widget = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Yellow))
+                .title(" Tip "),
+        );
+        frame.render_widget(widget, area);
+    }
+
+    /// The current tutorial step in place of the results list
+    fn draw_tutorial(&self, frame: &mut Frame, area: Rect, tutorial: &Tutorial) {
+        let cmd = &self.ws.commands[tutorial.command()];
+        let frames = &self.ws.frames[tutorial.command()];
+        let done = tutorial.is_done(frames);
+        let dim = Style::default().fg(Color::DarkGray);
+        let green = Style::default().fg(Color::Green);
+        let mut lines = vec![
+            Line::from(Span::styled(
+                cmd.description.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::from(""),
+            Line::from(vec![
+                mode_chip(cmd.mode),
+                Span::raw(" "),
+                Span::styled(cmd.keys.clone(), Style::default().fg(Color::Cyan)),
+                Span::styled(" · ", dim),
+                self.category_span(&cmd.category),
+            ]),
+            Line::from(""),
+            Line::from(Span::styled(
+                format!("💡 {}", tutorial.step().mnemonic),
+                Style::default().fg(Color::Yellow),
+            )),
+        ];
+        if tutorial.practice {
+            let mut spans = vec![Span::styled("Type it: ", dim)];
+            for (i, kf) in frames.iter().enumerate() {
+                let style = if i < tutorial.typing.typed {
+                    green.add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                spans.push(Span::styled(kf.label(), style));
+                spans.push(Span::raw(" "));
+            }
+            if done {
+                spans.push(Span::styled("✓", green));
+            } else if tutorial.typing.missed {
+                spans.push(Span::styled(
+                    "✗ not quite, from the start",
+                    Style::default().fg(Color::Red),
+                ));
+            }
+            lines.push(Line::from(""));
+            lines.push(Line::from(spans));
+        }
+        let (step, count) = tutorial.position();
+        let next = if step == count { "finish" } else { "next" };
+        let hint = if done {
+            format!("Enter: {} · ←: back · Esc: quit", next)
+        } else {
+            "Type the keys to go on · ←: back · Esc: quit".to_string()
+        };
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(hint, dim)));
+
+        let widget = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Cyan))
+                .title(format!(" Tutorial {}/{} ", step, count)),
+        );
+        frame.render_widget(widget, area);
+    }
+
+    /// The challenge prompt, or the round's result and high scores once time is up
+    fn draw_challenge(&self, frame: &mut Frame, area: Rect, challenge: &Challenge) {
+        let dim = Style::default().fg(Color::DarkGray);
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        let green = Style::default().fg(Color::Green);
+        let mut lines = Vec::new();
+        let title = match challenge.result {
+            None => {
+                let cmd = &self.ws.commands[challenge.command()];
+                let left = challenge.time_left(Instant::now()).as_millis().div_ceil(1000);
+                if challenge.reverse {
+                    lines.push(Line::from(Span::styled("What do these keys do?", dim)));
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(vec![
+                        Span::styled(cmd.keys.clone(), bold.fg(Color::Cyan)),
+                        Span::raw(" "),
+                        mode_chip(cmd.mode),
+                    ]));
+                    lines.push(Line::from(""));
+                    for (i, &choice) in challenge.choices.iter().enumerate() {
+                        let description = self.ws.commands[choice].description.clone();
+                        lines.push(if challenge.wrong.contains(&choice) {
+                            Line::from(Span::styled(
+                                format!("  {}. {} ✗", i + 1, description),
+                                Style::default().fg(Color::Red).add_modifier(Modifier::CROSSED_OUT),
+                            ))
+                        } else {
+                            Line::from(vec![
+                                Span::styled(format!("  {}. ", i + 1), dim),
+                                Span::raw(description),
+                            ])
+                        });
+                    }
+                    lines.push(Line::from(""));
+                } else {
+                    lines.push(Line::from(Span::styled("Type the binding for:", dim)));
+                    lines.push(Line::from(""));
+                    lines.push(Line::from(Span::styled(cmd.description.clone(), bold)));
+                    lines.push(Line::from(vec![
+                        mode_chip(cmd.mode),
+                        Span::raw(" "),
+                        self.category_span(&cmd.category),
+                    ]));
+                    lines.push(Line::from(""));
+
+                    // Only what's been typed, so the rest stays a question
+                    let done = &challenge.frames()[..challenge.typing.typed];
+                    let mut typed = vec![
+                        Span::styled("Typed: ", dim),
+                        Span::styled(keynotation::format_with(done, &self.leaders), green),
+                        Span::raw(" "),
+                    ];
+                    if challenge.typing.missed {
+                        typed.push(Span::styled("✗", Style::default().fg(Color::Red)));
+                    }
+                    lines.push(Line::from(typed));
+                    lines.push(Line::from(""));
+                }
+
+                if let Some((idx, outcome)) = challenge.last {
+                    let last = &self.ws.commands[idx];
+                    lines.push(match outcome {
+                        Outcome::Scored(points) => Line::from(vec![
+                            Span::styled(format!("✓ +{} ", points), green),
+                            Span::styled(last.keys.clone(), Style::default().fg(Color::Cyan)),
+                            Span::styled(format!(" {}", last.description), dim),
+                        ]),
+                        Outcome::Skipped => Line::from(vec![
+                            Span::styled("↷ ", Style::default().fg(Color::Yellow)),
+                            Span::styled(last.keys.clone(), Style::default().fg(Color::Cyan)),
+                            Span::styled(format!(" was {}", last.description), dim),
+                        ]),
+                    });
+                    if let Some(mnemonic) = &last.mnemonic {
+                        lines.push(Line::from(Span::styled(
+                            format!("  {}", mnemonic),
+                            Style::default().fg(Color::Yellow),
+                        )));
+                    }
+                    lines.push(Line::from(""));
+                }
+                let hint = if challenge.reverse {
+                    "1-4: pick · Tab: skip · Esc: quit"
+                } else {
+                    "Tab: skip · Esc: quit"
+                };
+                lines.push(Line::from(Span::styled(hint, dim)));
+                format!(" Challenge · {}s left · {} points ", left, challenge.score)
+            }
+            Some((round, rank)) => {
+                lines.push(Line::from(Span::styled(
+                    format!(
+                        "Score {} · {} bindings · {}% accuracy",
+                        round.score, round.hits, round.accuracy
+                    ),
+                    bold,
+                )));
+                match rank {
+                    Some(0) => lines.push(Line::from(Span::styled(
+                        "🏆 New high score!",
+                        Style::default().fg(Color::Yellow),
+                    ))),
+                    Some(rank) => lines.push(Line::from(Span::styled(
+                        format!("#{} in your high scores", rank + 1),
+                        Style::default().fg(Color::Yellow),
+                    ))),
+                    None => {}
+                }
+                lines.push(Line::from(""));
+                let heading = if challenge.reverse {
+                    "High scores in reverse"
+                } else {
+                    "High scores"
+                };
+                lines.push(Line::from(Span::styled(heading, dim)));
+                let high_scores = self.state.stats.high_scores(challenge.reverse);
+                for (i, best) in high_scores.enumerate() {
+                    let (month, day) = stats::month_day(best.day);
+                    let style = if rank == Some(i) {
+                        Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+                    } else {
+                        Style::default()
+                    };
+                    lines.push(Line::from(Span::styled(
+                        format!(
+                            "{:>2}. {:>5}  {:>3} bindings  {:>3}%  {:02}-{:02}",
+                            i + 1,
+                            best.score,
+                            best.hits,
+                            best.accuracy,
+                            month,
+                            day
+                        ),
+                        style,
+                    )));
+                }
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled("Enter: play again · Esc: quit", dim)));
+                " Challenge · time's up ".to_string()
+            }
+        };
+
+        let widget = Paragraph::new(lines).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::Magenta))
+                .title(title),
+        );
+        frame.render_widget(widget, area);
+    }
+
+    /// Category cards in place of the results list, when nothing is typed
+    fn draw_start(&self, frame: &mut Frame, area: Rect) {
+        let cards = self.start_cards();
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!(
+                "Browse {} categories · Enter: open · type to search",
+                cards.len()
+            ));
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+        self.card_columns.set(browser::columns(inner.width));
+        browser::draw_cards(
+            frame,
+            inner,
+            &cards,
+            self.ws.card,
+            &self.ws.commands,
+            self.config.icons,
+            &self.config.categories,
+        );
+    }
+
+    /// The levels browsed into after the section, e.g. `Commands ▸ Git ▸ hunk ▸ key G`, with
+    /// the way back
+    fn breadcrumb(&self) -> String {
+        let mut crumbs = vec![self.ws.section.title()];
+        crumbs.extend(self.ws.trail.iter().map(|crumb| match crumb {
+            Crumb::Category(category) => self.config.categories.name(category),
+            Crumb::Filter(_) => crumb.label(),
+        }));
+        let key = self.explorer.as_ref().map(|explorer| {
+            let position = self.keyboard.key_positions.get(explorer.key);
+            format!("key {}", position.map_or(explorer.key, |pos| pos.label))
+        });
+        crumbs.extend(key.as_deref());
+        let mut breadcrumb = crumbs.join(" ▸ ");
+        if crumbs.len() > 1 {
+            breadcrumb.push_str(" · Esc: back");
+        }
+        breadcrumb
+    }
+
+    fn draw_results_list(&self, frame: &mut Frame, area: Rect) {
+        let results_count = self.ws.filtered_results.len();
+        let row_count = self.ws.rows.len();
+        let name = self.breadcrumb();
+        let title = if row_count > 0 {
+            format!(
+                "{} ({} results) {}/{}",
+                name,
+                results_count,
+                self.ws.selected_index + 1,
+                row_count
+            )
+        } else {
+            format!("{} ({} results)", name, results_count)
+        };
+        let counts = self.showing_counts().then(|| self.category_count_line());
+        let width = area.width.saturating_sub(2);
+        let two_line = width < TWO_LINE_ROWS_BELOW;
+        let width = width as usize;
+
+        let heights: Vec<usize> = self
+            .ws
+            .rows
+            .iter()
+            .map(|row| match row {
+                ResultRow::Command(_) | ResultRow::Pinned(_) if two_line => 2,
+                _ => 1,
+            })
+            .collect();
+        // Pinned rows stay put above the rest, which scroll under a line setting them apart
+        let pinned = self.ws.pinned.len();
+        let pinned_height = heights[..pinned].iter().sum::<usize>() + usize::from(pinned > 0);
+        let list_height = (area.height.saturating_sub(2 + counts.is_some() as u16) as usize)
+            .saturating_sub(pinned_height);
+        self.list_height.set(if two_line { list_height / 2 } else { list_height });
+        let selected = self.ws.selected_index.checked_sub(pinned);
+        let (start, end) = visible_rows(&heights[pinned..], selected.unwrap_or(0), list_height);
+
+        let items: Vec<ListItem> = (pinned + start..pinned + end)
+            .map(|i| self.result_item(i, two_line, width))
+            .collect();
+
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.pane_border(Focus::Results))
+            .title(title);
+        if !self.ws.marked.is_empty() {
+            block = block.title_bottom(Line::styled(
+                format!(" ◆ {} marked · F4: export ", self.ws.marked.len()),
+                Style::default().fg(Color::Magenta),
+            ));
+        }
+        if let Some(progress) = self.learned_progress() {
+            block = block.title_bottom(
+                Line::styled(progress, Style::default().fg(Color::Green)).right_aligned(),
+            );
+        }
+        let mut list_area = block.inner(area);
+        frame.render_widget(block, area);
+        if let Some(counts) = counts {
+            frame.render_widget(Paragraph::new(counts), Rect { height: 1, ..list_area });
+            list_area.y += 1;
+            list_area.height = list_area.height.saturating_sub(1);
+        }
+        if pinned > 0 {
+            let pinned_area = Rect {
+                height: (pinned_height as u16).min(list_area.height),
+                ..list_area
+            };
+            let items: Vec<ListItem> =
+                (0..pinned).map(|i| self.result_item(i, two_line, width)).collect();
+            frame.render_widget(List::new(items), pinned_area);
+            let rule = format!("{:─<width$}", "── ↑ pinned (Alt+P) ", width = width);
+            let dim = Style::default().fg(Color::DarkGray);
+            let rule_y = pinned_area.bottom().saturating_sub(1);
+            frame.render_widget(
+                Paragraph::new(Span::styled(rule, dim)),
+                Rect { y: rule_y, height: 1, ..pinned_area },
+            );
+            list_area.y = pinned_area.bottom();
+            list_area.height -= pinned_area.height;
+        }
+        let list = List::new(items).highlight_style(Style::default().bg(Color::DarkGray));
+
+        let mut state = ListState::default();
+        if let Some(selected) = selected.filter(|_| row_count > 0 && list_height > 0) {
+            state.select(Some(selected.saturating_sub(start)));
+        }
+
+        frame.render_stateful_widget(list, list_area, &mut state);
+
+        let rest = row_count - pinned;
+        if rest > list_height {
+            let mut scrollbar_state = ScrollbarState::new(rest)
+                .viewport_content_length(list_height)
+                .position(selected.unwrap_or(0));
+            frame.render_stateful_widget(
+                Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                    .begin_symbol(None)
+                    .end_symbol(None),
+                Rect {
+                    x: area.x,
+                    width: area.width,
+                    ..list_area
+                },
+                &mut scrollbar_state,
+            );
+        }
+    }
+
+    /// Row `i` of the results list, highlighted when it's the selected one
+    fn result_item(&self, i: usize, two_line: bool, width: usize) -> ListItem<'_> {
+        let selected = i == self.ws.selected_index;
+        let cmd_idx = match &self.ws.rows[i] {
+            &ResultRow::Command(idx) | &ResultRow::Pinned(idx) => idx,
+            ResultRow::Header(category, count) => {
+                return self.header_item(category, *count, selected);
+            }
+        };
+        let cmd = &self.ws.commands[cmd_idx];
+        let mut style = if selected {
+            Style::default()
+                .bg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        if self.state.is_hidden(cmd) {
+            style = style.add_modifier(Modifier::DIM | Modifier::CROSSED_OUT);
+        }
+
+        let mark = if self.ws.marked.contains(&cmd_idx) { "◆" } else { " " };
+        let marker = if self.state.is_favorite(cmd) { "★" } else { " " };
+        let learned_marker = if self.state.is_learned(cmd) { "✓" } else { " " };
+        let note_marker = if self.overrides.note(cmd).is_some() { "📝" } else { "  " };
+
+        let mut spans = vec![
+            Span::styled(mark, style.fg(Color::Magenta)),
+            Span::styled(marker, style.fg(Color::Yellow)),
+            Span::styled(learned_marker, style.fg(Color::Green)),
+            Span::styled(note_marker, style),
+            Span::styled(" ", style),
+            mode_chip(cmd.mode),
+            Span::styled(" ", style),
+        ];
+        let indent: usize = spans.iter().map(Span::width).sum();
+        let category = Span::styled(
+            self.category_label(&cmd.category),
+            style.fg(color(self.config.categories.color(&cmd.category))),
+        );
+        let tags = cmd
+            .tags
+            .iter()
+            .map(|tag| Span::styled(format!(" #{}", tag), style.fg(Color::Gray)));
+        // Builtin commands are most of them, so only the others get a badge
+        let badge = (cmd.source != Source::Builtin)
+            .then(|| source_badge(&cmd.source, style))
+            .into_iter();
+
+        if two_line {
+            // The description goes under the keys, and tags after it while they fit
+            spans.extend([
+                Span::styled(cmd.keys.clone(), style.fg(Color::Cyan)),
+                Span::styled(" ", style),
+                category,
+            ]);
+            spans.extend(badge);
+            let description = ellipsize(&cmd.description, width.saturating_sub(indent));
+            let mut below = vec![
+                Span::styled(" ".repeat(indent), style),
+                Span::styled(description, style),
+            ];
+            push_while_fits(&mut below, tags, width);
+            return ListItem::new(vec![Line::from(spans), Line::from(below)]);
+        }
+
+        let keys = ellipsize(&cmd.keys, KEYS_COLUMN);
+        spans.push(Span::styled(
+            format!("{:width$}", keys, width = KEYS_COLUMN),
+            style.fg(Color::Cyan),
+        ));
+        spans.push(Span::styled(" │ ", style.fg(Color::DarkGray)));
+        // Cut the description short rather than the category and badge after it
+        let badge: Vec<Span> = badge.collect();
+        let used = spans.iter().chain(&badge).map(Span::width).sum::<usize>()
+            + 3
+            + category.width();
+        spans.extend([
+            Span::styled(ellipsize(&cmd.description, width.saturating_sub(used)), style),
+            Span::styled(" │ ", style.fg(Color::DarkGray)),
+            category,
+        ]);
+        spans.extend(badge);
+        push_while_fits(&mut spans, tags, width);
+
+        ListItem::new(Line::from(spans))
+    }
+
+    /// Category icon followed by a space, or nothing when icons are disabled
+    fn category_icon(&self, category: &Category) -> String {
+        if self.config.icons {
+            format!("{} ", category.icon())
+        } else {
+            String::new()
+        }
+    }
+
+    /// Category label for list rows: the icon when enabled, brackets otherwise
+    fn category_label(&self, category: &Category) -> String {
+        let name = self.config.categories.name(category);
+        if self.config.icons {
+            format!("{}{}", self.category_icon(category), name)
+        } else {
+            format!("[{}]", name)
+        }
+    }
+
+    /// The category's name in its color
+    fn category_span(&self, category: &Category) -> Span<'static> {
+        Span::styled(
+            self.config.categories.name(category).to_string(),
+            Style::default().fg(color(self.config.categories.color(category))),
+        )
+    }
+
+    fn header_item(&self, category: &Category, count: usize, selected: bool) -> ListItem<'static> {
+        let arrow = if self.ws.collapsed.contains(category) { "▶" } else { "▼" };
+        let mut style = Style::default()
+            .fg(color(self.config.categories.color(category)))
+            .add_modifier(Modifier::BOLD);
+        if selected {
+            style = style.bg(Color::DarkGray);
+        }
+        ListItem::new(Line::from(vec![
+            Span::styled(
+                format!(
+                    "{} {}{} ",
+                    arrow,
+                    self.category_icon(category),
+                    self.config.categories.name(category)
+                ),
+                style,
+            ),
+            Span::styled(format!("({})", count), style.fg(Color::Gray)),
+        ]))
+    }
+
+    /// How `ViewMode::Both` fits in a frame of `area`: beside each other with room left for the
+    /// details, else stacked when the terminal is tall enough, else beside each other without
+    /// details. Without room for either, only the animation shows.
+    fn arrangement(&self, area: Rect) -> Arrangement {
+        if self.view_mode != ViewMode::Both || self.config.plain {
+            return Arrangement::Single;
+        }
+        let margin = 2 * u16::from(!self.tmux_popup);
+        let (width, height) = (
+            area.width.saturating_sub(margin),
+            area.height.saturating_sub(margin),
+        );
+        // Search box, the smallest results list, two keyboards with their sequence bars and
+        // the status bar
+        let keyboards_height = 2 * (KEYBOARD_HEIGHT + self.legend_height(KEYBOARD_WIDTH));
+        let stacked_height = 3 + 8 + keyboards_height + 1;
+        if width >= 2 * KEYBOARD_WIDTH + DETAIL_MIN_WIDTH {
+            Arrangement::SideBySide
+        } else if height >= stacked_height {
+            Arrangement::Stacked
+        } else if width >= 2 * KEYBOARD_WIDTH {
+            Arrangement::SideBySide
+        } else {
+            Arrangement::Single
+        }
+    }
+
+    fn draw_keyboard(&self, frame: &mut Frame, area: Rect) {
+        if let Some(explorer) = &self.explorer {
+            self.draw_explorer(frame, area, explorer);
+            return;
+        }
+        if self.config.plain {
+            self.draw_spoken_sequence(frame, area);
+            return;
+        }
+        // Ctrl+V only switches views when animation is on
+        let hint = if self.config.animation {
+            format!(" (Ctrl+V: {})", self.view_mode.next().label())
+        } else {
+            String::new()
+        };
+        match self.view_mode {
+            ViewMode::Animation => self.draw_keyboard_animation(frame, area, &hint),
+            ViewMode::Legend => self.draw_keyboard_legend(frame, area, &hint),
+            ViewMode::Both => {
+                let direction = if area.width >= 2 * KEYBOARD_WIDTH {
+                    Direction::Horizontal
+                } else if area.height >= 2 * (KEYBOARD_HEIGHT + 1) {
+                    Direction::Vertical
+                } else {
+                    self.draw_keyboard_animation(frame, area, &hint);
+                    return;
+                };
+                let halves = Layout::default()
+                    .direction(direction)
+                    .constraints([Constraint::Ratio(1, 2), Constraint::Ratio(1, 2)])
+                    .split(area);
+                self.draw_keyboard_animation(frame, halves[0], &hint);
+                self.draw_keyboard_legend(frame, halves[1], "");
+            }
+        }
+    }
+
+    /// The keyboard with the key explorer's focused key lit, in place of the animation
+    fn draw_explorer(&self, frame: &mut Frame, area: Rect, explorer: &KeyFilter) {
+        let position = self.keyboard.key_positions.get(explorer.key);
+        let label = position.map_or(explorer.key, |pos| pos.label);
+        let which = if explorer.first_frame { "first frame" } else { "any frame" };
+        let mut state = self.keyboard_state(KeyboardState::keys(vec![explorer.key]));
+        let kb_widget = KeyboardWidget::new()
+            .layout(self.keyboard.layout)
+            .platform(self.keyboard.platform)
+            .large(self.large_keyboard_fits(area))
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::Yellow))
+                    .title(format!(" Key explorer: {} in {} (Tab) ", label, which)),
+            );
+        frame.render_stateful_widget(kb_widget, area, &mut state);
+    }
+
+    /// The sequence in words, in place of the keyboard
+    fn draw_spoken_sequence(&self, frame: &mut Frame, area: Rect) {
+        let title = self
+            .animated_keys()
+            .map(|keys| format!("Keys: {} ", keys))
+            .unwrap_or_else(|| "Keys".to_string());
+        let text = commands::describe_sequence(&self.cached_frames);
+        let paragraph = Paragraph::new(text)
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(paragraph, area);
+    }
+
+    fn draw_keyboard_animation(&self, frame: &mut Frame, area: Rect, hint: &str) {
+        let mut legend = wrap_spans(
+            self.build_legend_bar(Some(self.current_frame)),
+            area.width as usize,
+            LEGEND_MAX_ROWS,
+        );
+        legend.extend(self.narration());
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(KEYBOARD_HEIGHT),
+                Constraint::Length(legend.len() as u16),
+            ])
+            .split(area);
+
+        // Lit in the frame's legend color, so the two views match
+        let mut state = self.keyboard_state(
+            KeyboardState::frame(self.get_current_frame_keys(), self.current_frame)
+                .precondition(self.mode_precondition()),
+        );
+
+        let title = if let Some(keys) = self.animated_keys() {
+            let total_frames = self.cached_frames.len();
+            let note = self
+                .cached_frames
+                .get(self.current_frame)
+                .and_then(|kf| kf.note.as_deref())
+                .map(|note| format!(" · {}", note))
+                .unwrap_or_default();
+            if total_frames > 1 {
+                format!(
+                    " {} [frame {}/{}{}] ",
+                    keys,
+                    self.current_frame + 1,
+                    total_frames,
+                    note
+                )
+            } else {
+                format!(" {} ", keys)
+            }
+        } else {
+            String::new()
+        };
+
+        let images = self.draws_keycap_images();
+        #[cfg(feature = "graphics")]
+        if images {
+            self.place_keycaps(chunks[0], &state);
+        }
+        let border = self.pane_border(Focus::Keyboard);
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(if self.pulsing() {
+                Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+            } else if self.settling.is_some() {
+                Style::default().fg(Color::DarkGray)
+            } else {
+                border
+            })
+            .title(format!("Keyboard{}{}", title, hint));
+        if let Some(banner) = self.mode_banner() {
+            block = block.title_bottom(banner);
+        }
+        let kb_widget = KeyboardWidget::new()
+            .layout(self.keyboard.layout)
+            .platform(self.keyboard.platform)
+            .large(self.large_keyboard_fits(area))
+            .labels_only(images)
+            .block(block);
+
+        frame.render_stateful_widget(kb_widget, chunks[0], &mut state);
+
+        frame.render_widget(Paragraph::new(legend), chunks[1]);
+    }
+
+    fn draw_keyboard_legend(&self, frame: &mut Frame, area: Rect, hint: &str) {
+        // Split area for keyboard and legend bar
+        let legend = wrap_spans(self.build_legend_bar(None), area.width as usize, LEGEND_MAX_ROWS);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(KEYBOARD_HEIGHT),
+                Constraint::Length(legend.len() as u16),
+            ])
+            .split(area);
+
+        // Get all frames as key lists
+        let all_frames: Vec<Vec<&str>> = self
+            .cached_frames
+            .iter()
+            .map(|kf| self.keyboard.frame_ids(kf))
+            .collect();
+
+        let mut state = self.keyboard_state(
+            KeyboardState::legend(all_frames)
+                .numbered()
+                .precondition(self.mode_precondition()),
+        );
+
+        let title = self
+            .animated_keys()
+            .map(|keys| format!(" {} ", keys))
+            .unwrap_or_default();
+
+        let images = self.draws_keycap_images();
+        #[cfg(feature = "graphics")]
+        if images {
+            self.place_keycaps(chunks[0], &state);
+        }
+        let mut block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(self.pane_border(Focus::Keyboard))
+            .title(format!("Keyboard{}{}", title, hint));
+        if let Some(banner) = self.mode_banner() {
+            block = block.title_bottom(banner);
+        }
+        let kb_widget = KeyboardWidget::new()
+            .layout(self.keyboard.layout)
+            .platform(self.keyboard.platform)
+            .large(self.large_keyboard_fits(area))
+            .labels_only(images)
+            .block(block);
+
+        frame.render_stateful_widget(kb_widget, chunks[0], &mut state);
+
+        // Draw legend bar showing sequence
+        frame.render_widget(Paragraph::new(legend), chunks[1]);
+    }
+
+    /// Lines the sequence bar and narration take under a keyboard `width` columns wide
+    fn legend_height(&self, width: u16) -> u16 {
+        let bar = wrap_spans(self.build_legend_bar(None), width as usize, LEGEND_MAX_ROWS);
+        (bar.len() + usize::from(self.narration().is_some())) as u16
+    }
+
+    /// The animation's current frame in words, e.g. "Now: Hold Ctrl, press w", while narrating
+    fn narration(&self) -> Option<Line<'static>> {
+        if !self.narrating || self.view_mode == ViewMode::Legend {
+            return None;
+        }
+        let kf = self.cached_frames.get(self.current_frame)?;
+        Some(Line::from(vec![
+            Span::styled("Now: ", Style::default().fg(Color::Gray)),
+            Span::raw(commands::narrate_frame(kf)),
+        ]))
+    }
+
+    /// Whether keyboards are drawn as labels over keycap images. Popups would sit under the
+    /// images, and the zoomed keyboard has larger keys than they're drawn for.
+    fn draws_keycap_images(&self) -> bool {
+        self.keycap_images
+            && self.popup.is_none()
+            && !self.zoomed
+            && !self.config.theme.is_monochrome()
+    }
+
+    /// Keycap images for the keyboard drawn in `area`, under its labels
+    #[cfg(feature = "graphics")]
+    fn place_keycaps(&self, area: Rect, state: &KeyboardState) {
+        let inner = area.inner(ratatui::layout::Margin::new(1, 1));
+        let art = Rect {
+            width: inner.width.min(KEYBOARD_WIDTH - 2),
+            height: inner.height.min(KEYBOARD_HEIGHT - 2),
+            ..inner
+        };
+        self.keycaps.borrow_mut().push(graphics::Placement::new(art, state));
+    }
+
+    /// Keyboard highlights in the configured theme and palette
+    fn keyboard_state(&self, state: KeyboardState) -> KeyboardState {
+        let state = state.palette(self.keyboard.palette);
+        if self.config.theme.is_monochrome() {
+            state.monochrome()
+        } else {
+            state
+        }
+    }
+
+    /// Legend style of the `i`th frame: its color, or its modifiers without color
+    fn frame_style(&self, i: usize) -> Style {
+        if self.config.theme.is_monochrome() {
+            Style::default().add_modifier(FRAME_MODIFIERS[i % FRAME_MODIFIERS.len()])
+        } else {
+            Style::default()
+                .fg(Color::Black)
+                .bg(self.keyboard.palette.frame_color(i))
+        }
+    }
+
+    /// The sequence as a line of pills in the frame colors, a run of the same key press making
+    /// one pill like `▲2■3 F ×2`; with a `current` frame, the others are dimmed. Each pill,
+    /// arrow and the label before them is a piece of its own, for lines to break between.
+    fn build_legend_bar(&self, current: Option<usize>) -> Vec<Vec<Span<'static>>> {
+        let mut pieces = Vec::new();
+        pieces.push(vec![Span::styled("Sequence: ", Style::default().fg(Color::Gray))]);
+
+        let frames = &self.cached_frames;
+        let mut i = 0;
+        while i < frames.len() {
+            let kf = &frames[i];
+            let same = |other: &&KeyFrame| *other == kf && kf.note.is_none();
+            let repeats = frames[i..].iter().take_while(same).count().max(1);
+            let first = i;
+            // A run takes its last frame's color for its keys, and `i` moves to that frame
+            i += repeats - 1;
+            let dim = if current.is_some_and(|current| !(first..=i).contains(&current)) {
+                Modifier::DIM
+            } else {
+                Modifier::empty()
+            };
+            // Counts and registers show their meaning rather than the keys typed
+            if let Some(note) = &kf.note {
+                let style = if self.config.theme.is_monochrome() {
+                    self.frame_style(i)
+                } else {
+                    Style::default().fg(self.keyboard.palette.frame_color(i))
+                };
+                pieces.push(vec![Span::styled(
+                    format!("{} [{}]", frame_mark(i), note),
+                    style.add_modifier(Modifier::ITALIC | dim),
+                )]);
+                if i < frames.len() - 1 {
+                    pieces.push(vec![Span::styled(" → ", Style::default().fg(Color::DarkGray))]);
+                }
+                i += 1;
+                continue;
+            }
+
+            let pill = if repeats > 1 {
+                // Every frame of the run keeps its own mark in its own color
+                let style = |j: usize| self.frame_style(j).add_modifier(dim);
+                let mut pill = vec![Span::styled(" ", style(first))];
+                pill.extend((first..=i).map(|j| Span::styled(frame_mark(j), style(j))));
+                pill.push(Span::styled(format!(" {} ×{} ", kf.label(), repeats), style(i)));
+                pill
+            } else {
+                let text = format!(" {} {} ", frame_mark(i), kf.label());
+                vec![Span::styled(text, self.frame_style(i).add_modifier(dim))]
+            };
+            pieces.push(pill);
+
+            if i < frames.len() - 1 {
+                pieces.push(vec![Span::styled(" → ", Style::default().fg(Color::DarkGray))]);
+            }
+            i += 1;
+        }
+
+        pieces
+    }
+
+    /// The sequence as a row of boxed keys in the frame colors, `width` wide at most, then the
+    /// mode banner if there's room; with a `current` frame, the others are dimmed
+    fn build_chips(&self, current: Option<usize>, width: usize) -> Vec<Line<'static>> {
+        let arrow = Style::default().fg(Color::DarkGray);
+        let mut rows: [Vec<Span<'static>>; 3] = Default::default();
+        let mut used = 0;
+        for (i, kf) in self.cached_frames.iter().enumerate() {
+            let dim = if current.is_some_and(|current| current != i) {
+                Modifier::DIM
+            } else {
+                Modifier::empty()
+            };
+            let (text, style) = match &kf.note {
+                Some(note) => (
+                    format!(" {} [{}] ", frame_mark(i), note),
+                    self.frame_style(i).add_modifier(Modifier::ITALIC),
+                ),
+                None => (format!(" {} {} ", frame_mark(i), kf.label()), self.frame_style(i)),
+            };
+            let chip = Span::raw(text.as_str()).width();
+            let gap = if i == 0 { 0 } else { 3 };
+            // Room for the chip, and for an ellipsis after it unless it's the last
+            let last = i == self.cached_frames.len() - 1;
+            if used + gap + chip + 2 + usize::from(!last) > width {
+                rows[1].push(Span::styled(" …", arrow));
+                break;
+            }
+            let border = if self.config.theme.is_monochrome() {
+                Style::default()
+            } else {
+                Style::default().fg(self.keyboard.palette.frame_color(i))
+            };
+            let border = border.add_modifier(dim);
+            if gap > 0 {
+                rows[0].push(Span::raw("   "));
+                rows[1].push(Span::styled(" → ", arrow));
+                rows[2].push(Span::raw("   "));
+            }
+            rows[0].push(Span::styled(format!("┌{}┐", "─".repeat(chip)), border));
+            rows[1].push(Span::styled("│", border));
+            rows[1].push(Span::styled(text, style.add_modifier(Modifier::BOLD | dim)));
+            rows[1].push(Span::styled("│", border));
+            rows[2].push(Span::styled(format!("└{}┘", "─".repeat(chip)), border));
+            used += gap + chip + 2;
+        }
+        if let Some(banner) = self.mode_banner() {
+            if used + 1 + banner.width() <= width {
+                rows[1].push(Span::raw(" "));
+                rows[1].extend(banner.spans.into_iter().map(|span| span.patch_style(banner.style)));
+            }
+        }
+        rows.into_iter().map(Line::from).collect()
+    }
+
+    /// Each frame with a mouse pseudo-key, for the mouse beside the keyboard; while animating,
+    /// frames other than the one showing are dimmed
+    fn mouse_presses(&self) -> Vec<MousePress> {
+        let animating = self.config.animation && self.view_mode != ViewMode::Legend;
+        self.cached_frames
+            .iter()
+            .enumerate()
+            .filter_map(|(i, kf)| {
+                let mouse = kf.keys.iter().find(|key| key.is_mouse)?;
+                let held: String = kf
+                    .keys
+                    .iter()
+                    .filter(|key| !key.is_mouse)
+                    .map(|key| format!("{} + ", key.key))
+                    .collect();
+                let dim = if animating && i != self.current_frame {
+                    Modifier::DIM
+                } else {
+                    Modifier::empty()
+                };
+                Some(MousePress {
+                    name: mouse.key.clone(),
+                    prefix: format!("{} {}", frame_mark(i), held),
+                    style: self.frame_style(i).add_modifier(dim),
+                })
+            })
+            .collect()
+    }
+
+    fn get_current_frame_keys(&self) -> Vec<&'static str> {
+        if self.cached_frames.is_empty() {
+            return Vec::new();
+        }
+
+        self.keyboard.frame_ids(&self.cached_frames[self.current_frame])
+    }
+}
+
+/// The shape and number marking frame `i` in the sequence bar, e.g. `▲2`
+fn frame_mark(i: usize) -> String {
+    format!("{}{}", FRAME_SHAPES[i % FRAME_SHAPES.len()], i + 1)
+}
+
+/// First and one-past-last of the rows with these `heights` to show in `height` lines, keeping
+/// the `selected` one mid-list
+fn visible_rows(heights: &[usize], selected: usize, height: usize) -> (usize, usize) {
+    if heights.is_empty() || height == 0 {
+        return (0, 0);
+    }
+    let selected = selected.min(heights.len() - 1);
+    let mut start = selected;
+    let mut above = 0;
+    while start > 0 && above + heights[start - 1] <= height / 2 {
+        start -= 1;
+        above += heights[start];
+    }
+    let (mut end, mut used) = (start, 0);
+    while end < heights.len() && used + heights[end] <= height {
+        used += heights[end];
+        end += 1;
+    }
+    // Past the last row, fill the space left with rows above instead
+    while end == heights.len() && start > 0 && used + heights[start - 1] <= height {
+        start -= 1;
+        used += heights[start];
+    }
+    (start, end.max(selected + 1))
+}
+
+/// The terminal color a category or mode is drawn in
+pub(crate) fn color(hue: Hue) -> Color {
+    match hue {
+        Hue::Black => Color::Black,
+        Hue::Red => Color::Red,
+        Hue::Green => Color::Green,
+        Hue::Yellow => Color::Yellow,
+        Hue::Blue => Color::Blue,
+        Hue::Magenta => Color::Magenta,
+        Hue::Cyan => Color::Cyan,
+        Hue::Gray => Color::Gray,
+        Hue::DarkGray => Color::DarkGray,
+        Hue::LightRed => Color::LightRed,
+        Hue::LightGreen => Color::LightGreen,
+        Hue::LightYellow => Color::LightYellow,
+        Hue::LightBlue => Color::LightBlue,
+        Hue::LightMagenta => Color::LightMagenta,
+        Hue::LightCyan => Color::LightCyan,
+        Hue::White => Color::White,
+        Hue::Indexed(index) => Color::Indexed(index),
+        Hue::Rgb(r, g, b) => Color::Rgb(r, g, b),
+    }
+}
+
+/// `text` cut down to `width` columns, ending in `…` when it doesn't fit
+pub(crate) fn ellipsize(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut fitted = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let char_width = c.width().unwrap_or(0);
+        // Leave a column for the ellipsis
+        if used + char_width + 1 > width {
+            break;
+        }
+        used += char_width;
+        fitted.push(c);
+    }
+    if width > 0 {
+        fitted.push('…');
+    }
+    fitted
+}
+
+/// Ring the terminal bell; a terminal without one just misses the beat
+fn ring_bell() {
+    let mut stdout = io::stdout();
+    let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+}
+
+/// Append `more` spans to `spans` until the next one would run past `width` columns
+fn push_while_fits<'a>(
+    spans: &mut Vec<Span<'a>>,
+    more: impl IntoIterator<Item = Span<'a>>,
+    width: usize,
+) {
+    let mut used: usize = spans.iter().map(Span::width).sum();
+    for span in more {
+        if used + span.width() > width {
+            break;
+        }
+        used += span.width();
+        spans.push(span);
+    }
+}
+
+/// Keep the spans that fit in `width` columns, with `…` standing in for the rest.
+/// Widths are terminal columns, so wide characters in labels count twice.
+fn fit_spans(spans: Vec<Span<'static>>, width: usize) -> Vec<Span<'static>> {
+    if spans.iter().map(Span::width).sum::<usize>() <= width {
+        return spans;
+    }
+    let mut used = 0;
+    let mut fitted = Vec::new();
+    for span in spans {
+        // Leave a column for the ellipsis
+        if used + span.width() + 1 > width {
+            break;
+        }
+        used += span.width();
+        fitted.push(span);
+    }
+    fitted.push(Span::styled("…", Style::default().fg(Color::DarkGray)));
+    fitted
+}
+
+/// Lay `pieces` of spans out on lines of `width` columns, breaking between pieces, with the
+/// spans past `max_rows` lines cut short with `…`. Always at least one line.
+fn wrap_spans(
+    pieces: Vec<Vec<Span<'static>>>,
+    width: usize,
+    max_rows: usize,
+) -> Vec<Line<'static>> {
+    let mut rows: Vec<Vec<Span<'static>>> = vec![Vec::new()];
+    let mut used = 0;
+    let mut rest = pieces.into_iter();
+    for piece in rest.by_ref() {
+        let piece_width: usize = piece.iter().map(Span::width).sum();
+        if used > 0 && used + piece_width > width {
+            if rows.len() == max_rows {
+                // Back on the last line, to cut it short
+                let last = rows.pop().unwrap_or_default();
+                let spilled = last.into_iter().chain(piece).chain(rest.flatten()).collect();
+                rows.push(fit_spans(spilled, width));
+                break;
+            }
+            rows.push(Vec::new());
+            used = 0;
+        }
+        used += piece_width;
+        rows.last_mut().unwrap().extend(piece);
+    }
+    rows.into_iter().map(Line::from).collect()
+}
+
+/// Badge after a command loaded from somewhere other than the builtin keymaps
+fn source_badge(source: &Source, style: Style) -> Span<'static> {
+    Span::styled(format!(" ‹{}›", source.label()), style.fg(SOURCE_COLOR))
+}
+
+/// Compact colored chip showing a command's mode
+fn mode_chip(mode: Mode) -> Span<'static> {
+    Span::styled(
+        format!(" {} ", mode.short()),
+        Style::default().fg(Color::Black).bg(color(mode.color())),
+    )
+}
+
+/// Sample text with the covered region highlighted and a marker line underneath
+fn diagram_lines(diagram: &Diagram) -> Vec<Line<'static>> {
+    let region = Style::default().fg(Color::Black).bg(Color::Cyan);
+    let cursor = Style::default().add_modifier(Modifier::REVERSED);
+
+    let mut text = Vec::new();
+    let mut markers = String::new();
+    for (i, c) in diagram.text.chars().enumerate() {
+        let in_region = (diagram.start..diagram.end).contains(&i);
+        let style = if i == diagram.cursor {
+            cursor
+        } else if in_region {
+            region
+        } else {
+            Style::default()
+        };
+        text.push(Span::styled(c.to_string(), style));
+        markers.push(match (i == diagram.cursor, in_region) {
+            (true, _) => '^',
+            (false, true) => '~',
+            (false, false) => ' ',
+        });
+    }
+
+    let mut lines = vec![
+        Line::from(text),
+        Line::from(Span::styled(
+            markers.trim_end().to_string(),
+            Style::default().fg(Color::Cyan),
+        )),
+    ];
+    if let Some(caption) = &diagram.caption {
+        lines.push(Line::from(Span::styled(
+            caption.clone(),
+            Style::default().fg(Color::DarkGray),
+        )));
+    }
+    lines
+}
+
+/// Draw the simulated sample buffer with its cursor and any visual selection
+fn draw_simulation(frame: &mut Frame, area: Rect, snapshot: &Snapshot) {
+    let cursor = Style::default().add_modifier(Modifier::REVERSED);
+    let selected = Style::default().fg(Color::Black).bg(Color::Cyan);
+
+    let offsets = snapshot.line_offsets();
+    let lines: Vec<Line> = snapshot
+        .lines
+        .iter()
+        .enumerate()
+        .map(|(row, line)| {
+            let mut spans: Vec<Span> = line
+                .chars()
+                .enumerate()
+                .map(|(col, c)| {
+                    let pos = offsets[row] + col;
+                    let style = if (row, col) == snapshot.cursor {
+                        cursor
+                    } else if snapshot.selection.is_some_and(|(start, end)| (start..end).contains(&pos)) {
+                        selected
+                    } else {
+                        Style::default()
+                    };
+                    Span::styled(c.to_string(), style)
+                })
+                .collect();
+            // The cursor can sit just past the last char, e.g. after `A`
+            if snapshot.cursor == (row, line.chars().count()) {
+                spans.push(Span::styled(" ", cursor));
+            }
+            Line::from(spans)
+        })
+        .collect();
+
+    let mut title = format!(" Sample -- {} -- ", snapshot.mode.label());
+    if !snapshot.pending.is_empty() {
+        title.push_str(&format!("{} ", snapshot.pending));
+    }
+    let widget = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(widget, area);
+}
+
+/// Neovim's command line after typing `frames`, with the cursor or the Enter that ran it
+fn draw_command_line(frame: &mut Frame, area: Rect, frames: &[KeyFrame]) {
+    let mut text = String::new();
+    let mut entered = false;
+    for kf in frames {
+        let shift = kf.keys.iter().any(|k| k.is_modifier && k.key == "Shift");
+        for key in kf.keys.iter().filter(|k| !k.is_modifier) {
+            let mut chars = key.key.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if shift => text.push(commands::shifted(c)),
+                (Some(c), None) => text.push(c),
+                _ if key.key == "Space" => text.push(' '),
+                _ if key.key == "Enter" => entered = true,
+                _ => text.push_str(&format!("<{}>", key.key)),
+            }
+        }
+    }
+
+    let mut spans = vec![Span::styled(
+        text,
+        Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD),
+    )];
+    spans.push(if entered {
+        Span::styled(" ⏎ Enter", Style::default().fg(Color::DarkGray))
+    } else {
+        Span::styled("█", Style::default().fg(Color::Gray))
+    });
+    let paragraph = Paragraph::new(Line::from(spans))
+        .block(Block::default().borders(Borders::ALL).title("Command line"));
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests;
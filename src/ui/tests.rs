@@ -0,0 +1,364 @@
+//! Whole screens drawn on a test backend after feeding the app key presses, compared with the
+//! snapshots next to this file once the counts of commands are blanked out. Run `cargo insta
+//! review` (or `INSTA_UPDATE=always cargo test`) to accept a change.
+
+use super::*;
+use crate::cast;
+use crate::demo::{Demo, Script};
+use crate::keyboard::{KeyboardLayout, Platform};
+use ratatui::{backend::TestBackend, Terminal};
+
+/// An app over the embedded commands with the default settings and LazyVim's leaders, saving
+/// nothing and reading nothing of the user's, whose animation stays on its first frame and
+/// starts on every move of the selection
+fn app() -> App {
+    let mut commands = commands::embedded_commands().unwrap();
+    commands.extend(commands::ex_commands().unwrap());
+    let config = Config {
+        leader: Some(" ".to_string()),
+        localleader: Some("\\".to_string()),
+        platform: Some(Platform::Linux),
+        frame_ms: 3_600_000,
+        settle_ms: 0,
+        ..Config::default()
+    };
+    let grammar = Grammar::load().unwrap();
+    App::new(commands, config, UserState::default(), Overrides::default(), grammar)
+}
+
+fn key(code: KeyCode) -> Event {
+    Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn ctrl(c: char) -> Event {
+    Event::Key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL))
+}
+
+/// A key press for each character of `text`
+fn typed(text: &str) -> Vec<Event> {
+    text.chars().map(|c| key(KeyCode::Char(c))).collect()
+}
+
+/// Handle `events` in order, ticking after each the way the main loop does
+fn send(app: &mut App, events: impl IntoIterator<Item = Event>) {
+    for event in events {
+        app.handle_event(event).unwrap();
+        app.tick();
+    }
+}
+
+/// Snapshot settings that blank out the counts of commands, so that adding one to the data
+/// doesn't change every screen
+fn redacted_counts() -> insta::Settings {
+    let mut settings = insta::Settings::clone_current();
+    settings.add_filter(r"all \d+/\d+", "all [n]/[n]");
+    settings.add_filter(r"(✓ [^·✓]+?) \d+/\d+", "$1 [n]/[n]");
+    settings.add_filter(r"(order|match) · \d+/\d+", "$1 · [n]/[n]");
+    settings.add_filter(r"\(\d+ results\) \d+/\d+", "([n] results) [n]/[n]");
+    settings.add_filter(r"(┌ [^┌┐(]+) \(\d+\)", "$1 ([n])");
+    settings
+}
+
+/// The app drawn on a `width` by `height` terminal, as text
+fn screen(app: &App, width: u16, height: u16) -> String {
+    let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+    terminal.draw(|frame| app.draw(frame)).unwrap();
+    cast::text_lines(terminal.backend().buffer()).join("\n")
+}
+
+#[test]
+fn test_start_screen() {
+    let _counts = redacted_counts().bind_to_scope();
+    let app = app();
+    insta::assert_snapshot!(screen(&app, 100, 32));
+}
+
+#[test]
+fn test_search() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    send(&mut app, typed("find files"));
+    assert_eq!(app.selected_command().unwrap().keys, "<leader>ff");
+    insta::assert_snapshot!(screen(&app, 120, 40));
+}
+
+#[test]
+fn test_animation_frame() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    send(&mut app, typed("split window right"));
+    assert_eq!(app.selected_command().unwrap().keys, "<leader>w|");
+    app.current_frame = 1;
+    insta::assert_snapshot!(screen(&app, 120, 40));
+}
+
+#[test]
+fn test_scroll_detail_before_drawing() {
+    let mut app = app();
+    // Nothing drawn yet, so the detail pane has no lines to scroll through
+    send(&mut app, [Event::Key(KeyEvent::new(KeyCode::PageDown, KeyModifiers::ALT))]);
+    assert_eq!(app.detail_scroll, 0);
+}
+
+#[test]
+fn test_explored_key_leaves_keyboard() {
+    let mut app = app();
+    let us = app.keyboard.key_positions.clone();
+    let mut config = app.config.clone();
+    config.layout = KeyboardLayout::Qwertz;
+    app.set_config(config.clone());
+    let key = *app.keyboard.key_positions.keys().find(|key| !us.contains_key(*key)).unwrap();
+    app.explorer = Some(KeyFilter {
+        key,
+        first_frame: false,
+    });
+    screen(&app, 120, 40);
+
+    config.layout = KeyboardLayout::Us;
+    app.set_config(config);
+    assert_eq!(app.explorer, None);
+    screen(&app, 120, 40);
+}
+
+#[test]
+fn test_narration() {
+    let mut app = app();
+    send(&mut app, typed("split window right"));
+    assert!(!screen(&app, 120, 40).contains("Now: "));
+    send(&mut app, [Event::Key(KeyEvent::new(KeyCode::Char('n'), KeyModifiers::ALT))]);
+    assert!(app.narrating);
+    assert!(screen(&app, 120, 40).contains("Now: Press Space (leader)"));
+    app.current_frame = 1;
+    assert!(screen(&app, 120, 40).contains("Now: Press w"));
+    // The legend shows every frame at once, with none to narrate
+    send(&mut app, [ctrl('v')]);
+    assert!(!screen(&app, 120, 40).contains("Now: "));
+}
+
+#[test]
+fn test_stacked_with_narration() {
+    let mut app = app();
+    send(&mut app, typed("split window right"));
+    app.view_mode = ViewMode::Both;
+    let area = Rect::new(0, 0, 80, 50);
+    assert_eq!(app.arrangement(area), Arrangement::Stacked);
+    // Narration takes a line under each keyboard, which no longer fit one above the other
+    app.narrating = true;
+    assert_eq!(app.arrangement(area), Arrangement::Single);
+}
+
+#[test]
+fn test_legend() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    send(&mut app, typed("find files"));
+    send(&mut app, [ctrl('v')]);
+    assert_eq!(app.view_mode, ViewMode::Legend);
+    insta::assert_snapshot!(screen(&app, 120, 40));
+}
+
+#[test]
+fn test_run_keeps_frame_colors() {
+    let mut app = app();
+    send(&mut app, typed("find files"));
+    assert_eq!(app.selected_command().unwrap().keys, "<leader>ff");
+    let pieces = app.build_legend_bar(None);
+    let run = pieces.iter().find(|piece| piece.len() > 1).unwrap();
+    let marks: Vec<(&str, Style)> = run.iter().map(|span| (&*span.content, span.style)).collect();
+    assert_eq!(marks[1], ("▲2", app.frame_style(1)));
+    assert_eq!(marks[2], ("■3", app.frame_style(2)));
+    assert_eq!(marks[3], (" F ×2 ", app.frame_style(2)));
+}
+
+#[test]
+fn test_small_terminal() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    send(&mut app, typed("buffer"));
+    // Focus only moves to panes on screen, which drawing finds out
+    screen(&app, 60, 20);
+    send(&mut app, [key(KeyCode::Down), key(KeyCode::Tab)]);
+    assert_eq!(app.focus, Focus::Results);
+    insta::assert_snapshot!(screen(&app, 60, 20));
+}
+
+#[test]
+fn test_demo() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    let script: Script = toml::from_str(
+        "[[step]]\ntype = \"window\"\n[[step]]\nselect = 2\n\
+         [[step]]\nview = \"both\"\n[[step]]\nkey = \"<C-v><C-v>\"",
+    )
+    .unwrap();
+    let start = Instant::now();
+    let mut demo = Demo::new(&script, start).unwrap();
+    assert!(demo.advance(&mut app, start).unwrap());
+    assert!(app.ws.query.as_str().is_empty());
+    // Everything is due an hour in
+    assert!(!demo.advance(&mut app, start + Duration::from_secs(3600)).unwrap());
+    app.tick();
+    assert_eq!(app.ws.query.as_str(), "window");
+    let listed: Vec<usize> = app
+        .ws
+        .rows
+        .iter()
+        .filter_map(|row| match row {
+            ResultRow::Command(i) | ResultRow::Pinned(i) => Some(*i),
+            ResultRow::Header(..) => None,
+        })
+        .collect();
+    assert_eq!(app.selected_command_index(), Some(listed[1]));
+    assert_eq!(app.view_mode, ViewMode::Legend);
+    insta::assert_snapshot!(screen(&app, 120, 40));
+}
+
+#[test]
+fn test_workflow() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    send(&mut app, typed("stage and commit"));
+    assert_eq!(app.selected_command().unwrap().keys, "<leader>gg<Space>c");
+    let alt_down = Event::Key(KeyEvent::new(KeyCode::Down, KeyModifiers::ALT));
+    send(&mut app, [alt_down]);
+    // Past the three frames of `<leader>gg`
+    app.current_frame = 3;
+    insta::assert_snapshot!(screen(&app, 120, 40));
+
+    // The highlighted step leads to its command
+    send(&mut app, [key(KeyCode::Enter)]);
+    assert_eq!(app.selected_command().unwrap().description, "Open LazyGit");
+}
+
+#[test]
+fn test_category_layout() {
+    let _counts = redacted_counts().bind_to_scope();
+    let mut app = app();
+    let categories = toml::from_str(
+        r#"
+order = ["git", "lsp"]
+hidden = ["general", "debug"]
+names = { git = "Version control" }
+"#,
+    )
+    .unwrap();
+    app.set_config(Config {
+        categories,
+        ..app.config.clone()
+    });
+    insta::assert_snapshot!(screen(&app, 100, 32));
+
+    // Hidden categories' commands aren't found either
+    send(&mut app, typed("breakpoint"));
+    let counted: Vec<&Category> = app.ws.category_counts.iter().map(|(c, _)| c).collect();
+    assert!(!counted.contains(&&Category::Debug));
+}
+
+#[test]
+fn test_settle() {
+    let mut app = app();
+    app.config.settle_ms = 100;
+    send(&mut app, typed("buffer"));
+    app.settling = None;
+    app.selected_at = Instant::now() - Duration::from_secs(1);
+
+    // A move on its own animates at once, and one right after it waits for the next to settle
+    send(&mut app, [key(KeyCode::Down)]);
+    assert!(!app.cached_frames.is_empty());
+    send(&mut app, [key(KeyCode::Down)]);
+    assert!(app.cached_frames.is_empty());
+    assert!(app.poll_timeout() <= Duration::from_millis(100));
+
+    app.settling = Some(Instant::now() - Duration::from_millis(100));
+    app.tick();
+    let idx = app.selected_command_index().unwrap();
+    assert_eq!(app.cached_frames, app.ws.frames[idx]);
+}
+
+#[test]
+fn test_ctrl_u() {
+    let mut app = app();
+    send(&mut app, typed("buffer"));
+    screen(&app, 120, 40);
+    let half_page = (app.page_size() / 2) as usize;
+
+    // In the search box it only ever deletes, even with nothing before the cursor
+    send(&mut app, [ctrl('d'), ctrl('a'), ctrl('u')]);
+    assert_eq!((app.ws.query.as_str(), app.ws.selected_index), ("buffer", half_page));
+    send(&mut app, [ctrl('e'), ctrl('u')]);
+    assert!(app.ws.query.as_str().is_empty());
+
+    // The focused results move by half a page both ways
+    send(&mut app, typed("buffer"));
+    send(&mut app, [key(KeyCode::Tab), ctrl('d'), ctrl('d'), ctrl('u')]);
+    assert_eq!((app.focus, app.ws.selected_index), (Focus::Results, half_page));
+    assert_eq!(app.ws.query.as_str(), "buffer");
+}
+
+#[test]
+fn test_ctrl_n() {
+    let mut app = app();
+    app.history = History::new(vec!["gd".to_string(), "buffer".to_string()]);
+    send(&mut app, typed("win"));
+
+    // Stepping through history, back to the draft past the newest entry
+    send(&mut app, [ctrl('p'), ctrl('p'), ctrl('n')]);
+    assert_eq!(app.ws.query.as_str(), "buffer");
+    send(&mut app, [ctrl('n')]);
+    assert_eq!(app.ws.query.as_str(), "win");
+    assert!(app.popup.is_none());
+
+    // An edited recall is a query like any other, so Ctrl+N adds a command again
+    send(&mut app, [ctrl('p')]);
+    send(&mut app, typed("s"));
+    send(&mut app, [ctrl('n')]);
+    assert_eq!(app.ws.query.as_str(), "buffers");
+    assert!(matches!(app.popup, Some(Popup::NewCommand(_))));
+}
+
+#[test]
+fn test_focus_pauses_animation() {
+    let mut app = app();
+    app.config.frame_ms = 100;
+    send(&mut app, typed("split window right"));
+    assert!(app.cached_frames.len() > 1);
+    app.animation_start = Instant::now() - Duration::from_millis(150);
+    app.tick();
+
+    send(&mut app, [Event::FocusLost]);
+    assert_eq!(app.current_frame, 1);
+    assert!(app.poll_timeout() >= Duration::from_secs(1));
+    // A tenth of a second in the background, a frame's worth
+    app.paused_at = app.paused_at.map(|at| at - Duration::from_millis(100));
+    app.animation_start -= Duration::from_millis(100);
+    app.tick();
+    assert_eq!(app.current_frame, 1);
+
+    // The time in the background doesn't count, so the animation goes on from the same frame
+    send(&mut app, [Event::FocusGained]);
+    assert_eq!(app.current_frame, 1);
+    assert!(app.poll_timeout() <= Duration::from_millis(100));
+}
+
+#[cfg(feature = "watch")]
+#[test]
+fn test_reload_tab() {
+    let mut app = app();
+    let command = |keys: &str| Command {
+        keys: keys.to_string(),
+        description: format!("Do {}", keys),
+        ..Command::default()
+    };
+    let tab = |path: &str, commands| Workspace {
+        path: Some(PathBuf::from(path)),
+        ..Workspace::new("mine", commands)
+    };
+    app.insert_tab(app.tabs.len(), tab("/config/mine.json", vec![command("gx")]));
+    let last = app.tabs.len() - 1;
+
+    app.reload_tab(tab("/elsewhere/mine.json", vec![]));
+    assert_eq!(app.tabs[last].commands.len(), 1);
+    app.reload_tab(tab("/config/mine.json", vec![command("gx"), command("gy")]));
+    assert_eq!(app.tabs[last].commands.len(), 2);
+    assert_eq!(app.tab_paths(), [PathBuf::from("/config/mine.json")]);
+}
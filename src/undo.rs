@@ -0,0 +1,79 @@
+use crate::commands::Command;
+
+/// Actions kept to undo; older ones are dropped
+const UNDO_LIMIT: usize = 20;
+
+/// An action that took something away, with what it takes to bring it back
+#[derive(Debug, Clone)]
+pub enum Undo {
+    /// The command was hidden
+    Hide(Command),
+    /// The command was taken out of the favorites
+    Unfavorite(Command),
+    /// The command's note was deleted or rewritten; this is the old one
+    Note(Command, String),
+}
+
+impl Undo {
+    /// For the status bar once it's undone, e.g. "Undid hiding <leader>ff"
+    pub fn describe(&self) -> String {
+        let (what, cmd) = match self {
+            Undo::Hide(cmd) => ("hiding", cmd),
+            Undo::Unfavorite(cmd) => ("unstarring", cmd),
+            Undo::Note(cmd, _) => ("the note edit on", cmd),
+        };
+        format!("Undid {} {}", what, cmd.keys)
+    }
+}
+
+/// The last destructive actions, undone newest first with Ctrl+Z
+#[derive(Debug, Clone, Default)]
+pub struct UndoStack {
+    actions: Vec<Undo>,
+}
+
+impl UndoStack {
+    pub fn push(&mut self, action: Undo) {
+        self.actions.push(action);
+        if self.actions.len() > UNDO_LIMIT {
+            self.actions.remove(0);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<Undo> {
+        self.actions.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(keys: &str) -> Command {
+        Command {
+            keys: keys.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_undo_stack() {
+        let mut stack = UndoStack::default();
+        assert!(stack.pop().is_none());
+        for i in 0..UNDO_LIMIT + 2 {
+            stack.push(Undo::Hide(command(&i.to_string())));
+        }
+        stack.push(Undo::Note(command("<leader>ff"), "old".to_string()));
+
+        let undo = stack.pop().unwrap();
+        assert_eq!(undo.describe(), "Undid the note edit on <leader>ff");
+        let mut hidden = Vec::new();
+        while let Some(Undo::Hide(cmd)) = stack.pop() {
+            hidden.push(cmd.keys);
+        }
+        // The oldest three were dropped to stay at the limit
+        assert_eq!(hidden.len(), UNDO_LIMIT - 1);
+        assert_eq!(hidden.first().map(String::as_str), Some("21"));
+        assert_eq!(hidden.last().map(String::as_str), Some("3"));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::commands::{self, Command, FileFormat};
+use crate::validate::{self, Severity};
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// The maintained keymap data on the main branch
+pub const DEFAULT_URL: &str = "https://raw.githubusercontent.com/caioldcarvalho/lazyvim-interactive-cheatsheet/main/data/commands.json";
+
+/// Download the command data at `url`, check it, and save it where `load_commands` looks first.
+/// Returns the saved path and the number of commands in it.
+pub fn update(url: &str) -> anyhow::Result<(PathBuf, usize)> {
+    let body = ureq::get(url)
+        .call()
+        .with_context(|| format!("failed to download {}", url))?
+        .into_string()
+        .with_context(|| format!("failed to read the response from {}", url))?;
+    let commands = verify(&body).with_context(|| format!("{} is not usable command data", url))?;
+
+    let path = commands::downloaded_commands_path().context("no data directory available")?;
+    save(&path, &body)?;
+    Ok((path, commands.len()))
+}
+
+/// Remove the downloaded data so the embedded snapshot is used again.
+/// Returns whether there was anything to remove.
+pub fn reset() -> anyhow::Result<bool> {
+    let Some(path) = commands::downloaded_commands_path().filter(|path| path.exists()) else {
+        return Ok(false);
+    };
+    std::fs::remove_file(&path).with_context(|| format!("failed to remove {}", path.display()))?;
+    Ok(true)
+}
+
+/// Accept only data that `lvcheat validate` finds no errors in
+fn verify(body: &str) -> anyhow::Result<Vec<Command>> {
+    let errors: Vec<String> = validate::validate(body, FileFormat::Json)
+        .into_iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| format!("line {}: {}", d.line, d.message))
+        .collect();
+    if let Some(first) = errors.first() {
+        anyhow::bail!("{} error(s), first at {}", errors.len(), first);
+    }
+
+    let commands = commands::parse_commands(body, FileFormat::Json)?;
+    if commands.is_empty() {
+        anyhow::bail!("it has no commands");
+    }
+    Ok(commands)
+}
+
+/// Write through a temporary file so an interrupted update never leaves half a file behind
+fn save(path: &Path, body: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("failed to create {}", parent.display()))?;
+    }
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, body).with_context(|| format!("failed to write {}", tmp.display()))?;
+    std::fs::rename(&tmp, path).with_context(|| format!("failed to replace {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify() {
+        let embedded = include_str!("../data/commands.json");
+        assert_eq!(
+            verify(embedded).unwrap().len(),
+            commands::embedded_commands().unwrap().len()
+        );
+
+        assert!(verify("[]").is_err());
+        assert!(verify("<!DOCTYPE html><title>404</title>").is_err());
+        let bad = r#"[{"keys": "<leader>x", "description": "", "category": "nope"}]"#;
+        let err = verify(bad).unwrap_err().to_string();
+        assert!(err.contains("error(s), first at line 1"), "{}", err);
+    }
+}
@@ -0,0 +1,449 @@
+use crate::commands::{self, Category, Diagram, Doc, FileFormat, Mode, WorkflowStep};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Fields understood by `Command`; anything else is likely a typo
+const KNOWN_FIELDS: &[&str] = &[
+    "keys",
+    "description",
+    "category",
+    "mode",
+    "tags",
+    "mnemonic",
+    "see_also",
+    "alternatives",
+    "diagram",
+    "doc",
+    "workflow",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A single problem found in a command file, tied to a 1-based line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    pub line: usize,
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn error(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            severity: Severity::Error,
+            message: message.into(),
+        }
+    }
+
+    fn warning(line: usize, message: impl Into<String>) -> Self {
+        Self {
+            line,
+            severity: Severity::Warning,
+            message: message.into(),
+        }
+    }
+}
+
+/// Validate the contents of a command file in the given format
+pub fn validate(source: &str, format: FileFormat) -> Vec<Diagnostic> {
+    let entries = match commands::raw_entries(source, format) {
+        Ok(entries) => entries,
+        Err((line, message)) => return vec![Diagnostic::error(line, message)],
+    };
+
+    let mut diagnostics = Vec::new();
+    let mut seen: HashMap<(String, Mode), usize> = HashMap::new();
+
+    for (line, entry) in &entries {
+        diagnostics.extend(validate_entry(entry, *line, &mut seen));
+    }
+
+    diagnostics
+}
+
+fn validate_entry(
+    entry: &Value,
+    line: usize,
+    seen: &mut HashMap<(String, Mode), usize>,
+) -> Vec<Diagnostic> {
+    let Some(fields) = entry.as_object() else {
+        return vec![Diagnostic::error(line, "entry is not an object")];
+    };
+
+    let mut diagnostics = Vec::new();
+
+    for name in fields.keys() {
+        if !KNOWN_FIELDS.contains(&name.as_str()) {
+            diagnostics.push(Diagnostic::warning(line, format!("unknown field \"{}\"", name)));
+        }
+    }
+
+    let keys = match fields.get("keys") {
+        Some(Value::String(keys)) if !keys.trim().is_empty() => Some(keys.as_str()),
+        Some(Value::String(_)) => {
+            diagnostics.push(Diagnostic::error(line, "empty keys"));
+            None
+        }
+        Some(_) => {
+            diagnostics.push(Diagnostic::error(line, "\"keys\" must be a string"));
+            None
+        }
+        None => {
+            diagnostics.push(Diagnostic::error(line, "missing \"keys\""));
+            None
+        }
+    };
+
+    match fields.get("description") {
+        Some(Value::String(desc)) if desc.trim().is_empty() => {
+            diagnostics.push(Diagnostic::error(line, "empty description"));
+        }
+        Some(Value::String(_)) => {}
+        Some(_) => diagnostics.push(Diagnostic::error(line, "\"description\" must be a string")),
+        None => diagnostics.push(Diagnostic::error(line, "missing \"description\"")),
+    }
+
+    match fields.get("category") {
+        Some(Value::String(name)) if name.trim().is_empty() => {
+            diagnostics.push(Diagnostic::error(line, "empty category"));
+        }
+        // Any name works, but one that isn't LazyVim's may be a typo
+        Some(Value::String(name)) => {
+            if let Category::Custom(name) = Category::named(name) {
+                let message = format!("unknown category \"{}\", listed as a custom one", name);
+                diagnostics.push(Diagnostic::warning(line, message));
+            }
+        }
+        Some(_) => diagnostics.push(Diagnostic::error(line, "\"category\" must be a string")),
+        None => diagnostics.push(Diagnostic::error(line, "missing \"category\"")),
+    }
+
+    match fields.get("tags") {
+        Some(Value::Array(tags)) if tags.iter().all(Value::is_string) => {}
+        Some(_) => diagnostics.push(Diagnostic::error(line, "\"tags\" must be a list of strings")),
+        None => {}
+    }
+
+    match fields.get("see_also") {
+        Some(Value::Array(keys)) if keys.iter().all(Value::is_string) => {}
+        Some(_) => {
+            diagnostics.push(Diagnostic::error(line, "\"see_also\" must be a list of keys"));
+        }
+        None => {}
+    }
+
+    match fields.get("alternatives") {
+        Some(Value::Array(alternatives)) if alternatives.iter().all(Value::is_string) => {
+            for alternative in alternatives.iter().filter_map(Value::as_str) {
+                for issue in commands::check_keys(alternative) {
+                    let message = format!("alternative \"{}\": {}", alternative, issue);
+                    diagnostics.push(if issue.is_warning() {
+                        Diagnostic::warning(line, message)
+                    } else {
+                        Diagnostic::error(line, message)
+                    });
+                }
+            }
+        }
+        Some(_) => {
+            diagnostics.push(Diagnostic::error(line, "\"alternatives\" must be a list of keys"));
+        }
+        None => {}
+    }
+
+    match fields.get("mnemonic") {
+        Some(Value::String(_)) | None => {}
+        Some(_) => diagnostics.push(Diagnostic::error(line, "\"mnemonic\" must be a string")),
+    }
+
+    if let Some(value) = fields.get("diagram") {
+        match serde_json::from_value::<Diagram>(value.clone()).map_err(|e| e.to_string()) {
+            Ok(diagram) => {
+                if let Err(error) = diagram.check() {
+                    diagnostics.push(Diagnostic::error(line, format!("diagram: {}", error)));
+                }
+            }
+            Err(error) => diagnostics.push(Diagnostic::error(line, format!("diagram: {}", error))),
+        }
+    }
+
+    if let Some(value) = fields.get("doc") {
+        match serde_json::from_value::<Doc>(value.clone()) {
+            Ok(doc) if doc.text.trim().is_empty() => {
+                diagnostics.push(Diagnostic::warning(line, "empty doc excerpt"));
+            }
+            Ok(_) => {}
+            Err(error) => diagnostics.push(Diagnostic::error(line, format!("doc: {}", error))),
+        }
+    }
+
+    if let Some(value) = fields.get("workflow") {
+        match serde_json::from_value::<Vec<WorkflowStep>>(value.clone()) {
+            Ok(steps) => {
+                for step in &steps {
+                    for issue in commands::check_keys(&step.keys) {
+                        let message = format!("workflow step \"{}\": {}", step.keys, issue);
+                        diagnostics.push(if issue.is_warning() {
+                            Diagnostic::warning(line, message)
+                        } else {
+                            Diagnostic::error(line, message)
+                        });
+                    }
+                }
+                let typed: String = steps.iter().map(|step| step.keys.as_str()).collect();
+                if keys.is_some_and(|keys| !steps.is_empty() && keys != typed) {
+                    let message = format!("keys differ from the workflow steps, \"{}\"", typed);
+                    diagnostics.push(Diagnostic::warning(line, message));
+                }
+            }
+            Err(error) => diagnostics.push(Diagnostic::error(line, format!("workflow: {}", error))),
+        }
+    }
+
+    // One mode, or a list binding the keys in each
+    let modes = match fields.get("mode") {
+        Some(Value::Array(values)) if values.is_empty() => {
+            diagnostics.push(Diagnostic::error(line, "empty \"mode\" list"));
+            Vec::new()
+        }
+        Some(Value::Array(values)) => values
+            .iter()
+            .filter_map(|value| {
+                let mode = serde_json::from_value::<Mode>(value.clone()).ok();
+                if mode.is_none() {
+                    diagnostics.push(Diagnostic::error(line, format!("unknown mode {}", value)));
+                }
+                mode
+            })
+            .collect(),
+        Some(value) => match serde_json::from_value::<Mode>(value.clone()) {
+            Ok(mode) => vec![mode],
+            Err(_) => {
+                diagnostics.push(Diagnostic::error(line, format!("unknown mode {}", value)));
+                Vec::new()
+            }
+        },
+        None => vec![Mode::default()],
+    };
+
+    if let Some(keys) = keys {
+        for issue in commands::check_keys(keys) {
+            let message = format!("keys \"{}\": {}", keys, issue);
+            diagnostics.push(if issue.is_warning() {
+                Diagnostic::warning(line, message)
+            } else {
+                Diagnostic::error(line, message)
+            });
+        }
+
+        for mode in modes {
+            match seen.get(&(keys.to_string(), mode)) {
+                Some(&first) if first == line => diagnostics.push(Diagnostic::warning(
+                    line,
+                    format!("mode {} listed twice", mode.as_str()),
+                )),
+                Some(first) => diagnostics.push(Diagnostic::error(
+                    line,
+                    format!("duplicate of entry at line {} in {} mode", first, mode.as_str()),
+                )),
+                None => {
+                    seen.insert((keys.to_string(), mode), line);
+                }
+            }
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_file() {
+        let source = r#"[
+  { "keys": "<leader>ff", "description": "Find files", "category": "search" }
+]"#;
+        assert!(validate(source, FileFormat::Json).is_empty());
+    }
+
+    #[test]
+    fn test_mnemonic() {
+        let source = r#"[
+  { "keys": "<leader>sg", "description": "Grep", "category": "search", "mnemonic": "Search Grep" },
+  { "keys": "<leader>sk", "description": "Keymaps", "category": "search", "mnemonic": 2 }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.contains("\"mnemonic\" must be a string"));
+    }
+
+    #[test]
+    fn test_doc() {
+        let source = r#"[
+  { "keys": "s", "description": "Flash", "category": "navigation",
+    "doc": { "plugin": "flash.nvim", "text": "Jump to any label" } },
+  { "keys": "S", "description": "Flash Treesitter", "category": "navigation",
+    "doc": { "text": "Select a node" } }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 4);
+        assert!(diagnostics[0].message.contains("missing field `plugin`"));
+    }
+
+    #[test]
+    fn test_workflow() {
+        let source = r#"[
+  { "keys": "<leader>gg<Space>c", "description": "Commit a file", "category": "git",
+    "workflow": [
+      { "keys": "<leader>gg", "description": "Open lazygit" },
+      { "keys": "<Space>", "description": "Stage the file" },
+      { "keys": "c", "description": "Commit" }
+    ] },
+  { "keys": "<leader>gp", "description": "Push", "category": "git",
+    "workflow": [{ "keys": "<leader>gg" }, { "keys": "<Nope>", "description": "Push" }] },
+  { "keys": "<leader>gP", "description": "Pull", "category": "git",
+    "workflow": [{ "keys": "<Nope>", "description": "Pull" }] }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        let messages: Vec<(usize, &str)> =
+            diagnostics.iter().map(|d| (d.line, d.message.as_str())).collect();
+        assert_eq!(messages.len(), 3, "{:?}", messages);
+        assert_eq!(messages[0].0, 8);
+        assert!(messages[0].1.contains("missing field `description`"));
+        assert_eq!(messages[1], (10, "workflow step \"<Nope>\": unknown special key \"<Nope>\""));
+        assert!(messages[2].1.contains("differ from the workflow steps, \"<Nope>\""));
+    }
+
+    #[test]
+    fn test_reports_entry_lines() {
+        let source = r#"[
+  { "keys": "<leader>ff", "description": "Find files", "category": "search" },
+  {
+    "keys": "<leader>fg",
+    "description": "",
+    "category": "searching"
+  }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics.iter().all(|d| d.line == 3));
+        assert!(diagnostics[0].message.contains("empty description"));
+        assert!(diagnostics[1].message.contains("unknown category"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+    }
+
+    #[test]
+    fn test_duplicates_and_bad_keys() {
+        let source = r#"[
+  { "keys": "gd", "description": "Go to definition", "category": "lsp" },
+  { "keys": "gd", "description": "Goto definition", "category": "lsp" },
+  { "keys": "gd", "description": "Select definition", "category": "lsp", "mode": "visual" },
+  { "keys": "<X-w>", "description": "Window", "category": "window", "mode": "sideways" }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.contains("line 2"));
+        assert!(diagnostics[1].message.contains("unknown mode"));
+        assert!(diagnostics[2].message.contains("unknown modifier"));
+        assert_eq!(diagnostics[2].line, 5);
+    }
+
+    #[test]
+    fn test_alternatives() {
+        let source = r#"[
+  { "keys": "<S-h>", "description": "Previous buffer", "category": "buffer",
+    "alternatives": ["[b", "<X-b>"] },
+  { "keys": "<S-l>", "description": "Next buffer", "category": "buffer", "alternatives": "]b" }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics[0].message.contains("alternative \"<X-b>\": unknown modifier"));
+        assert_eq!(diagnostics[1].line, 4);
+        assert!(diagnostics[1].message.contains("must be a list of keys"));
+    }
+
+    #[test]
+    fn test_mode_lists() {
+        let source = r#"[
+  { "keys": "gc", "description": "Comment", "category": "code", "mode": ["normal", "visual"] },
+  { "keys": "gc", "description": "Comment lines", "category": "code", "mode": "visual" },
+  { "keys": "gx", "description": "Open", "category": "general", "mode": ["normal", "normal"] },
+  { "keys": "gy", "description": "Yank", "category": "general", "mode": [] }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].line, 3);
+        assert!(diagnostics[0].message.contains("line 2 in visual mode"));
+        assert_eq!(diagnostics[1].severity, Severity::Warning);
+        assert!(diagnostics[1].message.contains("mode normal listed twice"));
+        assert!(diagnostics[2].message.contains("empty \"mode\" list"));
+    }
+
+    #[test]
+    fn test_toml_and_yaml_lines() {
+        let toml = r#"# My keymaps
+[[commands]]
+keys = "gd"
+description = "Go to definition"
+category = "lsp"
+
+[[commands]]
+keys = "gr"
+description = ""
+category = "lsp"
+"#;
+        let diagnostics = validate(toml, FileFormat::Toml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 7);
+
+        let yaml = "- keys: gd\n  description: Go to definition\n  category: lsp\n- keys: gr\n  description: References\n  category: lsp\n  mode: nope\n";
+        let diagnostics = validate(yaml, FileFormat::Yaml);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 4);
+    }
+
+    #[test]
+    fn test_diagram_bounds() {
+        let source = r#"[
+  { "keys": "iw", "description": "Inner word", "category": "general",
+    "diagram": { "text": "foo bar", "cursor": 1, "start": 0, "end": 3 } },
+  { "keys": "aw", "description": "Around word", "category": "general",
+    "diagram": { "text": "foo bar", "cursor": 1, "start": 0, "end": 9 } },
+  { "keys": "ip", "description": "Inner paragraph", "category": "general",
+    "diagram": { "text": "foo bar" } }
+]"#;
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].line, 4);
+        assert!(diagnostics[0].message.contains("range 0..9"));
+        assert_eq!(diagnostics[1].line, 6);
+    }
+
+    #[test]
+    fn test_syntax_error_line() {
+        let source = "[\n  { \"keys\": \"gd\", }\n]";
+        let diagnostics = validate(source, FileFormat::Json);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].line, 2);
+    }
+}
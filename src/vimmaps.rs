@@ -0,0 +1,181 @@
+use crate::commands::{Command, Mode};
+use crate::keynotation;
+use std::collections::HashSet;
+
+/// Tag on imported mappings that had no description, so `#needs-description` finds them
+pub const UNDESCRIBED_TAG: &str = "needs-description";
+
+/// One mapping from the `:map` listing, before it becomes commands
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Mapping {
+    modes: String,
+    lhs: String,
+    rhs: String,
+    desc: Option<String>,
+}
+
+/// Parse the output of `:verbose map` (or plain `:map`) into commands, one per supported mode.
+/// Mappings without a description are described by their right-hand side and tagged
+/// [`UNDESCRIBED_TAG`].
+pub fn import(output: &str) -> Vec<Command> {
+    let mut seen = HashSet::new();
+    parse_mappings(output)
+        .into_iter()
+        .filter(|m| !m.lhs.starts_with("<Plug>") && !m.lhs.starts_with("<SNR>"))
+        .flat_map(|m| {
+            let keys = leader_notation(&m.lhs);
+            let (description, tags) = match m.desc {
+                Some(desc) => (desc, Vec::new()),
+                None => (m.rhs, vec![UNDESCRIBED_TAG.to_string()]),
+            };
+            modes(&m.modes)
+                .into_iter()
+                .map(move |mode| Command {
+                    keys: keys.clone(),
+                    description: description.clone(),
+                    mode,
+                    tags: tags.clone(),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>()
+        })
+        // `:map` lists each mapping once, but the same keys may be spelled two ways
+        .filter(|cmd| seen.insert((cmd.mode, keynotation::normalize(&cmd.keys))))
+        .collect()
+}
+
+fn parse_mappings(output: &str) -> Vec<Mapping> {
+    let mut mappings: Vec<Mapping> = Vec::new();
+    for line in output.lines() {
+        if line.trim().is_empty() || line.starts_with('\t') {
+            // Blank, or a `Last set from ...` line
+            continue;
+        }
+        // Mapping lines have up to three mode chars, then the lhs in the fourth column;
+        // anything indented further continues the mapping above it
+        let starts_mapping =
+            line.len() > 3 && line.is_char_boundary(3) && !line[3..].starts_with(' ');
+        if starts_mapping {
+            let (modes, rest) = line.split_at(3);
+            let (lhs, rhs) = rest.split_once(' ').unwrap_or((rest, ""));
+            let rhs = rhs.trim_start().trim_start_matches(['*', '&', '@', ' ']);
+            mappings.push(Mapping {
+                modes: modes.trim().to_string(),
+                lhs: lhs.to_string(),
+                rhs: rhs.trim_end().to_string(),
+                desc: None,
+            });
+        } else if let Some(last) = mappings.last_mut() {
+            let text = line.trim();
+            if last.desc.is_none() && !text.starts_with("Last set from") {
+                last.desc = Some(text.to_string());
+            }
+        }
+    }
+    mappings
+}
+
+/// Modes of a `:map` mode column; blank means `:map` (normal, visual, operator-pending)
+/// and `!` means `:map!` (insert, command line). `v` covers visual and select mode, but
+/// mappings made for visual mode mostly don't mean select mode, so it's only visual here.
+fn modes(column: &str) -> Vec<Mode> {
+    let mut modes = Vec::new();
+    let column = match column {
+        "" => "nvo",
+        "!" => "ic",
+        other => other,
+    };
+    for c in column.chars() {
+        let mode = match c {
+            'n' => Mode::Normal,
+            'v' | 'x' => Mode::Visual,
+            's' => Mode::Select,
+            'o' => Mode::OperatorPending,
+            'i' => Mode::Insert,
+            'c' => Mode::Command,
+            't' => Mode::Terminal,
+            _ => continue,
+        };
+        if !modes.contains(&mode) {
+            modes.push(mode);
+        }
+    }
+    modes
+}
+
+/// `:map` shows the leader as the key it stands for; assume the common `<Space>`, in any case
+fn leader_notation(lhs: &str) -> String {
+    let space = "<Space>".len();
+    let first = lhs.get(..space).map(keynotation::normalize);
+    let is_space = first.as_deref() == Some("<Space>");
+    match lhs.get(space..) {
+        Some(rest) if is_space && !rest.is_empty() => format!("<leader>{}", rest),
+        _ => lhs.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: &str = "\
+n  <Space>ff   * <Lua 212: ~/.config/nvim/lua/config/keymaps.lua:10>
+                 Find Files
+\tLast set from Lua (run Nvim with -V1 for more details)
+x  <Space>gs   * <Cmd>Telescope git_status<CR>
+\tLast set from ~/.config/nvim/init.vim line 4
+   <C-L>       * <Cmd>nohlsearch|diffupdate|normal! <C-L><CR>
+                 :help CTRL-L-default
+!  <C-R><C-R>  * <C-R><C-O>
+o  af          * <Plug>(textobj)
+t  <Esc><Esc>  * <C-\\><C-N>
+                 Enter Normal Mode
+n  <Plug>(foo)   :echo<CR>
+";
+
+    #[test]
+    fn test_parse_mappings() {
+        let mappings = parse_mappings(OUTPUT);
+        assert_eq!(mappings.len(), 7);
+        assert_eq!(mappings[0].desc.as_deref(), Some("Find Files"));
+        assert_eq!(mappings[1].rhs, "<Cmd>Telescope git_status<CR>");
+        assert_eq!(mappings[1].desc, None);
+        assert_eq!(mappings[2].modes, "");
+        assert_eq!(mappings[2].desc.as_deref(), Some(":help CTRL-L-default"));
+        assert_eq!(mappings[3].modes, "!");
+    }
+
+    #[test]
+    fn test_import() {
+        let commands = import(OUTPUT);
+        let summary: Vec<(&str, Mode, bool)> = commands
+            .iter()
+            .map(|c| (c.keys.as_str(), c.mode, c.has_tag(UNDESCRIBED_TAG)))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("<leader>ff", Mode::Normal, false),
+                ("<leader>gs", Mode::Visual, true),
+                ("<C-L>", Mode::Normal, false),
+                ("<C-L>", Mode::Visual, false),
+                ("<C-L>", Mode::OperatorPending, false),
+                ("<C-R><C-R>", Mode::Insert, true),
+                ("<C-R><C-R>", Mode::Command, true),
+                ("af", Mode::OperatorPending, true),
+                ("<Esc><Esc>", Mode::Terminal, false),
+            ]
+        );
+        assert_eq!(commands[1].description, "<Cmd>Telescope git_status<CR>");
+    }
+
+    #[test]
+    fn test_leader_notation() {
+        assert_eq!(leader_notation("<Space>ff"), "<leader>ff");
+        assert_eq!(leader_notation("<space>ff"), "<leader>ff");
+        assert_eq!(leader_notation("<Space>"), "<Space>");
+        assert_eq!(leader_notation("<Spa"), "<Spa");
+        let deduped = import("n  <C-W>v      * <C-W>v\nn  <c-w>v      * <C-W>v\n");
+        assert_eq!(deduped.len(), 1);
+    }
+}
@@ -0,0 +1,130 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::time::{Duration, Instant};
+
+/// How long the watched files have to stay untouched before their change is reported, since
+/// an editor often saves in several writes
+const QUIET: Duration = Duration::from_millis(100);
+
+/// Notices when the user's command, override and config files, or the files tabs were opened
+/// from, change on disk, so the running app can pick up edits made in another window
+pub struct FileWatcher {
+    watcher: notify::RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+    /// Directories watched so far
+    dirs: BTreeSet<PathBuf>,
+    changes: Changes,
+}
+
+impl FileWatcher {
+    /// Watch `files`, which don't have to exist yet. Their directories are watched rather than
+    /// the files, since editors often save by writing a new file over the old one.
+    pub fn new(files: Vec<PathBuf>) -> anyhow::Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = Self {
+            watcher: notify::recommended_watcher(sender)?,
+            events,
+            dirs: BTreeSet::new(),
+            changes: Changes::default(),
+        };
+        for file in files {
+            watcher.watch(file)?;
+        }
+        Ok(watcher)
+    }
+
+    /// Watch `file` as well, unless it already is
+    pub fn watch(&mut self, file: PathBuf) -> anyhow::Result<()> {
+        if self.changes.files.contains(&file) {
+            return Ok(());
+        }
+        if let Some(dir) = file.parent().filter(|dir| dir.is_dir()) {
+            if !self.dirs.contains(dir) {
+                self.watcher.watch(dir, RecursiveMode::NonRecursive)?;
+                self.dirs.insert(dir.to_path_buf());
+            }
+        }
+        self.changes.files.push(file);
+        Ok(())
+    }
+
+    /// The watched files written, created or removed and quiet since, that weren't reported yet
+    pub fn changed(&mut self) -> Vec<PathBuf> {
+        let now = Instant::now();
+        for event in self.events.try_iter().flatten() {
+            self.changes.record(event, now);
+        }
+        self.changes.settled(now)
+    }
+
+    /// Drop the changes so far, e.g. ones the app made itself
+    pub fn forget(&mut self) {
+        self.events.try_iter().for_each(drop);
+        self.changes.touched.clear();
+    }
+}
+
+/// The watched files touched by file events, held back until none was for [`QUIET`]
+#[derive(Debug, Default)]
+struct Changes {
+    files: Vec<PathBuf>,
+    touched: BTreeSet<PathBuf>,
+    /// When a watched file was last touched
+    last: Option<Instant>,
+}
+
+impl Changes {
+    fn record(&mut self, event: notify::Event, at: Instant) {
+        if matches!(event.kind, EventKind::Access(_)) {
+            return;
+        }
+        for path in event.paths {
+            if self.files.contains(&path) {
+                self.touched.insert(path);
+                self.last = Some(at);
+            }
+        }
+    }
+
+    /// The files touched, once they've been quiet for long enough by `now`
+    fn settled(&mut self, now: Instant) -> Vec<PathBuf> {
+        if self.touched.is_empty() || self.last.is_some_and(|last| now - last < QUIET) {
+            return Vec::new();
+        }
+        std::mem::take(&mut self.touched).into_iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{AccessKind, CreateKind, ModifyKind};
+    use std::path::Path;
+
+    fn event(kind: EventKind, path: &Path) -> notify::Event {
+        notify::Event::new(kind).add_path(path.to_path_buf())
+    }
+
+    #[test]
+    fn test_changes() {
+        let (watched, other) = (PathBuf::from("/config/commands.json"), PathBuf::from("/notes"));
+        let mut changes = Changes {
+            files: vec![watched.clone()],
+            ..Changes::default()
+        };
+        let start = Instant::now();
+        let at = |ms| start + Duration::from_millis(ms);
+        changes.record(event(EventKind::Access(AccessKind::Any), &watched), at(0));
+        changes.record(event(EventKind::Modify(ModifyKind::Any), &other), at(0));
+        assert!(changes.settled(at(500)).is_empty());
+
+        // A save written in two steps is one change, once the second is quiet
+        changes.record(event(EventKind::Create(CreateKind::File), &watched), at(0));
+        changes.record(event(EventKind::Modify(ModifyKind::Any), &watched), at(80));
+        assert!(changes.settled(at(120)).is_empty());
+        assert_eq!(changes.settled(at(180)), [watched]);
+        assert!(changes.settled(at(500)).is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::commands::{Category, Command, Mode, Source};
+use crate::keynotation;
+use anyhow::Context;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::path::Path;
+
+/// One entry of the dump written by `contrib/which-key-dump.lua`
+#[derive(Debug, Clone, Deserialize)]
+pub struct DumpEntry {
+    pub lhs: String,
+    #[serde(default = "default_mode")]
+    pub mode: String,
+    pub desc: String,
+    /// Whether this entry names a prefix group rather than a keymap
+    #[serde(default)]
+    pub group: bool,
+}
+
+fn default_mode() -> String {
+    "n".to_string()
+}
+
+/// Words in a which-key group name and the category they point to, first match wins
+const GROUP_CATEGORIES: &[(&str, Category)] = &[
+    ("git", Category::Git),
+    ("hunk", Category::Git),
+    ("debug", Category::Debug),
+    ("test", Category::Debug),
+    ("lsp", Category::Lsp),
+    ("code", Category::Code),
+    ("search", Category::Search),
+    ("find", Category::Search),
+    ("buffer", Category::Buffer),
+    ("window", Category::Window),
+    ("tab", Category::Tab),
+    ("terminal", Category::Terminal),
+    ("ui", Category::Ui),
+    ("toggle", Category::Ui),
+    ("goto", Category::Navigation),
+    ("prev", Category::Navigation),
+    ("next", Category::Navigation),
+    ("plugin", Category::Plugin),
+];
+
+/// Read a which-key dump file
+pub fn load_dump(path: &Path) -> anyhow::Result<Vec<DumpEntry>> {
+    let source = std::fs::read_to_string(path)
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    serde_json::from_str(&source)
+        .with_context(|| format!("{} is not a which-key dump", path.display()))
+}
+
+/// Turn a dump into commands: each keymap takes its category from the closest
+/// enclosing group, and the group name is kept as a tag
+pub fn import(entries: &[DumpEntry]) -> Vec<Command> {
+    // Keys are matched as normalized, so `<C-W>` and `<c-w>` are the same keymap
+    let groups: Vec<(String, &DumpEntry)> = entries
+        .iter()
+        .filter(|e| e.group)
+        .map(|group| (keynotation::normalize(&group.lhs), group))
+        .collect();
+    let mut seen = HashSet::new();
+
+    entries
+        .iter()
+        .filter(|entry| !entry.group)
+        .filter_map(|entry| {
+            let mode = parse_mode(&entry.mode)?;
+            let lhs = keynotation::normalize(&entry.lhs);
+            let group = groups
+                .iter()
+                .filter(|(prefix, _)| lhs.starts_with(prefix) && lhs.len() > prefix.len())
+                .max_by_key(|(prefix, _)| prefix.len())
+                .map(|(_, group)| group);
+            let cmd = Command {
+                keys: entry.lhs.clone(),
+                description: entry.desc.clone(),
+                category: group.map_or(Category::General, |g| group_category(&g.desc)),
+                mode,
+                tags: group.map(|g| slug(&g.desc)).into_iter().collect(),
+                mnemonic: None,
+                see_also: Vec::new(),
+                alternatives: Vec::new(),
+                diagram: None,
+                doc: None,
+                workflow: Vec::new(),
+                source: Source::default(),
+                beside: false,
+            };
+            // which-key entries come first in the dump, so they win over plain keymaps
+            seen.insert((mode, lhs)).then_some(cmd)
+        })
+        .collect()
+}
+
+/// Lua spec for `require("which-key").add()` describing every keymap; workflows aren't one
+pub fn export(commands: &[Command]) -> String {
+    let mut lua = String::from("-- Generated by `lvcheat export which-key`\nreturn {\n");
+    for cmd in commands.iter().filter(|cmd| !cmd.is_ex() && !cmd.is_workflow()) {
+        let _ = writeln!(
+            lua,
+            "  {{ {}, desc = {}, mode = \"{}\" }},",
+            lua_string(&cmd.keys),
+            lua_string(&cmd.description),
+            vim_mode(cmd.mode)
+        );
+    }
+    lua.push_str("}\n");
+    lua
+}
+
+fn parse_mode(mode: &str) -> Option<Mode> {
+    match mode {
+        "n" => Some(Mode::Normal),
+        "i" => Some(Mode::Insert),
+        "v" | "x" => Some(Mode::Visual),
+        "c" => Some(Mode::Command),
+        "t" => Some(Mode::Terminal),
+        "o" => Some(Mode::OperatorPending),
+        "s" => Some(Mode::Select),
+        _ => None,
+    }
+}
+
+fn vim_mode(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Normal => "n",
+        Mode::Insert => "i",
+        Mode::Visual => "x",
+        Mode::Command => "c",
+        Mode::Terminal => "t",
+        Mode::OperatorPending => "o",
+        Mode::Select => "s",
+    }
+}
+
+fn group_category(name: &str) -> Category {
+    let name = name.to_lowercase();
+    let words: Vec<&str> = name
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect();
+    GROUP_CATEGORIES
+        .iter()
+        .find(|(word, _)| words.iter().any(|w| w.trim_end_matches('s') == *word))
+        .map_or(Category::General, |(_, category)| category.clone())
+}
+
+/// `file/find` -> `file-find`
+fn slug(name: &str) -> String {
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+fn lua_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_import_groups() {
+        let dump: Vec<DumpEntry> = serde_json::from_str(
+            r#"[
+                {"lhs": "<leader>g", "mode": "n", "desc": "git", "group": true},
+                {"lhs": "<leader>gh", "mode": "n", "desc": "hunks", "group": true},
+                {"lhs": "<leader>f", "mode": "n", "desc": "file/find", "group": true},
+                {"lhs": "<leader>gg", "mode": "n", "desc": "Lazygit"},
+                {"lhs": "<leader>ghs", "mode": "x", "desc": "Stage Hunk"},
+                {"lhs": "<leader>ff", "desc": "Find Files"},
+                {"lhs": "<leader>ff", "mode": "n", "desc": "Find Files (keymap)"},
+                {"lhs": "gx", "mode": "o", "desc": "Operator pending"},
+                {"lhs": "<C-s>", "mode": "i", "desc": "Save File"},
+                {"lhs": "<c-S>", "mode": "i", "desc": "Save File (keymap)"},
+                {"lhs": "<Leader>gb", "mode": "n", "desc": "Blame"}
+            ]"#,
+        )
+        .unwrap();
+        let commands = import(&dump);
+        assert_eq!(commands.len(), 6);
+
+        assert_eq!(commands[0].category, Category::Git);
+        assert_eq!(commands[0].tags, ["git"]);
+        assert_eq!(commands[1].mode, Mode::Visual);
+        assert_eq!(commands[1].tags, ["hunks"]);
+        assert_eq!(commands[2].category, Category::Search);
+        assert_eq!(commands[2].tags, ["file-find"]);
+        assert_eq!(commands[2].description, "Find Files");
+        assert_eq!(commands[3].mode, Mode::OperatorPending);
+        assert_eq!(commands[4].category, Category::General);
+        assert!(commands[4].tags.is_empty());
+        assert_eq!(commands[5].tags, ["git"]);
+    }
+
+    #[test]
+    fn test_export_spec() {
+        let commands = vec![
+            Command {
+                keys: "<leader>sg".to_string(),
+                description: r#"Grep "root" \ cwd"#.to_string(),
+                ..Default::default()
+            },
+            Command {
+                keys: ":Lazy".to_string(),
+                description: "Open lazy.nvim".to_string(),
+                ..Default::default()
+            },
+        ];
+        assert_eq!(
+            export(&commands),
+            "-- Generated by `lvcheat export which-key`\nreturn {\n  \
+             { \"<leader>sg\", desc = \"Grep \\\"root\\\" \\\\ cwd\", mode = \"n\" },\n}\n"
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! The whole cheatsheet as one widget, for embedding in other ratatui apps: a search box, the
+//! commands it finds, and the keyboard playing the selected command's keys.
+//!
+//! ```no_run
+//! use lazyvim_helper::widget::{CheatsheetState, CheatsheetWidget};
+//! # fn draw(frame: &mut ratatui::Frame, event: crossterm::event::Event) -> anyhow::Result<()> {
+//! let mut cheatsheet = CheatsheetState::lazyvim()?;
+//! // In the event loop, before the app's own key handling
+//! if !cheatsheet.handle_event(&event) {
+//!     // Not the cheatsheet's key, e.g. Enter or Tab
+//! }
+//! // In a draw callback
+//! frame.render_stateful_widget(CheatsheetWidget::new(), frame.area(), &mut cheatsheet);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::commands::{self, Command, KeyFrame, Leaders};
+use crate::keyboard::{Keyboard, KeyboardState, KeyboardWidget};
+use crate::search::SearchEngine;
+use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{
+        Block, HighlightSpacing, List, ListItem, ListState, Paragraph, StatefulWidget, Widget,
+    },
+};
+use std::time::{Duration, Instant};
+
+/// Keyboard art plus borders
+const KEYBOARD_HEIGHT: u16 = 17;
+/// Width given to the keys before each command's description
+const KEYS_WIDTH: usize = 16;
+
+/// What a [`CheatsheetWidget`] shows: the commands, the search typed so far, what it found,
+/// and when the selected command's animation started
+pub struct CheatsheetState {
+    commands: Vec<Command>,
+    /// Key presses of each command, parsed once
+    frames: Vec<Vec<KeyFrame>>,
+    engine: SearchEngine,
+    keyboard: Keyboard,
+    query: String,
+    /// Indices into `commands`, best first
+    results: Vec<usize>,
+    list: ListState,
+    animation_start: Instant,
+    /// How long each frame of the animation shows; redraw at least this often to play it
+    pub frame_duration: Duration,
+}
+
+impl CheatsheetState {
+    /// A cheatsheet of `commands`, with `<leader>` and `<localleader>` standing for `leaders`
+    pub fn new(commands: Vec<Command>, leaders: &Leaders) -> Self {
+        let mut engine = SearchEngine::new();
+        engine.index(&commands);
+        let mut state = Self {
+            frames: commands::parse_frames(&commands, leaders),
+            commands,
+            engine,
+            keyboard: Keyboard::new(),
+            query: String::new(),
+            results: Vec::new(),
+            list: ListState::default(),
+            animation_start: Instant::now(),
+            frame_duration: Duration::from_millis(500),
+        };
+        state.search();
+        state
+    }
+
+    /// LazyVim's keymaps and ex commands, with its leaders
+    pub fn lazyvim() -> anyhow::Result<Self> {
+        let mut commands = commands::embedded_commands()?;
+        commands.extend(commands::ex_commands()?);
+        Ok(Self::new(commands, &Leaders::default()))
+    }
+
+    /// Light the keys on `keyboard`, e.g. one with another layout or platform
+    pub fn with_keyboard(mut self, keyboard: Keyboard) -> Self {
+        self.keyboard = keyboard;
+        self
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Search for `query` as if it was typed, selecting the best match
+    pub fn set_query(&mut self, query: &str) {
+        self.query = query.to_string();
+        self.search();
+    }
+
+    /// The commands found, best first
+    pub fn results(&self) -> impl Iterator<Item = &Command> {
+        self.results.iter().map(|&idx| &self.commands[idx])
+    }
+
+    pub fn selected(&self) -> Option<&Command> {
+        self.selected_index().map(|idx| &self.commands[idx])
+    }
+
+    /// Act on a key press: typing edits the search, Backspace and Ctrl+U erase it, Esc clears
+    /// it, and Up/Down (or Ctrl+P/N) and PageUp/PageDown move the selection. Returns whether
+    /// the event was used, so the rest (Enter, Tab, Esc on an empty search, ...) can go to
+    /// the app around it.
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        let Event::Key(key) = event else {
+            return false;
+        };
+        if key.kind == KeyEventKind::Release {
+            return false;
+        }
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('p') if ctrl => self.move_selection(-1),
+            KeyCode::Char('n') if ctrl => self.move_selection(1),
+            KeyCode::Char('u') if ctrl => self.set_query(""),
+            KeyCode::Char(c) if !ctrl && !key.modifiers.contains(KeyModifiers::ALT) => {
+                self.query.push(c);
+                self.search();
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                self.search();
+            }
+            KeyCode::Esc if !self.query.is_empty() => self.set_query(""),
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::PageUp => self.move_selection(-10),
+            KeyCode::PageDown => self.move_selection(10),
+            _ => return false,
+        }
+        true
+    }
+
+    fn selected_index(&self) -> Option<usize> {
+        self.list.selected().and_then(|row| self.results.get(row)).copied()
+    }
+
+    /// Run the search again and select the best match
+    fn search(&mut self) {
+        let found = self.engine.search(&self.commands, &self.query);
+        self.results = found
+            .into_iter()
+            .filter_map(|(cmd, _)| self.commands.iter().position(|c| std::ptr::eq(c, cmd)))
+            .collect();
+        let first = (!self.results.is_empty()).then_some(0);
+        self.list = ListState::default().with_selected(first);
+        self.animation_start = Instant::now();
+    }
+
+    /// Move the selection `delta` rows, stopping at either end
+    fn move_selection(&mut self, delta: isize) {
+        let Some(row) = self.list.selected() else {
+            return;
+        };
+        let last = self.results.len().saturating_sub(1) as isize;
+        let moved = (row as isize + delta).clamp(0, last) as usize;
+        if moved != row {
+            self.list.select(Some(moved));
+            self.animation_start = Instant::now();
+        }
+    }
+
+    /// The selected command's frames, and which one shows now
+    fn current_frame(&self) -> Option<(&[KeyFrame], usize)> {
+        let frames = self.frames.get(self.selected_index()?)?;
+        if frames.is_empty() {
+            return None;
+        }
+        let per_frame = self.frame_duration.as_millis().max(1);
+        let index = self.animation_start.elapsed().as_millis() / per_frame % frames.len() as u128;
+        Some((frames, index as usize))
+    }
+}
+
+/// The cheatsheet in one widget: the search on its first line, the results under it, and the
+/// keyboard at the bottom when there's room for it
+#[derive(Debug, Clone, Default)]
+pub struct CheatsheetWidget<'a> {
+    block: Option<Block<'a>>,
+}
+
+impl<'a> CheatsheetWidget<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Wrap the cheatsheet in a block, e.g. a bordered and titled one
+    pub fn block(mut self, block: Block<'a>) -> Self {
+        self.block = Some(block);
+        self
+    }
+}
+
+impl StatefulWidget for CheatsheetWidget<'_> {
+    type State = CheatsheetState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let inner = match self.block {
+            Some(block) => {
+                let inner = block.inner(area);
+                block.render(area, buf);
+                inner
+            }
+            None => area,
+        };
+        // The keyboard only shows with a few results above it
+        let keyboard_height = if inner.height >= KEYBOARD_HEIGHT + 4 { KEYBOARD_HEIGHT } else { 0 };
+        let [search, results, keyboard] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(keyboard_height),
+        ])
+        .areas(inner);
+
+        let dim = Style::default().fg(Color::DarkGray);
+        let typed = if state.query.is_empty() {
+            Span::styled("type to search", dim)
+        } else {
+            Span::raw(state.query.as_str())
+        };
+        let count = format!("  {}/{}", state.results.len(), state.commands.len());
+        Paragraph::new(Line::from(vec![
+            Span::styled("Search: ", Style::default().fg(Color::Yellow)),
+            typed,
+            Span::styled(count, dim),
+        ]))
+        .render(search, buf);
+
+        let items: Vec<ListItem> = state
+            .results
+            .iter()
+            .map(|&idx| {
+                let cmd = &state.commands[idx];
+                ListItem::new(Line::from(vec![
+                    Span::styled(
+                        format!("{:<width$} ", cmd.keys, width = KEYS_WIDTH),
+                        Style::default().fg(Color::Cyan),
+                    ),
+                    Span::raw(cmd.description.as_str()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+            .highlight_spacing(HighlightSpacing::Never);
+        StatefulWidget::render(list, results, buf, &mut state.list);
+
+        if keyboard.height == 0 {
+            return;
+        }
+        let (title, mut lit) = match (state.selected(), state.current_frame()) {
+            (Some(cmd), Some((frames, index))) => {
+                let ids = state.keyboard.frame_ids(&frames[index]);
+                let title = format!(" {} [{}/{}] ", cmd.keys, index + 1, frames.len());
+                (title, KeyboardState::frame(ids, index))
+            }
+            _ => (String::new(), KeyboardState::default()),
+        };
+        lit = lit.palette(state.keyboard.palette);
+        KeyboardWidget::new()
+            .layout(state.keyboard.layout)
+            .platform(state.keyboard.platform)
+            .block(Block::bordered().border_style(dim).title(title))
+            .render(keyboard, buf, &mut lit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::event::KeyEvent;
+
+    fn press(code: KeyCode) -> Event {
+        Event::Key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    /// The buffer's lines, as text
+    fn text(buf: &Buffer) -> Vec<String> {
+        let width = buf.area.width as usize;
+        let symbols: Vec<&str> = buf.content.iter().map(|cell| cell.symbol()).collect();
+        symbols.chunks(width).map(|line| line.concat()).collect()
+    }
+
+    #[test]
+    fn test_cheatsheet() {
+        let mut state = CheatsheetState::lazyvim().unwrap();
+        for c in "find files".chars() {
+            assert!(state.handle_event(&press(KeyCode::Char(c))));
+        }
+        assert_eq!(state.selected().unwrap().keys, "<leader>ff");
+        assert!(!state.handle_event(&press(KeyCode::Enter)));
+
+        let area = Rect::new(0, 0, 60, 30);
+        let mut buf = Buffer::empty(area);
+        CheatsheetWidget::new().render(area, &mut buf, &mut state);
+        let lines = text(&buf);
+        assert!(lines[0].starts_with("Search: find files  "));
+        assert!(lines[1].starts_with("<leader>ff       Find files (root dir)"));
+        assert!(lines.iter().any(|line| line.contains(" <leader>ff [1/3] ")));
+
+        assert!(state.handle_event(&press(KeyCode::Down)));
+        assert_ne!(state.selected().unwrap().keys, "<leader>ff");
+        assert!(state.handle_event(&press(KeyCode::Esc)));
+        assert_eq!(state.query(), "");
+        assert_eq!(state.results().count(), state.commands.len());
+        assert!(!state.handle_event(&press(KeyCode::Esc)));
+    }
+}
@@ -0,0 +1,200 @@
+use crate::commands::{self, Category, Command, KeyFrame, Leaders, Problem, Source};
+use crate::input::TextInput;
+use crate::state::{ListMode, Section, SortMode};
+use crate::ui::ResultRow;
+use anyhow::Context;
+use std::path::{Path, PathBuf};
+
+/// A level of the browsing trail, shown in the breadcrumb over the results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Crumb {
+    /// A category opened from the start screen, whose commands alone are listed
+    Category(Category),
+    /// An earlier query narrowed into with Ctrl+F
+    Filter(String),
+}
+
+/// One set of commands open in a tab, with its own search and selection
+#[derive(Debug, Clone, Default)]
+pub struct Workspace {
+    /// Shown in the tab bar
+    pub name: String,
+    /// The command file it was loaded from, if it was, reloaded when it changes
+    #[cfg(feature = "watch")]
+    pub path: Option<PathBuf>,
+    pub commands: Vec<Command>,
+    /// Key presses of each command, parsed once by `parse_frames`
+    pub frames: Vec<Vec<KeyFrame>>,
+    /// [`commands::complexity`] of each command's frames, worked out with them
+    pub complexity: Vec<(usize, usize)>,
+    pub query: TextInput,
+    /// Levels browsed into, outermost first; Esc backs out of the last
+    pub trail: Vec<Crumb>,
+    pub filtered_results: Vec<usize>,
+    /// How many results each category would list, before an opened category keeps its own
+    pub category_counts: Vec<(Category, usize)>,
+    /// Pinned commands still listed, above the results
+    pub pinned: Vec<usize>,
+    pub rows: Vec<ResultRow>,
+    /// Index into `rows`
+    pub selected_index: usize,
+    pub list_mode: ListMode,
+    pub sort: SortMode,
+    pub section: Section,
+    pub collapsed: Vec<Category>,
+    /// Index of the highlighted card on the start screen
+    pub card: usize,
+    /// Highlighted entry of the selected command's "See also" list, if one is
+    pub related_index: Option<usize>,
+    /// Commands marked for export, in the order they were marked
+    pub marked: Vec<usize>,
+    /// Entries of the command files that were left out
+    pub problems: Vec<Problem>,
+}
+
+impl Workspace {
+    pub fn new(name: impl Into<String>, commands: Vec<Command>) -> Self {
+        Self {
+            name: name.into(),
+            commands,
+            ..Default::default()
+        }
+    }
+
+    /// A workspace for a command file, named after it. Relative paths are taken from the
+    /// config directory.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let path = resolve(path);
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .with_context(|| format!("no file name in {}", path.display()))?;
+        let (commands, problems) = commands::load_valid_commands(&path)?;
+        let commands = commands::with_source(commands, source(&path));
+        Ok(Self {
+            problems,
+            #[cfg(feature = "watch")]
+            path: Some(path.clone()),
+            ..Self::new(name, commands)
+        })
+    }
+
+    /// Parse every command's keys with `leaders`, again whenever the commands change
+    pub fn parse_frames(&mut self, leaders: &Leaders) {
+        self.frames = commands::parse_frames(&self.commands, leaders);
+        self.complexity = self.frames.iter().map(|frames| commands::complexity(frames)).collect();
+    }
+
+    /// The open category, if any
+    pub fn entered(&self) -> Option<&Category> {
+        self.trail.iter().find_map(|crumb| match crumb {
+            Crumb::Category(category) => Some(category),
+            Crumb::Filter(_) => None,
+        })
+    }
+
+    /// Open `category` where the trail has one, else as its next level; `None` closes it
+    pub fn set_entered(&mut self, category: Option<Category>) {
+        let at = self.trail.iter().position(|crumb| matches!(crumb, Crumb::Category(_)));
+        match (at, category) {
+            (Some(at), Some(category)) => self.trail[at] = Crumb::Category(category),
+            (Some(at), None) => {
+                self.trail.remove(at);
+            }
+            (None, Some(category)) => self.trail.push(Crumb::Category(category)),
+            (None, None) => {}
+        }
+    }
+
+    /// The queries narrowed into, outermost first
+    pub fn filters(&self) -> Vec<&str> {
+        self.trail
+            .iter()
+            .filter_map(|crumb| match crumb {
+                Crumb::Filter(filter) => Some(filter.as_str()),
+                Crumb::Category(_) => None,
+            })
+            .collect()
+    }
+
+    /// Replace the queries narrowed into, keeping the open category
+    pub fn set_filters(&mut self, filters: &[String]) {
+        self.trail.retain(|crumb| matches!(crumb, Crumb::Category(_)));
+        self.trail.extend(filters.iter().cloned().map(Crumb::Filter));
+    }
+}
+
+impl Crumb {
+    /// How the breadcrumb shows it
+    pub fn label(&self) -> &str {
+        match self {
+            Crumb::Category(category) => category.as_str(),
+            Crumb::Filter(filter) => filter,
+        }
+    }
+}
+
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(rest) = path.strip_prefix("~") {
+        return dirs::home_dir().unwrap_or_default().join(rest);
+    }
+    match commands::config_dir() {
+        Some(dir) if path.is_relative() => dir.join(path),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Where the commands of the file at `path` come from: the path from the config directory, or
+/// in full when it's elsewhere, so files with the same name in two directories stay apart
+fn source(path: &Path) -> Source {
+    let dir = commands::config_dir();
+    let shown = dir.as_deref().and_then(|dir| path.strip_prefix(dir).ok()).unwrap_or(path);
+    Source::File(shown.display().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trail() {
+        let mut ws = Workspace::default();
+        ws.set_filters(&["lsp".to_string()]);
+        ws.set_entered(Some(Category::Git));
+        ws.trail.push(Crumb::Filter("hunk".to_string()));
+        assert_eq!(ws.entered(), Some(&Category::Git));
+        assert_eq!(ws.filters(), ["lsp", "hunk"]);
+
+        // Switching category keeps its place in the trail
+        ws.set_entered(Some(Category::Search));
+        let labels: Vec<&str> = ws.trail.iter().map(Crumb::label).collect();
+        assert_eq!(labels, ["lsp", "Search", "hunk"]);
+
+        ws.set_filters(&[]);
+        assert_eq!(ws.trail, [Crumb::Category(Category::Search)]);
+        ws.set_entered(None);
+        assert!(ws.trail.is_empty());
+    }
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(resolve(Path::new("/tmp/pack.json")), Path::new("/tmp/pack.json"));
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(resolve(Path::new("~/pack.json")), home.join("pack.json"));
+        }
+        if let Some(dir) = commands::config_dir() {
+            assert_eq!(resolve(Path::new("packs/a.toml")), dir.join("packs/a.toml"));
+        }
+    }
+
+    #[test]
+    fn test_source() {
+        let outside = Path::new("/tmp/b/maps.json");
+        assert_eq!(source(outside), Source::File("/tmp/b/maps.json".to_string()));
+        if let Some(dir) = commands::config_dir() {
+            let inside = dir.join("a").join("maps.json");
+            let shown = Path::new("a").join("maps.json").display().to_string();
+            assert_eq!(source(&inside), Source::File(shown));
+        }
+    }
+}